@@ -0,0 +1,163 @@
+use crate::{objects::*, Error, Gtfs, PathwayConstraints};
+use std::collections::BTreeSet;
+
+/// Wheelchair accessibility of a stop, see [Gtfs::stop_accessibility]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StopAccessibility {
+    /// `wheelchair_boarding` of the stop, or of its parent station when the stop has no information
+    pub wheelchair_boarding: Availability,
+    /// True if a platform can be reached from an entrance of its station without stairs, false if it can not.
+    /// None if the stop is not a platform of a station with pathways
+    pub step_free_access: Option<bool>,
+    /// Number of trips calling at the stop, or at one of its child stops
+    pub trips: usize,
+    /// Number of these trips that are wheelchair accessible
+    pub accessible_trips: usize,
+}
+
+/// Wheelchair accessibility of the trips and stops of a route, see [Gtfs::route_accessibility]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteAccessibility {
+    /// Number of trips of the route
+    pub trips: usize,
+    /// Number of trips of the route that are wheelchair accessible
+    pub accessible_trips: usize,
+    /// Number of trips of the route that are not wheelchair accessible
+    pub inaccessible_trips: usize,
+    /// Number of stops where the trips of the route call
+    pub stops: usize,
+    /// Number of these stops with a wheelchair boarding, see [StopAccessibility::wheelchair_boarding]
+    pub accessible_stops: usize,
+}
+
+impl StopAccessibility {
+    /// Share of the trips calling at the stop that are wheelchair accessible. None if no trip calls at the stop
+    pub fn accessible_trips_ratio(&self) -> Option<f64> {
+        ratio(self.accessible_trips, self.trips)
+    }
+}
+
+impl RouteAccessibility {
+    /// Share of the trips of the route that are wheelchair accessible. None if the route has no trip
+    pub fn accessible_trips_ratio(&self) -> Option<f64> {
+        ratio(self.accessible_trips, self.trips)
+    }
+}
+
+fn ratio(count: usize, total: usize) -> Option<f64> {
+    if total == 0 {
+        None
+    } else {
+        Some(count as f64 / total as f64)
+    }
+}
+
+impl Gtfs {
+    /// Summarizes the wheelchair accessibility of a stop
+    ///
+    /// A stop, an entrance or a generic node without `wheelchair_boarding` inherits the one of its parent station.
+    /// The step-free access of a platform is found with [Gtfs::pathway_path] avoiding the stairs, from any
+    /// entrance of its station. [Gtfs::trips_at_stop] gives the trips calling at the stop
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// // The elevator leads from the entrance to platform1
+    /// let accessibility = gtfs.stop_accessibility("platform1")?;
+    /// assert_eq!(Some(true), accessibility.step_free_access);
+    /// assert_eq!(2, accessibility.trips);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn stop_accessibility(&self, stop_id: &str) -> Result<StopAccessibility, Error> {
+        let stop = self.get_stop(stop_id)?;
+        let trips: Vec<&Trip> = self.trips_at_stop(stop_id).collect();
+        Ok(StopAccessibility {
+            wheelchair_boarding: self.wheelchair_boarding(stop),
+            step_free_access: self.step_free_access(stop),
+            trips: trips.len(),
+            accessible_trips: trips
+                .iter()
+                .filter(|t| t.wheelchair_accessible == Availability::Available)
+                .count(),
+        })
+    }
+
+    /// Summarizes the wheelchair accessibility of the trips of a route and of the stops where they call
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let accessibility = gtfs.route_accessibility("routeA")?;
+    /// assert_eq!(3, accessibility.trips);
+    /// // No trip gives its wheelchair accessibility
+    /// assert_eq!(Some(0.), accessibility.accessible_trips_ratio());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn route_accessibility(&self, route_id: &str) -> Result<RouteAccessibility, Error> {
+        self.get_route(route_id)?;
+        let mut accessibility = RouteAccessibility {
+            trips: 0,
+            accessible_trips: 0,
+            inaccessible_trips: 0,
+            stops: 0,
+            accessible_stops: 0,
+        };
+        let mut stops = BTreeSet::new();
+        for trip in self.trips_for_route(route_id) {
+            accessibility.trips += 1;
+            match trip.wheelchair_accessible {
+                Availability::Available => accessibility.accessible_trips += 1,
+                Availability::NotAvailable => accessibility.inaccessible_trips += 1,
+                _ => (),
+            }
+            stops.extend(trip.stop_times.iter().map(|st| &st.stop.id));
+        }
+        accessibility.stops = stops.len();
+        accessibility.accessible_stops = stops
+            .into_iter()
+            .filter_map(|id| self.stops.get(id))
+            .filter(|stop| self.wheelchair_boarding(stop) == Availability::Available)
+            .count();
+        Ok(accessibility)
+    }
+
+    /// `wheelchair_boarding` of the stop, or of its parent station if the stop has no information
+    fn wheelchair_boarding(&self, stop: &Stop) -> Availability {
+        let parent = stop
+            .parent_station
+            .as_ref()
+            .filter(|_| stop.location_type != LocationType::StopArea)
+            .and_then(|parent| self.stops.get(parent));
+        match (stop.wheelchair_boarding, parent) {
+            (Availability::InformationNotAvailable, Some(parent)) => parent.wheelchair_boarding,
+            (availability, _) => availability,
+        }
+    }
+
+    fn step_free_access(&self, stop: &Stop) -> Option<bool> {
+        let station_id = stop
+            .parent_station
+            .as_deref()
+            .filter(|_| stop.location_type == LocationType::StopPoint)?;
+        let locations: Vec<&Stop> = self
+            .stops
+            .values()
+            .filter(|s| s.parent_station.as_deref() == Some(station_id))
+            .map(|s| &**s)
+            .collect();
+        if locations.iter().all(|s| s.pathways.is_empty()) {
+            return None;
+        }
+        let step_free = PathwayConstraints {
+            avoid_stairs: true,
+            ..Default::default()
+        };
+        Some(
+            locations
+                .iter()
+                .filter(|s| s.location_type == LocationType::StationEntrance)
+                .any(|entrance| {
+                    self.pathway_path(&entrance.id, &stop.id, &step_free)
+                        .is_some()
+                }),
+        )
+    }
+}
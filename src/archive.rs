@@ -0,0 +1,218 @@
+use crate::{objects::*, CompactGtfs, Error};
+use chrono::{Datelike, NaiveDate};
+use rgb::RGB8;
+use rkyv::rancor::{Fallible, Source};
+use rkyv::ser::writer::IoWriter;
+use rkyv::string::{ArchivedString, StringResolver};
+use rkyv::with::{ArchiveWith, DeserializeWith, SerializeWith};
+use rkyv::{Archive, Archived, Place, SerializeUnsized};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// [CompactGtfs] with plain maps and vectors, that can be archived with [rkyv] and then read without being deserialized
+///
+/// Requires the feature `rkyv`. The archived form is [ArchivedGtfs], see [MappedGtfs] to read it from a file
+///
+/// ```
+/// use std::convert::TryFrom;
+/// let gtfs = gtfs_structures::CompactGtfs::from_path("fixtures/basic")?;
+/// let path = std::env::temp_dir().join("gtfs_structures_doc.rkyv");
+/// gtfs_structures::FlatGtfs::try_from(gtfs)?.save(&path)?;
+///
+/// let archived = gtfs_structures::MappedGtfs::open(&path)?;
+/// let trip = archived.trips.get("trip1").unwrap();
+/// let stop = &archived.stops[trip.stop_times[0].stop_index.to_native() as usize];
+/// assert_eq!("stop2", stop.id);
+/// # std::fs::remove_file(&path)?;
+/// # Ok::<(), gtfs_structures::error::Error>(())
+/// ```
+#[derive(Archive, rkyv::Serialize, rkyv::Deserialize, Default)]
+#[rkyv(archived = ArchivedGtfs)]
+pub struct FlatGtfs {
+    /// All Calendar by `service_id`
+    pub calendar: HashMap<String, Calendar>,
+    /// All calendar dates grouped by service_id
+    pub calendar_dates: HashMap<String, Vec<CalendarDate>>,
+    /// All stops, sorted by `stop_id`. They are referenced by their index
+    pub stops: Vec<Stop>,
+    /// Index in [FlatGtfs::stops] by `stop_id`
+    pub stop_indices: HashMap<String, u32>,
+    /// All routes by `route_id`
+    pub routes: HashMap<String, Route>,
+    /// All trips by `trip_id`
+    pub trips: HashMap<String, CompactTrip>,
+    /// All agencies
+    pub agencies: Vec<Agency>,
+    /// All shapes by shape_id
+    pub shapes: HashMap<String, Vec<Shape>>,
+    /// All fare attributes by `fare_id`
+    pub fare_attributes: HashMap<String, FareAttribute>,
+    /// All fare rules by `fare_id`
+    pub fare_rules: HashMap<String, Vec<FareRule>>,
+    /// All feed information
+    pub feed_info: Vec<FeedInfo>,
+}
+
+impl TryFrom<CompactGtfs> for FlatGtfs {
+    type Error = Error;
+
+    /// The shapes read with [crate::GtfsReader::lazy_shapes] are all parsed and kept with the other shapes
+    fn try_from(gtfs: CompactGtfs) -> Result<FlatGtfs, Error> {
+        fn plain<T>(map: crate::IdMap<T>) -> HashMap<String, T> {
+            map.into_iter()
+                .map(|(id, obj)| (id.to_string(), obj))
+                .collect()
+        }
+        let mut shapes = plain(gtfs.shapes);
+        for id in gtfs.lazy_shapes.ids() {
+            if let Some(shape) = gtfs.lazy_shapes.get(id) {
+                shapes.insert(id.to_string(), shape?.clone());
+            }
+        }
+        Ok(FlatGtfs {
+            calendar: plain(gtfs.calendar),
+            calendar_dates: plain(gtfs.calendar_dates),
+            stops: gtfs.stops,
+            stop_indices: plain(gtfs.stop_indices),
+            routes: plain(gtfs.routes),
+            trips: plain(gtfs.trips),
+            agencies: gtfs.agencies,
+            shapes,
+            fare_attributes: plain(gtfs.fare_attributes),
+            fare_rules: plain(gtfs.fare_rules),
+            feed_info: gtfs.feed_info,
+        })
+    }
+}
+
+impl FlatGtfs {
+    /// Archives the GTFS in a file, that can then be opened with [MappedGtfs::open]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = std::fs::File::create(path)?;
+        let writer = rkyv::api::high::to_bytes_in::<_, rkyv::rancor::Error>(
+            self,
+            IoWriter::new(BufWriter::new(file)),
+        )?;
+        writer.into_inner().flush()?;
+        Ok(())
+    }
+}
+
+/// A file written by [FlatGtfs::save], mapped in memory and read as an [ArchivedGtfs]
+///
+/// Requires the feature `rkyv`. Nothing is deserialized, and the processes mapping the same file share its memory.
+/// The file is validated once when it is opened, and must not be modified while it is mapped
+pub struct MappedGtfs {
+    mmap: memmap2::Mmap,
+}
+
+impl MappedGtfs {
+    /// Maps the file in memory and checks that it is a valid [ArchivedGtfs]
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MappedGtfs, Error> {
+        let file = std::fs::File::open(path)?;
+        // Safety: the file must not be modified while it is mapped, as documented
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        rkyv::access::<ArchivedGtfs, rkyv::rancor::Error>(&mmap)?;
+        Ok(MappedGtfs { mmap })
+    }
+}
+
+impl std::ops::Deref for MappedGtfs {
+    type Target = ArchivedGtfs;
+
+    fn deref(&self) -> &ArchivedGtfs {
+        // Safety: the content was validated in MappedGtfs::open
+        unsafe { rkyv::access_unchecked::<ArchivedGtfs>(&self.mmap) }
+    }
+}
+
+/// Archives a [GtfsId] as an [ArchivedString], with or without the feature `compact-ids`
+pub struct IdAsString;
+
+impl ArchiveWith<GtfsId> for IdAsString {
+    type Archived = ArchivedString;
+    type Resolver = StringResolver;
+
+    fn resolve_with(field: &GtfsId, resolver: StringResolver, out: Place<ArchivedString>) {
+        ArchivedString::resolve_from_str(field, resolver, out);
+    }
+}
+
+impl<S: Fallible + ?Sized> SerializeWith<GtfsId, S> for IdAsString
+where
+    S::Error: Source,
+    str: SerializeUnsized<S>,
+{
+    fn serialize_with(field: &GtfsId, serializer: &mut S) -> Result<StringResolver, S::Error> {
+        ArchivedString::serialize_from_str(field, serializer)
+    }
+}
+
+impl<D: Fallible + ?Sized> DeserializeWith<ArchivedString, GtfsId, D> for IdAsString {
+    fn deserialize_with(field: &ArchivedString, _: &mut D) -> Result<GtfsId, D::Error> {
+        Ok(GtfsId::from(field.as_str()))
+    }
+}
+
+/// Archives a [NaiveDate] as its number of days since January 1, 1 CE, see [archived_date]
+pub struct DateAsDays;
+
+impl ArchiveWith<NaiveDate> for DateAsDays {
+    type Archived = Archived<i32>;
+    type Resolver = ();
+
+    fn resolve_with(field: &NaiveDate, _: (), out: Place<Archived<i32>>) {
+        field.num_days_from_ce().resolve((), out);
+    }
+}
+
+impl<S: Fallible + ?Sized> SerializeWith<NaiveDate, S> for DateAsDays {
+    fn serialize_with(_: &NaiveDate, _: &mut S) -> Result<(), S::Error> {
+        Ok(())
+    }
+}
+
+impl<D: Fallible + ?Sized> DeserializeWith<Archived<i32>, NaiveDate, D> for DateAsDays
+where
+    D::Error: Source,
+{
+    fn deserialize_with(field: &Archived<i32>, _: &mut D) -> Result<NaiveDate, D::Error> {
+        archived_date(field).ok_or_else(|| {
+            D::Error::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "invalid archived date",
+            ))
+        })
+    }
+}
+
+/// Date of an archived [NaiveDate], like [ArchivedCalendar::start_date]. None if the number of days is out of range
+pub fn archived_date(days: &Archived<i32>) -> Option<NaiveDate> {
+    NaiveDate::from_num_days_from_ce_opt(days.to_native())
+}
+
+/// Archives a [RGB8] as its `[r, g, b]` components
+pub struct ColorAsArray;
+
+impl ArchiveWith<RGB8> for ColorAsArray {
+    type Archived = [u8; 3];
+    type Resolver = [(); 3];
+
+    fn resolve_with(field: &RGB8, resolver: [(); 3], out: Place<[u8; 3]>) {
+        [field.r, field.g, field.b].resolve(resolver, out);
+    }
+}
+
+impl<S: Fallible + ?Sized> SerializeWith<RGB8, S> for ColorAsArray {
+    fn serialize_with(_: &RGB8, _: &mut S) -> Result<[(); 3], S::Error> {
+        Ok([(); 3])
+    }
+}
+
+impl<D: Fallible + ?Sized> DeserializeWith<[u8; 3], RGB8, D> for ColorAsArray {
+    fn deserialize_with(field: &[u8; 3], _: &mut D) -> Result<RGB8, D::Error> {
+        Ok(RGB8::new(field[0], field[1], field[2]))
+    }
+}
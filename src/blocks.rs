@@ -0,0 +1,197 @@
+use crate::trip_times::trip_span;
+use crate::{objects::*, Error, Gtfs};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+/// Trips run one after the other by the same vehicle, sharing a `block_id`, see [Gtfs::blocks]
+#[derive(Debug, Clone)]
+pub struct Block<'a> {
+    /// `block_id` of the trips
+    pub id: &'a str,
+    /// Trips of the block, sorted by their time at the first stop. The trips without time come last
+    pub trips: Vec<&'a Trip>,
+    /// Pairs of trips of the block running at the same time on a same day, that a single vehicle can not run
+    pub overlaps: Vec<(&'a Trip, &'a Trip)>,
+}
+
+/// Trip that the vehicle of another trip runs next, see [Gtfs::continuations_of]
+#[derive(Debug, Clone)]
+pub struct Continuation<'a> {
+    /// The next trip of the vehicle
+    pub trip: &'a Trip,
+    /// Route of the next trip. None if its `route_id` does not exist
+    pub route: Option<&'a Route>,
+    /// Time in seconds between the arrival of the trip and the departure of the next one
+    pub layover: u32,
+    /// True if the next trip starts at the last stop of the trip, or at another stop of its station,
+    /// so the riders can stay on board
+    pub same_stop: bool,
+}
+
+impl Gtfs {
+    /// Lists the trips the vehicle of a trip continues onto, like "continues as line 12 toward X"
+    ///
+    /// The next trip shares the `block_id` of the trip, and is the first one starting after its arrival on a day both run.
+    /// A trip has several continuations when they depend on the day. They are sorted by departure time, then by `trip_id`.
+    /// A trip with [Frequency] lasts from the departure of its first vehicle to the arrival of its last one
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// for trip_id in ["tripA1", "tripA2"] {
+    ///     gtfs.trips.get_mut(trip_id).unwrap().block_id = Some("block1".into());
+    /// }
+    /// // tripA1 arrives at stop1 at 08:10:00, and tripA2 leaves stop1 at 09:00:00
+    /// let continuations = gtfs.continuations_of("tripA1")?;
+    /// assert_eq!("tripA2", continuations[0].trip.id);
+    /// assert_eq!(50 * 60, continuations[0].layover);
+    /// assert!(continuations[0].same_stop);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn continuations_of(&self, trip_id: &str) -> Result<Vec<Continuation<'_>>, Error> {
+        let trip = self.get_trip(trip_id)?;
+        let (block_id, end) = match (&trip.block_id, trip_span(trip)) {
+            (Some(block_id), Some((_, end))) => (block_id, end),
+            _ => return Ok(Vec::new()),
+        };
+        let mut candidates: Vec<(u32, &Trip)> = self
+            .trips
+            .values()
+            .filter(|t| t.block_id.as_ref() == Some(block_id) && t.id != trip.id)
+            .filter_map(|t| Some((trip_span(t)?.0, t)))
+            .filter(|(start, _)| *start >= end)
+            .collect();
+        candidates.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.id.cmp(&b.1.id)));
+
+        let mut next_trips: Vec<(u32, &Trip)> = Vec::new();
+        if let Some((first, last)) = self.service_range(&trip.service_id) {
+            for day in first.iter_days().take_while(|day| *day <= last) {
+                if !self.is_service_active(&trip.service_id, day) {
+                    continue;
+                }
+                let next = candidates
+                    .iter()
+                    .find(|(_, t)| self.is_service_active(&t.service_id, day));
+                if let Some(next) = next {
+                    if !next_trips.iter().any(|(_, t)| t.id == next.1.id) {
+                        next_trips.push(*next);
+                    }
+                }
+            }
+        }
+        next_trips.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.id.cmp(&b.1.id)));
+
+        let last_stop = trip.stop_times.last().map(|st| station_id(&st.stop));
+        Ok(next_trips
+            .into_iter()
+            .map(|(start, next)| Continuation {
+                trip: next,
+                route: self.routes.get(&next.route_id),
+                layover: start - end,
+                same_stop: next.stop_times.first().map(|st| station_id(&st.stop)) == last_stop,
+            })
+            .collect())
+    }
+
+    /// Groups the trips by `block_id`, sorted by `block_id`
+    ///
+    /// A `block_id` can have trips on different service days. Two trips of a block only overlap
+    /// if their times intersect and their services have at least one day in common.
+    /// A trip can start at the time the previous one ends.
+    /// A trip with [Frequency] lasts from the departure of its first vehicle to the arrival of its last one
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// for trip_id in ["tripA3", "tripA1"] {
+    ///     gtfs.trips.get_mut(trip_id).unwrap().block_id = Some("block1".into());
+    /// }
+    /// let blocks = gtfs.blocks();
+    /// let trips: Vec<_> = blocks[0].trips.iter().map(|t| t.id.as_str()).collect();
+    /// assert_eq!(vec!["tripA1", "tripA3"], trips);
+    /// assert!(blocks[0].overlaps.is_empty());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn blocks(&self) -> Vec<Block<'_>> {
+        let mut trips_by_block: HashMap<&str, Vec<&Trip>> = HashMap::new();
+        for trip in self.trips.values() {
+            if let Some(block_id) = &trip.block_id {
+                trips_by_block.entry(block_id).or_default().push(trip);
+            }
+        }
+        let mut blocks: Vec<_> = trips_by_block
+            .into_iter()
+            .map(|(id, mut trips)| {
+                trips.sort_by(|a, b| {
+                    let start = |trip: &Trip| trip_span(trip).map(|(start, _)| start);
+                    match (start(a), start(b)) {
+                        (Some(a_start), Some(b_start)) => a_start.cmp(&b_start),
+                        (a_start, b_start) => b_start.cmp(&a_start),
+                    }
+                    .then_with(|| a.id.cmp(&b.id))
+                });
+                let mut overlaps = Vec::new();
+                for (i, a) in trips.iter().enumerate() {
+                    for b in &trips[i + 1..] {
+                        if let (Some((a_start, a_end)), Some((b_start, b_end))) =
+                            (trip_span(a), trip_span(b))
+                        {
+                            if a_start < b_end
+                                && b_start < a_end
+                                && self.services_share_day(&a.service_id, &b.service_id)
+                            {
+                                overlaps.push((*a, *b));
+                            }
+                        }
+                    }
+                }
+                Block {
+                    id,
+                    trips,
+                    overlaps,
+                }
+            })
+            .collect();
+        blocks.sort_by_key(|b| b.id);
+        blocks
+    }
+
+    /// True if both services run on at least one common day
+    fn services_share_day(&self, a: &str, b: &str) -> bool {
+        if a == b {
+            return true;
+        }
+        match (self.service_range(a), self.service_range(b)) {
+            (Some((a_start, a_end)), Some((b_start, b_end))) => a_start
+                .max(b_start)
+                .iter_days()
+                .take_while(|day| *day <= a_end.min(b_end))
+                .any(|day| self.is_service_active(a, day) && self.is_service_active(b, day)),
+            _ => false,
+        }
+    }
+
+    /// First and last days a service can run, from its [Calendar] and added [CalendarDate]
+    fn service_range(&self, service_id: &str) -> Option<(NaiveDate, NaiveDate)> {
+        let calendar = self
+            .calendar
+            .get(service_id)
+            .into_iter()
+            .flat_map(|c| [c.start_date, c.end_date]);
+        let added_dates = self
+            .calendar_dates
+            .get(service_id)
+            .into_iter()
+            .flatten()
+            .filter(|d| d.exception_type == Exception::Added)
+            .map(|d| d.date);
+        let mut dates = calendar.chain(added_dates);
+        let first = dates.next()?;
+        Some(dates.fold((first, first), |(start, end), date| {
+            (start.min(date), end.max(date))
+        }))
+    }
+}
+
+/// `stop_id` of the parent station of the stop, or of the stop itself if it has no parent station
+fn station_id(stop: &Stop) -> &str {
+    stop.parent_station.as_deref().unwrap_or(&stop.id)
+}
@@ -0,0 +1,314 @@
+use crate::gtfs::{to_route_trips, to_stop_trips};
+use crate::gtfs_writer::raw_trip;
+use crate::id_map::IdHashMap;
+use crate::serde_helpers::{deserialize_date, serialize_date};
+use crate::{objects::*, Error, Gtfs, IdMap, ParseMetrics};
+use bincode::{BincodeRead, Options};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Written at the start of a cache, to reject the files that are not a cache
+const MAGIC: &[u8] = b"GTFSRSCACHE";
+/// Incremented when the layout of the cache changes
+const FORMAT_VERSION: u32 = 2;
+
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// Layout of a [Calendar] in the cache
+///
+/// The csv representation of the days (written as numbers, read as text) cannot be read back by bincode
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Calendar")]
+struct CachedCalendar {
+    id: GtfsId,
+    monday: bool,
+    tuesday: bool,
+    wednesday: bool,
+    thursday: bool,
+    friday: bool,
+    saturday: bool,
+    sunday: bool,
+    #[serde(
+        deserialize_with = "deserialize_date",
+        serialize_with = "serialize_date"
+    )]
+    start_date: NaiveDate,
+    #[serde(
+        deserialize_with = "deserialize_date",
+        serialize_with = "serialize_date"
+    )]
+    end_date: NaiveDate,
+}
+
+#[derive(Serialize)]
+struct CalendarRef<'a>(#[serde(with = "CachedCalendar")] &'a Calendar);
+
+#[derive(Deserialize)]
+struct CalendarOwned(#[serde(with = "CachedCalendar")] Calendar);
+
+/// Layout of a [Stop] in the cache, with its transfers and pathways
+///
+/// In the csv files, an empty `wheelchair_boarding` is read as an [Option], but it is not written as one
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Stop")]
+struct CachedStop {
+    id: GtfsId,
+    code: Option<Box<str>>,
+    name: Option<String>,
+    description: Option<Box<str>>,
+    location_type: LocationType,
+    parent_station: Option<GtfsId>,
+    zone_id: Option<GtfsId>,
+    url: Option<Box<str>>,
+    longitude: Option<f64>,
+    latitude: Option<f64>,
+    timezone: Option<Box<str>>,
+    wheelchair_boarding: Availability,
+    level_id: Option<GtfsId>,
+    platform_code: Option<Box<str>>,
+    transfers: Vec<StopTransfer>,
+    pathways: Vec<Pathway>,
+    tts_name: Option<Box<str>>,
+}
+
+#[derive(Serialize)]
+struct StopRef<'a>(#[serde(with = "CachedStop")] &'a Stop);
+
+#[derive(Deserialize)]
+struct StopOwned(#[serde(with = "CachedStop")] Stop);
+
+impl Gtfs {
+    /// Saves the GTFS in a binary file, that [Gtfs::load_cache] reads much faster than the csv files
+    ///
+    /// Requires the feature `cache`.
+    /// The cache can only be read by the same version of this crate.
+    /// The shapes read with [crate::GtfsReader::lazy_shapes] are all parsed and saved with the other shapes
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let path = std::env::temp_dir().join("gtfs_structures_doc.cache");
+    /// gtfs.save_cache(&path)?;
+    /// let cached = gtfs_structures::Gtfs::load_cache(&path)?;
+    /// assert_eq!(gtfs.trips.len(), cached.trips.len());
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn save_cache<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        let mut ser = bincode::Serializer::new(&mut writer, options());
+        (FORMAT_VERSION, env!("CARGO_PKG_VERSION")).serialize(&mut ser)?;
+
+        write_section(
+            &mut ser,
+            self.calendar.len(),
+            self.calendar.values().map(CalendarRef),
+        )?;
+        write_section(
+            &mut ser,
+            self.calendar_dates.len(),
+            self.calendar_dates.iter(),
+        )?;
+
+        // The stop times reference their stop by its position in this section
+        let mut stop_indices = IdHashMap::default();
+        stop_indices.reserve(self.stops.len());
+        for (index, id) in self.stops.keys().enumerate() {
+            stop_indices.insert(id.clone(), index as u32);
+        }
+        write_section(
+            &mut ser,
+            self.stops.len(),
+            self.stops.values().map(|stop| StopRef(stop)),
+        )?;
+
+        write_section(&mut ser, self.routes.len(), self.routes.values())?;
+        write_section(
+            &mut ser,
+            self.trips.len(),
+            self.trips.values().map(|trip| {
+                let stop_times: Vec<_> = trip
+                    .stop_times
+                    .iter()
+                    .map(|st| {
+                        CompactStopTime::from_stop_time(st.clone(), stop_indices[&st.stop.id])
+                    })
+                    .collect();
+                (raw_trip(trip), &trip.frequencies, stop_times)
+            }),
+        )?;
+        write_section(&mut ser, self.agencies.len(), &self.agencies)?;
+
+        let lazy_shapes = self
+            .lazy_shapes
+            .ids()
+            .filter_map(|id| self.lazy_shapes.get(id).map(|shape| shape.map(|s| (id, s))))
+            .collect::<Result<Vec<_>, Error>>()?;
+        write_section(
+            &mut ser,
+            self.shapes.len() + lazy_shapes.len(),
+            self.shapes.iter().chain(lazy_shapes),
+        )?;
+
+        write_section(
+            &mut ser,
+            self.fare_attributes.len(),
+            self.fare_attributes.values(),
+        )?;
+        write_section(&mut ser, self.fare_rules.len(), self.fare_rules.iter())?;
+        write_section(&mut ser, self.feed_info.len(), &self.feed_info)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Loads a GTFS saved by [Gtfs::save_cache]
+    ///
+    /// Requires the feature `cache`.
+    /// [Gtfs::metrics] only has the time needed to load the cache
+    pub fn load_cache<P: AsRef<Path>>(path: P) -> Result<Gtfs, Error> {
+        let start = Instant::now();
+        let bytes = std::fs::read(path)?;
+        let content = bytes.strip_prefix(MAGIC).ok_or(Error::InvalidCache)?;
+        let mut de = bincode::Deserializer::from_slice(content, options());
+        let version: (u32, &str) = Deserialize::deserialize(&mut de)?;
+        if version != (FORMAT_VERSION, env!("CARGO_PKG_VERSION")) {
+            return Err(Error::InvalidCache);
+        }
+
+        let calendar = read_section(&mut de)?
+            .into_iter()
+            .map(|CalendarOwned(c)| (c.id.clone(), c))
+            .collect();
+        let calendar_dates = read_section(&mut de)?.into_iter().collect();
+
+        let stops: Vec<Arc<Stop>> = read_section(&mut de)?
+            .into_iter()
+            .map(|StopOwned(stop)| Arc::new(stop))
+            .collect();
+
+        let routes = read_section(&mut de)?
+            .into_iter()
+            .map(|r: Route| (r.id.clone(), r))
+            .collect();
+        let trips = read_section(&mut de)?
+            .into_iter()
+            .map(|(raw, frequencies, stop_times)| {
+                let trip = trip(raw, frequencies, stop_times, &stops)?;
+                Ok((trip.id.clone(), trip))
+            })
+            .collect::<Result<IdMap<_>, Error>>()?;
+        let agencies = read_section(&mut de)?;
+        let shapes = read_section(&mut de)?.into_iter().collect();
+        let fare_attributes = read_section(&mut de)?
+            .into_iter()
+            .map(|f: FareAttribute| (f.id.clone(), f))
+            .collect();
+        let fare_rules = read_section(&mut de)?.into_iter().collect();
+        let feed_info = read_section(&mut de)?;
+
+        let stops: IdMap<_> = stops.into_iter().map(|s| (s.id.clone(), s)).collect();
+
+        Ok(Gtfs {
+            metrics: ParseMetrics {
+                total_duration: start.elapsed(),
+                ..ParseMetrics::default()
+            },
+            calendar,
+            calendar_dates,
+            routes,
+            route_trips: to_route_trips(&trips),
+            stop_trips: to_stop_trips(&trips, &stops),
+            #[cfg(feature = "spatial")]
+            stop_tree: crate::spatial::to_stop_tree(&stops),
+            stops,
+            trips,
+            agencies,
+            shapes,
+            fare_attributes,
+            fare_rules,
+            feed_info,
+            ..Gtfs::default()
+        })
+    }
+}
+
+/// Writes the number of items, followed by each item
+fn write_section<W, O, T, I>(
+    ser: &mut bincode::Serializer<W, O>,
+    len: usize,
+    items: I,
+) -> Result<(), Error>
+where
+    W: Write,
+    O: Options,
+    T: Serialize,
+    I: IntoIterator<Item = T>,
+{
+    len.serialize(&mut *ser)?;
+    for item in items {
+        item.serialize(&mut *ser)?;
+    }
+    Ok(())
+}
+
+fn read_section<'de, R, O, T>(de: &mut bincode::Deserializer<R, O>) -> Result<Vec<T>, Error>
+where
+    R: BincodeRead<'de>,
+    O: Options,
+    T: Deserialize<'de>,
+{
+    let len = usize::deserialize(&mut *de)?;
+    (0..len)
+        .map(|_| T::deserialize(&mut *de).map_err(Error::from))
+        .collect()
+}
+
+fn trip(
+    raw: RawTrip,
+    frequencies: Vec<Frequency>,
+    stop_times: Vec<CompactStopTime>,
+    stops: &[Arc<Stop>],
+) -> Result<Trip, Error> {
+    let stop_times = stop_times
+        .into_iter()
+        .map(|st| {
+            let stop = stops
+                .get(st.stop_index as usize)
+                .ok_or(Error::InvalidCache)?;
+            Ok(StopTime {
+                arrival_time: st.arrival_time,
+                stop: stop.clone(),
+                departure_time: st.departure_time,
+                pickup_type: st.pickup_type,
+                drop_off_type: st.drop_off_type,
+                stop_sequence: st.stop_sequence,
+                stop_headsign: st.stop_headsign,
+                continuous_pickup: st.continuous_pickup,
+                continuous_drop_off: st.continuous_drop_off,
+                shape_dist_traveled: st.shape_dist_traveled,
+                timepoint: st.timepoint,
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+    Ok(Trip {
+        id: raw.id,
+        service_id: raw.service_id,
+        route_id: raw.route_id,
+        stop_times,
+        shape_id: raw.shape_id,
+        trip_headsign: raw.trip_headsign,
+        trip_short_name: raw.trip_short_name,
+        direction_id: raw.direction_id,
+        block_id: raw.block_id,
+        wheelchair_accessible: raw.wheelchair_accessible,
+        bikes_allowed: raw.bikes_allowed,
+        frequencies,
+    })
+}
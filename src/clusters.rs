@@ -0,0 +1,197 @@
+use crate::{objects::*, Gtfs};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Parameters of the clustering by [Gtfs::cluster_stops]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClusterParams {
+    /// Longest distance in meters between two stops of a cluster that are grouped directly
+    pub max_distance: f64,
+    /// Only groups the stops with the same normalized name, see [normalize_name]
+    pub same_name: bool,
+}
+
+impl Default for ClusterParams {
+    fn default() -> Self {
+        Self {
+            max_distance: 100.,
+            same_name: true,
+        }
+    }
+}
+
+/// Stops close to each other that can be consolidated, found by [Gtfs::cluster_stops]
+#[derive(Debug, Clone)]
+pub struct StopCluster<'a> {
+    /// Identifier of the cluster, the smallest `stop_id` of its stops
+    pub id: String,
+    /// Stops of the cluster, sorted by `stop_id`
+    pub stops: Vec<&'a Arc<Stop>>,
+    /// Most common name of the stops, the smallest one in case of a tie
+    pub name: Option<String>,
+    /// Average latitude of the stops
+    pub latitude: f64,
+    /// Average longitude of the stops
+    pub longitude: f64,
+}
+
+impl StopCluster<'_> {
+    /// Proposes a station grouping the stops of the cluster, at its average position
+    pub fn to_station(&self, station_id: &str) -> Stop {
+        Stop {
+            id: station_id.into(),
+            name: self.name.clone(),
+            location_type: LocationType::StopArea,
+            latitude: Some(self.latitude),
+            longitude: Some(self.longitude),
+            ..Default::default()
+        }
+    }
+}
+
+/// The clusters of stops found by [Gtfs::cluster_stops]
+#[derive(Debug, Clone, Default)]
+pub struct StopClusters<'a> {
+    /// Clusters, sorted by identifier
+    pub clusters: Vec<StopCluster<'a>>,
+    stop_clusters: HashMap<&'a str, usize>,
+}
+
+impl<'a> StopClusters<'a> {
+    /// The cluster of a stop. None if the stop is not a stop (`location_type` 0) with coordinates
+    pub fn cluster_of(&self, stop_id: &str) -> Option<&StopCluster<'a>> {
+        self.stop_clusters
+            .get(stop_id)
+            .map(|index| &self.clusters[*index])
+    }
+
+    /// Maps the `stop_id` of each stop to the identifier of its cluster
+    pub fn mapping(&self) -> HashMap<&'a str, &str> {
+        self.stop_clusters
+            .iter()
+            .map(|(stop_id, index)| (*stop_id, self.clusters[*index].id.as_str()))
+            .collect()
+    }
+}
+
+/// Lowercase alphanumeric words of a stop name, separated by a space: `Gare de l'Est` becomes `gare de l est`
+pub fn normalize_name(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Gtfs {
+    /// Groups the stops (`location_type` 0) close to each other, like the copies of a stop in a feed aggregating several operators
+    ///
+    /// Two stops at most [ClusterParams::max_distance] apart are in the same cluster, if they have the same normalized name
+    /// with [ClusterParams::same_name]. The grouping is transitive, so a cluster can be wider than the distance.
+    /// A stop without close stops is alone in its cluster, and the stops without coordinates are ignored
+    ///
+    /// ```
+    /// use gtfs_structures::ClusterParams;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let params = ClusterParams { same_name: false, ..Default::default() };
+    /// let clusters = gtfs.cluster_stops(&params);
+    /// // platform1 and platform2 have the same position
+    /// assert_eq!("platform1", clusters.cluster_of("platform2").unwrap().id);
+    /// assert_eq!(3, clusters.clusters.len());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn cluster_stops(&self, params: &ClusterParams) -> StopClusters<'_> {
+        let mut stops: Vec<&Arc<Stop>> = self
+            .stops
+            .values()
+            .filter(|stop| stop.location_type == LocationType::StopPoint)
+            .filter(|stop| stop.latitude.is_some() && stop.longitude.is_some())
+            .collect();
+        stops.sort_by(|a, b| a.id.cmp(&b.id));
+        let indexes: HashMap<&str, usize> = stops
+            .iter()
+            .enumerate()
+            .map(|(i, stop)| (&*stop.id, i))
+            .collect();
+        let names: Vec<Option<String>> = stops
+            .iter()
+            .map(|stop| stop.name.as_deref().map(normalize_name))
+            .collect();
+
+        let mut parents: Vec<usize> = (0..stops.len()).collect();
+        for (i, stop) in stops.iter().enumerate() {
+            let (latitude, longitude) = match (stop.latitude, stop.longitude) {
+                (Some(latitude), Some(longitude)) => (latitude, longitude),
+                _ => continue,
+            };
+            let close = self.stops_within(
+                latitude,
+                longitude,
+                params.max_distance,
+                Some(LocationType::StopPoint),
+            );
+            for (other, _) in close {
+                let j = match indexes.get(&*other.id) {
+                    Some(j) => *j,
+                    None => continue,
+                };
+                if !params.same_name || (names[i].is_some() && names[i] == names[j]) {
+                    let (root_i, root_j) = (root(&mut parents, i), root(&mut parents, j));
+                    parents[root_i.max(root_j)] = root_i.min(root_j);
+                }
+            }
+        }
+
+        // The root of a cluster is its stop with the smallest `stop_id`, so the clusters are built sorted
+        let mut clusters: Vec<StopCluster> = Vec::new();
+        let mut root_clusters = HashMap::new();
+        let mut stop_clusters = HashMap::new();
+        for (i, stop) in stops.iter().enumerate() {
+            let index = *root_clusters
+                .entry(root(&mut parents, i))
+                .or_insert_with(|| {
+                    clusters.push(StopCluster {
+                        id: stop.id.to_string(),
+                        stops: Vec::new(),
+                        name: None,
+                        latitude: 0.,
+                        longitude: 0.,
+                    });
+                    clusters.len() - 1
+                });
+            clusters[index].stops.push(stop);
+            stop_clusters.insert(&*stop.id, index);
+        }
+        for cluster in &mut clusters {
+            let count = cluster.stops.len() as f64;
+            cluster.latitude = cluster.stops.iter().filter_map(|s| s.latitude).sum::<f64>() / count;
+            cluster.longitude = cluster
+                .stops
+                .iter()
+                .filter_map(|s| s.longitude)
+                .sum::<f64>()
+                / count;
+            let mut names: HashMap<&str, usize> = HashMap::new();
+            for name in cluster.stops.iter().filter_map(|s| s.name.as_deref()) {
+                *names.entry(name).or_default() += 1;
+            }
+            cluster.name = names
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(name, _)| name.to_owned());
+        }
+        StopClusters {
+            clusters,
+            stop_clusters,
+        }
+    }
+}
+
+/// Root of the set of an element in a union-find forest, halving the path on the way
+fn root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
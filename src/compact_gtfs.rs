@@ -0,0 +1,255 @@
+use crate::error::{BrokenReference, ValidationReport};
+use crate::gtfs::BuildCheck;
+use crate::id_map::IdHashMap;
+use crate::{
+    objects::*, DuplicatePolicy, Error, Gtfs, GtfsReader, IdMap, LazyShapes, ParseMetrics, RawGtfs,
+};
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// Alternative layout of [Gtfs] that uses less memory for the large feeds
+///
+/// The [CompactStopTime] reference their [Stop] by a `u32` index in [CompactGtfs::stops]
+/// instead of an [Arc], so there is no reference count to update for each of the millions of stop times
+///
+/// ```
+/// let gtfs = gtfs_structures::CompactGtfs::from_path("fixtures/basic")?;
+/// let trip = gtfs.get_trip("trip1")?;
+/// assert_eq!("stop2", gtfs.stop_of(&trip.stop_times[0]).id);
+/// # Ok::<(), gtfs_structures::error::Error>(())
+/// ```
+#[derive(Default)]
+pub struct CompactGtfs {
+    /// Time needed to read and parse the archive, and of each of its files
+    pub metrics: ParseMetrics,
+    /// Rows dropped because of a broken reference, a duplicated identifier or a duplicated stop time, and stops with invalid coordinates, see [ValidationReport]
+    pub validation_report: ValidationReport,
+    /// All Calendar by `service_id`
+    pub calendar: IdMap<Calendar>,
+    /// All calendar dates grouped by service_id
+    pub calendar_dates: IdMap<Vec<CalendarDate>>,
+    /// All stops, sorted by `stop_id`. They are referenced by their index
+    pub stops: Vec<Stop>,
+    /// Index in [CompactGtfs::stops] by `stop_id`
+    pub stop_indices: IdMap<u32>,
+    /// All routes by `route_id`
+    pub routes: IdMap<Route>,
+    /// All trips by `trip_id`
+    pub trips: IdMap<CompactTrip>,
+    /// All agencies. They can not be read by `agency_id`, as it is not a required field
+    pub agencies: Vec<Agency>,
+    /// All shapes by shape_id
+    pub shapes: IdMap<Vec<Shape>>,
+    /// Shapes parsed only when requested, if read with [crate::GtfsReader::lazy_shapes]. [CompactGtfs::shapes] is then empty
+    pub lazy_shapes: LazyShapes,
+    /// All fare attributes by `fare_id`
+    pub fare_attributes: IdMap<FareAttribute>,
+    /// All fare rules by `fare_id`
+    pub fare_rules: IdMap<Vec<FareRule>>,
+    /// All feed information. There is no identifier
+    pub feed_info: Vec<FeedInfo>,
+}
+
+impl TryFrom<RawGtfs> for CompactGtfs {
+    type Error = Error;
+    /// Tries to build a [CompactGtfs] from a [RawGtfs]
+    ///
+    /// It might fail if some mandatory files couldn’t be read or if there are references to other objects that are invalid.
+    fn try_from(raw: RawGtfs) -> Result<CompactGtfs, Error> {
+        CompactGtfs::build(raw, false, DuplicatePolicy::default())
+    }
+}
+
+impl From<Gtfs> for CompactGtfs {
+    fn from(gtfs: Gtfs) -> CompactGtfs {
+        let mut stop_ids: Vec<_> = gtfs.stops.keys().cloned().collect();
+        stop_ids.sort();
+        let stop_indices: IdMap<_> = stop_ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (id.clone(), index as u32))
+            .collect();
+
+        let trips = gtfs
+            .trips
+            .into_iter()
+            .map(|(id, trip)| {
+                let stop_times = trip
+                    .stop_times
+                    .into_iter()
+                    .map(|st| {
+                        let index = stop_indices[&st.stop.id];
+                        CompactStopTime::from_stop_time(st, index)
+                    })
+                    .collect();
+                let trip = CompactTrip {
+                    id: trip.id,
+                    service_id: trip.service_id,
+                    route_id: trip.route_id,
+                    stop_times,
+                    shape_id: trip.shape_id,
+                    trip_headsign: trip.trip_headsign,
+                    trip_short_name: trip.trip_short_name,
+                    direction_id: trip.direction_id,
+                    block_id: trip.block_id,
+                    wheelchair_accessible: trip.wheelchair_accessible,
+                    bikes_allowed: trip.bikes_allowed,
+                    frequencies: trip.frequencies,
+                };
+                (id, trip)
+            })
+            .collect();
+
+        // Once the stop times are converted, the stops are no longer shared
+        let mut stops: IdHashMap<_> = gtfs.stops.into_iter().collect();
+        let stops = stop_ids
+            .iter()
+            .filter_map(|id| stops.remove(id))
+            .map(|stop| Arc::try_unwrap(stop).unwrap_or_else(|stop| (*stop).clone()))
+            .collect();
+
+        CompactGtfs {
+            metrics: gtfs.metrics,
+            validation_report: gtfs.validation_report,
+            calendar: gtfs.calendar,
+            calendar_dates: gtfs.calendar_dates,
+            stops,
+            stop_indices,
+            routes: gtfs.routes,
+            trips,
+            agencies: gtfs.agencies,
+            shapes: gtfs.shapes,
+            lazy_shapes: gtfs.lazy_shapes,
+            fare_attributes: gtfs.fare_attributes,
+            fare_rules: gtfs.fare_rules,
+            feed_info: gtfs.feed_info,
+        }
+    }
+}
+
+impl CompactGtfs {
+    /// Builds a [CompactGtfs] from a [RawGtfs]. The broken references are errors unless `skip_broken_references` is set,
+    /// the duplicated identifiers are handled according to `duplicate_policy`
+    pub(crate) fn build(
+        mut raw: RawGtfs,
+        skip_broken_references: bool,
+        duplicate_policy: DuplicatePolicy,
+    ) -> Result<CompactGtfs, Error> {
+        let raw_stop_times = std::mem::replace(&mut raw.stop_times, Ok(Vec::new()))?;
+        let mut gtfs =
+            CompactGtfs::from(Gtfs::build(raw, skip_broken_references, duplicate_policy)?);
+        for stop_time in raw_stop_times {
+            gtfs.add_stop_time(stop_time, skip_broken_references)?;
+        }
+        gtfs.sort_stop_times();
+        Ok(gtfs)
+    }
+
+    /// Reads from an url (if starts with `"http"`), or a local path (either a directory or zipped file)
+    ///
+    /// To read from an url, build with read-url feature
+    pub fn new(gtfs: &str) -> Result<CompactGtfs, Error> {
+        GtfsReader::default().read_compact(gtfs)
+    }
+
+    /// Reads the GTFS from a local zip archive or local directory
+    pub fn from_path<P>(path: P) -> Result<CompactGtfs, Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        GtfsReader::default().read_compact_from_path(path)
+    }
+
+    /// Links a stop time to its trip and stop, when the stop times are parsed after the rest of the GTFS
+    ///
+    /// [CompactGtfs::sort_stop_times] must be called once all the stop times are added
+    pub(crate) fn add_stop_time(
+        &mut self,
+        s: RawStopTime,
+        skip_broken_references: bool,
+    ) -> Result<(), Error> {
+        let mut check = BuildCheck::new(skip_broken_references, DuplicatePolicy::default());
+        let (field_name, value) = match (
+            self.trips.get_mut(&s.trip_id),
+            self.stop_indices.get(&s.stop_id),
+        ) {
+            (Some(trip), Some(index)) => {
+                trip.stop_times.push(CompactStopTime::from(s, *index));
+                return Ok(());
+            }
+            (None, _) => ("trip_id", s.trip_id.to_string()),
+            (Some(_), None) => ("stop_id", s.stop_id.to_string()),
+        };
+        check.broken(
+            BrokenReference {
+                table: TableName::StopTimes,
+                object_id: s.trip_id.to_string(),
+                field_name,
+                value: value.clone(),
+            },
+            || Error::ReferenceError(value),
+        )?;
+        self.validation_report
+            .broken_references
+            .extend(check.report.broken_references);
+        Ok(())
+    }
+
+    /// Stores all the objects in vectors sorted by identifier, see [IdMap]
+    pub fn sort_by_id(&mut self) {
+        self.calendar.sort_by_id();
+        self.calendar_dates.sort_by_id();
+        self.stop_indices.sort_by_id();
+        self.routes.sort_by_id();
+        self.trips.sort_by_id();
+        self.shapes.sort_by_id();
+        self.fare_attributes.sort_by_id();
+        self.fare_rules.sort_by_id();
+    }
+
+    pub(crate) fn sort_stop_times(&mut self) {
+        for trip in self.trips.values_mut() {
+            trip.stop_times.sort_by_key(|a| a.stop_sequence);
+        }
+    }
+
+    /// Gets the [Stop] of a [CompactStopTime]
+    pub fn stop_of(&self, stop_time: &CompactStopTime) -> &Stop {
+        &self.stops[stop_time.stop_index as usize]
+    }
+
+    /// Gets a [Stop] by its `stop_id`
+    pub fn get_stop<'a>(&'a self, id: &str) -> Result<&'a Stop, Error> {
+        self.stop_indices
+            .get(id)
+            .map(|index| &self.stops[*index as usize])
+            .ok_or_else(|| Error::ReferenceError(id.to_owned()))
+    }
+
+    /// Gets a [CompactTrip] by its `trip_id`
+    pub fn get_trip<'a>(&'a self, id: &str) -> Result<&'a CompactTrip, Error> {
+        self.trips
+            .get(id)
+            .ok_or_else(|| Error::ReferenceError(id.to_owned()))
+    }
+
+    /// Gets all [Shape] points of a `shape_id`
+    ///
+    /// With [crate::GtfsReader::lazy_shapes], the shape is parsed at the first call
+    pub fn get_shape<'a>(&'a self, id: &str) -> Result<&'a Vec<Shape>, Error> {
+        match self.lazy_shapes.get(id) {
+            Some(shape) => shape,
+            None => self
+                .shapes
+                .get(id)
+                .ok_or_else(|| Error::ReferenceError(id.to_owned())),
+        }
+    }
+
+    /// Gets a [Route] by its `route_id`
+    pub fn get_route<'a>(&'a self, id: &str) -> Result<&'a Route, Error> {
+        self.routes
+            .get(id)
+            .ok_or_else(|| Error::ReferenceError(id.to_owned()))
+    }
+}
@@ -0,0 +1,192 @@
+use crate::gtfs::MAX_STOP_DEPTH;
+use crate::trip_times::departure_times;
+use crate::{objects::*, Gtfs};
+use chrono::NaiveDate;
+
+/// Trip serving a stop and then another one, without a transfer, see [Gtfs::trips_between]
+#[derive(Debug, Clone)]
+pub struct DirectTrip<'a> {
+    /// The trip
+    pub trip: &'a Trip,
+    /// Stop time of the trip where the ride starts
+    pub from: &'a StopTime,
+    /// Stop time of the trip where the ride ends
+    pub to: &'a StopTime,
+}
+
+/// Ride of a vehicle from a stop to the next one, see [Gtfs::connections]
+#[derive(Debug, Clone)]
+pub struct Connection<'a> {
+    /// Stop time where the vehicle leaves, with the stop and whether passengers can board
+    pub departure: &'a StopTime,
+    /// Time the vehicle leaves, in seconds since the midnight of the day of the connections
+    pub departure_time: u32,
+    /// Stop time where the vehicle arrives, with the stop and whether passengers can alight
+    pub arrival: &'a StopTime,
+    /// Time the vehicle arrives, in seconds since the midnight of the day of the connections
+    pub arrival_time: u32,
+    /// Trip of the vehicle
+    pub trip: &'a Trip,
+    /// Date of the service day the trip runs on. It is the previous day for a trip running past midnight
+    pub service_date: NaiveDate,
+}
+
+impl DirectTrip<'_> {
+    /// Duration of the ride in seconds, from the departure at the first stop to the arrival at the second one.
+    /// None if a time is missing
+    pub fn duration(&self) -> Option<u32> {
+        let departure = self.from.departure_time.or(self.from.arrival_time)?;
+        let arrival = self.to.arrival_time.or(self.to.departure_time)?;
+        arrival.checked_sub(departure)
+    }
+}
+
+impl Gtfs {
+    /// Finds the trips calling at a stop and then at another one, with the stop times of the ride.
+    /// With a `date`, only the trips running on that day are kept
+    ///
+    /// Like in [Gtfs::routes_at_stop], a station is served by the trips calling at its platforms.
+    /// When a trip serves the stops several times, the shortest ride is kept.
+    /// The trips are sorted by time at the first stop, then by `trip_id`. Their [Frequency] are ignored
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let trips = gtfs.trips_between("station1", "stop1", None);
+    /// let trip_ids: Vec<_> = trips.iter().map(|t| &t.trip.id).collect();
+    /// assert_eq!(vec!["tripA1", "tripA3"], trip_ids);
+    /// assert_eq!(Some(600), trips[0].duration());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn trips_between(
+        &self,
+        from_stop_id: &str,
+        to_stop_id: &str,
+        date: Option<NaiveDate>,
+    ) -> Vec<DirectTrip<'_>> {
+        let to_trips = match self.stop_trips.get(to_stop_id) {
+            Some(trip_ids) => trip_ids,
+            None => return Vec::new(),
+        };
+        let mut trips: Vec<DirectTrip> = self
+            .stop_trips
+            .get(from_stop_id)
+            .into_iter()
+            .flatten()
+            .filter(|id| to_trips.binary_search(id).is_ok())
+            .filter_map(|id| self.trips.get(id))
+            .filter(|trip| date.is_none_or(|d| self.is_service_active(&trip.service_id, d)))
+            .filter_map(|trip| {
+                let mut from = None;
+                for stop_time in &trip.stop_times {
+                    if let Some(from) = from.filter(|_| self.serves(&stop_time.stop, to_stop_id)) {
+                        return Some(DirectTrip {
+                            trip,
+                            from,
+                            to: stop_time,
+                        });
+                    }
+                    if self.serves(&stop_time.stop, from_stop_id) {
+                        from = Some(stop_time);
+                    }
+                }
+                None
+            })
+            .collect();
+        trips.sort_by(|a, b| {
+            let time = |t: &DirectTrip| t.from.departure_time.or(t.from.arrival_time);
+            time(a)
+                .cmp(&time(b))
+                .then_with(|| a.trip.id.cmp(&b.trip.id))
+        });
+        trips
+    }
+
+    /// Lists the connections leaving on a day, sorted by departure time, like a Connection Scan Algorithm expects them
+    ///
+    /// There is a connection between each pair of consecutive stop times of the trips given by [Gtfs::trips_active_on],
+    /// including the trips of the previous day running past midnight. The times are counted from the midnight of `date`,
+    /// and the trips with [Frequency] give a connection for each vehicle. A connection leaving at 24:00:00 or later
+    /// is only listed with the next day, so each connection is in a single list. The stop times without time are skipped:
+    /// use [Trip::interpolate_stop_times] beforehand to get their connections
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    /// let connections = gtfs.connections(monday);
+    /// // tripA1 leaves platform1 at 08:00:00
+    /// assert_eq!("platform1", connections[0].departure.stop.id);
+    /// assert_eq!(8 * 3600, connections[0].departure_time);
+    /// assert_eq!("stop1", connections[0].arrival.stop.id);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn connections(&self, date: NaiveDate) -> Vec<Connection<'_>> {
+        const DAY: u32 = 24 * 3600;
+        let mut connections = Vec::new();
+        for (service_date, trip) in self.trips_active_on(date, true) {
+            let day_offset = if service_date < date { DAY } else { 0 };
+            for pair in trip.stop_times.windows(2) {
+                let (departure, arrival) = (&pair[0], &pair[1]);
+                let times = departure
+                    .departure_time
+                    .or(departure.arrival_time)
+                    .zip(arrival.arrival_time.or(arrival.departure_time));
+                let (departure_time, arrival_time) = match times {
+                    Some(times) => times,
+                    None => continue,
+                };
+                // One connection for each vehicle of the trip
+                let runs = departure_times(trip, departure_time)
+                    .into_iter()
+                    .zip(departure_times(trip, arrival_time));
+                for (departure_time, arrival_time) in runs {
+                    if departure_time < day_offset || departure_time >= day_offset + DAY {
+                        continue;
+                    }
+                    connections.push(Connection {
+                        departure,
+                        departure_time: departure_time - day_offset,
+                        arrival,
+                        arrival_time: arrival_time.saturating_sub(day_offset),
+                        trip,
+                        service_date,
+                    });
+                }
+            }
+        }
+        connections.sort_by(|a, b| {
+            (
+                a.departure_time,
+                a.arrival_time,
+                &a.trip.id,
+                a.departure.stop_sequence,
+            )
+                .cmp(&(
+                    b.departure_time,
+                    b.arrival_time,
+                    &b.trip.id,
+                    b.departure.stop_sequence,
+                ))
+        });
+        connections
+    }
+
+    /// True if the stop is `stop_id`, or one of its parents
+    fn serves(&self, stop: &Stop, stop_id: &str) -> bool {
+        let mut stop = Some(stop);
+        for _ in 0..MAX_STOP_DEPTH {
+            match stop {
+                Some(current) if current.id == stop_id => return true,
+                Some(current) => {
+                    stop = current
+                        .parent_station
+                        .as_ref()
+                        .and_then(|parent| self.stops.get(parent))
+                        .map(|parent| &**parent)
+                }
+                None => return false,
+            }
+        }
+        false
+    }
+}
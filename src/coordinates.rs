@@ -0,0 +1,89 @@
+use crate::error::CoordinateFinding;
+use crate::{objects::*, Gtfs, IdMap};
+use std::sync::Arc;
+
+/// Why the coordinates of a stop are invalid, see [Gtfs::invalid_coordinates]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CoordinateIssue {
+    /// The latitude is not in [-90, 90] or the longitude is not in [-180, 180]
+    OutOfRange,
+    /// Both the latitude and the longitude are 0, usually a placeholder for an unknown position
+    NullIsland,
+    /// The latitude or the longitude is missing, while the [LocationType] requires them
+    /// (stops, stations and entrances), or only one of them is given
+    Missing,
+}
+
+/// Stop with invalid coordinates, see [Gtfs::invalid_coordinates]
+#[derive(Debug, Clone)]
+pub struct InvalidCoordinates<'a> {
+    /// The stop
+    pub stop: &'a Stop,
+    /// Why its coordinates are invalid
+    pub issue: CoordinateIssue,
+}
+
+impl Gtfs {
+    /// Lists the stops whose coordinates are out of range, at (0, 0), or missing, sorted by `stop_id`
+    ///
+    /// They are also listed in [Gtfs::validation_report] when the GTFS is built
+    ///
+    /// ```
+    /// use gtfs_structures::CoordinateIssue;
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// assert!(gtfs.invalid_coordinates().is_empty());
+    /// let stop = std::sync::Arc::make_mut(gtfs.stops.get_mut("stop2").unwrap());
+    /// stop.latitude = Some(148.796058);
+    /// let invalid = gtfs.invalid_coordinates();
+    /// assert_eq!("stop2", invalid[0].stop.id);
+    /// assert_eq!(CoordinateIssue::OutOfRange, invalid[0].issue);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn invalid_coordinates(&self) -> Vec<InvalidCoordinates<'_>> {
+        invalid_coordinates(&self.stops)
+    }
+}
+
+/// The findings of [Gtfs::invalid_coordinates] for the [crate::error::ValidationReport]
+pub(crate) fn coordinate_findings(stops: &IdMap<Arc<Stop>>) -> Vec<CoordinateFinding> {
+    invalid_coordinates(stops)
+        .into_iter()
+        .map(|invalid| CoordinateFinding {
+            stop_id: invalid.stop.id.to_string(),
+            issue: invalid.issue,
+        })
+        .collect()
+}
+
+fn invalid_coordinates(stops: &IdMap<Arc<Stop>>) -> Vec<InvalidCoordinates<'_>> {
+    let mut stops: Vec<&Arc<Stop>> = stops.values().collect();
+    stops.sort_by(|a, b| a.id.cmp(&b.id));
+    stops
+        .into_iter()
+        .filter_map(|stop| {
+            let issue = coordinate_issue(stop)?;
+            Some(InvalidCoordinates { stop, issue })
+        })
+        .collect()
+}
+
+fn coordinate_issue(stop: &Stop) -> Option<CoordinateIssue> {
+    match (stop.latitude, stop.longitude) {
+        (Some(lat), Some(lon)) => {
+            if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+                Some(CoordinateIssue::OutOfRange)
+            } else if lat == 0.0 && lon == 0.0 {
+                Some(CoordinateIssue::NullIsland)
+            } else {
+                None
+            }
+        }
+        (None, None) => match stop.location_type {
+            LocationType::StopPoint | LocationType::StopArea | LocationType::StationEntrance => {
+                Some(CoordinateIssue::Missing)
+            }
+            _ => None,
+        },
+        _ => Some(CoordinateIssue::Missing),
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{objects::*, Gtfs};
+
+/// Which times of a stop time go back in time, see [DecreasingTime]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecreasingTimeKind {
+    /// The arrival is before the departure from the previous stop with a time
+    ArrivalBeforePreviousDeparture,
+    /// The departure is before the arrival at the same stop
+    DepartureBeforeArrival,
+}
+
+/// Stop time whose times go back in time, see [Gtfs::decreasing_times]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecreasingTime<'a> {
+    /// `trip_id` of the trip
+    pub trip_id: &'a str,
+    /// Index of the stop time in [Trip::stop_times]
+    pub index: usize,
+    /// `stop_sequence` of the stop time
+    pub stop_sequence: u16,
+    /// Which times go back in time
+    pub kind: DecreasingTimeKind,
+    /// The time that should not be after the time of the stop time, in seconds since midnight
+    pub previous_time: u32,
+    /// The time of the stop time, in seconds since midnight
+    pub time: u32,
+}
+
+impl Gtfs {
+    /// Lists the stop times arriving before the departure from the previous stop, or leaving before their arrival.
+    /// They are sorted by `trip_id` and by their order in the trip
+    ///
+    /// The stop times without time are ignored: an arrival is compared to the departure from the last stop with a time
+    ///
+    /// ```
+    /// use gtfs_structures::DecreasingTimeKind;
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// assert!(gtfs.decreasing_times().is_empty());
+    /// // The second stop is given an arrival at 13:00:00, before the departure from the first stop at 14:00:00
+    /// gtfs.trips.get_mut("trip1").unwrap().stop_times[1].arrival_time = Some(13 * 3600);
+    /// let decreasing = gtfs.decreasing_times();
+    /// assert_eq!(1, decreasing.len());
+    /// assert_eq!(1, decreasing[0].index);
+    /// assert_eq!(DecreasingTimeKind::ArrivalBeforePreviousDeparture, decreasing[0].kind);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn decreasing_times(&self) -> Vec<DecreasingTime<'_>> {
+        let mut trips: Vec<&Trip> = self.trips.values().collect();
+        trips.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut result = Vec::new();
+        for trip in trips {
+            let mut early_arrivals =
+                arrivals_before_previous_departure(&trip.stop_times).peekable();
+            for (index, stop_time) in trip.stop_times.iter().enumerate() {
+                let mut decreasing = |kind, previous_time, time| {
+                    result.push(DecreasingTime {
+                        trip_id: trip.id.as_str(),
+                        index,
+                        stop_sequence: stop_time.stop_sequence,
+                        kind,
+                        previous_time,
+                        time,
+                    })
+                };
+                if let Some((_, previous, arrival)) =
+                    early_arrivals.next_if(|(early, _, _)| *early == index)
+                {
+                    decreasing(
+                        DecreasingTimeKind::ArrivalBeforePreviousDeparture,
+                        previous,
+                        arrival,
+                    );
+                }
+                if let (Some(arrival), Some(departure)) =
+                    (stop_time.arrival_time, stop_time.departure_time)
+                {
+                    if departure < arrival {
+                        decreasing(
+                            DecreasingTimeKind::DepartureBeforeArrival,
+                            arrival,
+                            departure,
+                        );
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+/// The stop times arriving before the departure from the last previous stop with a time,
+/// given by their index in `stop_times`, the departure from the previous stop and their arrival
+///
+/// A stop time without arrival arrives at its departure, and one without departure leaves at its arrival
+pub(crate) fn arrivals_before_previous_departure(
+    stop_times: &[StopTime],
+) -> impl Iterator<Item = (usize, u32, u32)> + '_ {
+    stop_times
+        .iter()
+        .enumerate()
+        .scan(None, |previous_departure, (index, stop_time)| {
+            let arrival = stop_time.arrival_time.or(stop_time.departure_time);
+            let early = match (*previous_departure, arrival) {
+                (Some(previous), Some(arrival)) if arrival < previous => {
+                    Some((index, previous, arrival))
+                }
+                _ => None,
+            };
+            *previous_departure = stop_time
+                .departure_time
+                .or(stop_time.arrival_time)
+                .or(*previous_departure);
+            Some(early)
+        })
+        .flatten()
+}
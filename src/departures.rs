@@ -0,0 +1,237 @@
+use crate::trip_times::{departure_times, first_time};
+use crate::{objects::*, Error, Gtfs};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ops::Range;
+
+/// Departure of a vehicle from a stop at a given date and time, see [Gtfs::departures_from]
+#[derive(Debug, Clone)]
+pub struct Departure<'a> {
+    /// Scheduled date and time of the departure
+    pub time: NaiveDateTime,
+    /// Date of the service day the trip runs on. It is the previous day for a departure after midnight at 24:10:00
+    pub service_date: NaiveDate,
+    /// Trip of the vehicle
+    pub trip: &'a Trip,
+    /// Route of the trip. None if the `route_id` of the trip does not exist
+    pub route: Option<&'a Route>,
+    /// Stop time of the trip at the stop
+    pub stop_time: &'a StopTime,
+    /// Headsign of the stop time, or of the trip if the stop time has none
+    pub headsign: Option<&'a str>,
+}
+
+/// Time between the successive departures of a route from a stop, see [Gtfs::headways]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Headways {
+    /// Times of the departures, sorted
+    pub departures: Vec<NaiveDateTime>,
+    /// Time between each departure and the next one
+    pub headways: Vec<TimeDelta>,
+}
+
+/// Number of departures of the trips of a route in one direction in each hour of a day, see [Gtfs::frequency_profile]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrequencyProfile {
+    /// `direction_id` of the trips
+    pub direction_id: Option<DirectionType>,
+    /// Number of trips leaving their first stop in each hour, indexed by hour since the midnight of the service day.
+    /// It has at least 24 hours, and more if trips leave after midnight, like at 25:10:00
+    pub trips_per_hour: Vec<u32>,
+}
+
+impl Headways {
+    /// Average headway. None with less than two departures
+    pub fn mean(&self) -> Option<TimeDelta> {
+        let total: TimeDelta = self.headways.iter().sum();
+        i32::try_from(self.headways.len())
+            .ok()
+            .filter(|n| *n > 0)
+            .map(|n| total / n)
+    }
+
+    /// Shortest headway. None with less than two departures
+    pub fn min(&self) -> Option<TimeDelta> {
+        self.headways.iter().min().copied()
+    }
+
+    /// Longest headway. None with less than two departures
+    pub fn max(&self) -> Option<TimeDelta> {
+        self.headways.iter().max().copied()
+    }
+}
+
+impl Gtfs {
+    /// Lists the departures from a stop between `datetime` and `datetime + horizon` (both included), sorted by time
+    ///
+    /// The days a trip runs are given by [Gtfs::is_service_active], and the trips with [Frequency] are repeated every headway.
+    /// The times of the stop times are counted from the midnight of the service day:
+    /// a departure at 25:10:00 is on the next day at 01:10. The times are local, regardless of daylight saving time changes.
+    /// The last stop time of a trip is not a departure
+    ///
+    /// ```
+    /// use chrono::{NaiveDate, TimeDelta};
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let saturday = NaiveDate::from_ymd_opt(2017, 1, 7).unwrap().and_hms_opt(6, 0, 0).unwrap();
+    /// let departures = gtfs.departures_from("stop3", saturday, TimeDelta::hours(1));
+    /// assert_eq!(1, departures.len());
+    /// assert_eq!("trip1", departures[0].trip.id);
+    /// assert_eq!(saturday + TimeDelta::minutes(30), departures[0].time);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn departures_from(
+        &self,
+        stop_id: &str,
+        datetime: NaiveDateTime,
+        horizon: TimeDelta,
+    ) -> Vec<Departure<'_>> {
+        let end = datetime + horizon;
+        let mut active_services = HashMap::new();
+        let mut departures = Vec::new();
+        for trip in self.trips.values() {
+            let last = trip.stop_times.len().saturating_sub(1);
+            for (i, stop_time) in trip.stop_times.iter().enumerate() {
+                if i == last || stop_time.stop.id != stop_id {
+                    continue;
+                }
+                let time = match stop_time.departure_time.or(stop_time.arrival_time) {
+                    Some(time) => time,
+                    None => continue,
+                };
+                for seconds in departure_times(trip, time) {
+                    let offset = TimeDelta::seconds(i64::from(seconds));
+                    let last_day = (end - offset).date();
+                    for day in (datetime - offset).date().iter_days() {
+                        if day > last_day {
+                            break;
+                        }
+                        let departure_time = day.and_time(NaiveTime::MIN) + offset;
+                        if departure_time < datetime || departure_time > end {
+                            continue;
+                        }
+                        let active = *active_services
+                            .entry((&trip.service_id, day))
+                            .or_insert_with(|| self.is_service_active(&trip.service_id, day));
+                        if active {
+                            departures.push(Departure {
+                                time: departure_time,
+                                service_date: day,
+                                trip,
+                                route: self.routes.get(&trip.route_id),
+                                stop_time,
+                                headsign: stop_time
+                                    .stop_headsign
+                                    .as_deref()
+                                    .or(trip.trip_headsign.as_deref()),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        departures.sort_by_key(|d| d.time);
+        departures
+    }
+
+    /// Computes the headways of a route at a stop, from its departures on `date` during the `time_window` (its end excluded)
+    ///
+    /// The departures are given by [Gtfs::departures_from], with both the stop times and the [Frequency]
+    ///
+    /// ```
+    /// use chrono::{NaiveDate, NaiveTime};
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let monday = NaiveDate::from_ymd_opt(2024, 1, 8).unwrap();
+    /// let window = NaiveTime::from_hms_opt(0, 0, 0).unwrap()..NaiveTime::from_hms_opt(23, 0, 0).unwrap();
+    /// let headways = gtfs.headways("routeA", "stop1", monday, window);
+    /// assert_eq!(2, headways.departures.len());
+    /// assert_eq!(headways.min(), headways.mean());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn headways(
+        &self,
+        route_id: &str,
+        stop_id: &str,
+        date: NaiveDate,
+        time_window: Range<NaiveTime>,
+    ) -> Headways {
+        let start = date.and_time(time_window.start);
+        let end = date.and_time(time_window.end);
+        let departures: Vec<_> = self
+            .departures_from(stop_id, start, end - start)
+            .into_iter()
+            .filter(|d| d.trip.route_id == route_id && d.time < end)
+            .map(|d| d.time)
+            .collect();
+        let headways = departures.windows(2).map(|w| w[1] - w[0]).collect();
+        Headways {
+            departures,
+            headways,
+        }
+    }
+
+    /// Counts the trips of a route leaving their first stop in each hour of a day, with one profile per direction
+    ///
+    /// The days a trip runs are given by [Gtfs::is_service_active], and the trips with [Frequency] count once per departure.
+    /// The profiles are sorted by direction: [DirectionType::Outbound], [DirectionType::Inbound], then the trips without direction.
+    /// The trips without any time are ignored
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let monday = chrono::NaiveDate::from_ymd_opt(2024, 1, 8).unwrap();
+    /// let profiles = gtfs.frequency_profile("routeA", monday)?;
+    /// assert_eq!(2, profiles.len());
+    /// // tripA1 leaves at 08:00 and tripA3 at 10:00
+    /// assert_eq!(1, profiles[0].trips_per_hour[8]);
+    /// assert_eq!(1, profiles[0].trips_per_hour[10]);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn frequency_profile(
+        &self,
+        route_id: &str,
+        date: NaiveDate,
+    ) -> Result<Vec<FrequencyProfile>, Error> {
+        self.get_route(route_id)?;
+        let mut profiles: Vec<FrequencyProfile> = Vec::new();
+        for trip in self
+            .trips_for_route(route_id)
+            .filter(|trip| self.is_service_active(&trip.service_id, date))
+        {
+            let has_time = trip
+                .stop_times
+                .iter()
+                .any(|st| st.departure_time.or(st.arrival_time).is_some());
+            if !has_time {
+                continue;
+            }
+            let index = match profiles
+                .iter()
+                .position(|p| p.direction_id == trip.direction_id)
+            {
+                Some(index) => index,
+                None => {
+                    profiles.push(FrequencyProfile {
+                        direction_id: trip.direction_id,
+                        trips_per_hour: vec![0; 24],
+                    });
+                    profiles.len() - 1
+                }
+            };
+            let trips_per_hour = &mut profiles[index].trips_per_hour;
+            for time in departure_times(trip, first_time(trip)) {
+                let hour = (time / 3600) as usize;
+                if trips_per_hour.len() <= hour {
+                    trips_per_hour.resize(hour + 1, 0);
+                }
+                trips_per_hour[hour] += 1;
+            }
+        }
+        profiles.sort_by_key(|p| match p.direction_id {
+            Some(DirectionType::Outbound) => (0, 0),
+            Some(DirectionType::Inbound) => (1, 0),
+            Some(DirectionType::Unknown(i)) => (2, i),
+            None => (3, 0),
+        });
+        Ok(profiles)
+    }
+}
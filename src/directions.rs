@@ -0,0 +1,140 @@
+use crate::{objects::*, Gtfs};
+use std::collections::HashMap;
+
+/// Direction given to a trip without `direction_id` by [Gtfs::infer_direction_ids]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredDirection {
+    /// `trip_id` of the trip
+    pub trip_id: GtfsId,
+    /// Direction given to the trip
+    pub direction_id: DirectionType,
+    /// Confidence in the direction, from 0 (a guess) to 1 (all the clues agree)
+    pub confidence: f64,
+}
+
+impl Gtfs {
+    /// Sets the `direction_id` of the trips without one, and returns the directions given with their confidence, sorted by `trip_id`
+    ///
+    /// The trips of each route are compared to a reference trip: the trip with a direction and the most stops,
+    /// or else the trip with the most stops, which is then [DirectionType::Outbound].
+    /// A trip visiting the stops of the reference in the same order has the same direction, and the opposite one in the reverse order.
+    /// The confidence is the share of the successive stops that agree, minus the share that disagree.
+    /// With less than two stops in common with the reference, the line from the first to the last stop is compared to the one of the reference
+    ///
+    /// ```
+    /// use gtfs_structures::DirectionType;
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// gtfs.trips.get_mut("tripA2").unwrap().direction_id = None;
+    /// let inferred = gtfs.infer_direction_ids();
+    /// assert_eq!(1, inferred.len());
+    /// assert_eq!(DirectionType::Inbound, inferred[0].direction_id);
+    /// assert_eq!(Some(DirectionType::Inbound), gtfs.trips["tripA2"].direction_id);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn infer_direction_ids(&mut self) -> Vec<InferredDirection> {
+        let mut trips_by_route: HashMap<&str, Vec<&Trip>> = HashMap::new();
+        for trip in self.trips.values() {
+            trips_by_route.entry(&trip.route_id).or_default().push(trip);
+        }
+        let mut inferred = Vec::new();
+        for trips in trips_by_route.values() {
+            if trips.iter().all(|t| t.direction_id.is_some()) {
+                continue;
+            }
+            let with_direction = trips.iter().copied().filter(|t| {
+                matches!(
+                    t.direction_id,
+                    Some(DirectionType::Outbound) | Some(DirectionType::Inbound)
+                )
+            });
+            let (reference, reference_direction) = match longest(with_direction) {
+                Some(trip) => (trip, trip.direction_id.unwrap_or(DirectionType::Outbound)),
+                None => match longest(trips.iter().copied()) {
+                    Some(trip) => (trip, DirectionType::Outbound),
+                    None => continue,
+                },
+            };
+            let opposite = match reference_direction {
+                DirectionType::Outbound => DirectionType::Inbound,
+                _ => DirectionType::Outbound,
+            };
+            let mut positions = HashMap::new();
+            for (i, stop_time) in reference.stop_times.iter().enumerate() {
+                positions.entry(&*stop_time.stop.id).or_insert(i);
+            }
+            for trip in trips.iter().filter(|t| t.direction_id.is_none()) {
+                let score = order_score(trip, &positions)
+                    .or_else(|| terminals_score(trip, reference))
+                    .unwrap_or(0.);
+                inferred.push(InferredDirection {
+                    trip_id: trip.id.clone(),
+                    direction_id: if score >= 0. {
+                        reference_direction
+                    } else {
+                        opposite
+                    },
+                    confidence: score.abs(),
+                });
+            }
+        }
+        inferred.sort_by(|a, b| a.trip_id.cmp(&b.trip_id));
+        for direction in &inferred {
+            if let Some(trip) = self.trips.get_mut(&direction.trip_id) {
+                trip.direction_id = Some(direction.direction_id);
+            }
+        }
+        inferred
+    }
+}
+
+/// The trip with the most stop times, the first by `trip_id` in case of a tie
+fn longest<'a>(trips: impl Iterator<Item = &'a Trip>) -> Option<&'a Trip> {
+    trips.max_by(|a, b| {
+        a.stop_times
+            .len()
+            .cmp(&b.stop_times.len())
+            .then_with(|| b.id.cmp(&a.id))
+    })
+}
+
+/// From 1 if the trip visits the stops in the order of their positions in the reference, to -1 in the reverse order.
+/// None with less than two stops in common
+fn order_score(trip: &Trip, positions: &HashMap<&str, usize>) -> Option<f64> {
+    let common: Vec<usize> = trip
+        .stop_times
+        .iter()
+        .filter_map(|st| positions.get(&*st.stop.id).copied())
+        .collect();
+    let (forward, backward) =
+        common
+            .windows(2)
+            .fold((0, 0), |(forward, backward), w| match w[0].cmp(&w[1]) {
+                std::cmp::Ordering::Less => (forward + 1, backward),
+                std::cmp::Ordering::Greater => (forward, backward + 1),
+                std::cmp::Ordering::Equal => (forward, backward),
+            });
+    if forward + backward == 0 {
+        return None;
+    }
+    Some(f64::from(forward - backward) / f64::from(forward + backward))
+}
+
+/// Cosine of the angle between the lines from the first to the last stop of both trips. None without coordinates
+fn terminals_score(trip: &Trip, reference: &Trip) -> Option<f64> {
+    let line = |trip: &Trip| {
+        let first = &trip.stop_times.first()?.stop;
+        let last = &trip.stop_times.last()?.stop;
+        let scale = first.latitude?.to_radians().cos();
+        Some((
+            (last.longitude? - first.longitude?) * scale,
+            last.latitude? - first.latitude?,
+        ))
+    };
+    let (x1, y1) = line(trip)?;
+    let (x2, y2) = line(reference)?;
+    let norms = (x1 * x1 + y1 * y1).sqrt() * (x2 * x2 + y2 * y2).sqrt();
+    if norms == 0. {
+        return None;
+    }
+    Some((x1 * x2 + y1 * y2) / norms)
+}
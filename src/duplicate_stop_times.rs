@@ -0,0 +1,192 @@
+use crate::error::RemovedStopTime;
+use crate::{objects::*, CompactGtfs, Gtfs};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// Why a stop time duplicates another stop time of its trip, see [Gtfs::duplicate_stop_times]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DuplicateStopTimeKind {
+    /// Same stop, arrival and departure times, usually a row repeated in stop_times.txt
+    SameStopAndTimes,
+    /// Same `stop_sequence`, but another stop or other times
+    ///
+    /// [crate::StopSequenceIssue::Repeated] reports the same problem once per trip and `stop_sequence`, to repair the order
+    /// of the stop times, while this lists each duplicated stop time with the index of the first one
+    SameStopSequence,
+}
+
+/// Stop time duplicating an earlier stop time of its trip, see [Gtfs::duplicate_stop_times]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateStopTime<'a> {
+    /// `trip_id` of the trip
+    pub trip_id: &'a str,
+    /// Index in [Trip::stop_times] of the first stop time
+    pub first_index: usize,
+    /// Index in [Trip::stop_times] of the duplicate
+    pub index: usize,
+    /// What both stop times have in common
+    pub kind: DuplicateStopTimeKind,
+}
+
+impl Gtfs {
+    /// Lists the stop times with the same stop and times as an earlier stop time of their trip, or with the same `stop_sequence`.
+    /// They are sorted by `trip_id` and by their order in the trip
+    ///
+    /// The stop times without any time are never duplicates of the same stop, as a trip can go several times through a stop.
+    /// See [Gtfs::remove_duplicate_stop_times] and [crate::GtfsReader::remove_duplicate_stop_times]
+    ///
+    /// ```
+    /// use gtfs_structures::DuplicateStopTimeKind;
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// assert!(gtfs.duplicate_stop_times().is_empty());
+    /// let trip = gtfs.trips.get_mut("trip1").unwrap();
+    /// trip.stop_times.insert(1, trip.stop_times[0].clone());
+    /// let duplicates = gtfs.duplicate_stop_times();
+    /// assert_eq!((0, 1), (duplicates[0].first_index, duplicates[0].index));
+    /// assert_eq!(DuplicateStopTimeKind::SameStopAndTimes, duplicates[0].kind);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn duplicate_stop_times(&self) -> Vec<DuplicateStopTime<'_>> {
+        let mut trips: Vec<&Trip> = self.trips.values().collect();
+        trips.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut result = Vec::new();
+        for trip in trips {
+            let mut first_indices = HashMap::new();
+            let mut first_sequences = HashMap::new();
+            for (index, stop_time) in trip.stop_times.iter().enumerate() {
+                let key = timed_key(
+                    stop_time.stop.id.as_str(),
+                    stop_time.arrival_time,
+                    stop_time.departure_time,
+                );
+                let same_stop = key.and_then(|key| first_indices.get(&key).copied());
+                let same_sequence = match first_sequences.entry(stop_time.stop_sequence) {
+                    Entry::Occupied(first) => Some(*first.get()),
+                    Entry::Vacant(entry) => {
+                        entry.insert(index);
+                        None
+                    }
+                };
+                let duplicate = match (same_stop, same_sequence) {
+                    (Some(first_index), _) => {
+                        Some((first_index, DuplicateStopTimeKind::SameStopAndTimes))
+                    }
+                    (None, Some(first_index)) => {
+                        Some((first_index, DuplicateStopTimeKind::SameStopSequence))
+                    }
+                    (None, None) => None,
+                };
+                if let Some((first_index, kind)) = duplicate {
+                    result.push(DuplicateStopTime {
+                        trip_id: trip.id.as_str(),
+                        first_index,
+                        index,
+                        kind,
+                    });
+                }
+                if let Some(key) = key {
+                    first_indices.entry(key).or_insert(index);
+                }
+            }
+        }
+        result
+    }
+
+    /// Removes the stop times with the same stop and times as an earlier stop time of their trip,
+    /// see [DuplicateStopTimeKind::SameStopAndTimes]. Returns the number of removed stop times
+    ///
+    /// With [crate::GtfsReader::remove_duplicate_stop_times], they are removed while reading and listed in [Gtfs::validation_report]
+    ///
+    /// The stop times with the same `stop_sequence` but another stop or other times are kept, as it is unknown which one is right
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let trip = gtfs.trips.get_mut("trip1").unwrap();
+    /// trip.stop_times.insert(1, trip.stop_times[0].clone());
+    /// assert_eq!(1, gtfs.remove_duplicate_stop_times());
+    /// assert_eq!(3, gtfs.get_trip("trip1")?.stop_times.len());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn remove_duplicate_stop_times(&mut self) -> usize {
+        self.take_duplicate_stop_times().len()
+    }
+
+    /// Removes the same stop times as [Gtfs::remove_duplicate_stop_times] and returns them, for the [crate::error::ValidationReport]
+    pub(crate) fn take_duplicate_stop_times(&mut self) -> Vec<RemovedStopTime> {
+        let mut removed: Vec<RemovedStopTime> = self
+            .trips
+            .values_mut()
+            .flat_map(|trip| {
+                remove_duplicates(
+                    &trip.id,
+                    &mut trip.stop_times,
+                    |st| timed_key(st.stop.id.clone(), st.arrival_time, st.departure_time),
+                    |st| st.stop_sequence,
+                )
+            })
+            .collect();
+        removed.sort();
+        removed
+    }
+}
+
+impl CompactGtfs {
+    /// Removes the stop times with the same stop and times as an earlier stop time of their trip,
+    /// like [Gtfs::remove_duplicate_stop_times]. Returns the number of removed stop times
+    pub fn remove_duplicate_stop_times(&mut self) -> usize {
+        self.take_duplicate_stop_times().len()
+    }
+
+    /// Removes the same stop times as [CompactGtfs::remove_duplicate_stop_times] and returns them, for the [crate::error::ValidationReport]
+    pub(crate) fn take_duplicate_stop_times(&mut self) -> Vec<RemovedStopTime> {
+        let mut removed: Vec<RemovedStopTime> = self
+            .trips
+            .values_mut()
+            .flat_map(|trip| {
+                remove_duplicates(
+                    &trip.id,
+                    &mut trip.stop_times,
+                    |st| timed_key(st.stop_index, st.arrival_time, st.departure_time),
+                    |st| st.stop_sequence,
+                )
+            })
+            .collect();
+        removed.sort();
+        removed
+    }
+}
+
+/// The stop and times of a stop time, None if it has no time
+fn timed_key<S>(
+    stop: S,
+    arrival_time: Option<u32>,
+    departure_time: Option<u32>,
+) -> Option<(S, Option<u32>, Option<u32>)> {
+    if arrival_time.is_none() && departure_time.is_none() {
+        None
+    } else {
+        Some((stop, arrival_time, departure_time))
+    }
+}
+
+/// Keeps the first of the stop times of the trip with the same key, and lists the removed ones
+fn remove_duplicates<T, K: Hash + Eq>(
+    trip_id: &str,
+    stop_times: &mut Vec<T>,
+    key: impl Fn(&T) -> Option<K>,
+    stop_sequence: impl Fn(&T) -> u16,
+) -> Vec<RemovedStopTime> {
+    let mut seen = HashSet::new();
+    let (kept, removed): (Vec<T>, Vec<T>) = std::mem::take(stop_times)
+        .into_iter()
+        .partition(|st| key(st).is_none_or(|key| seen.insert(key)));
+    *stop_times = kept;
+    removed
+        .iter()
+        .map(|st| RemovedStopTime {
+            trip_id: trip_id.to_owned(),
+            stop_sequence: stop_sequence(st),
+        })
+        .collect()
+}
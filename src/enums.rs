@@ -22,9 +22,96 @@ pub enum ObjectType {
     Pathway,
 }
 
+/// All the files (tables) of the GTFS specification that this library handles
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TableName {
+    /// agency.txt, see [Agency]
+    Agency,
+    /// stops.txt, see [Stop]
+    Stops,
+    /// routes.txt, see [Route]
+    Routes,
+    /// trips.txt, see [Trip]
+    Trips,
+    /// stop_times.txt, see [StopTime]
+    StopTimes,
+    /// calendar.txt, see [Calendar]
+    Calendar,
+    /// calendar_dates.txt, see [CalendarDate]
+    CalendarDates,
+    /// shapes.txt, see [Shape]
+    Shapes,
+    /// fare_attributes.txt, see [FareAttribute]
+    FareAttributes,
+    /// fare_rules.txt, see [FareRule]
+    FareRules,
+    /// frequencies.txt, see [Frequency]
+    Frequencies,
+    /// transfers.txt, see [StopTransfer]
+    Transfers,
+    /// pathways.txt, see [Pathway]
+    Pathways,
+    /// feed_info.txt, see [FeedInfo]
+    FeedInfo,
+    /// translations.txt, see [RawTranslation]
+    Translations,
+}
+
+impl TableName {
+    /// All the tables, in the order they are usually listed in the specification
+    pub const ALL: [TableName; 15] = [
+        TableName::Agency,
+        TableName::Stops,
+        TableName::Routes,
+        TableName::Trips,
+        TableName::StopTimes,
+        TableName::Calendar,
+        TableName::CalendarDates,
+        TableName::Shapes,
+        TableName::FareAttributes,
+        TableName::FareRules,
+        TableName::Frequencies,
+        TableName::Transfers,
+        TableName::Pathways,
+        TableName::FeedInfo,
+        TableName::Translations,
+    ];
+
+    /// Name of the file as defined by the specification (e.g. `stops.txt`)
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            TableName::Agency => "agency.txt",
+            TableName::Stops => "stops.txt",
+            TableName::Routes => "routes.txt",
+            TableName::Trips => "trips.txt",
+            TableName::StopTimes => "stop_times.txt",
+            TableName::Calendar => "calendar.txt",
+            TableName::CalendarDates => "calendar_dates.txt",
+            TableName::Shapes => "shapes.txt",
+            TableName::FareAttributes => "fare_attributes.txt",
+            TableName::FareRules => "fare_rules.txt",
+            TableName::Frequencies => "frequencies.txt",
+            TableName::Transfers => "transfers.txt",
+            TableName::Pathways => "pathways.txt",
+            TableName::FeedInfo => "feed_info.txt",
+            TableName::Translations => "translations.txt",
+        }
+    }
+}
+
+impl std::fmt::Display for TableName {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.file_name())
+    }
+}
+
 /// Describes the kind of [Stop]. See <https://gtfs.org/reference/static/#stopstxt> `location_type`
 #[derive(Derivative, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[derivative(Default(bound = ""))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum LocationType {
     /// Stop (or Platform). A location where passengers board or disembark from a transit vehicle. Is called a platform when defined within a parent_station
     #[derivative(Default)]
@@ -90,6 +177,10 @@ impl Serialize for LocationType {
 /// -ome route types are extended GTFS (<https://developers.google.com/transit/gtfs/reference/extended-route-types)>
 #[derive(Debug, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
 #[derivative(Default(bound = ""))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum RouteType {
     /// Tram, Streetcar, Light rail. Any light rail or street level system within a metropolitan area
     Tramway,
@@ -169,6 +260,10 @@ impl Serialize for RouteType {
 /// Describes if and how a traveller can board or alight the vehicle. See <https://gtfs.org/reference/static/#stop_timestxt> `pickup_type` and `dropoff_type`
 #[derive(Debug, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
 #[derivative(Default(bound = ""))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum PickupDropOffType {
     /// Regularly scheduled pickup or drop off (default when empty).
     #[derivative(Default)]
@@ -227,6 +322,10 @@ impl Serialize for PickupDropOffType {
 /// Those values are only defined on <https://developers.google.com/transit/gtfs/reference#routestxt,> not on <https://gtfs.org/reference/static/#routestxt>
 #[derive(Debug, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
 #[derivative(Default(bound = ""))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum ContinuousPickupDropOff {
     /// Continuous stopping pickup or drop off.
     Continuous,
@@ -281,16 +380,20 @@ impl<'de> Deserialize<'de> for ContinuousPickupDropOff {
 }
 
 /// Describes if the stop time is exact or not. See <https://gtfs.org/reference/static/#stop_timestxt> `timepoint`
-#[derive(Debug, Derivative, Serialize, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
 #[derivative(Default)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum TimepointType {
     /// Times are considered approximate
-    #[serde(rename = "0")]
-    Approximate = 0,
+    Approximate,
     /// Times are considered exact
     #[derivative(Default)]
-    #[serde(rename = "1")]
-    Exact = 1,
+    Exact,
+    /// An unknown value not in the specification
+    Unknown(i16),
 }
 
 impl<'de> Deserialize<'de> for TimepointType {
@@ -299,19 +402,41 @@ impl<'de> Deserialize<'de> for TimepointType {
         D: Deserializer<'de>,
     {
         let s = <&str>::deserialize(deserializer)?;
-        match s {
-            "" | "1" => Ok(Self::Exact),
-            "0" => Ok(Self::Approximate),
-            v => Err(serde::de::Error::custom(format!(
-                "invalid value for timepoint: {v}"
-            ))),
-        }
+        Ok(match s {
+            "" | "1" => Self::Exact,
+            "0" => Self::Approximate,
+            s => Self::Unknown(s.parse().map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "invalid value for timepoint, must be an integer: {s}"
+                ))
+            })?),
+        })
+    }
+}
+
+impl Serialize for TimepointType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_i16_as_str(
+            serializer,
+            match self {
+                TimepointType::Approximate => 0,
+                TimepointType::Exact => 1,
+                TimepointType::Unknown(i) => *i,
+            },
+        )
     }
 }
 
 /// Generic enum to define if a service (like wheelchair boarding) is available
 #[derive(Debug, Derivative, PartialEq, Eq, Hash, Clone, Copy)]
 #[derivative(Default)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum Availability {
     /// No information if the service is available
     #[derivative(Default)]
@@ -362,30 +487,110 @@ impl Serialize for Availability {
 }
 
 /// Defines if a [CalendarDate] is added or deleted from a [Calendar]
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum Exception {
     /// There will be a service on that day
-    #[serde(rename = "1")]
     Added,
     /// There won’t be a service on that day
-    #[serde(rename = "2")]
     Deleted,
+    /// An unknown value not in the specification
+    Unknown(i16),
+}
+
+impl<'de> Deserialize<'de> for Exception {
+    fn deserialize<D>(deserializer: D) -> Result<Exception, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = <&str>::deserialize(deserializer)?;
+        Ok(match s {
+            "1" => Exception::Added,
+            "2" => Exception::Deleted,
+            s => Exception::Unknown(s.parse().map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "invalid value for Exception, must be an integer: {s}"
+                ))
+            })?),
+        })
+    }
+}
+
+impl Serialize for Exception {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_i16_as_str(
+            serializer,
+            match self {
+                Exception::Added => 1,
+                Exception::Deleted => 2,
+                Exception::Unknown(i) => *i,
+            },
+        )
+    }
 }
 
 /// Defines the direction of a [Trip], only for display, not for routing. See <https://gtfs.org/reference/static/#tripstxt> `direction_id`
-#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum DirectionType {
     /// Travel in one direction (e.g. outbound travel).
-    #[serde(rename = "0")]
     Outbound,
     /// Travel in the opposite direction (e.g. inbound travel).
-    #[serde(rename = "1")]
     Inbound,
+    /// An unknown value not in the specification
+    Unknown(i16),
+}
+
+impl<'de> Deserialize<'de> for DirectionType {
+    fn deserialize<D>(deserializer: D) -> Result<DirectionType, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = <&str>::deserialize(deserializer)?;
+        Ok(match s {
+            "0" => DirectionType::Outbound,
+            "1" => DirectionType::Inbound,
+            s => DirectionType::Unknown(s.parse().map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "invalid value for DirectionType, must be an integer: {s}"
+                ))
+            })?),
+        })
+    }
+}
+
+impl Serialize for DirectionType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_i16_as_str(
+            serializer,
+            match self {
+                DirectionType::Outbound => 0,
+                DirectionType::Inbound => 1,
+                DirectionType::Unknown(i) => *i,
+            },
+        )
+    }
 }
 
 /// Is the [Trip] accessible with a bike. See <https://gtfs.org/reference/static/#tripstxt> `bikes_allowed`
 #[derive(Debug, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
 #[derivative(Default())]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum BikesAllowedType {
     /// No bike information for the trip
     #[derivative(Default)]
@@ -436,23 +641,67 @@ impl Serialize for BikesAllowedType {
 }
 
 /// Defines where a [FareAttribute] can be paid
-#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum PaymentMethod {
     /// Fare is paid on board
-    #[serde(rename = "0")]
     Aboard,
     /// Fare must be paid before boarding
-    #[serde(rename = "1")]
     PreBoarding,
+    /// An unknown value not in the specification
+    Unknown(i16),
+}
+
+impl<'de> Deserialize<'de> for PaymentMethod {
+    fn deserialize<D>(deserializer: D) -> Result<PaymentMethod, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = <&str>::deserialize(deserializer)?;
+        Ok(match s {
+            "0" => PaymentMethod::Aboard,
+            "1" => PaymentMethod::PreBoarding,
+            s => PaymentMethod::Unknown(s.parse().map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "invalid value for PaymentMethod, must be an integer: {s}"
+                ))
+            })?),
+        })
+    }
+}
+
+impl Serialize for PaymentMethod {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_i16_as_str(
+            serializer,
+            match self {
+                PaymentMethod::Aboard => 0,
+                PaymentMethod::PreBoarding => 1,
+                PaymentMethod::Unknown(i) => *i,
+            },
+        )
+    }
 }
 
 /// Defines if the [Frequency] is exact (the vehicle runs exactly every n minutes) or not
-#[derive(Debug, Serialize, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum ExactTimes {
     /// Frequency-based trips
-    FrequencyBased = 0,
+    FrequencyBased,
     /// Schedule-based trips with the exact same headway throughout the day.
-    ScheduleBased = 1,
+    ScheduleBased,
+    /// An unknown value not in the specification
+    Unknown(i16),
 }
 
 impl<'de> Deserialize<'de> for ExactTimes {
@@ -464,18 +713,38 @@ impl<'de> Deserialize<'de> for ExactTimes {
         Ok(match s {
             "" | "0" => ExactTimes::FrequencyBased,
             "1" => ExactTimes::ScheduleBased,
-            &_ => {
-                return Err(serde::de::Error::custom(format!(
-                    "Invalid value `{s}`, expected 0 or 1"
-                )))
-            }
+            s => ExactTimes::Unknown(s.parse().map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "invalid value for ExactTimes, must be an integer: {s}"
+                ))
+            })?),
         })
     }
 }
 
+impl Serialize for ExactTimes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_i16_as_str(
+            serializer,
+            match self {
+                ExactTimes::FrequencyBased => 0,
+                ExactTimes::ScheduleBased => 1,
+                ExactTimes::Unknown(i) => *i,
+            },
+        )
+    }
+}
+
 /// Defines how many transfers can be done with on [FareAttribute]
 #[derive(Debug, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
 #[derivative(Default(bound = ""))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum Transfers {
     /// Unlimited transfers are permitted
     #[derivative(Default)]
@@ -521,29 +790,29 @@ impl Serialize for Transfers {
     }
 }
 /// Defines the type of a [StopTransfer]
-#[derive(Debug, Serialize, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
 #[derivative(Default)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum TransferType {
     /// Recommended transfer point between routes
-    #[serde(rename = "0")]
     #[derivative(Default)]
     Recommended,
     /// Departing vehicle waits for arriving one
-    #[serde(rename = "1")]
     Timed,
     /// Transfer requires a minimum amount of time between arrival and departure to ensure a connection.
-    #[serde(rename = "2")]
     MinTime,
     /// Transfer is not possible at this location
-    #[serde(rename = "3")]
     Impossible,
     /// Passengers can stay onboard the same vehicle to transfer from one trip to another
-    #[serde(rename = "4")]
     StayOnBoard,
     /// In-seat transfers aren't allowed between sequential trips.
     /// The passenger must alight from the vehicle and re-board.
-    #[serde(rename = "5")]
     MustAlight,
+    /// An unknown value not in the specification
+    Unknown(i16),
 }
 
 impl<'de> Deserialize<'de> for TransferType {
@@ -559,54 +828,155 @@ impl<'de> Deserialize<'de> for TransferType {
             "3" => TransferType::Impossible,
             "4" => TransferType::StayOnBoard,
             "5" => TransferType::MustAlight,
-            s => {
-                return Err(serde::de::Error::custom(format!(
-                    "Invalid value `{s}`, expected 0, 1, 2, 3, 4, 5"
-                )))
-            }
+            s => TransferType::Unknown(s.parse().map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "invalid value for TransferType, must be an integer: {s}"
+                ))
+            })?),
         })
     }
 }
 
+impl Serialize for TransferType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_i16_as_str(
+            serializer,
+            match self {
+                TransferType::Recommended => 0,
+                TransferType::Timed => 1,
+                TransferType::MinTime => 2,
+                TransferType::Impossible => 3,
+                TransferType::StayOnBoard => 4,
+                TransferType::MustAlight => 5,
+                TransferType::Unknown(i) => *i,
+            },
+        )
+    }
+}
+
 /// Type of pathway between [from_stop] and [to_stop]
-#[derive(Debug, Serialize, Deserialize, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
 #[derivative(Default)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum PathwayMode {
     /// A walkway
-    #[serde(rename = "1")]
     #[derivative(Default)]
     Walkway,
     /// Stairs
-    #[serde(rename = "2")]
     Stairs,
     /// Moving sidewalk / travelator
-    #[serde(rename = "3")]
     MovingSidewalk,
     /// Escalator
-    #[serde(rename = "4")]
     Escalator,
     /// Elevator
-    #[serde(rename = "5")]
     Elevator,
     /// A pathway that crosses into an area of the station where a
     /// proof of payment is required (usually via a physical payment gate)
-    #[serde(rename = "6")]
     FareGate,
     /// Indicates a pathway exiting an area where proof-of-payment is required
     /// into an area where proof-of-payment is no longer required.
-    #[serde(rename = "7")]
     ExitGate,
+    /// An unknown value not in the specification
+    Unknown(i16),
+}
+
+impl<'de> Deserialize<'de> for PathwayMode {
+    fn deserialize<D>(deserializer: D) -> Result<PathwayMode, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = <&str>::deserialize(deserializer)?;
+        Ok(match s {
+            "1" => PathwayMode::Walkway,
+            "2" => PathwayMode::Stairs,
+            "3" => PathwayMode::MovingSidewalk,
+            "4" => PathwayMode::Escalator,
+            "5" => PathwayMode::Elevator,
+            "6" => PathwayMode::FareGate,
+            "7" => PathwayMode::ExitGate,
+            s => PathwayMode::Unknown(s.parse().map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "invalid value for PathwayMode, must be an integer: {s}"
+                ))
+            })?),
+        })
+    }
+}
+
+impl Serialize for PathwayMode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_i16_as_str(
+            serializer,
+            match self {
+                PathwayMode::Walkway => 1,
+                PathwayMode::Stairs => 2,
+                PathwayMode::MovingSidewalk => 3,
+                PathwayMode::Escalator => 4,
+                PathwayMode::Elevator => 5,
+                PathwayMode::FareGate => 6,
+                PathwayMode::ExitGate => 7,
+                PathwayMode::Unknown(i) => *i,
+            },
+        )
+    }
 }
 
 /// Indicates in which direction the pathway can be used
-#[derive(Debug, Serialize, Deserialize, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Derivative, Copy, Clone, PartialEq, Eq, Hash)]
 #[derivative(Default)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum PathwayDirectionType {
     /// Unidirectional pathway, it can only be used from [from_stop_id] to [to_stop_id].
-    #[serde(rename = "0")]
     #[derivative(Default)]
     Unidirectional,
     /// Bidirectional pathway, it can be used in the two directions.
-    #[serde(rename = "1")]
     Bidirectional,
+    /// An unknown value not in the specification
+    Unknown(i16),
+}
+
+impl<'de> Deserialize<'de> for PathwayDirectionType {
+    fn deserialize<D>(deserializer: D) -> Result<PathwayDirectionType, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = <&str>::deserialize(deserializer)?;
+        Ok(match s {
+            "0" => PathwayDirectionType::Unidirectional,
+            "1" => PathwayDirectionType::Bidirectional,
+            s => PathwayDirectionType::Unknown(s.parse().map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "invalid value for PathwayDirectionType, must be an integer: {s}"
+                ))
+            })?),
+        })
+    }
+}
+
+impl Serialize for PathwayDirectionType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_i16_as_str(
+            serializer,
+            match self {
+                PathwayDirectionType::Unidirectional => 0,
+                PathwayDirectionType::Bidirectional => 1,
+                PathwayDirectionType::Unknown(i) => *i,
+            },
+        )
+    }
 }
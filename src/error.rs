@@ -1,15 +1,156 @@
 //! Module for the error management
+use crate::{CoordinateIssue, TableName};
+use std::fmt;
 use thiserror::Error;
 
 /// Specific line from a CSV file that could not be read
 #[derive(Debug)]
 pub struct LineError {
+    /// Line number of the row in the file (the headers are on line 1)
+    pub line: u64,
     /// Headers of the CSV file
     pub headers: Vec<String>,
     /// Values of the line that could not be parsed
     pub values: Vec<String>,
 }
 
+/// Row that could not be parsed and was skipped (see [crate::GtfsReader::skip_invalid_rows])
+#[derive(Debug)]
+pub struct InvalidRow {
+    /// File containing the row
+    pub file_name: String,
+    /// Line number of the row in the file (the headers are on line 1)
+    pub line: u64,
+    /// Headers of the file
+    pub headers: Vec<String>,
+    /// Values of the row
+    pub values: Vec<String>,
+    /// Why the row could not be parsed
+    pub error: csv::Error,
+}
+
+/// Reference from an object to another object that does not exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenReference {
+    /// Table of the object holding the reference
+    pub table: TableName,
+    /// Identifier of the object holding the reference (for a [crate::StopTime] it is the `trip_id`)
+    pub object_id: String,
+    /// Field holding the reference (e.g. `route_id`)
+    pub field_name: &'static str,
+    /// Value of the reference that could not be resolved
+    pub value: String,
+}
+
+impl fmt::Display for BrokenReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: '{}' references an unknown {} '{}'",
+            self.table, self.object_id, self.field_name, self.value
+        )
+    }
+}
+
+/// Identifier used by several objects of a table, see [crate::DuplicatePolicy]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateId {
+    /// Table containing the objects
+    pub table: TableName,
+    /// The identifier that is not unique
+    pub id: String,
+}
+
+impl fmt::Display for DuplicateId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: '{}' is not unique", self.table, self.id)
+    }
+}
+
+/// Stop time removed because it has the same stop and times as an earlier stop time of its trip,
+/// see [crate::GtfsReader::remove_duplicate_stop_times]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RemovedStopTime {
+    /// `trip_id` of the trip
+    pub trip_id: String,
+    /// `stop_sequence` of the removed stop time
+    pub stop_sequence: u16,
+}
+
+impl fmt::Display for RemovedStopTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "stop_times.txt: the stop time {} of '{}' duplicates an earlier one",
+            self.stop_sequence, self.trip_id
+        )
+    }
+}
+
+/// Stop kept with invalid coordinates, see [crate::Gtfs::invalid_coordinates]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoordinateFinding {
+    /// `stop_id` of the stop
+    pub stop_id: String,
+    /// Why its coordinates are invalid
+    pub issue: CoordinateIssue,
+}
+
+impl fmt::Display for CoordinateFinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let issue = match self.issue {
+            CoordinateIssue::OutOfRange => "are out of range",
+            CoordinateIssue::NullIsland => "are (0, 0)",
+            CoordinateIssue::Missing => "are missing",
+        };
+        write!(
+            f,
+            "stops.txt: the coordinates of '{}' {}",
+            self.stop_id, issue
+        )
+    }
+}
+
+/// Why a language is not a valid IETF BCP 47 language tag, see [crate::check_language_tag]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LanguageTagError {
+    /// The language is empty
+    #[error("the language is empty")]
+    Empty,
+    /// A subtag is malformed or misplaced
+    #[error("the subtag '{0}' is not valid")]
+    InvalidSubtag(String),
+    /// The language is a common but non-standard value (see [crate::normalize_language])
+    #[error("the language is not standard, '{0}' should be used")]
+    NonStandard(String),
+}
+
+/// Rows dropped while building a [crate::Gtfs], and stops kept with invalid coordinates
+///
+/// The rows are listed with [crate::GtfsReader::skip_broken_references], [crate::DuplicatePolicy::Report]
+/// and [crate::GtfsReader::remove_duplicate_stop_times]. The stops are always listed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Every broken reference, in the order the rows were read
+    pub broken_references: Vec<BrokenReference>,
+    /// Every duplicated identifier, once for each dropped row
+    pub duplicates: Vec<DuplicateId>,
+    /// Every stop time removed as a duplicate, sorted by `trip_id` and `stop_sequence`
+    pub removed_stop_times: Vec<RemovedStopTime>,
+    /// Every stop with invalid coordinates, sorted by `stop_id`
+    pub invalid_coordinates: Vec<CoordinateFinding>,
+}
+
+impl ValidationReport {
+    /// True if no row was dropped and all the coordinates are valid
+    pub fn is_valid(&self) -> bool {
+        self.broken_references.is_empty()
+            && self.duplicates.is_empty()
+            && self.removed_stop_times.is_empty()
+            && self.invalid_coordinates.is_empty()
+    }
+}
+
 /// An error that can occur when processing GTFS data.
 #[derive(Error, Debug)]
 pub enum Error {
@@ -19,12 +160,27 @@ pub enum Error {
     /// A file references an Id that is not present
     #[error("The id {0} is not known")]
     ReferenceError(String),
+    /// The route has no `agency_id`, while the GTFS does not have a single agency, see [crate::Gtfs::agency_for_route]
+    #[error("the route {0} has no agency_id, and the GTFS does not have a single agency")]
+    MissingAgencyId(String),
     /// The given path to the GTFS is neither a file nor a directory
     #[error("Could not read GTFS: {0} is neither a file nor a directory")]
     NotFileNorDirectory(String),
     /// The time is not given in the HH:MM:SS format
     #[error("'{0}' is not a valid time; HH:MM:SS format is expected.")]
     InvalidTime(String),
+    /// The trip has no stop time with this `stop_sequence`, or the stop time has no time
+    #[error("trip '{trip_id}' has no timed stop time with the stop_sequence {stop_sequence}")]
+    UnknownStopTime {
+        /// The trip of the stop time
+        trip_id: String,
+        /// The `stop_sequence` of the stop time
+        stop_sequence: u16,
+    },
+    /// The timezone is not a valid name of the tz database, see [crate::Gtfs::stop_time_utc]
+    #[cfg(feature = "timezones")]
+    #[error("'{0}' is not a valid timezone")]
+    InvalidTimezone(String),
     /// The color is not given in the RRGGBB format, without a leading `#`
     #[error("'{0}' is not a valid color; RRGGBB format is expected, without a leading `#`")]
     InvalidColor(String),
@@ -40,6 +196,58 @@ pub enum Error {
         #[source]
         source: Box<dyn std::error::Error + Send + Sync>,
     },
+    /// An enumeration has a value that is not defined by the specification (only in [crate::ParseMode::Strict])
+    #[error("'{value}' is not a valid {field_name} in '{file_name}'")]
+    UnknownEnumValue {
+        /// File containing the value
+        file_name: String,
+        /// Field containing the value
+        field_name: String,
+        /// The unknown value
+        value: String,
+    },
+    /// A file is larger, once uncompressed, than the limit set by [crate::GtfsReader::file_size_limit]
+    #[error("'{file_name}' is larger than the limit of {limit} bytes")]
+    FileSizeLimitExceeded {
+        /// The file that is too large
+        file_name: String,
+        /// The limit in bytes
+        limit: u64,
+    },
+    /// A file has more rows than the limit set by [crate::GtfsReader::row_limit]
+    #[error("'{file_name}' has more than the limit of {limit} rows")]
+    RowLimitExceeded {
+        /// The file that has too many rows
+        file_name: String,
+        /// The maximum number of rows
+        limit: usize,
+    },
+    /// The reading was cancelled with the flag given to [crate::GtfsReader::cancel_flag]
+    #[error("the reading of the GTFS was cancelled")]
+    Cancelled,
+    /// Several objects of a table have the same identifier (only with [crate::DuplicatePolicy::Error])
+    #[error("the id '{id}' is not unique in {table}")]
+    DuplicateId {
+        /// Table containing the objects
+        table: TableName,
+        /// The identifier that is not unique
+        id: String,
+    },
+    /// Some objects reference objects that do not exist
+    #[error("{} references are invalid", .0.len())]
+    BrokenReferences(Vec<BrokenReference>),
+    /// The table can not be written by a [crate::GtfsWriter], as its content is not kept in a [crate::Gtfs]
+    #[error("{0} can not be written, its content is not kept in a Gtfs")]
+    UnwritableTable(TableName),
+    /// Impossible to write a file
+    #[error("impossible to write '{file_name}'")]
+    NamedFileWrite {
+        /// The file name that could not be written
+        file_name: String,
+        /// The inital error that caused the unability to write the file
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
     /// Impossible to fetch the remote archive by the URL
     #[cfg(feature = "read-url")]
     #[error("impossible to remotely access file")]
@@ -58,4 +266,16 @@ pub enum Error {
     /// Error when trying to unzip the GTFS archive
     #[error(transparent)]
     Zip(#[from] zip::result::ZipError),
+    /// Impossible to write or read a cache, see [crate::Gtfs::save_cache]
+    #[cfg(feature = "cache")]
+    #[error("impossible to write or read the cache")]
+    Cache(#[from] bincode::Error),
+    /// The file given to [crate::Gtfs::load_cache] is not a cache written by this version of the crate
+    #[cfg(feature = "cache")]
+    #[error("the file is not a cache written by this version of gtfs-structures")]
+    InvalidCache,
+    /// Impossible to write or read an archive, see [crate::FlatGtfs::save]
+    #[cfg(feature = "rkyv")]
+    #[error("impossible to write or read the archive")]
+    Archive(#[from] rkyv::rancor::Error),
 }
@@ -0,0 +1,99 @@
+use crate::{objects::*, Error, Gtfs};
+use std::collections::BTreeSet;
+
+impl Gtfs {
+    /// Finds the fares that apply to a ride on a route from a stop to another one, from the cheapest
+    ///
+    /// A [FareRule] applies when its `route_id`, `origin_id` and `destination_id` are empty or equal
+    /// to the route and to the `zone_id` of the stops. The rules of a fare with a `contains_id` apply together:
+    /// their zones must be exactly the zones of the stops of the ride, taken from the first trip
+    /// of the route calling at both stops in that order (or else only the zones of both stops).
+    /// When there is no [FareRule] at all, every [FareAttribute] applies
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let fares: Vec<_> = gtfs.fare_for("platform1", "stop1", "routeA")?.iter().map(|f| &f.id).collect();
+    /// assert_eq!(vec!["short", "fareA"], fares);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn fare_for(
+        &self,
+        origin_stop_id: &str,
+        destination_stop_id: &str,
+        route_id: &str,
+    ) -> Result<Vec<&FareAttribute>, Error> {
+        let origin = self.get_stop(origin_stop_id)?;
+        let destination = self.get_stop(destination_stop_id)?;
+        self.get_route(route_id)?;
+        let origin_zone = origin.zone_id.as_deref();
+        let destination_zone = destination.zone_id.as_deref();
+        let zones = self.zones_between(origin_stop_id, destination_stop_id, route_id);
+
+        let matches = |expected: &Option<GtfsId>, value: Option<&str>| {
+            expected.as_deref().is_none_or(|e| Some(e) == value)
+        };
+        let mut fares: Vec<&FareAttribute> = self
+            .fare_attributes
+            .values()
+            .filter(|fare| {
+                if self.fare_rules.is_empty() {
+                    return true;
+                }
+                let rules: Vec<&FareRule> = self
+                    .fare_rules
+                    .get(&fare.id)
+                    .into_iter()
+                    .flatten()
+                    .filter(|rule| {
+                        matches(&rule.route_id, Some(route_id))
+                            && matches(&rule.origin_id, origin_zone)
+                            && matches(&rule.destination_id, destination_zone)
+                    })
+                    .collect();
+                let contained: BTreeSet<&str> = rules
+                    .iter()
+                    .filter_map(|r| r.contains_id.as_deref())
+                    .collect();
+                rules.iter().any(|r| r.contains_id.is_none())
+                    || (!contained.is_empty() && contained == zones)
+            })
+            .collect();
+        fares.sort_by(|a, b| {
+            let price = |fare: &FareAttribute| fare.price.parse::<f64>().unwrap_or(f64::MAX);
+            price(a).total_cmp(&price(b)).then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(fares)
+    }
+
+    /// Zones of the stops between both stops on the first trip of the route calling at them in that order.
+    /// Only the zones of both stops if there is no such trip
+    fn zones_between(
+        &self,
+        origin_stop_id: &str,
+        destination_stop_id: &str,
+        route_id: &str,
+    ) -> BTreeSet<&str> {
+        let ride = self.trips_for_route(route_id).find_map(|trip| {
+            let start = trip
+                .stop_times
+                .iter()
+                .position(|st| st.stop.id == origin_stop_id)?;
+            let end = start
+                + trip.stop_times[start..]
+                    .iter()
+                    .position(|st| st.stop.id == destination_stop_id)?;
+            Some(&trip.stop_times[start..=end])
+        });
+        match ride {
+            Some(stop_times) => stop_times
+                .iter()
+                .filter_map(|st| st.stop.zone_id.as_deref())
+                .collect(),
+            None => [origin_stop_id, destination_stop_id]
+                .iter()
+                .copied()
+                .filter_map(|id| self.stops.get(id)?.zone_id.as_deref())
+                .collect(),
+        }
+    }
+}
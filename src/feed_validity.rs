@@ -0,0 +1,101 @@
+use crate::Gtfs;
+use chrono::NaiveDate;
+use std::collections::BTreeSet;
+
+/// Dates of the feed that are inconsistent or need attention, see [Gtfs::feed_date_issues]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedDateIssue<'a> {
+    /// The last day of the feed is before today
+    Expired {
+        /// `feed_end_date`, or the last day of service without it
+        end: NaiveDate,
+    },
+    /// The last day of the feed is in less than the given number of days
+    ExpiresSoon {
+        /// `feed_end_date`, or the last day of service without it
+        end: NaiveDate,
+        /// Number of days from today to `end`
+        days: i64,
+    },
+    /// All the days of the [crate::Calendar] are before `feed_start_date` or after `feed_end_date`
+    CalendarOutsideFeed {
+        /// `service_id` of the calendar
+        service_id: &'a str,
+    },
+    /// The service runs on some days before `feed_start_date` or after `feed_end_date`
+    ServiceOutsideFeed {
+        /// `service_id` of the service
+        service_id: &'a str,
+        /// First day the service runs outside of the feed dates
+        first: NaiveDate,
+        /// Last day the service runs outside of the feed dates
+        last: NaiveDate,
+    },
+}
+
+impl Gtfs {
+    /// Compares the days of service to the `feed_start_date` and `feed_end_date` of the first [crate::FeedInfo],
+    /// and the end of the feed to `today`
+    ///
+    /// The feed expires soon if its end is less than `warning_days` days after `today`.
+    /// Without `feed_end_date`, the end of the feed is the last day of service (see [Gtfs::service_span]).
+    /// The issues of the whole feed come first, then the ones of the services sorted by `service_id`.
+    /// A calendar outside of the feed dates is not also reported as a service outside of the feed dates
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use gtfs_structures::FeedDateIssue;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let today = NaiveDate::from_ymd_opt(2018, 9, 20).unwrap();
+    /// let issues = gtfs.feed_date_issues(today, 30);
+    /// let end = NaiveDate::from_ymd_opt(2018, 9, 27).unwrap();
+    /// assert_eq!(FeedDateIssue::ExpiresSoon { end, days: 7 }, issues[0]);
+    /// // The feed is valid from 2018-07-09, and service1 runs in January 2017
+    /// assert_eq!(FeedDateIssue::CalendarOutsideFeed { service_id: "service1" }, issues[1]);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn feed_date_issues(&self, today: NaiveDate, warning_days: u32) -> Vec<FeedDateIssue<'_>> {
+        let feed_info = self.feed_info.first();
+        let feed_start = feed_info.and_then(|f| f.start_date);
+        let feed_end = feed_info.and_then(|f| f.end_date);
+        let outside = |day: NaiveDate| {
+            feed_start.is_some_and(|start| day < start) || feed_end.is_some_and(|end| day > end)
+        };
+
+        let mut issues = Vec::new();
+        if let Some(end) = feed_end.or_else(|| self.service_span().map(|(_, end)| end)) {
+            let days = (end - today).num_days();
+            if days < 0 {
+                issues.push(FeedDateIssue::Expired { end });
+            } else if days < i64::from(warning_days) {
+                issues.push(FeedDateIssue::ExpiresSoon { end, days });
+            }
+        }
+
+        let service_ids: BTreeSet<&str> = self
+            .calendar
+            .keys()
+            .chain(self.calendar_dates.keys())
+            .map(|id| id.as_str())
+            .collect();
+        for service_id in service_ids {
+            if let Some(calendar) = self.calendar.get(service_id) {
+                let before = feed_start.is_some_and(|start| calendar.end_date < start);
+                let after = feed_end.is_some_and(|end| calendar.start_date > end);
+                if before || after {
+                    issues.push(FeedDateIssue::CalendarOutsideFeed { service_id });
+                    continue;
+                }
+            }
+            let mut days_outside = self.service_dates(service_id).filter(|day| outside(*day));
+            if let Some(first) = days_outside.next() {
+                issues.push(FeedDateIssue::ServiceOutsideFeed {
+                    service_id,
+                    first,
+                    last: days_outside.last().unwrap_or(first),
+                });
+            }
+        }
+        issues
+    }
+}
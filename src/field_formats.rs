@@ -0,0 +1,284 @@
+use crate::{Gtfs, TableName};
+use std::fmt;
+
+/// Format of a text field defined by the specification, see [Gtfs::invalid_fields]
+///
+/// The colors are already checked when the GTFS is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldFormat {
+    /// A fully qualified URL, with `http://` or `https://`
+    Url,
+    /// An email address
+    Email,
+    /// A phone number, with digits and optionally letters and punctuation
+    Phone,
+    /// An ISO 4217 alphabetical currency code, like `EUR` (only the format is checked)
+    Currency,
+    /// A timezone of the tz database, like `Europe/Paris`.
+    /// Only its format is checked, unless the feature `timezones` is enabled
+    Timezone,
+}
+
+impl FieldFormat {
+    /// True if the value has the format
+    ///
+    /// ```
+    /// use gtfs_structures::FieldFormat;
+    /// assert!(FieldFormat::Url.is_valid("https://example.com/timetables"));
+    /// assert!(!FieldFormat::Url.is_valid("www.example.com"));
+    /// assert!(FieldFormat::Email.is_valid("contact@example.com"));
+    /// assert!(FieldFormat::Currency.is_valid("EUR"));
+    /// assert!(!FieldFormat::Currency.is_valid("€"));
+    /// ```
+    pub fn is_valid(&self, value: &str) -> bool {
+        match self {
+            FieldFormat::Url => is_url(value),
+            FieldFormat::Email => is_email(value),
+            FieldFormat::Phone => is_phone(value),
+            FieldFormat::Currency => {
+                value.len() == 3 && value.bytes().all(|b| b.is_ascii_uppercase())
+            }
+            FieldFormat::Timezone => is_timezone(value),
+        }
+    }
+}
+
+impl fmt::Display for FieldFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FieldFormat::Url => "URL",
+            FieldFormat::Email => "email",
+            FieldFormat::Phone => "phone number",
+            FieldFormat::Currency => "currency code",
+            FieldFormat::Timezone => "timezone",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Field whose value does not have the expected [FieldFormat], see [Gtfs::invalid_fields]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidField<'a> {
+    /// Table of the object
+    pub table: TableName,
+    /// Identifier of the object. The `agency_name` for an agency without `agency_id`, the `feed_publisher_name` for a [crate::FeedInfo]
+    pub object_id: &'a str,
+    /// Name of the field (e.g. `agency_url`)
+    pub field_name: &'static str,
+    /// Value of the field
+    pub value: &'a str,
+    /// Expected format of the value
+    pub format: FieldFormat,
+}
+
+impl fmt::Display for InvalidField<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: the {} '{}' of '{}' is not a valid {}",
+            self.table, self.field_name, self.value, self.object_id, self.format
+        )
+    }
+}
+
+impl Gtfs {
+    /// Lists the URL, email, phone, currency and timezone fields of the agencies, routes, stops, feed information
+    /// and fare attributes whose value does not have the expected [FieldFormat]
+    ///
+    /// The empty values are ignored. The fields are listed by table, then by identifier
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// assert!(gtfs.invalid_fields().is_empty());
+    /// gtfs.agencies[0].url = "www.bibus.fr".to_owned();
+    /// assert_eq!(
+    ///     "agency.txt: the agency_url 'www.bibus.fr' of 'BIBUS' is not a valid URL",
+    ///     gtfs.invalid_fields()[0].to_string()
+    /// );
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn invalid_fields<'a>(&'a self) -> Vec<InvalidField<'a>> {
+        let mut result = Vec::new();
+        let mut check =
+            |table, object_id: &'a str, field_name, value: Option<&'a str>, format: FieldFormat| {
+                if let Some(value) = value.filter(|v| !v.is_empty() && !format.is_valid(v)) {
+                    result.push(InvalidField {
+                        table,
+                        object_id,
+                        field_name,
+                        value,
+                        format,
+                    });
+                }
+            };
+
+        for agency in &self.agencies {
+            let id = agency.id.as_deref().unwrap_or(&agency.name);
+            let table = TableName::Agency;
+            check(table, id, "agency_url", Some(&agency.url), FieldFormat::Url);
+            check(
+                table,
+                id,
+                "agency_timezone",
+                Some(&agency.timezone),
+                FieldFormat::Timezone,
+            );
+            check(
+                table,
+                id,
+                "agency_phone",
+                agency.phone.as_deref(),
+                FieldFormat::Phone,
+            );
+            check(
+                table,
+                id,
+                "agency_fare_url",
+                agency.fare_url.as_deref(),
+                FieldFormat::Url,
+            );
+            check(
+                table,
+                id,
+                "agency_email",
+                agency.email.as_deref(),
+                FieldFormat::Email,
+            );
+        }
+
+        let mut routes: Vec<_> = self.routes.values().collect();
+        routes.sort_by(|a, b| a.id.cmp(&b.id));
+        for route in routes {
+            check(
+                TableName::Routes,
+                &route.id,
+                "route_url",
+                route.url.as_deref(),
+                FieldFormat::Url,
+            );
+        }
+
+        let mut stops: Vec<_> = self.stops.values().collect();
+        stops.sort_by(|a, b| a.id.cmp(&b.id));
+        for stop in stops {
+            check(
+                TableName::Stops,
+                &stop.id,
+                "stop_url",
+                stop.url.as_deref(),
+                FieldFormat::Url,
+            );
+            check(
+                TableName::Stops,
+                &stop.id,
+                "stop_timezone",
+                stop.timezone.as_deref(),
+                FieldFormat::Timezone,
+            );
+        }
+
+        for feed_info in &self.feed_info {
+            let id = &feed_info.name;
+            let table = TableName::FeedInfo;
+            check(
+                table,
+                id,
+                "feed_publisher_url",
+                Some(&feed_info.url),
+                FieldFormat::Url,
+            );
+            check(
+                table,
+                id,
+                "feed_contact_email",
+                feed_info.contact_email.as_deref(),
+                FieldFormat::Email,
+            );
+            check(
+                table,
+                id,
+                "feed_contact_url",
+                feed_info.contact_url.as_deref(),
+                FieldFormat::Url,
+            );
+        }
+
+        let mut fare_attributes: Vec<_> = self.fare_attributes.values().collect();
+        fare_attributes.sort_by(|a, b| a.id.cmp(&b.id));
+        for fare in fare_attributes {
+            check(
+                TableName::FareAttributes,
+                &fare.id,
+                "currency_type",
+                Some(&fare.currency),
+                FieldFormat::Currency,
+            );
+        }
+        result
+    }
+}
+
+fn is_url(value: &str) -> bool {
+    let rest = match value
+        .strip_prefix("https://")
+        .or_else(|| value.strip_prefix("http://"))
+    {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = host.rsplit('@').next().unwrap_or_default();
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host,
+        _ => host,
+    };
+    !host.is_empty()
+        && !host.starts_with('.')
+        && !host.ends_with('.')
+        && host
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '.')
+        && !value.chars().any(char::is_whitespace)
+}
+
+fn is_email(value: &str) -> bool {
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !value.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+fn is_phone(value: &str) -> bool {
+    value.chars().any(|c| c.is_ascii_digit())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || " +-().,/#*".contains(c))
+}
+
+#[cfg(feature = "timezones")]
+fn is_timezone(value: &str) -> bool {
+    value.parse::<chrono_tz::Tz>().is_ok()
+}
+
+/// Without the tz database, only checks that the name looks like `UTC` or `Area/Location`
+#[cfg(not(feature = "timezones"))]
+fn is_timezone(value: &str) -> bool {
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_+-".contains(c))
+    };
+    value.split('/').all(valid_part)
+        && (value.contains('/')
+            || value
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()))
+}
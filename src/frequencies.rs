@@ -0,0 +1,105 @@
+use crate::serde_helpers::format_time;
+use crate::trip_times::first_time;
+use crate::{objects::*, Gtfs};
+use std::collections::HashSet;
+
+impl Gtfs {
+    /// Replaces each trip with [Frequency] by the trips of [Trip::materialized_departures], and updates the indices
+    ///
+    /// The trips without frequency are unchanged. When the identifier of a materialized trip is already used by another trip,
+    /// it is followed by `_2`, `_3`…
+    pub fn expand_frequencies(&mut self) {
+        let sorted = self.trips.is_sorted_by_id();
+        let (mut with_frequencies, mut trips): (Vec<_>, Vec<_>) = std::mem::take(&mut self.trips)
+            .into_iter()
+            .partition(|(_, trip)| !trip.frequencies.is_empty());
+        let mut used_ids: HashSet<GtfsId> = trips.iter().map(|(id, _)| id.clone()).collect();
+        // Sorted so that the suffixes do not depend on the order of the map
+        with_frequencies.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (_, trip) in with_frequencies {
+            for mut materialized in trip.materialized_departures() {
+                materialized.id = unique_id(&materialized.id, &used_ids);
+                used_ids.insert(materialized.id.clone());
+                trips.push((materialized.id.clone(), materialized));
+            }
+        }
+        self.trips = trips.into_iter().collect();
+        self.rebuild_indexes();
+        if sorted {
+            self.sort_by_id();
+        }
+    }
+}
+
+impl Trip {
+    /// Converts the [Frequency] of the trip into one trip per departure, without frequency, for the tools that only handle schedules
+    ///
+    /// A vehicle leaves the first stop every `headway_secs` from `start_time` until `end_time` (excluded),
+    /// and the stop times are shifted by the same delay. Each trip has the `trip_id` followed by the time of its departure,
+    /// and by `_2`, `_3`… when overlapping frequencies have the same departure.
+    /// With [ExactTimes::FrequencyBased], the times are only an estimation of the service,
+    /// and the stop times are marked as [TimepointType::Approximate].
+    /// The trip is returned unchanged if it has no frequency
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let trips = gtfs.trips["trip1"].materialized_departures();
+    /// assert_eq!(1, trips.len());
+    /// assert_eq!("trip1_05:30:00", trips[0].id);
+    /// assert_eq!(Some(6 * 3600 + 30 * 60), trips[0].stop_times[1].arrival_time);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn materialized_departures(&self) -> Vec<Trip> {
+        if self.frequencies.is_empty() {
+            return vec![self.clone()];
+        }
+        let first_time = first_time(self);
+        let mut used_ids = HashSet::new();
+        self.frequencies
+            .iter()
+            .filter(|f| f.headway_secs > 0)
+            .flat_map(|f| {
+                (f.start_time..f.end_time)
+                    .step_by(f.headway_secs as usize)
+                    .map(move |start| (f, start))
+            })
+            .map(|(frequency, start)| {
+                let shift =
+                    |time: Option<u32>| time.map(|t| (t + start).saturating_sub(first_time));
+                let exact = frequency.exact_times == Some(ExactTimes::ScheduleBased);
+                let id = unique_id(&format!("{}_{}", self.id, format_time(start)), &used_ids);
+                used_ids.insert(id.clone());
+                Trip {
+                    id,
+                    stop_times: self
+                        .stop_times
+                        .iter()
+                        .map(|st| StopTime {
+                            arrival_time: shift(st.arrival_time),
+                            departure_time: shift(st.departure_time),
+                            timepoint: if exact {
+                                st.timepoint
+                            } else {
+                                TimepointType::Approximate
+                            },
+                            ..st.clone()
+                        })
+                        .collect(),
+                    frequencies: Vec::new(),
+                    ..self.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+/// The identifier, followed by `_2`, `_3`… if it is already used
+fn unique_id(id: &str, used_ids: &HashSet<GtfsId>) -> GtfsId {
+    let mut unique = GtfsId::from(id);
+    let mut n = 2;
+    while used_ids.contains(&unique) {
+        unique = GtfsId::from(format!("{}_{}", id, n).as_str());
+        n += 1;
+    }
+    unique
+}
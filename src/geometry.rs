@@ -0,0 +1,463 @@
+use crate::{objects::*, Error, Gtfs};
+use std::collections::HashMap;
+
+/// Mean radius of the Earth in meters
+pub(crate) const EARTH_RADIUS: f64 = 6_371_000.;
+
+/// Distance in meters between two coordinates along the surface of the Earth, considered as a sphere
+///
+/// ```
+/// // One degree of longitude at the equator
+/// let distance = gtfs_structures::haversine(0., 179.5, 0., -179.5);
+/// assert_eq!(111_195., distance.round());
+/// ```
+pub fn haversine(latitude1: f64, longitude1: f64, latitude2: f64, longitude2: f64) -> f64 {
+    let (latitude1, latitude2) = (latitude1.to_radians(), latitude2.to_radians());
+    let half_latitude = (latitude2 - latitude1) / 2.;
+    let half_longitude = (longitude2 - longitude1).to_radians() / 2.;
+    let a = half_latitude.sin().powi(2)
+        + latitude1.cos() * latitude2.cos() * half_longitude.sin().powi(2);
+    2. * EARTH_RADIUS * a.sqrt().min(1.).asin()
+}
+
+/// Length in meters of the line going through the points in their order, like the points of a shape.
+/// 0 with less than two points
+///
+/// The points of the shapes of a [crate::Gtfs] are sorted by `shape_pt_sequence` when it is read
+pub fn polyline_length(points: &[Shape]) -> f64 {
+    points
+        .windows(2)
+        .map(|w| haversine(w[0].latitude, w[0].longitude, w[1].latitude, w[1].longitude))
+        .sum()
+}
+
+/// Distance along the points of a shape: their `shape_dist_traveled` if they all have one, or else the length in meters from the first point
+fn point_distances(points: &[Shape]) -> Vec<f64> {
+    if let Some(distances) = points
+        .iter()
+        .map(|p| p.dist_traveled.map(f64::from))
+        .collect::<Option<Vec<_>>>()
+    {
+        return distances;
+    }
+    point_lengths(points)
+}
+
+/// Length in meters along the points of a shape from the first point
+pub(crate) fn point_lengths(points: &[Shape]) -> Vec<f64> {
+    let mut length = 0.;
+    let mut distances = Vec::with_capacity(points.len());
+    for (i, point) in points.iter().enumerate() {
+        if i > 0 {
+            let previous = &points[i - 1];
+            length += haversine(
+                previous.latitude,
+                previous.longitude,
+                point.latitude,
+                point.longitude,
+            );
+        }
+        distances.push(length);
+    }
+    distances
+}
+
+/// Measures of a [Trip], see [Trip::stats]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TripStats {
+    /// Seconds between the departure from the first stop and the arrival at the last one. None without these times
+    pub duration: Option<u32>,
+    /// Length in meters of the line followed by the trip between its first and last stops, see [Gtfs::segment_geometry]
+    pub distance: f64,
+    /// Number of stop times
+    pub stops: usize,
+    /// Average speed in meters per second over the duration. None without duration
+    pub average_speed: Option<f64>,
+    /// Seconds between the arrival and the departure at each stop, None if one of the times is missing
+    pub dwell_times: Vec<Option<u32>>,
+}
+
+impl Trip {
+    /// Measures the duration, distance and speed of the trip
+    ///
+    /// The distance follows the shape of the trip if it has one, or else straight lines between its stops
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let stats = gtfs.trips["trip1"].stats(&gtfs)?;
+    /// assert_eq!(Some(2 * 3600), stats.duration);
+    /// assert_eq!(3, stats.stops);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn stats(&self, gtfs: &Gtfs) -> Result<TripStats, Error> {
+        let (first, last) = match (self.stop_times.first(), self.stop_times.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(TripStats::default()),
+        };
+        let duration = match (
+            first.departure_time.or(first.arrival_time),
+            last.arrival_time.or(last.departure_time),
+        ) {
+            (Some(start), Some(end)) => Some(end.saturating_sub(start)),
+            _ => None,
+        };
+        let line = gtfs.trip_segment(self, 0, self.stop_times.len() - 1)?;
+        let distance = line
+            .windows(2)
+            .map(|w| haversine(w[0].0, w[0].1, w[1].0, w[1].1))
+            .sum();
+        Ok(TripStats {
+            duration,
+            distance,
+            stops: self.stop_times.len(),
+            average_speed: duration.filter(|d| *d > 0).map(|d| distance / f64::from(d)),
+            dwell_times: self
+                .stop_times
+                .iter()
+                .map(|st| match (st.arrival_time, st.departure_time) {
+                    (Some(arrival), Some(departure)) => Some(departure.saturating_sub(arrival)),
+                    _ => None,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Projection of a stop on the shape of its trip, see [Gtfs::snap_stop_times_to_shape]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapeProjection {
+    /// Index in the shape points of the start of the segment the stop is projected on
+    pub point_index: usize,
+    /// Position of the projection on the segment, from 0 at the point `point_index` to 1 at the next point
+    pub offset: f64,
+    /// Distance of the projection along the shape, in the unit of its `shape_dist_traveled`, or in meters if it has none
+    pub distance: f64,
+}
+
+/// Projects each position on the line going through the points.
+/// A projection is never before the previous one, so that a line going back on its steps is followed in order
+pub(crate) fn project(
+    points: &[Shape],
+    distances: &[f64],
+    positions: impl Iterator<Item = Option<(f64, f64)>>,
+) -> Vec<Option<ShapeProjection>> {
+    let mut segment = 0;
+    let mut ratio = 0.;
+    positions
+        .map(|position| {
+            let (latitude, longitude) = position?;
+            if points.len() < 2 {
+                return distances.first().map(|distance| ShapeProjection {
+                    point_index: 0,
+                    offset: 0.,
+                    distance: *distance,
+                });
+            }
+            let mut best: Option<(f64, usize, f64)> = None;
+            for i in segment..points.len() - 1 {
+                let min_offset = if i == segment { ratio } else { 0. };
+                let (distance_2, t) =
+                    closest_on_segment(&points[i], &points[i + 1], latitude, longitude, min_offset);
+                if best.is_none_or(|(d, _, _)| distance_2 < d) {
+                    best = Some((distance_2, i, t));
+                }
+            }
+            let (_, i, t) = best?;
+            segment = i;
+            ratio = t;
+            Some(ShapeProjection {
+                point_index: i,
+                offset: t,
+                distance: distances[i] + t * (distances[i + 1] - distances[i]),
+            })
+        })
+        .collect()
+}
+
+/// Closest point to a position on the segment from `a` to `b`, at an offset of at least `min_offset` on the segment.
+/// Returns its squared distance to the position, only meant to be compared, and its offset, from 0 at `a` to 1 at `b`
+fn closest_on_segment(
+    a: &Shape,
+    b: &Shape,
+    latitude: f64,
+    longitude: f64,
+    min_offset: f64,
+) -> (f64, f64) {
+    // Coordinates on a plane tangent to the Earth at the position, good enough along a segment
+    let scale = latitude.to_radians().cos();
+    let plane = |p: &Shape| ((p.longitude - longitude) * scale, p.latitude - latitude);
+    let (ax, ay) = plane(a);
+    let (bx, by) = plane(b);
+    let (dx, dy) = (bx - ax, by - ay);
+    let length_2 = dx * dx + dy * dy;
+    let t = if length_2 > 0. {
+        (-(ax * dx + ay * dy) / length_2).clamp(0., 1.)
+    } else {
+        0.
+    }
+    .max(min_offset);
+    let (x, y) = (ax + t * dx, ay + t * dy);
+    (x * x + y * y, t)
+}
+
+/// Distance in meters from a position to the closest point of the line going through the points. None without points
+fn distance_to_line(points: &[Shape], latitude: f64, longitude: f64) -> Option<f64> {
+    let mut best: Option<(f64, &Shape, &Shape, f64)> = None;
+    let segments = points
+        .windows(2)
+        .map(|w| (&w[0], &w[1]))
+        .chain(points.first().filter(|_| points.len() == 1).map(|p| (p, p)));
+    for (a, b) in segments {
+        let (distance_2, t) = closest_on_segment(a, b, latitude, longitude, 0.);
+        if best.is_none_or(|(d, _, _, _)| distance_2 < d) {
+            best = Some((distance_2, a, b, t));
+        }
+    }
+    let (_, a, b, t) = best?;
+    Some(haversine(
+        latitude,
+        longitude,
+        a.latitude + t * (b.latitude - a.latitude),
+        a.longitude + t * (b.longitude - a.longitude),
+    ))
+}
+
+/// Stop time whose stop is far from the shape of its trip, see [Gtfs::stops_far_from_shape]
+#[derive(Debug, Clone, Copy)]
+pub struct StopFarFromShape<'a> {
+    /// The trip
+    pub trip: &'a Trip,
+    /// Index of the stop time in [Trip::stop_times]
+    pub index: usize,
+    /// Distance in meters between the stop and the closest point of the shape
+    pub distance: f64,
+}
+
+impl Gtfs {
+    /// Lists the stop times whose stop is more than `max_distance` meters away from the shape of their trip,
+    /// which usually means that the trip has the wrong shape or that the stop has wrong coordinates
+    ///
+    /// The distance is measured to the closest point of the shape, wherever it is along the shape.
+    /// The trips without shape, and the stops without coordinates, are ignored.
+    /// The stop times are sorted by `trip_id`, then by their order in the trip
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// assert!(gtfs.stops_far_from_shape(100.)?.is_empty());
+    /// gtfs.trips.get_mut("trip1").unwrap().shape_id = Some("A_shp".into());
+    /// // The shape is in San Francisco, and the stops in Paris
+    /// let far = gtfs.stops_far_from_shape(100.)?;
+    /// assert_eq!(3, far.len());
+    /// assert!(far[0].distance > 8_000_000.);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn stops_far_from_shape(
+        &self,
+        max_distance: f64,
+    ) -> Result<Vec<StopFarFromShape<'_>>, Error> {
+        let mut trips: Vec<&Trip> = self.trips.values().collect();
+        trips.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut distances: HashMap<(&str, &str), Option<f64>> = HashMap::new();
+        let mut result = Vec::new();
+        for trip in trips {
+            let shape_id = match trip.shape_id.as_deref() {
+                Some(shape_id) => shape_id,
+                None => continue,
+            };
+            let points = match self
+                .lazy_shapes
+                .get(shape_id)
+                .or_else(|| self.shapes.get(shape_id).map(Ok))
+            {
+                Some(points) => points?,
+                None => continue,
+            };
+            for (index, stop_time) in trip.stop_times.iter().enumerate() {
+                let stop = &stop_time.stop;
+                let distance = *distances
+                    .entry((shape_id, stop.id.as_str()))
+                    .or_insert_with(|| {
+                        let (latitude, longitude) = stop.latitude.zip(stop.longitude)?;
+                        distance_to_line(points, latitude, longitude)
+                    });
+                if let Some(distance) = distance.filter(|d| *d > max_distance) {
+                    result.push(StopFarFromShape {
+                        trip,
+                        index,
+                        distance,
+                    });
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Projects the stop of each stop time of a trip on the shape of the trip, in order
+    ///
+    /// A projection is never before the previous one along the shape, so that a shape going back on its steps is followed in order.
+    /// A stop time has no projection if its stop has no coordinates, and the result is empty if the trip has no shape
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// gtfs.trips.get_mut("trip1").unwrap().shape_id = Some("A_shp".into());
+    /// let projections = gtfs.snap_stop_times_to_shape("trip1")?;
+    /// // The stops of the fixture are far from the shape, and closest to its last point
+    /// assert_eq!(1, projections[0].unwrap().point_index);
+    /// assert_eq!(1., projections[0].unwrap().offset);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn snap_stop_times_to_shape(
+        &self,
+        trip_id: &str,
+    ) -> Result<Vec<Option<ShapeProjection>>, Error> {
+        let trip = self.get_trip(trip_id)?;
+        let points = match &trip.shape_id {
+            Some(shape_id) => self.get_shape(shape_id)?,
+            None => return Ok(Vec::new()),
+        };
+        Ok(project(
+            points,
+            &point_distances(points),
+            stop_positions(trip),
+        ))
+    }
+
+    /// Coordinates `(latitude, longitude)` of the line followed by a trip: the points of its shape,
+    /// or else straight lines between its stops with coordinates
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// assert_eq!(3, gtfs.trip_geometry("trip1")?.len());
+    /// gtfs.trips.get_mut("trip1").unwrap().shape_id = Some("A_shp".into());
+    /// assert_eq!((37.61956, -122.48161), gtfs.trip_geometry("trip1")?[0]);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn trip_geometry(&self, trip_id: &str) -> Result<Vec<(f64, f64)>, Error> {
+        let trip = self.get_trip(trip_id)?;
+        Ok(match &trip.shape_id {
+            Some(shape_id) => self
+                .get_shape(shape_id)?
+                .iter()
+                .map(|p| (p.latitude, p.longitude))
+                .collect(),
+            None => stop_positions(trip).flatten().collect(),
+        })
+    }
+
+    /// Coordinates `(latitude, longitude)` of the line followed by a trip between two of its stop times, given by their `stop_sequence`
+    ///
+    /// The shape of the trip is cut between the projections of both stops, see [Gtfs::snap_stop_times_to_shape].
+    /// Without a shape, or if one of the stops has no coordinates, the line goes straight between the stops with coordinates.
+    /// Empty if `to_sequence` is before `from_sequence`
+    pub fn segment_geometry(
+        &self,
+        trip_id: &str,
+        from_sequence: u16,
+        to_sequence: u16,
+    ) -> Result<Vec<(f64, f64)>, Error> {
+        let trip = self.get_trip(trip_id)?;
+        let position = |sequence: u16| {
+            trip.stop_times
+                .iter()
+                .position(|st| st.stop_sequence == sequence)
+                .ok_or_else(|| Error::ReferenceError(format!("{trip_id} stop_sequence {sequence}")))
+        };
+        self.trip_segment(trip, position(from_sequence)?, position(to_sequence)?)
+    }
+
+    /// Line followed by the trip between the stop times at the indices `from` and `to` of [Trip::stop_times],
+    /// see [Gtfs::segment_geometry]
+    pub(crate) fn trip_segment(
+        &self,
+        trip: &Trip,
+        from: usize,
+        to: usize,
+    ) -> Result<Vec<(f64, f64)>, Error> {
+        if to < from {
+            return Ok(Vec::new());
+        }
+        if let Some(shape_id) = &trip.shape_id {
+            let points = self.get_shape(shape_id)?;
+            let projections = project(points, &point_distances(points), stop_positions(trip));
+            if let (Some(start), Some(end)) = (projections[from], projections[to]) {
+                let at = |p: ShapeProjection| {
+                    let a = &points[p.point_index];
+                    let b = points.get(p.point_index + 1).unwrap_or(a);
+                    (
+                        a.latitude + p.offset * (b.latitude - a.latitude),
+                        a.longitude + p.offset * (b.longitude - a.longitude),
+                    )
+                };
+                let mut line = vec![at(start)];
+                line.extend(
+                    points[start.point_index + 1..=end.point_index]
+                        .iter()
+                        .map(|p| (p.latitude, p.longitude)),
+                );
+                line.push(at(end));
+                line.dedup();
+                return Ok(line);
+            }
+        }
+        Ok(trip.stop_times[from..=to]
+            .iter()
+            .filter_map(|st| st.stop.latitude.zip(st.stop.longitude))
+            .collect())
+    }
+
+    /// Fills the missing `shape_dist_traveled` of the shape points and of the stop times
+    ///
+    /// The points of a shape without any distance get their distance in meters from the first point.
+    /// The stop times of a trip with a shape but without any distance get the distance of the projection of their stop on the shape,
+    /// in the unit of the distances of the shape. The stops are projected in order, without going back along the shape.
+    /// The shapes read with [crate::GtfsReader::lazy_shapes] are not modified, but are used for the stop times
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// gtfs.shapes.get_mut("A_shp").unwrap().iter_mut().for_each(|p| p.dist_traveled = None);
+    /// gtfs.fill_shape_dist_traveled()?;
+    /// assert_eq!(Some(15_970.), gtfs.get_shape("A_shp")?[2].dist_traveled.map(f32::round));
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn fill_shape_dist_traveled(&mut self) -> Result<(), Error> {
+        for points in self.shapes.values_mut() {
+            if points.iter().all(|p| p.dist_traveled.is_none()) {
+                let distances = point_distances(points);
+                for (point, distance) in points.iter_mut().zip(distances) {
+                    point.dist_traveled = Some(distance as f32);
+                }
+            }
+        }
+
+        let (shapes, lazy_shapes) = (&self.shapes, &self.lazy_shapes);
+        for trip in self.trips.values_mut() {
+            if trip
+                .stop_times
+                .iter()
+                .any(|st| st.shape_dist_traveled.is_some())
+            {
+                continue;
+            }
+            let points = match trip
+                .shape_id
+                .as_deref()
+                .and_then(|id| lazy_shapes.get(id).or_else(|| shapes.get(id).map(Ok)))
+            {
+                Some(points) => points?,
+                None => continue,
+            };
+            let projections = project(points, &point_distances(points), stop_positions(trip));
+            for (stop_time, distance) in trip.stop_times.iter_mut().zip(projections) {
+                stop_time.shape_dist_traveled = distance.map(|p| p.distance as f32);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Latitude and longitude of the stop of each stop time
+pub(crate) fn stop_positions(trip: &Trip) -> impl Iterator<Item = Option<(f64, f64)>> + '_ {
+    trip.stop_times
+        .iter()
+        .map(|st| st.stop.latitude.zip(st.stop.longitude))
+}
@@ -1,9 +1,17 @@
-use crate::{objects::*, Error, RawGtfs};
+use crate::error::{BrokenReference, DuplicateId, ValidationReport};
+use crate::geometry::{haversine, polyline_length};
+use crate::id_map::IdHashMap;
+use crate::trip_times::trip_span;
+use crate::{
+    objects::*, BoundingBox, DuplicatePolicy, Error, GtfsReader, GtfsWriter, IdMap, LazyShapes,
+    ParseMetrics, RawGtfs,
+};
 use chrono::prelude::NaiveDate;
-use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 /// Data structure with all the GTFS objects
 ///
@@ -21,26 +29,44 @@ use std::time::{Duration, Instant};
 /// The [StopTime] are accessible from the [Trip]
 #[derive(Default)]
 pub struct Gtfs {
-    /// Time needed to read and parse the archive
-    pub read_duration: Duration,
+    /// Time needed to read and parse the archive, and of each of its files
+    pub metrics: ParseMetrics,
+    /// Rows dropped because of a broken reference, a duplicated identifier or a duplicated stop time, and stops with invalid coordinates, see [ValidationReport]
+    pub validation_report: ValidationReport,
     /// All Calendar by `service_id`
-    pub calendar: HashMap<String, Calendar>,
+    pub calendar: IdMap<Calendar>,
     /// All calendar dates grouped by service_id
-    pub calendar_dates: HashMap<String, Vec<CalendarDate>>,
+    pub calendar_dates: IdMap<Vec<CalendarDate>>,
     /// All stop by `stop_id`. Stops are in an [Arc] because they are also referenced by each [StopTime]
-    pub stops: HashMap<String, Arc<Stop>>,
+    pub stops: IdMap<Arc<Stop>>,
     /// All routes by `route_id`
-    pub routes: HashMap<String, Route>,
+    pub routes: IdMap<Route>,
     /// All trips by `trip_id`
-    pub trips: HashMap<String, Trip>,
+    pub trips: IdMap<Trip>,
+    /// `trip_id` of the trips of each route, sorted, by `route_id`. See [Gtfs::trips_for_route]
+    ///
+    /// It is built when the GTFS is read, and again by [Gtfs::rebuild_indexes]
+    pub(crate) route_trips: IdMap<Vec<GtfsId>>,
+    /// `trip_id` of the trips calling at each stop or at one of its child stops, sorted, by `stop_id`.
+    /// See [Gtfs::trips_at_stop]
+    ///
+    /// It is built when the GTFS is read, and again by [Gtfs::rebuild_indexes]
+    pub(crate) stop_trips: IdMap<Vec<GtfsId>>,
+    /// Position of each stop with coordinates, see [Gtfs::nearest_stops]. Requires the feature `spatial`
+    ///
+    /// It is built when the GTFS is read, and again by [Gtfs::rebuild_indexes]
+    #[cfg(feature = "spatial")]
+    pub(crate) stop_tree: rstar::RTree<crate::StopPosition>,
     /// All agencies. They can not be read by `agency_id`, as it is not a required field
     pub agencies: Vec<Agency>,
     /// All shapes by shape_id
-    pub shapes: HashMap<String, Vec<Shape>>,
+    pub shapes: IdMap<Vec<Shape>>,
+    /// Shapes parsed only when requested, if read with [crate::GtfsReader::lazy_shapes]. [Gtfs::shapes] is then empty
+    pub lazy_shapes: LazyShapes,
     /// All fare attributes by `fare_id`
-    pub fare_attributes: HashMap<String, FareAttribute>,
+    pub fare_attributes: IdMap<FareAttribute>,
     /// All fare rules by `fare_id`
-    pub fare_rules: HashMap<String, Vec<FareRule>>,
+    pub fare_rules: IdMap<Vec<FareRule>>,
     /// All feed information. There is no identifier
     pub feed_info: Vec<FeedInfo>,
 }
@@ -51,51 +77,205 @@ impl TryFrom<RawGtfs> for Gtfs {
     ///
     /// It might fail if some mandatory files couldn’t be read or if there are references to other objects that are invalid.
     fn try_from(raw: RawGtfs) -> Result<Gtfs, Error> {
+        Gtfs::build(raw, false, DuplicatePolicy::default())
+    }
+}
+
+impl Gtfs {
+    /// Builds a [Gtfs] from a [RawGtfs], like [Gtfs::try_from], but drops the rows with references to unknown objects
+    ///
+    /// The dropped rows are listed in [Gtfs::validation_report]
+    /// ```
+    /// let raw = gtfs_structures::RawGtfs::from_path("fixtures/broken_references")?;
+    /// let gtfs = gtfs_structures::Gtfs::try_from_skipping_broken_references(raw)?;
+    /// assert_eq!(2, gtfs.get_trip("trip1")?.stop_times.len());
+    /// assert_eq!(4, gtfs.validation_report.broken_references.len());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn try_from_skipping_broken_references(raw: RawGtfs) -> Result<Gtfs, Error> {
+        Gtfs::build(raw, true, DuplicatePolicy::default())
+    }
+
+    /// Builds a [Gtfs] from a [RawGtfs]. The broken references are errors unless `skip_broken_references` is set,
+    /// the duplicated identifiers are handled according to `duplicate_policy`
+    pub(crate) fn build(
+        raw: RawGtfs,
+        skip_broken_references: bool,
+        duplicate_policy: DuplicatePolicy,
+    ) -> Result<Gtfs, Error> {
         let start = Instant::now();
+        let mut check = BuildCheck::new(skip_broken_references, duplicate_policy);
+        let mut stop_check = BuildCheck::new(skip_broken_references, duplicate_policy);
+        let mut trip_check = BuildCheck::new(skip_broken_references, duplicate_policy);
 
-        let stops = to_stop_map(
-            raw.stops?,
-            raw.transfers.unwrap_or_else(|| Ok(Vec::new()))?,
-            raw.pathways.unwrap_or(Ok(Vec::new()))?,
-        )?;
+        let raw_stops = raw.stops?;
+        let transfers = raw.transfers.unwrap_or_else(|| Ok(Vec::new()))?;
+        let pathways = raw.pathways.unwrap_or(Ok(Vec::new()))?;
         let frequencies = raw.frequencies.unwrap_or_else(|| Ok(Vec::new()))?;
-        let trips = create_trips(raw.trips?, raw.stop_times?, frequencies, &stops)?;
+        let raw_trips = raw.trips?;
+        let raw_shapes = raw.shapes.unwrap_or_else(|| Ok(Vec::new()))?;
+
+        // The stops, trips and shapes do not depend on each other and are built at the same time
+        #[cfg(feature = "parallel")]
+        let (stops, (trips, shapes)) = rayon::join(
+            || to_stop_map(raw_stops, transfers, pathways, &mut stop_check),
+            || {
+                rayon::join(
+                    || create_trips(raw_trips, frequencies, &mut trip_check),
+                    || to_shape_map(raw_shapes),
+                )
+            },
+        );
+        #[cfg(not(feature = "parallel"))]
+        let (stops, trips, shapes) = (
+            to_stop_map(raw_stops, transfers, pathways, &mut stop_check),
+            create_trips(raw_trips, frequencies, &mut trip_check),
+            to_shape_map(raw_shapes),
+        );
+        let stops = stops?;
+        let mut trips = trips?;
 
-        let mut fare_rules = HashMap::<String, Vec<FareRule>>::new();
+        // The raw stop times are consumed one by one to limit the peak of memory
+        let mut raw_stop_times = raw.stop_times?;
+        raw_stop_times.reverse();
+        let mut st_idx = 0;
+        while let Some(s) = raw_stop_times.pop() {
+            st_idx += 1;
+            add_stop_time(&mut trips, &stops, s, &mut trip_check)?;
+            if st_idx % NB_STOP_TIMES_BEFORE_SHRINK == 0 {
+                raw_stop_times.shrink_to_fit();
+            }
+        }
+        drop(raw_stop_times);
+        sort_stop_times(&mut trips);
+
+        let mut fare_rules = IdHashMap::<Vec<FareRule>>::default();
         for f in raw.fare_rules.unwrap_or_else(|| Ok(Vec::new()))? {
             (*fare_rules.entry(f.fare_id.clone()).or_default()).push(f);
         }
 
+        let routes = to_map(raw.routes?, TableName::Routes, &mut check)?;
+        let calendar = to_map(
+            raw.calendar.unwrap_or_else(|| Ok(Vec::new()))?,
+            TableName::Calendar,
+            &mut check,
+        )?;
+        let fare_attributes = to_map(
+            raw.fare_attributes.unwrap_or_else(|| Ok(Vec::new()))?,
+            TableName::FareAttributes,
+            &mut check,
+        )?;
+        stop_check.merge(trip_check);
+        stop_check.merge(check);
+        stop_check.report.invalid_coordinates = crate::coordinates::coordinate_findings(&stops);
+
         Ok(Gtfs {
+            validation_report: stop_check.report,
+            routes,
+            route_trips: to_route_trips(&trips),
+            stop_trips: to_stop_trips(&trips, &stops),
+            #[cfg(feature = "spatial")]
+            stop_tree: crate::spatial::to_stop_tree(&stops),
             stops,
-            routes: to_map(raw.routes?),
             trips,
             agencies: raw.agencies?,
-            shapes: to_shape_map(raw.shapes.unwrap_or_else(|| Ok(Vec::new()))?),
-            fare_attributes: to_map(raw.fare_attributes.unwrap_or_else(|| Ok(Vec::new()))?),
-            fare_rules,
+            shapes,
+            lazy_shapes: LazyShapes::default(),
+            fare_attributes,
+            fare_rules: fare_rules.into(),
             feed_info: raw.feed_info.unwrap_or_else(|| Ok(Vec::new()))?,
-            calendar: to_map(raw.calendar.unwrap_or_else(|| Ok(Vec::new()))?),
+            calendar,
             calendar_dates: to_calendar_dates(
                 raw.calendar_dates.unwrap_or_else(|| Ok(Vec::new()))?,
             ),
-            read_duration: raw.read_duration + start.elapsed(),
+            metrics: ParseMetrics {
+                total_duration: raw.metrics.total_duration + start.elapsed(),
+                files: raw.metrics.files,
+            },
         })
     }
-}
 
-impl Gtfs {
+    /// Links a stop time to its trip and stop, when the stop times are parsed after the rest of the GTFS
+    ///
+    /// [Gtfs::sort_stop_times] must be called once all the stop times are added
+    pub(crate) fn add_stop_time(
+        &mut self,
+        stop_time: RawStopTime,
+        skip_broken_references: bool,
+    ) -> Result<(), Error> {
+        let mut check = BuildCheck::new(skip_broken_references, DuplicatePolicy::default());
+        add_stop_time(&mut self.trips, &self.stops, stop_time, &mut check)?;
+        self.validation_report
+            .broken_references
+            .extend(check.report.broken_references);
+        Ok(())
+    }
+
+    /// Sorts the stop times of each trip once they are all added, and indexes the trips by stop
+    pub(crate) fn sort_stop_times(&mut self) {
+        sort_stop_times(&mut self.trips);
+        self.stop_trips = to_stop_trips(&self.trips, &self.stops);
+    }
+
+    /// Builds again the indexes of the trips by route and by stop, and of the stops by position
+    ///
+    /// They are built when the GTFS is read. [Gtfs::trips_for_route], [Gtfs::trips_at_stop], [Gtfs::routes_at_stop],
+    /// [Gtfs::stops_in_bbox], [Gtfs::stops_within] and [Gtfs::trips_between] use them, so this must be called
+    /// once [Gtfs::trips] or [Gtfs::stops] are modified
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let mut trip = gtfs.get_trip("trip1")?.clone();
+    /// trip.id = "trip2".into();
+    /// gtfs.trips.insert(trip.id.clone(), trip);
+    /// assert_eq!(1, gtfs.trips_for_route("route1").count());
+    /// gtfs.rebuild_indexes();
+    /// assert_eq!(2, gtfs.trips_for_route("route1").count());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn rebuild_indexes(&mut self) {
+        self.route_trips = to_route_trips(&self.trips);
+        self.stop_trips = to_stop_trips(&self.trips, &self.stops);
+        #[cfg(feature = "spatial")]
+        {
+            self.stop_tree = crate::spatial::to_stop_tree(&self.stops);
+        }
+        if self.trips.is_sorted_by_id() {
+            self.route_trips.sort_by_id();
+            self.stop_trips.sort_by_id();
+        }
+    }
+
+    /// Stores all the objects in vectors sorted by identifier, see [IdMap]
+    ///
+    /// They use less memory, but inserting or removing an object is slower
+    pub fn sort_by_id(&mut self) {
+        self.calendar.sort_by_id();
+        self.calendar_dates.sort_by_id();
+        self.stops.sort_by_id();
+        self.routes.sort_by_id();
+        self.trips.sort_by_id();
+        self.route_trips.sort_by_id();
+        self.stop_trips.sort_by_id();
+        self.shapes.sort_by_id();
+        self.fare_attributes.sort_by_id();
+        self.fare_rules.sort_by_id();
+    }
+
     /// Prints on stdout some basic statistics about the GTFS file (numbers of elements for each object). Mostly to be sure that everything was read
+    ///
+    /// See [Gtfs::stats] to get these numbers as a serializable struct
     pub fn print_stats(&self) {
+        let stats = self.stats();
         println!("GTFS data:");
-        println!("  Read in {:?}", self.read_duration);
-        println!("  Stops: {}", self.stops.len());
-        println!("  Routes: {}", self.routes.len());
-        println!("  Trips: {}", self.trips.len());
-        println!("  Agencies: {}", self.agencies.len());
-        println!("  Shapes: {}", self.shapes.len());
-        println!("  Fare attributes: {}", self.fare_attributes.len());
-        println!("  Feed info: {}", self.feed_info.len());
+        println!("  Read in {:?}", self.metrics.total_duration);
+        println!("  Stops: {}", stats.stops);
+        println!("  Routes: {}", stats.routes);
+        println!("  Trips: {}", stats.trips);
+        println!("  Agencies: {}", stats.agencies);
+        println!("  Shapes: {}", stats.shapes);
+        println!("  Fare attributes: {}", stats.fare_attributes);
+        println!("  Feed info: {}", stats.feed_info);
     }
 
     /// Reads from an url (if starts with `"http"`), or a local path (either a directory or zipped file)
@@ -103,7 +283,7 @@ impl Gtfs {
     /// To read from an url, build with read-url feature
     /// See also [Gtfs::from_url] and [Gtfs::from_path] if you don’t want the library to guess
     pub fn new(gtfs: &str) -> Result<Gtfs, Error> {
-        RawGtfs::new(gtfs).and_then(Gtfs::try_from)
+        GtfsReader::default().read(gtfs)
     }
 
     /// Reads the GTFS from a local zip archive or local directory
@@ -111,7 +291,22 @@ impl Gtfs {
     where
         P: AsRef<std::path::Path>,
     {
-        RawGtfs::from_path(path).and_then(Gtfs::try_from)
+        GtfsReader::default().read_from_path(path)
+    }
+
+    /// Reads again a local zip archive or local directory, only parsing the files changed since `previous` was read
+    ///
+    /// `previous` must have been read with [crate::GtfsReader::hash_files], see [crate::RawGtfsReader::reload_if_changed]
+    pub fn reload_if_changed<P>(path: P, previous: &RawGtfs) -> Result<Gtfs, Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        Gtfs::try_from(
+            GtfsReader::default()
+                .hash_files(true)
+                .raw()
+                .reload_if_changed(path, previous)?,
+        )
     }
 
     /// Reads the GTFS from a remote url
@@ -119,7 +314,7 @@ impl Gtfs {
     /// The library must be built with the read-url feature
     #[cfg(feature = "read-url")]
     pub fn from_url<U: reqwest::IntoUrl>(url: U) -> Result<Gtfs, Error> {
-        RawGtfs::from_url(url).and_then(Gtfs::try_from)
+        GtfsReader::default().read_from_url(url)
     }
 
     /// Asynchronously reads the GTFS from a remote url
@@ -127,14 +322,55 @@ impl Gtfs {
     /// The library must be built with the read-url feature
     #[cfg(feature = "read-url")]
     pub async fn from_url_async<U: reqwest::IntoUrl>(url: U) -> Result<Gtfs, Error> {
-        RawGtfs::from_url_async(url).await.and_then(Gtfs::try_from)
+        GtfsReader::default().read_from_url_async(url).await
     }
 
     /// Reads for any object implementing [std::io::Read] and [std::io::Seek]
     ///
     /// Mostly an internal function that abstracts reading from an url or local file
     pub fn from_reader<T: std::io::Read + std::io::Seek>(reader: T) -> Result<Gtfs, Error> {
-        RawGtfs::from_reader(reader).and_then(Gtfs::try_from)
+        GtfsReader::default().read_from_reader(reader)
+    }
+
+    /// Reads for any object implementing [std::io::Read], even if it cannot seek (e.g. the standard input)
+    ///
+    /// The whole archive is first loaded in memory
+    pub fn from_stream<T: std::io::Read>(reader: T) -> Result<Gtfs, Error> {
+        GtfsReader::default().read_from_stream(reader)
+    }
+
+    /// Reads a zipped GTFS already loaded in memory (e.g. from a database or a cache)
+    ///
+    /// ```
+    ///let bytes = std::fs::read("fixtures/zips/gtfs.zip")?;
+    ///let gtfs = gtfs_structures::Gtfs::from_bytes(&bytes)?;
+    ///assert_eq!(1, gtfs.trips.len());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    ///```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Gtfs, Error> {
+        GtfsReader::default().read_from_reader(std::io::Cursor::new(bytes))
+    }
+
+    /// Writes only the given tables as a zip archive in any object implementing [std::io::Write] and [std::io::Seek]
+    ///
+    /// ```
+    ///let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    ///let tables = [gtfs_structures::TableName::Stops, gtfs_structures::TableName::Pathways];
+    ///let archive = gtfs.export(&tables, std::io::Cursor::new(Vec::new()))?;
+    ///let files = gtfs_structures::RawGtfs::from_reader(archive)?.files;
+    ///assert_eq!(vec!["stops.txt", "pathways.txt"], files);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    ///```
+    ///
+    /// [TableName::Translations] can not be exported, see [GtfsWriter::tables] and [GtfsWriter] for more options
+    pub fn export<W: std::io::Write + std::io::Seek>(
+        &self,
+        tables: &[TableName],
+        writer: W,
+    ) -> Result<W, Error> {
+        GtfsWriter::default()
+            .tables(tables)
+            .write_to_writer(self, writer)
     }
 
     /// For a given a `service_id` and a starting date returns all the following day offset the vehicle runs
@@ -186,6 +422,218 @@ impl Gtfs {
         result
     }
 
+    /// Returns true if the service runs on that day
+    ///
+    /// A [CalendarDate] of that day for the service overrides its [Calendar]
+    pub fn is_service_active(&self, service_id: &str, date: NaiveDate) -> bool {
+        let exception = self
+            .calendar_dates
+            .get(service_id)
+            .and_then(|dates| dates.iter().find(|d| d.date == date));
+        match exception {
+            Some(d) => d.exception_type == Exception::Added,
+            None => self.calendar.get(service_id).is_some_and(|c| {
+                c.start_date <= date && date <= c.end_date && c.valid_weekday(date)
+            }),
+        }
+    }
+
+    /// Returns the `service_id` of all the services running on that day, see [Gtfs::is_service_active]
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let new_year = chrono::NaiveDate::from_ymd_opt(2017, 1, 1).unwrap();
+    /// // The calendar of service1 runs on Sundays, but the day is removed by a calendar date
+    /// assert_eq!(std::collections::HashSet::from(["service2"]), gtfs.services_active_on(new_year));
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn services_active_on(&self, date: NaiveDate) -> HashSet<&str> {
+        self.calendar
+            .keys()
+            .chain(self.calendar_dates.keys())
+            .map(|id| &**id)
+            .filter(|id| self.is_service_active(id, date))
+            .collect()
+    }
+
+    /// Iterates over the trips running on that service day, with the date of their service day
+    ///
+    /// With `include_previous_day`, the trips of the previous service day still running after midnight are also given,
+    /// with the previous date. A trip running past midnight every day can then be given twice
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let saturday = chrono::NaiveDate::from_ymd_opt(2017, 1, 7).unwrap();
+    /// let trips: Vec<_> = gtfs.trips_active_on(saturday, true).collect();
+    /// assert_eq!(1, trips.len());
+    /// assert_eq!((saturday, "trip1"), (trips[0].0, trips[0].1.id.as_str()));
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn trips_active_on(
+        &self,
+        date: NaiveDate,
+        include_previous_day: bool,
+    ) -> impl Iterator<Item = (NaiveDate, &Trip)> {
+        let services = self.services_active_on(date);
+        let previous_day = date.pred_opt().filter(|_| include_previous_day);
+        let previous_services = previous_day
+            .map(|day| self.services_active_on(day))
+            .unwrap_or_default();
+        let of_the_day = self
+            .trips
+            .values()
+            .filter(move |trip| services.contains(&*trip.service_id))
+            .map(move |trip| (date, trip));
+        let of_the_previous_day = self
+            .trips
+            .values()
+            .filter(move |trip| {
+                previous_services.contains(&*trip.service_id)
+                    && trip_span(trip).is_some_and(|(_, end)| end >= 24 * 3600)
+            })
+            .filter_map(move |trip| previous_day.map(|day| (day, trip)));
+        of_the_day.chain(of_the_previous_day)
+    }
+
+    /// Returns the first and last days (both included) covered by the [Calendar] and the added [CalendarDate]
+    ///
+    /// None if there is no calendar at all
+    pub fn service_span(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let calendar_dates = self
+            .calendar
+            .values()
+            .flat_map(|c| [c.start_date, c.end_date]);
+        let added_dates = self
+            .calendar_dates
+            .values()
+            .flatten()
+            .filter(|d| d.exception_type == Exception::Added)
+            .map(|d| d.date);
+        calendar_dates
+            .chain(added_dates)
+            .fold(None, |span, date| match span {
+                None => Some((date, date)),
+                Some((start, end)) => Some((start.min(date), end.max(date))),
+            })
+    }
+
+    /// Lists all the references between objects that can not be resolved
+    ///
+    /// Checks that every [Trip] has a [Route] and a service (a [Calendar] or some [CalendarDate]),
+    /// that every [StopTime], [StopTransfer] and [Pathway] references an existing [Stop].
+    /// Also checks the optional references: the [Shape] of the trips, the parent station of the stops,
+    /// the [Agency] of the routes and the zones of the [FareRule] (that must be the `zone_id` of a stop).
+    /// The shapes must have been read for their references to be resolved.
+    /// This can be useful when the [Gtfs] has been built or modified programmatically
+    ///
+    /// `level_id` is not checked, as `levels.txt` is not read
+    pub fn broken_references(&self) -> Vec<BrokenReference> {
+        let mut result = Vec::new();
+        let mut broken = |table, object_id: &str, field_name, value: &str| {
+            result.push(BrokenReference {
+                table,
+                object_id: object_id.to_owned(),
+                field_name,
+                value: value.to_owned(),
+            })
+        };
+
+        let mut trips: Vec<&Trip> = self.trips.values().collect();
+        trips.sort_by(|a, b| a.id.cmp(&b.id));
+        for trip in trips {
+            if !self.routes.contains_key(&trip.route_id) {
+                broken(TableName::Trips, &trip.id, "route_id", &trip.route_id);
+            }
+            if !self.calendar.contains_key(&trip.service_id)
+                && !self.calendar_dates.contains_key(&trip.service_id)
+            {
+                broken(TableName::Trips, &trip.id, "service_id", &trip.service_id);
+            }
+            if let Some(shape_id) = &trip.shape_id {
+                if !self.shapes.contains_key(shape_id) && !self.lazy_shapes.contains(shape_id) {
+                    broken(TableName::Trips, &trip.id, "shape_id", shape_id);
+                }
+            }
+            for stop_time in &trip.stop_times {
+                if !self.stops.contains_key(&stop_time.stop.id) {
+                    broken(
+                        TableName::StopTimes,
+                        &trip.id,
+                        "stop_id",
+                        &stop_time.stop.id,
+                    );
+                }
+            }
+        }
+
+        let mut stops: Vec<&Arc<Stop>> = self.stops.values().collect();
+        stops.sort_by(|a, b| a.id.cmp(&b.id));
+        for stop in stops {
+            if let Some(parent) = &stop.parent_station {
+                if !self.stops.contains_key(parent) {
+                    broken(TableName::Stops, &stop.id, "parent_station", parent);
+                }
+            }
+            for transfer in &stop.transfers {
+                if !self.stops.contains_key(&transfer.to_stop_id) {
+                    broken(
+                        TableName::Transfers,
+                        &stop.id,
+                        "to_stop_id",
+                        &transfer.to_stop_id,
+                    );
+                }
+            }
+            for pathway in &stop.pathways {
+                if !self.stops.contains_key(&pathway.to_stop_id) {
+                    broken(
+                        TableName::Pathways,
+                        &pathway.id,
+                        "to_stop_id",
+                        &pathway.to_stop_id,
+                    );
+                }
+            }
+        }
+
+        let mut routes: Vec<&Route> = self.routes.values().collect();
+        routes.sort_by(|a, b| a.id.cmp(&b.id));
+        for route in routes {
+            if let Some(agency_id) = &route.agency_id {
+                if !self
+                    .agencies
+                    .iter()
+                    .any(|a| a.id.as_ref() == Some(agency_id))
+                {
+                    broken(TableName::Routes, &route.id, "agency_id", agency_id);
+                }
+            }
+        }
+
+        let zone_ids: HashSet<&str> = self
+            .stops
+            .values()
+            .filter_map(|s| s.zone_id.as_deref())
+            .collect();
+        let mut fare_rules: Vec<(&GtfsId, &Vec<FareRule>)> = self.fare_rules.iter().collect();
+        fare_rules.sort_by(|a, b| a.0.cmp(b.0));
+        for rule in fare_rules.into_iter().flat_map(|(_, rules)| rules) {
+            let zones = [
+                ("origin_id", &rule.origin_id),
+                ("destination_id", &rule.destination_id),
+                ("contains_id", &rule.contains_id),
+            ];
+            for (field_name, zone_id) in zones {
+                if let Some(zone_id) = zone_id {
+                    if !zone_ids.contains(zone_id.as_str()) {
+                        broken(TableName::FareRules, &rule.fare_id, field_name, zone_id);
+                    }
+                }
+            }
+        }
+        result
+    }
+
     /// Gets a [Stop] by its `stop_id`
     pub fn get_stop<'a>(&'a self, id: &str) -> Result<&'a Stop, Error> {
         match self.stops.get(id) {
@@ -201,6 +649,180 @@ impl Gtfs {
             .ok_or_else(|| Error::ReferenceError(id.to_owned()))
     }
 
+    /// Iterates over the trips of a route, sorted by `trip_id`. Empty if the route has no trip
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let trips: Vec<_> = gtfs.trips_for_route("route1").map(|t| &t.id).collect();
+    /// assert_eq!(vec!["trip1"], trips);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn trips_for_route<'a>(&'a self, route_id: &str) -> impl Iterator<Item = &'a Trip> + 'a {
+        self.route_trips
+            .get(route_id)
+            .into_iter()
+            .flatten()
+            .filter_map(move |id| self.trips.get(id))
+    }
+
+    /// Iterates over the trips calling at a stop or at one of its child stops, sorted by `trip_id`
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let trips: Vec<_> = gtfs.trips_at_stop("platform1").map(|t| &t.id).collect();
+    /// assert_eq!(vec!["tripA1", "tripA2"], trips);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn trips_at_stop<'a>(&'a self, stop_id: &str) -> impl Iterator<Item = &'a Trip> + 'a {
+        self.stop_trips
+            .get(stop_id)
+            .into_iter()
+            .flatten()
+            .filter_map(move |id| self.trips.get(id))
+    }
+
+    /// Lists the routes of the trips calling at a stop, sorted by `route_id`
+    ///
+    /// The routes calling at the child stops are included: for a station, those are the routes of all its platforms
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let routes: Vec<_> = gtfs.routes_at_stop("station1").iter().map(|r| &r.id).collect();
+    /// assert_eq!(vec!["routeA", "routeB"], routes);
+    /// let routes: Vec<_> = gtfs.routes_at_stop("platform1").iter().map(|r| &r.id).collect();
+    /// assert_eq!(vec!["routeA"], routes);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn routes_at_stop(&self, stop_id: &str) -> Vec<&Route> {
+        let mut route_ids: Vec<&GtfsId> = self
+            .trips_at_stop(stop_id)
+            .map(|trip| &trip.route_id)
+            .collect();
+        route_ids.sort();
+        route_ids.dedup();
+        route_ids
+            .into_iter()
+            .filter_map(|id| self.routes.get(id))
+            .collect()
+    }
+
+    /// Lists the stops with coordinates inside a rectangle (borders included), sorted by `stop_id`, like the stops to show on a map
+    ///
+    /// The rectangle crosses the antimeridian when its `min_longitude` is greater than its `max_longitude`.
+    /// With the feature `spatial`, the stops are found with an index of their positions instead of going through all of them
+    ///
+    /// ```
+    /// use gtfs_structures::BoundingBox;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let bbox = BoundingBox { min_longitude: 2.355, min_latitude: 48.855, max_longitude: 2.4, max_latitude: 48.9 };
+    /// let stops: Vec<_> = gtfs.stops_in_bbox(&bbox).iter().map(|s| s.id.as_str()).collect();
+    /// assert_eq!(vec!["stop1", "stop2"], stops);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn stops_in_bbox(&self, bbox: &BoundingBox) -> Vec<&Arc<Stop>> {
+        #[cfg(feature = "spatial")]
+        let candidates = self
+            .stop_tree
+            .locate_in_envelope_intersecting(&crate::spatial::envelope(bbox))
+            .filter_map(|position| self.stops.get(&position.data));
+        #[cfg(not(feature = "spatial"))]
+        let candidates = self.stops.values();
+        let mut stops: Vec<_> = candidates
+            .filter(|stop| match (stop.longitude, stop.latitude) {
+                (Some(longitude), Some(latitude)) => bbox.contains(longitude, latitude),
+                _ => false,
+            })
+            .collect();
+        stops.sort_by(|a, b| a.id.cmp(&b.id));
+        stops
+    }
+
+    /// Lists the stops at most `meters` away from a position, with their distance, from the nearest to the farthest
+    ///
+    /// The distance is computed with the haversine formula. The stops without coordinates are ignored,
+    /// and with a `location_type`, only the stops of this type are kept.
+    /// With the feature `spatial`, the stops are found with an index of their positions instead of going through all of them
+    ///
+    /// ```
+    /// use gtfs_structures::LocationType;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let stops = gtfs.stops_within(48.85, 2.35, 100., Some(LocationType::StopArea));
+    /// assert_eq!(1, stops.len());
+    /// assert_eq!("station1", stops[0].0.id);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn stops_within(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        meters: f64,
+        location_type: Option<LocationType>,
+    ) -> Vec<(&Arc<Stop>, f64)> {
+        #[cfg(feature = "spatial")]
+        let candidates = crate::spatial::stops_around(self, latitude, longitude, meters);
+        #[cfg(not(feature = "spatial"))]
+        let candidates = self.stops.values();
+        let mut stops: Vec<_> = candidates
+            .filter(|stop| location_type.is_none_or(|t| stop.location_type == t))
+            .filter_map(|stop| match (stop.latitude, stop.longitude) {
+                (Some(lat), Some(lon)) => Some((stop, haversine(latitude, longitude, lat, lon))),
+                _ => None,
+            })
+            .filter(|(_, distance)| *distance <= meters)
+            .collect();
+        stops.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.id.cmp(&b.0.id)));
+        stops
+    }
+
+    /// Ordered stops of a route in a direction, for a line diagram or a timetable
+    ///
+    /// Only the trips with this `direction_id` are used. The stops of the trip with the most stops are taken first,
+    /// then each stop of the other trips is inserted after the previous stop of its trip
+    /// (or before the next one for the first stops of the trip)
+    ///
+    /// ```
+    /// use gtfs_structures::DirectionType;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let pattern = gtfs.stop_pattern("routeA", Some(DirectionType::Outbound));
+    /// let stop_ids: Vec<_> = pattern.iter().map(|s| &s.id).collect();
+    /// assert_eq!(vec!["platform2", "platform1", "stop1", "stop2"], stop_ids);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn stop_pattern(
+        &self,
+        route_id: &str,
+        direction_id: Option<DirectionType>,
+    ) -> Vec<Arc<Stop>> {
+        let mut patterns: Vec<Vec<&Arc<Stop>>> = self
+            .trips_for_route(route_id)
+            .filter(|trip| trip.direction_id == direction_id)
+            .map(|trip| trip.stop_times.iter().map(|st| &st.stop).collect())
+            .collect();
+        // The sort is stable: the trips with as many stops stay sorted by `trip_id`
+        patterns.sort_by_key(|pattern| std::cmp::Reverse(pattern.len()));
+
+        let mut patterns = patterns.into_iter();
+        let mut result = patterns.next().unwrap_or_default();
+        for pattern in patterns {
+            for (i, stop) in pattern.iter().enumerate() {
+                let position_of = |stop: &Arc<Stop>, result: &[&Arc<Stop>]| {
+                    result.iter().position(|s| s.id == stop.id)
+                };
+                if position_of(stop, &result).is_some() {
+                    continue;
+                }
+                let position = match i.checked_sub(1) {
+                    Some(previous) => position_of(pattern[previous], &result).map(|p| p + 1),
+                    None => pattern[1..]
+                        .iter()
+                        .find_map(|next| position_of(next, &result)),
+                };
+                result.insert(position.unwrap_or(result.len()), stop);
+            }
+        }
+        result.into_iter().cloned().collect()
+    }
+
     /// Gets a [Route] by its `route_id`
     pub fn get_route<'a>(&'a self, id: &str) -> Result<&'a Route, Error> {
         self.routes
@@ -208,6 +830,38 @@ impl Gtfs {
             .ok_or_else(|| Error::ReferenceError(id.to_owned()))
     }
 
+    /// Gets the [Agency] operating a route
+    ///
+    /// A route without `agency_id` is operated by the only agency of `agency.txt`: it is an error if there are several.
+    /// When the only agency has no `agency_id` itself, it operates every route
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// assert_eq!("Agency", gtfs.agency_for_route("routeA")?.name);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn agency_for_route<'a>(&'a self, route_id: &str) -> Result<&'a Agency, Error> {
+        let route = self.get_route(route_id)?;
+        let only_agency = match self.agencies.as_slice() {
+            [agency] => Some(agency),
+            _ => None,
+        };
+        match &route.agency_id {
+            Some(agency_id) => self
+                .agencies
+                .iter()
+                .find(|a| a.id.as_deref() == Some(agency_id.as_ref()))
+                .or(only_agency.filter(|a| a.id.is_none()))
+                .ok_or_else(|| Error::ReferenceError(agency_id.to_string())),
+            None => only_agency.ok_or_else(|| Error::MissingAgencyId(route.id.to_string())),
+        }
+    }
+
+    /// Gets the [Agency] operating the route of a trip, see [Gtfs::agency_for_route]
+    pub fn agency_for_trip<'a>(&'a self, trip_id: &str) -> Result<&'a Agency, Error> {
+        self.agency_for_route(&self.get_trip(trip_id)?.route_id)
+    }
+
     /// Gets a [Calendar] by its `service_id`
     pub fn get_calendar<'a>(&'a self, id: &str) -> Result<&'a Calendar, Error> {
         self.calendar
@@ -223,10 +877,28 @@ impl Gtfs {
     }
 
     /// Gets all [Shape] points of a `shape_id`
+    ///
+    /// With [crate::GtfsReader::lazy_shapes], the shape is parsed at the first call
     pub fn get_shape<'a>(&'a self, id: &str) -> Result<&'a Vec<Shape>, Error> {
-        self.shapes
-            .get(id)
-            .ok_or_else(|| Error::ReferenceError(id.to_owned()))
+        match self.lazy_shapes.get(id) {
+            Some(shape) => shape,
+            None => self
+                .shapes
+                .get(id)
+                .ok_or_else(|| Error::ReferenceError(id.to_owned())),
+        }
+    }
+
+    /// Length in meters of a shape, along its points, see [polyline_length]
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let length = gtfs.shape_length("A_shp")?;
+    /// assert_eq!(15_970., length.round());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn shape_length(&self, id: &str) -> Result<f64, Error> {
+        self.get_shape(id).map(|points| polyline_length(points))
     }
 
     /// Gets a [FareAttribute] by its `fare_id`
@@ -237,36 +909,77 @@ impl Gtfs {
     }
 }
 
-fn to_map<O: Id>(elements: impl IntoIterator<Item = O>) -> HashMap<String, O> {
-    elements
-        .into_iter()
-        .map(|e| (e.id().to_owned(), e))
-        .collect()
+fn to_map<O: Id>(
+    elements: impl IntoIterator<Item = O>,
+    table: TableName,
+    check: &mut BuildCheck,
+) -> Result<IdMap<O>, Error> {
+    Ok(to_hash_map(elements, table, check)?.into())
+}
+
+/// Indexes the objects by identifier, the duplicated identifiers are handled by [BuildCheck::duplicate]
+fn to_hash_map<O: Id>(
+    elements: impl IntoIterator<Item = O>,
+    table: TableName,
+    check: &mut BuildCheck,
+) -> Result<IdHashMap<O>, Error> {
+    let mut res = IdHashMap::<O>::default();
+    for e in elements {
+        match res.entry(e.id().into()) {
+            Entry::Vacant(entry) => {
+                entry.insert(e);
+            }
+            Entry::Occupied(mut entry) => {
+                if check.duplicate(table, entry.key())? {
+                    entry.insert(e);
+                }
+            }
+        }
+    }
+    Ok(res)
 }
 
 fn to_stop_map(
     stops: Vec<Stop>,
     raw_transfers: Vec<RawTransfer>,
     raw_pathways: Vec<RawPathway>,
-) -> Result<HashMap<String, Arc<Stop>>, Error> {
-    let mut stop_map: HashMap<String, Stop> =
-        stops.into_iter().map(|s| (s.id.clone(), s)).collect();
+    check: &mut BuildCheck,
+) -> Result<IdMap<Arc<Stop>>, Error> {
+    let mut stop_map = to_hash_map(stops, TableName::Stops, check)?;
 
     for transfer in raw_transfers {
-        stop_map.get(&transfer.to_stop_id).ok_or_else(|| {
-            let stop_id = &transfer.to_stop_id;
-            Error::ReferenceError(format!("'{stop_id}' in transfers.txt"))
-        })?;
+        let stop_id = &transfer.to_stop_id;
+        if !stop_map.contains_key(stop_id) {
+            check.broken(
+                BrokenReference {
+                    table: TableName::Transfers,
+                    object_id: transfer.from_stop_id.to_string(),
+                    field_name: "to_stop_id",
+                    value: stop_id.to_string(),
+                },
+                || Error::ReferenceError(format!("'{stop_id}' in transfers.txt")),
+            )?;
+            continue;
+        }
         stop_map
             .entry(transfer.from_stop_id.clone())
             .and_modify(|stop| stop.transfers.push(StopTransfer::from(transfer)));
     }
 
     for pathway in raw_pathways {
-        stop_map.get(&pathway.to_stop_id).ok_or_else(|| {
-            let stop_id = &pathway.to_stop_id;
-            Error::ReferenceError(format!("'{stop_id}' in pathways.txt"))
-        })?;
+        let stop_id = &pathway.to_stop_id;
+        if !stop_map.contains_key(stop_id) {
+            check.broken(
+                BrokenReference {
+                    table: TableName::Pathways,
+                    object_id: pathway.id.to_string(),
+                    field_name: "to_stop_id",
+                    value: stop_id.to_string(),
+                },
+                || Error::ReferenceError(format!("'{stop_id}' in pathways.txt")),
+            )?;
+            continue;
+        }
         stop_map
             .entry(pathway.from_stop_id.clone())
             .and_modify(|stop| stop.pathways.push(Pathway::from(pathway)));
@@ -279,27 +992,194 @@ fn to_stop_map(
     Ok(res)
 }
 
-fn to_shape_map(shapes: Vec<Shape>) -> HashMap<String, Vec<Shape>> {
-    let mut res = HashMap::default();
+/// Indexes the `trip_id` of the trips by `route_id`, see [Gtfs::trips_for_route]
+pub(crate) fn to_route_trips(trips: &IdMap<Trip>) -> IdMap<Vec<GtfsId>> {
+    let mut res = IdHashMap::<Vec<GtfsId>>::default();
+    for trip in trips.values() {
+        res.entry(trip.route_id.clone())
+            .or_default()
+            .push(trip.id.clone());
+    }
+    for trip_ids in res.values_mut() {
+        trip_ids.sort();
+    }
+    res.into()
+}
+
+/// Levels of parent stations followed when indexing the trips by stop, in case the parents make a cycle
+pub(crate) const MAX_STOP_DEPTH: usize = 4;
+
+/// Indexes the `trip_id` of the trips by the `stop_id` of their stops and of the parents of those stops,
+/// see [Gtfs::trips_at_stop]
+pub(crate) fn to_stop_trips(trips: &IdMap<Trip>, stops: &IdMap<Arc<Stop>>) -> IdMap<Vec<GtfsId>> {
+    let mut res = IdHashMap::<Vec<GtfsId>>::default();
+    for trip in trips.values() {
+        for stop_time in &trip.stop_times {
+            let mut stop = Some(&stop_time.stop);
+            for _ in 0..MAX_STOP_DEPTH {
+                let current = match stop {
+                    Some(current) => current,
+                    None => break,
+                };
+                let trip_ids = res.entry(current.id.clone()).or_default();
+                if trip_ids.last() != Some(&trip.id) {
+                    trip_ids.push(trip.id.clone());
+                }
+                stop = current
+                    .parent_station
+                    .as_ref()
+                    .and_then(|parent| stops.get(parent));
+            }
+        }
+    }
+    for trip_ids in res.values_mut() {
+        trip_ids.sort();
+        trip_ids.dedup();
+    }
+    res.into()
+}
+
+fn to_shape_map(shapes: Vec<Shape>) -> IdMap<Vec<Shape>> {
+    let mut res = IdHashMap::<Vec<Shape>>::default();
     for s in shapes {
         let shape = res.entry(s.id.to_owned()).or_insert_with(Vec::new);
         shape.push(s);
     }
     // we sort the shape by it's pt_sequence
-    for shapes in res.values_mut() {
-        shapes.sort_by_key(|s| s.sequence);
-    }
+    for_each_in_parallel(res.values_mut(), |shapes| {
+        shapes.sort_by_key(|s| s.sequence)
+    });
 
-    res
+    res.into()
 }
 
-fn to_calendar_dates(cd: Vec<CalendarDate>) -> HashMap<String, Vec<CalendarDate>> {
-    let mut res = HashMap::default();
+fn to_calendar_dates(cd: Vec<CalendarDate>) -> IdMap<Vec<CalendarDate>> {
+    let mut res = IdHashMap::<Vec<CalendarDate>>::default();
     for c in cd {
         let cal = res.entry(c.service_id.to_owned()).or_insert_with(Vec::new);
         cal.push(c);
     }
-    res
+    res.into()
+}
+
+fn add_stop_time(
+    trips: &mut IdMap<Trip>,
+    stops: &IdMap<Arc<Stop>>,
+    s: RawStopTime,
+    check: &mut BuildCheck,
+) -> Result<(), Error> {
+    let trip = match trips.get_mut(&s.trip_id) {
+        Some(trip) => trip,
+        None => {
+            return check.broken(
+                BrokenReference {
+                    table: TableName::StopTimes,
+                    object_id: s.trip_id.to_string(),
+                    field_name: "trip_id",
+                    value: s.trip_id.to_string(),
+                },
+                || Error::ReferenceError(s.trip_id.to_string()),
+            )
+        }
+    };
+    let stop = match stops.get(&s.stop_id) {
+        Some(stop) => stop,
+        None => {
+            return check.broken(
+                BrokenReference {
+                    table: TableName::StopTimes,
+                    object_id: s.trip_id.to_string(),
+                    field_name: "stop_id",
+                    value: s.stop_id.to_string(),
+                },
+                || Error::ReferenceError(s.stop_id.to_string()),
+            )
+        }
+    };
+    trip.stop_times.push(StopTime::from(s, Arc::clone(stop)));
+    Ok(())
+}
+
+/// Collects the broken references and duplicated identifiers in a [ValidationReport], or turns them into an error
+pub(crate) struct BuildCheck {
+    skip_broken_references: bool,
+    duplicate_policy: DuplicatePolicy,
+    pub(crate) report: ValidationReport,
+}
+
+impl BuildCheck {
+    pub(crate) fn new(skip_broken_references: bool, duplicate_policy: DuplicatePolicy) -> Self {
+        Self {
+            skip_broken_references,
+            duplicate_policy,
+            report: ValidationReport::default(),
+        }
+    }
+
+    /// Lists the reference if the broken references are skipped (the caller then drops the row), or returns the error
+    pub(crate) fn broken(
+        &mut self,
+        reference: BrokenReference,
+        error: impl FnOnce() -> Error,
+    ) -> Result<(), Error> {
+        if self.skip_broken_references {
+            self.report.broken_references.push(reference);
+            Ok(())
+        } else {
+            Err(error())
+        }
+    }
+
+    /// Applies the [DuplicatePolicy] to an identifier already used in the table.
+    /// True if the new object must replace the previous one
+    pub(crate) fn duplicate(&mut self, table: TableName, id: &str) -> Result<bool, Error> {
+        match self.duplicate_policy {
+            DuplicatePolicy::KeepLast => Ok(true),
+            DuplicatePolicy::KeepFirst => Ok(false),
+            DuplicatePolicy::Report => {
+                self.report.duplicates.push(DuplicateId {
+                    table,
+                    id: id.to_owned(),
+                });
+                Ok(false)
+            }
+            DuplicatePolicy::Error => Err(Error::DuplicateId {
+                table,
+                id: id.to_owned(),
+            }),
+        }
+    }
+
+    /// Adds what was found by a check run on another part of the GTFS
+    pub(crate) fn merge(&mut self, other: BuildCheck) {
+        self.report
+            .broken_references
+            .extend(other.report.broken_references);
+        self.report.duplicates.extend(other.report.duplicates);
+    }
+}
+
+fn sort_stop_times(trips: &mut IdMap<Trip>) {
+    for_each_in_parallel(trips.values_mut(), |trip| {
+        trip.stop_times.sort_by_key(|a| a.stop_sequence)
+    });
+}
+
+/// Calls `f` on each object, on several threads with the feature `parallel`
+#[cfg(feature = "parallel")]
+fn for_each_in_parallel<'a, T, F>(objs: impl Iterator<Item = &'a mut T>, f: F)
+where
+    T: Send + 'a,
+    F: Fn(&mut T) + Sync + Send,
+{
+    use rayon::prelude::*;
+    let mut objs: Vec<_> = objs.collect();
+    objs.par_iter_mut().for_each(|obj| f(obj));
+}
+
+#[cfg(not(feature = "parallel"))]
+fn for_each_in_parallel<'a, T: 'a>(objs: impl Iterator<Item = &'a mut T>, f: impl Fn(&mut T)) {
+    objs.for_each(f);
 }
 
 // Number of stoptimes to `pop` from the list before using shrink_to_fit to reduce the memory footprint
@@ -308,50 +1188,41 @@ const NB_STOP_TIMES_BEFORE_SHRINK: usize = 1_000_000;
 
 fn create_trips(
     raw_trips: Vec<RawTrip>,
-    mut raw_stop_times: Vec<RawStopTime>,
     raw_frequencies: Vec<RawFrequency>,
-    stops: &HashMap<String, Arc<Stop>>,
-) -> Result<HashMap<String, Trip>, Error> {
-    let mut trips = to_map(raw_trips.into_iter().map(|rt| Trip {
-        id: rt.id,
-        service_id: rt.service_id,
-        route_id: rt.route_id,
-        stop_times: vec![],
-        shape_id: rt.shape_id,
-        trip_headsign: rt.trip_headsign,
-        trip_short_name: rt.trip_short_name,
-        direction_id: rt.direction_id,
-        block_id: rt.block_id,
-        wheelchair_accessible: rt.wheelchair_accessible,
-        bikes_allowed: rt.bikes_allowed,
-        frequencies: vec![],
-    }));
-
-    let mut st_idx = 0;
-    while let Some(s) = raw_stop_times.pop() {
-        st_idx += 1;
-        let trip = &mut trips
-            .get_mut(&s.trip_id)
-            .ok_or_else(|| Error::ReferenceError(s.trip_id.to_string()))?;
-        let stop = stops
-            .get(&s.stop_id)
-            .ok_or_else(|| Error::ReferenceError(s.stop_id.to_string()))?;
-        trip.stop_times.push(StopTime::from(s, Arc::clone(stop)));
-        if st_idx % NB_STOP_TIMES_BEFORE_SHRINK == 0 {
-            raw_stop_times.shrink_to_fit();
-        }
-    }
-
-    for trip in &mut trips.values_mut() {
-        trip.stop_times
-            .sort_by(|a, b| a.stop_sequence.cmp(&b.stop_sequence));
-    }
+    check: &mut BuildCheck,
+) -> Result<IdMap<Trip>, Error> {
+    let mut trips = to_map(
+        raw_trips.into_iter().map(|rt| Trip {
+            id: rt.id,
+            service_id: rt.service_id,
+            route_id: rt.route_id,
+            stop_times: vec![],
+            shape_id: rt.shape_id,
+            trip_headsign: rt.trip_headsign,
+            trip_short_name: rt.trip_short_name,
+            direction_id: rt.direction_id,
+            block_id: rt.block_id,
+            wheelchair_accessible: rt.wheelchair_accessible,
+            bikes_allowed: rt.bikes_allowed,
+            frequencies: vec![],
+        }),
+        TableName::Trips,
+        check,
+    )?;
 
     for f in raw_frequencies {
-        let trip = &mut trips
-            .get_mut(&f.trip_id)
-            .ok_or_else(|| Error::ReferenceError(f.trip_id.to_string()))?;
-        trip.frequencies.push(Frequency::from(&f));
+        match trips.get_mut(&f.trip_id) {
+            Some(trip) => trip.frequencies.push(Frequency::from(&f)),
+            None => check.broken(
+                BrokenReference {
+                    table: TableName::Frequencies,
+                    object_id: f.trip_id.to_string(),
+                    field_name: "trip_id",
+                    value: f.trip_id.to_string(),
+                },
+                || Error::ReferenceError(f.trip_id.to_string()),
+            )?,
+        }
     }
 
     Ok(trips)
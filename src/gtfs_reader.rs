@@ -1,13 +1,176 @@
 use serde::Deserialize;
 use sha2::{Digest, Sha256};
 
-use crate::{Error, Gtfs, RawGtfs};
+use crate::error::{InvalidRow, RemovedStopTime, ValidationReport};
+use crate::id_map::IdHashMap;
+use crate::objects::RawStopTime;
+use crate::raw_gtfs::{stop_time_unknown_to_default, stop_time_unknown_value};
+use crate::{CompactGtfs, Error, Gtfs, LazyShapes, RawGtfs, TableName};
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufRead, Read};
 use std::path::Path;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How strictly the values of the GTFS should be checked when parsing
+#[derive(Derivative, Debug, Copy, Clone, PartialEq, Eq)]
+#[derivative(Default)]
+pub enum ParseMode {
+    /// Values not defined by the specification are kept as `Unknown` (or `Other`) variants,
+    /// or converted to the default value if [GtfsReader::unkown_enum_as_default] is set
+    #[derivative(Default)]
+    Lenient,
+    /// Values not defined by the specification are errors.
+    /// The whole file containing such a value is then considered as invalid
+    Strict,
+}
+
+/// Defines what is trimmed when reading the csv files
+#[derive(Derivative, Debug, Copy, Clone, PartialEq, Eq)]
+#[derivative(Default)]
+pub enum TrimMode {
+    /// Nothing is trimmed. A header with spaces will not be recognized
+    None,
+    /// Only the headers are trimmed, the values are kept as they are
+    Headers,
+    /// Both the headers and the values are trimmed
+    #[derivative(Default)]
+    All,
+}
+
+/// Character separating the fields of the csv files
+#[derive(Derivative, Debug, Copy, Clone, PartialEq, Eq)]
+#[derivative(Default)]
+pub enum Delimiter {
+    /// A comma, as required by the specification
+    #[derivative(Default)]
+    Comma,
+    /// The most frequent of comma, semicolon and tab in the header line of each file
+    Detect,
+    /// Any other character (e.g. `b';'`)
+    Custom(u8),
+}
+
+/// What to do when several stops, routes, trips, calendars or fare attributes have the same identifier
+#[derive(Derivative, Debug, Copy, Clone, PartialEq, Eq)]
+#[derivative(Default)]
+pub enum DuplicatePolicy {
+    /// The last object read replaces the previous ones
+    #[derivative(Default)]
+    KeepLast,
+    /// The first object read is kept, the next ones are dropped
+    KeepFirst,
+    /// The first object read is kept, the next ones are dropped and listed in [crate::error::ValidationReport::duplicates]
+    Report,
+    /// A duplicated identifier is an [Error::DuplicateId]
+    Error,
+}
+
+/// Row of a csv file, given to [GtfsReader::default_value_with] and [GtfsReader::visit_records]
+pub struct CsvRow<'a> {
+    headers: &'a csv::StringRecord,
+    record: &'a csv::StringRecord,
+}
+
+impl<'a> CsvRow<'a> {
+    /// The value of the field, None if the field does not exist
+    pub fn get(&self, field_name: &str) -> Option<&'a str> {
+        self.headers
+            .iter()
+            .position(|h| h == field_name)
+            .and_then(|i| self.record.get(i))
+    }
+
+    /// Deserializes the row, possibly in a structure that borrows the values like [crate::RawStopTimeRef]
+    pub fn deserialize<T: Deserialize<'a>>(&self) -> Result<T, csv::Error> {
+        self.record.deserialize(Some(self.headers))
+    }
+}
+
+/// What to do once a record was read
+enum Visit {
+    /// The record was used, and the next one must be read
+    Continue,
+    /// The record was not valid, and the next one must be read
+    Skipped,
+    /// No more record must be read
+    Stop,
+}
+
+type RecordVisitor<'a> =
+    dyn FnMut(&csv::StringRecord, &csv::StringRecord) -> Result<Visit, Error> + 'a;
+
+/// Value used by [GtfsReader] when a field is blank or missing
+pub struct DefaultValue {
+    table: TableName,
+    field_name: String,
+    value: Box<DefaultValueFn>,
+}
+
+type DefaultValueFn = dyn Fn(&CsvRow) -> Option<String> + Send + Sync;
+
+/// Blank or missing value that was set by [crate::GtfsReader::default_value]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefaultedValue {
+    /// File containing the value
+    pub file_name: String,
+    /// Line number of the row in the file (the headers are on line 1)
+    pub line: u64,
+    /// Field that was blank or missing
+    pub field_name: String,
+    /// Value that was used instead
+    pub value: String,
+}
+
+/// Long free-text fields skipped by [GtfsReader::drop_descriptive_fields], with the table they belong to
+pub const DESCRIPTIVE_FIELDS: &[(TableName, &str)] = &[
+    (TableName::Agency, "agency_url"),
+    (TableName::Agency, "agency_fare_url"),
+    (TableName::Stops, "stop_desc"),
+    (TableName::Stops, "stop_url"),
+    (TableName::Stops, "tts_stop_name"),
+    (TableName::Routes, "route_desc"),
+    (TableName::Routes, "route_url"),
+    (TableName::FeedInfo, "feed_publisher_url"),
+    (TableName::FeedInfo, "feed_contact_url"),
+];
+
+/// Measures of the reading of a GTFS, to find which file is slow to read
+#[derive(Debug, Clone, Default)]
+pub struct ParseMetrics {
+    /// Time needed to read and parse the whole archive
+    pub total_duration: Duration,
+    /// Measures of each file that was read
+    pub files: HashMap<TableName, FileMetrics>,
+}
+
+/// Measures of the reading of a single file, see [ParseMetrics]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileMetrics {
+    /// Name of the file
+    pub file_name: String,
+    /// Time spent reading and parsing the file.
+    /// With the feature `parallel`, the files are extracted beforehand and this is only the parsing time
+    pub duration: Duration,
+    /// Number of rows that were parsed
+    pub rows: usize,
+    /// Number of bytes read from the file (once uncompressed)
+    pub bytes: u64,
+    /// Number of rows that could not be parsed and were skipped (see [GtfsReader::skip_invalid_rows])
+    pub skipped_rows: usize,
+    /// Number of values set by [GtfsReader::default_value]
+    pub defaulted_values: usize,
+    /// sha256 sum of the file (once uncompressed), in lowercase hexadecimal. None unless [GtfsReader::hash_files] is set.
+    /// Only the part that was read is hashed when the reading stopped early, e.g. with [GtfsReader::max_rows_per_file]
+    pub sha256: Option<String>,
+    /// True if the file was unchanged and its objects were copied from the previous reading
+    /// (see [RawGtfsReader::reload_if_changed])
+    pub reused: bool,
+}
 
 /// Allows to parameterize how the parsing library behaves
 ///
@@ -31,24 +194,111 @@ use std::time::Instant;
 ///assert_eq!(0, gtfs.stop_times?.len());
 /// # Ok::<(), gtfs_structures::error::Error>(())
 ///```
-#[derive(Derivative)]
-#[derivative(Default)]
 pub struct GtfsReader {
     /// [crate::objects::StopTime] are very large and not always needed. This allows to skip reading them
-    #[derivative(Default(value = "true"))]
     pub read_stop_times: bool,
     /// [crate::objects::Shape] are very large and not always needed. This allows to skip reading them
-    #[derivative(Default(value = "true"))]
     pub read_shapes: bool,
+    /// Should the shapes only be indexed, and parsed one by one when they are requested (see [crate::LazyShapes])
+    pub lazy_shapes: bool,
+    /// Should the objects be stored in vectors sorted by identifier instead of hash maps (see [crate::IdMap])
+    pub sorted_storage: bool,
+    /// Should the files of a local zip archive be decompressed on another thread while they are parsed
+    pub background_decompression: bool,
+    /// [crate::objects::Pathway] are not always needed. This allows to skip reading them
+    pub read_pathways: bool,
+    /// [crate::objects::StopTransfer] are not always needed. This allows to skip reading them
+    pub read_transfers: bool,
+    /// [crate::objects::RawTranslation] are not always needed. This allows to skip reading them
+    pub read_translations: bool,
+    /// [crate::objects::FareAttribute] and [crate::objects::FareRule] are not always needed. This allows to skip reading them
+    pub read_fares: bool,
+    /// If set, only those files are read. The other tables are left empty
+    pub only_files: Option<Vec<String>>,
+    /// Should the [DESCRIPTIVE_FIELDS] be read as blank values to save memory
+    pub drop_descriptive_fields: bool,
+    /// Should the values not defined by the specification be errors
+    pub parse_mode: ParseMode,
     /// If a an enumeration has an unknown value, should we use the default value
-    #[derivative(Default(value = "false"))]
     pub unkown_enum_as_default: bool,
-    /// Avoid trimming the fields
+    /// What should be trimmed (the headers, the values or nothing)
+    ///
+    /// Trimming all the values is quite time consuming
+    /// If performance is an issue, and if your data is high quality, you can only trim the headers
+    pub trim: TrimMode,
+    /// Trim the values of all the fields, and not only the headers
     ///
-    /// It is quite time consuming
-    /// If performance is an issue, and if your data is high quality, you can switch it off
-    #[derivative(Default(value = "true"))]
+    /// When false, only the headers are trimmed if [GtfsReader::trim] is [TrimMode::All]
+    #[deprecated(note = "use `trim` and `TrimMode::Headers` instead")]
     pub trim_fields: bool,
+    /// If set, a file larger than this number of bytes (once uncompressed) is an error
+    pub file_size_limit: Option<u64>,
+    /// If set, a file with more rows than this number is an error
+    pub row_limit: Option<usize>,
+    /// If set, only the first rows of each file are read
+    pub max_rows_per_file: Option<usize>,
+    /// Should the sha256 sum of each file be computed, see [FileMetrics::sha256]
+    pub hash_files: bool,
+    /// If set, only the files in this directory of the archive are read
+    pub archive_subdirectory: Option<String>,
+    /// When reading a directory without any GTFS file, should we look for them one level down
+    pub search_subdirectories: bool,
+    /// Should the file names be matched regardless of their case (e.g. `Stops.txt` or `AGENCY.TXT`)
+    pub case_insensitive_file_names: bool,
+    /// Custom file names of some tables, used instead of the names of the specification
+    pub file_names: HashMap<TableName, String>,
+    /// Character separating the fields
+    pub delimiter: Delimiter,
+    /// Should the rows that cannot be parsed be skipped instead of making the whole file invalid
+    pub skip_invalid_rows: bool,
+    /// Should the rows referencing unknown objects be dropped instead of making the whole GTFS invalid
+    pub skip_broken_references: bool,
+    /// What to do with the objects having the same identifier
+    pub duplicate_policy: DuplicatePolicy,
+    /// Should the stop times repeating the stop and times of another stop time of their trip be removed
+    pub remove_duplicate_stop_times: bool,
+    /// Values used when a field is blank or missing
+    pub default_values: Vec<DefaultValue>,
+    /// When set to true by another thread, the reading stops with [Error::Cancelled]
+    pub cancel_flag: Option<Arc<AtomicBool>>,
+}
+
+impl Default for GtfsReader {
+    #[allow(deprecated)]
+    fn default() -> Self {
+        Self {
+            read_stop_times: true,
+            read_shapes: true,
+            lazy_shapes: Default::default(),
+            sorted_storage: Default::default(),
+            background_decompression: Default::default(),
+            read_pathways: true,
+            read_transfers: true,
+            read_translations: true,
+            read_fares: true,
+            only_files: Default::default(),
+            drop_descriptive_fields: Default::default(),
+            parse_mode: Default::default(),
+            unkown_enum_as_default: false,
+            trim: Default::default(),
+            trim_fields: true,
+            file_size_limit: Default::default(),
+            row_limit: Default::default(),
+            max_rows_per_file: Default::default(),
+            hash_files: Default::default(),
+            archive_subdirectory: Default::default(),
+            search_subdirectories: Default::default(),
+            case_insensitive_file_names: Default::default(),
+            file_names: Default::default(),
+            delimiter: Default::default(),
+            skip_invalid_rows: Default::default(),
+            skip_broken_references: Default::default(),
+            duplicate_policy: Default::default(),
+            remove_duplicate_stop_times: Default::default(),
+            default_values: Default::default(),
+            cancel_flag: Default::default(),
+        }
+    }
 }
 
 impl GtfsReader {
@@ -68,6 +318,96 @@ impl GtfsReader {
         self
     }
 
+    /// If true, `shapes.txt` is kept in memory and only indexed by `shape_id` (default: false).
+    /// A shape is parsed the first time it is requested with [Gtfs::get_shape]
+    ///
+    /// This saves time and memory when only a few shapes are used.
+    /// Only [Gtfs] and [CompactGtfs] read the shapes lazily, [RawGtfs] always parses them
+    /// Returns Self and can be chained
+    pub fn lazy_shapes(mut self, lazy_shapes: bool) -> Self {
+        self.lazy_shapes = lazy_shapes;
+        self
+    }
+
+    /// If true, the objects of [Gtfs] and [CompactGtfs] are stored in vectors sorted by identifier (default: false)
+    ///
+    /// They are found by binary search with the same methods. This uses less memory with large datasets,
+    /// but inserting or removing an object is slower, see [crate::IdMap]
+    /// Returns Self and can be chained
+    pub fn sorted_storage(mut self, sorted_storage: bool) -> Self {
+        self.sorted_storage = sorted_storage;
+        self
+    }
+
+    /// If true, the files of a local zip archive are decompressed on another thread,
+    /// at the same time as they are parsed (default: false)
+    ///
+    /// The thread opens the archive again, so it only applies when reading from a path.
+    /// It shortens the reading of large archives if several cores are available
+    /// Returns Self and can be chained
+    pub fn background_decompression(mut self, background_decompression: bool) -> Self {
+        self.background_decompression = background_decompression;
+        self
+    }
+
+    /// Configures the reader to read or not the pathways (default: true)
+    ///
+    /// This can be useful to save memory when the pathways are not needed
+    /// Returns Self and can be chained
+    pub fn read_pathways(mut self, read_pathways: bool) -> Self {
+        self.read_pathways = read_pathways;
+        self
+    }
+
+    /// Configures the reader to read or not the transfers (default: true)
+    ///
+    /// This can be useful to save memory when the transfers are not needed
+    /// Returns Self and can be chained
+    pub fn read_transfers(mut self, read_transfers: bool) -> Self {
+        self.read_transfers = read_transfers;
+        self
+    }
+
+    /// Configures the reader to read or not the translations (default: true)
+    ///
+    /// This can be useful to save memory when the translations are not needed
+    /// Returns Self and can be chained
+    pub fn read_translations(mut self, read_translations: bool) -> Self {
+        self.read_translations = read_translations;
+        self
+    }
+
+    /// Configures the reader to read or not the fare attributes and the fare rules (default: true)
+    ///
+    /// This can be useful to save memory when the fares are not needed
+    /// Returns Self and can be chained
+    pub fn read_fares(mut self, read_fares: bool) -> Self {
+        self.read_fares = read_fares;
+        self
+    }
+
+    /// Configures the reader to skip the long descriptive fields, like `stop_desc` or the urls (default: false)
+    ///
+    /// Their values are read as blank: `None`, or an empty string for the mandatory `agency_url` and `feed_publisher_url`.
+    /// See [DESCRIPTIVE_FIELDS] for the list of those fields.
+    /// This can be useful to save memory with large datasets when the data is not displayed, like for routing
+    /// Returns Self and can be chained
+    pub fn drop_descriptive_fields(mut self, drop_descriptive_fields: bool) -> Self {
+        self.drop_descriptive_fields = drop_descriptive_fields;
+        self
+    }
+
+    /// Configures the reader to only read the given files (e.g. `&["stops.txt", "routes.txt"]`)
+    ///
+    /// The other tables are left empty (or `Some` empty vector for the optional ones), without being read
+    /// The names given with [GtfsReader::file_name] are the ones to list here
+    /// This can be useful for quick inspections of a large dataset
+    /// Returns Self and can be chained
+    pub fn only(mut self, file_names: &[&str]) -> Self {
+        self.only_files = Some(file_names.iter().map(|f| f.to_string()).collect());
+        self
+    }
+
     /// If a an enumeration has un unknown value, should we use the default value (default: false)
     ///
     /// For instance, if [crate::objects::Stop] has a [crate::objects::LocationType] with a value 42 in the GTFS
@@ -79,12 +419,227 @@ impl GtfsReader {
         self
     }
 
+    /// How strictly the values should be checked (default: [ParseMode::Lenient])
+    ///
+    /// With [ParseMode::Strict], an enumeration with a value not in the specification (e.g. a `location_type` of 42)
+    /// makes the whole file an [Error::UnknownEnumValue] instead of being silently kept or converted.
+    /// [GtfsReader::unkown_enum_as_default] is then ignored
+    /// Returns Self and can be chained
+    pub fn parse_mode(mut self, parse_mode: ParseMode) -> Self {
+        self.parse_mode = parse_mode;
+        self
+    }
+
     /// Should the fields be trimmed (default: true)
     ///
     /// It is quite time consumming
     /// If performance is an issue, and if your data is high quality, you can set it to false
+    /// The headers are always trimmed, see [GtfsReader::trim] to also keep them untouched
+    #[allow(deprecated)]
     pub fn trim_fields(mut self, trim_fields: bool) -> Self {
         self.trim_fields = trim_fields;
+        self.trim = if trim_fields {
+            TrimMode::All
+        } else {
+            TrimMode::Headers
+        };
+        self
+    }
+
+    /// What should be trimmed (default: [TrimMode::All])
+    ///
+    /// Validators might want [TrimMode::None] to see the values exactly as they are in the files
+    /// Returns Self and can be chained
+    pub fn trim(mut self, trim: TrimMode) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Limits the size in bytes of each file once uncompressed (default: no limit)
+    ///
+    /// Reading a larger file stops as soon as the limit is reached and the table is an [Error::FileSizeLimitExceeded].
+    /// This protects services reading user-uploaded archives against decompression bombs
+    /// Returns Self and can be chained
+    pub fn file_size_limit(mut self, limit: u64) -> Self {
+        self.file_size_limit = Some(limit);
+        self
+    }
+
+    /// Limits the number of rows of each file (default: no limit)
+    ///
+    /// A file with more rows is an [Error::RowLimitExceeded]
+    /// The invalid rows skipped with [GtfsReader::skip_invalid_rows] are counted too
+    /// Returns Self and can be chained
+    pub fn row_limit(mut self, limit: usize) -> Self {
+        self.row_limit = Some(limit);
+        self
+    }
+
+    /// Only reads the first `max_rows` rows of each file (default: all the rows)
+    ///
+    /// This gives a quick preview of a large dataset. The other rows are not parsed.
+    /// As references are then likely broken, it is mostly useful with [GtfsReader::raw]
+    /// Returns Self and can be chained
+    pub fn max_rows_per_file(mut self, max_rows: usize) -> Self {
+        self.max_rows_per_file = Some(max_rows);
+        self
+    }
+
+    /// Computes the sha256 sum of each file while reading it (default: false)
+    ///
+    /// It is needed by [RawGtfsReader::reload_if_changed] to know which files changed since the previous reading
+    /// Returns Self and can be chained
+    pub fn hash_files(mut self, hash_files: bool) -> Self {
+        self.hash_files = hash_files;
+        self
+    }
+
+    /// Only reads the files in the given directory of the archive (e.g. `"idf/"`)
+    ///
+    /// By default, the files are found wherever they are in the archive.
+    /// When an archive contains multiple datasets in different directories, this allows to choose which one is read
+    /// Returns Self and can be chained
+    pub fn archive_subdirectory(mut self, subdirectory: &str) -> Self {
+        self.archive_subdirectory = Some(subdirectory.to_owned());
+        self
+    }
+
+    /// When reading a directory without any GTFS file, look for them in its subdirectories (default: false)
+    ///
+    /// Only one level is searched, and the first subdirectory (in alphabetical order) with GTFS files is read.
+    /// Archives always find their files in subdirectories, see [GtfsReader::archive_subdirectory]
+    /// Returns Self and can be chained
+    pub fn search_subdirectories(mut self, search_subdirectories: bool) -> Self {
+        self.search_subdirectories = search_subdirectories;
+        self
+    }
+
+    /// Match the file names regardless of their case (default: false)
+    ///
+    /// Some feeds have files named `Stops.txt` or `AGENCY.TXT` that would otherwise be reported as [Error::MissingFile]
+    /// Returns Self and can be chained
+    pub fn case_insensitive_file_names(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive_file_names = case_insensitive;
+        self
+    }
+
+    /// Reads the table from a file with a custom name (e.g. `stops.csv` instead of `stops.txt`)
+    ///
+    /// Useful for data that is almost a GTFS, but with renamed files
+    /// Returns Self and can be chained
+    pub fn file_name(mut self, table: TableName, file_name: &str) -> Self {
+        self.file_names.insert(table, file_name.to_owned());
+        self
+    }
+
+    /// Character separating the fields (default: [Delimiter::Comma])
+    ///
+    /// Some data almost in the GTFS format uses semicolons or tabs.
+    /// With [Delimiter::Detect], the delimiter is guessed from the header line of each file
+    /// Returns Self and can be chained
+    pub fn delimiter(mut self, delimiter: Delimiter) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Skips the rows that cannot be parsed instead of making the whole file invalid (default: false)
+    ///
+    /// Every skipped row is listed in [RawGtfs::invalid_rows], with its file, line number, values and error
+    /// Returns Self and can be chained
+    pub fn skip_invalid_rows(mut self, skip_invalid_rows: bool) -> Self {
+        self.skip_invalid_rows = skip_invalid_rows;
+        self
+    }
+
+    /// Drops the stop times, frequencies, transfers and pathways referencing an unknown trip or stop,
+    /// instead of failing on the first one (default: false)
+    ///
+    /// The dropped rows are listed in [Gtfs::validation_report]
+    /// ```
+    ///let gtfs = gtfs_structures::GtfsReader::default()
+    ///    .skip_broken_references(true)
+    ///    .read("fixtures/broken_references")?;
+    ///assert_eq!(2, gtfs.get_trip("trip1")?.stop_times.len());
+    ///assert!(!gtfs.validation_report.is_valid());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    ///```
+    /// Returns Self and can be chained
+    pub fn skip_broken_references(mut self, skip_broken_references: bool) -> Self {
+        self.skip_broken_references = skip_broken_references;
+        self
+    }
+
+    /// Sets what to do when several objects of a table have the same identifier (default: [DuplicatePolicy::KeepLast])
+    ///
+    /// ```
+    ///use gtfs_structures::DuplicatePolicy;
+    ///let gtfs = gtfs_structures::GtfsReader::default()
+    ///    .duplicate_policy(DuplicatePolicy::Report)
+    ///    .read("fixtures/duplicates")?;
+    ///assert_eq!(Some("First"), gtfs.stops["stop1"].name.as_deref());
+    ///assert_eq!("stop1", gtfs.validation_report.duplicates[0].id);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    ///```
+    /// Returns Self and can be chained
+    pub fn duplicate_policy(mut self, duplicate_policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = duplicate_policy;
+        self
+    }
+
+    /// Removes the stop times with the same stop and times as an earlier stop time of their trip (default: false)
+    ///
+    /// See [Gtfs::remove_duplicate_stop_times]. The removed stop times are listed in [Gtfs::validation_report]
+    /// Returns Self and can be chained
+    pub fn remove_duplicate_stop_times(mut self, remove_duplicate_stop_times: bool) -> Self {
+        self.remove_duplicate_stop_times = remove_duplicate_stop_times;
+        self
+    }
+
+    /// Uses `value` when the field of the table is blank or missing (e.g. a default `agency_timezone`)
+    ///
+    /// The values that were set this way are listed in [RawGtfs::defaulted_values]
+    /// Returns Self and can be chained
+    pub fn default_value(self, table: TableName, field_name: &str, value: &str) -> Self {
+        let value = value.to_owned();
+        self.default_value_with(table, field_name, move |_| Some(value.clone()))
+    }
+
+    /// Computes the value to use when the field of the table is blank or missing
+    ///
+    /// The closure gets the other values of the row and returns None to keep the field blank.
+    /// For instance, a default `route_color` depending on the `route_type`:
+    /// ```
+    ///use gtfs_structures::TableName;
+    ///let gtfs = gtfs_structures::GtfsReader::default()
+    ///    .default_value_with(TableName::Routes, "route_color", |row| match row.get("route_type") {
+    ///        Some("3") => Some("FFCC00".to_owned()),
+    ///        _ => None,
+    ///    })
+    ///    .raw()
+    ///    .read("fixtures/missing_values")?;
+    ///assert_eq!(rgb::RGB8::new(0xFF, 0xCC, 0x00), gtfs.routes?[0].color);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    ///```
+    /// Returns Self and can be chained
+    pub fn default_value_with<F>(mut self, table: TableName, field_name: &str, value: F) -> Self
+    where
+        F: Fn(&CsvRow) -> Option<String> + Send + Sync + 'static,
+    {
+        self.default_values.push(DefaultValue {
+            table,
+            field_name: field_name.to_owned(),
+            value: Box::new(value),
+        });
+        self
+    }
+
+    /// Allows to abort a long reading from another thread by setting the flag to true
+    ///
+    /// The flag is checked between the files and regularly while reading a file.
+    /// The reading then returns [Error::Cancelled]
+    /// Returns Self and can be chained
+    pub fn cancel_flag(mut self, cancel_flag: Arc<AtomicBool>) -> Self {
+        self.cancel_flag = Some(cancel_flag);
         self
     }
 
@@ -93,7 +648,7 @@ impl GtfsReader {
     /// To read from an url, build with read-url feature
     /// See also [Gtfs::from_url] and [Gtfs::from_path] if you don’t want the library to guess
     pub fn read(self, gtfs: &str) -> Result<Gtfs, Error> {
-        self.raw().read(gtfs).and_then(Gtfs::try_from)
+        self.raw().read_target(gtfs)
     }
 
     /// Reads the raw GTFS from a local zip archive or local directory
@@ -101,7 +656,53 @@ impl GtfsReader {
     where
         P: AsRef<Path>,
     {
-        self.raw().read_from_path(path).and_then(Gtfs::try_from)
+        self.raw().read_path(path.as_ref())
+    }
+
+    /// Reads a [CompactGtfs] from an url (if starts with `"http"`) or a local path
+    pub fn read_compact(self, gtfs: &str) -> Result<CompactGtfs, Error> {
+        self.raw().read_target(gtfs)
+    }
+
+    /// Reads a [CompactGtfs] from a local zip archive or local directory
+    pub fn read_compact_from_path<P>(self, path: P) -> Result<CompactGtfs, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.raw().read_path(path.as_ref())
+    }
+
+    /// Reads the GTFS from any object implementing [std::io::Read] and [std::io::Seek]
+    pub fn read_from_reader<T: std::io::Read + std::io::Seek>(
+        self,
+        reader: T,
+    ) -> Result<Gtfs, Error> {
+        self.raw().read_reader(reader)
+    }
+
+    /// Reads the GTFS from a reader that cannot seek, like the standard input or a pipe
+    ///
+    /// The whole archive is first loaded in memory
+    pub fn read_from_stream<T: std::io::Read>(self, reader: T) -> Result<Gtfs, Error> {
+        self.raw().read_stream(reader)
+    }
+
+    /// Gives the rows of the file of a table one by one to the visitor, without building any object
+    ///
+    /// See [RawGtfsReader::visit_records]
+    pub fn visit_records<P, F>(self, path: P, table: TableName, visitor: F) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&CsvRow) -> bool,
+    {
+        self.raw().visit_records(path, table, visitor)
+    }
+
+    /// Reads the stop times one by one, without keeping them all in memory
+    ///
+    /// See [RawGtfsReader::stop_times_iter]
+    pub fn stop_times_iter<P: AsRef<Path>>(self, path: P) -> Result<StopTimesIter, Error> {
+        self.raw().stop_times_iter(path)
     }
 
     /// Reads the GTFS from a remote url
@@ -109,7 +710,7 @@ impl GtfsReader {
     /// The library must be built with the read-url feature
     #[cfg(feature = "read-url")]
     pub fn read_from_url<U: reqwest::IntoUrl>(self, url: U) -> Result<Gtfs, Error> {
-        self.raw().read_from_url(url).and_then(Gtfs::try_from)
+        self.raw().read_url(url)
     }
 
     /// Asynchronously reads the GTFS from a remote url
@@ -117,10 +718,7 @@ impl GtfsReader {
     /// The library must be built with the read-url feature
     #[cfg(feature = "read-url")]
     pub async fn read_from_url_async<U: reqwest::IntoUrl>(self, url: U) -> Result<Gtfs, Error> {
-        self.raw()
-            .read_from_url_async(url)
-            .await
-            .and_then(Gtfs::try_from)
+        self.raw().read_url_async(url).await
     }
 
     /// Read the Gtfs as a [RawGtfs].
@@ -135,7 +733,12 @@ impl GtfsReader {
     /// # Ok::<(), gtfs_structures::error::Error>(())
     ///```
     pub fn raw(self) -> RawGtfsReader {
-        RawGtfsReader { reader: self }
+        RawGtfsReader {
+            reader: self,
+            invalid_rows: Mutex::new(Vec::new()),
+            defaulted_values: Mutex::new(Vec::new()),
+            metrics: Mutex::new(HashMap::new()),
+        }
     }
 }
 
@@ -144,188 +747,1035 @@ impl GtfsReader {
 /// The methods to read a Gtfs are the same as for [GtfsReader]
 pub struct RawGtfsReader {
     reader: GtfsReader,
+    /// Rows skipped while reading the files, see [GtfsReader::skip_invalid_rows]
+    invalid_rows: Mutex<Vec<InvalidRow>>,
+    /// Values set by [GtfsReader::default_value] while reading the files
+    defaulted_values: Mutex<Vec<DefaultedValue>>,
+    /// Measures of the files that were read
+    metrics: Mutex<HashMap<TableName, FileMetrics>>,
 }
 
 impl RawGtfsReader {
-    fn read_from_directory(&self, p: &std::path::Path) -> Result<RawGtfs, Error> {
+    fn read_directory<R: ReadTarget>(&self, p: &std::path::Path) -> Result<R, Error> {
         let start_of_read_instant = Instant::now();
-        // Thoses files are not mandatory
-        // We use None if they don’t exist, not an Error
-        let files = std::fs::read_dir(p)?
-            .filter_map(|d| {
-                d.ok().and_then(|e| {
-                    e.path()
-                        .strip_prefix(p)
-                        .ok()
-                        .and_then(|f| f.to_str().map(|s| s.to_owned()))
-                })
-            })
-            .collect();
+        let (mut source, files) = self.directory_source(p)?;
+        let mut result = R::build(self, &mut source, files, crate::SourceFormat::Directory)?;
+        result.set_read_info(start_of_read_instant.elapsed(), None);
+        Ok(result)
+    }
 
-        let mut result = RawGtfs {
-            trips: self.read_objs_from_path(p.join("trips.txt")),
-            calendar: self.read_objs_from_optional_path(p, "calendar.txt"),
-            calendar_dates: self.read_objs_from_optional_path(p, "calendar_dates.txt"),
-            stops: self.read_objs_from_path(p.join("stops.txt")),
-            routes: self.read_objs_from_path(p.join("routes.txt")),
-            stop_times: if self.reader.read_stop_times {
-                self.read_objs_from_path(p.join("stop_times.txt"))
+    fn directory_source(&self, p: &Path) -> Result<(DirectorySource, Vec<String>), Error> {
+        let mut directory = p.to_path_buf();
+        let mut files = list_files(p)?;
+
+        if self.reader.search_subdirectories && !self.contains_gtfs_file(&files) {
+            let mut subdirectories: Vec<_> = files.iter().filter(|f| p.join(f).is_dir()).collect();
+            subdirectories.sort();
+            for subdirectory in subdirectories {
+                let sub_files = list_files(&p.join(subdirectory))?;
+                if self.contains_gtfs_file(&sub_files) {
+                    directory = p.join(subdirectory);
+                    files = sub_files
+                        .iter()
+                        .map(|f| format!("{subdirectory}/{f}"))
+                        .collect();
+                    break;
+                }
+            }
+        }
+
+        let source = DirectorySource {
+            files: if self.reader.case_insensitive_file_names {
+                list_files(&directory)?
             } else {
-                Ok(Vec::new())
+                Vec::new()
             },
-            agencies: self.read_objs_from_path(p.join("agency.txt")),
-            shapes: self.read_objs_from_optional_path(p, "shapes.txt"),
-            fare_attributes: self.read_objs_from_optional_path(p, "fare_attributes.txt"),
-            fare_rules: self.read_objs_from_optional_path(p, "fare_rules.txt"),
-            frequencies: self.read_objs_from_optional_path(p, "frequencies.txt"),
-            transfers: self.read_objs_from_optional_path(p, "transfers.txt"),
-            pathways: self.read_objs_from_optional_path(p, "pathways.txt"),
-            feed_info: self.read_objs_from_optional_path(p, "feed_info.txt"),
-            read_duration: start_of_read_instant.elapsed(),
-            translations: self.read_objs_from_optional_path(p, "translations.txt"),
-            files,
-            source_format: crate::SourceFormat::Directory,
-            sha256: None,
+            path: directory,
         };
-
-        if self.reader.unkown_enum_as_default {
-            result.unknown_to_default();
-        }
-        Ok(result)
+        Ok((source, files))
     }
 
     /// Reads from an url (if starts with `"http"`) if the feature `read-url` is activated,
     /// or a local path (either a directory or zipped file)
     pub fn read(self, gtfs: &str) -> Result<RawGtfs, Error> {
+        self.read_target(gtfs)
+    }
+
+    fn read_target<R: ReadTarget>(self, gtfs: &str) -> Result<R, Error> {
         #[cfg(feature = "read-url")]
         if gtfs.starts_with("http") {
-            return self.read_from_url(gtfs);
+            return self.read_url(gtfs);
         }
-        self.read_from_path(gtfs)
+        self.read_path(gtfs.as_ref())
     }
 
     /// Reads the GTFS from a remote url
     #[cfg(feature = "read-url")]
     pub fn read_from_url<U: reqwest::IntoUrl>(self, url: U) -> Result<RawGtfs, Error> {
-        let mut res = reqwest::blocking::get(url)?;
-        let mut body = Vec::new();
-        res.read_to_end(&mut body)?;
-        let cursor = std::io::Cursor::new(body);
-        self.read_from_reader(cursor)
+        self.read_url(url)
+    }
+
+    #[cfg(feature = "read-url")]
+    fn read_url<R: ReadTarget, U: reqwest::IntoUrl>(self, url: U) -> Result<R, Error> {
+        let res = reqwest::blocking::get(url)?;
+        self.read_stream(res)
     }
 
     /// Asynchronously reads the GTFS from a remote url
     #[cfg(feature = "read-url")]
     pub async fn read_from_url_async<U: reqwest::IntoUrl>(self, url: U) -> Result<RawGtfs, Error> {
+        self.read_url_async(url).await
+    }
+
+    #[cfg(feature = "read-url")]
+    async fn read_url_async<R: ReadTarget, U: reqwest::IntoUrl>(self, url: U) -> Result<R, Error> {
         let res = reqwest::get(url).await?.bytes().await?;
         let reader = std::io::Cursor::new(res);
-        self.read_from_reader(reader)
+        self.read_reader(reader)
     }
 
-    /// Reads the raw GTFS from a local zip archive or local directory
+    /// Reads the raw GTFS from a local zip archive or local directory
     pub fn read_from_path<P>(&self, path: P) -> Result<RawGtfs, Error>
     where
         P: AsRef<Path>,
     {
-        let p = path.as_ref();
+        self.read_path(path.as_ref())
+    }
+
+    fn read_path<R: ReadTarget>(&self, p: &Path) -> Result<R, Error> {
         if p.is_file() {
+            #[cfg(feature = "mmap")]
+            let reader = std::io::Cursor::new(map_file(p)?);
+            #[cfg(not(feature = "mmap"))]
             let reader = File::open(p)?;
-            self.read_from_reader(reader)
+            self.read_archive(reader, Some(p))
         } else if p.is_dir() {
-            self.read_from_directory(p)
+            self.read_directory(p)
         } else {
             Err(Error::NotFileNorDirectory(format!("{}", p.display())))
         }
     }
 
-    pub fn read_from_reader<T: std::io::Read + std::io::Seek>(
+    /// Reads again a local zip archive or local directory, but only parses the files whose sha256 sum changed
+    /// since `previous` was read. The objects of the unchanged files are copied from `previous`
+    ///
+    /// The files that could not be read in `previous`, or that had rows skipped by [GtfsReader::skip_invalid_rows], are parsed again.
+    /// The reader should have the same options as the one that read `previous`, with [GtfsReader::hash_files]:
+    /// without the sha256 sums of `previous`, all the files are parsed again
+    /// ```
+    ///let reader = gtfs_structures::GtfsReader::default().hash_files(true).raw();
+    ///let previous = reader.read_from_path("fixtures/basic")?;
+    ///let gtfs = reader.reload_if_changed("fixtures/basic", &previous)?;
+    ///assert!(gtfs.metrics.files.values().all(|file| file.reused));
+    ///assert_eq!(previous.stops?.len(), gtfs.stops?.len());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    ///```
+    pub fn reload_if_changed<P: AsRef<Path>>(
         &self,
-        reader: T,
+        path: P,
+        previous: &RawGtfs,
     ) -> Result<RawGtfs, Error> {
         let start_of_read_instant = Instant::now();
-        let mut hasher = Sha256::new();
-        let mut buf_reader = std::io::BufReader::new(reader);
-        let _n = std::io::copy(&mut buf_reader, &mut hasher)?;
-        let hash = hasher.finalize();
-        let mut archive = zip::ZipArchive::new(buf_reader)?;
-        let mut file_mapping = HashMap::new();
-        let mut files = Vec::new();
-
-        for i in 0..archive.len() {
+        let path = path.as_ref();
+        if !path.is_file() && !path.is_dir() {
+            return Err(Error::NotFileNorDirectory(format!("{}", path.display())));
+        }
+        let mut result = self.with_source_of_path(path, |source, files, source_format| {
+            let mut unchanged = Vec::new();
+            for table in TableName::ALL {
+                if !self.must_read(table) || !previous.has_table(table) {
+                    continue;
+                }
+                // The skipped rows cannot be copied, their errors are built again
+                let previous_sha256 = previous
+                    .metrics
+                    .files
+                    .get(&table)
+                    .filter(|m| m.skipped_rows == 0)
+                    .and_then(|m| m.sha256.as_ref());
+                if previous_sha256.is_some()
+                    && previous_sha256 == self.sha256_of(source, table)?.as_ref()
+                {
+                    unchanged.push(table);
+                }
+            }
+
+            let mut source = SkippingSource {
+                inner: source,
+                skipped: &unchanged,
+            };
+            let mut result = self.read_tables(&mut source, files, source_format, &[])?;
+            for table in unchanged {
+                result.copy_table(previous, table);
+                let metrics = &previous.metrics.files[&table];
+                result.metrics.files.insert(
+                    table,
+                    FileMetrics {
+                        reused: true,
+                        ..metrics.clone()
+                    },
+                );
+                result.defaulted_values.extend(
+                    previous
+                        .defaulted_values
+                        .iter()
+                        .filter(|v| v.file_name == metrics.file_name)
+                        .cloned(),
+                );
+            }
+            result
+                .defaulted_values
+                .sort_by(|a, b| (&a.file_name, a.line).cmp(&(&b.file_name, b.line)));
+            Ok(result)
+        })?;
+
+        let sha256 = if path.is_file() {
+            let mut hasher = Sha256::new();
+            std::io::copy(&mut File::open(path)?, &mut hasher)?;
+            Some(format!("{:x}", hasher.finalize()))
+        } else {
+            None
+        };
+        result.set_read_info(start_of_read_instant.elapsed(), sha256);
+        Ok(result)
+    }
+
+    /// sha256 sum of the whole file of the table. None if the file does not exist
+    fn sha256_of(
+        &self,
+        source: &mut dyn FileSource,
+        table: TableName,
+    ) -> Result<Option<String>, Error> {
+        let file_name = self.file_name_of(table);
+        let io_error = |e| Error::NamedFileIO {
+            file_name: file_name.to_owned(),
+            source: e,
+        };
+        match source.open(table, file_name) {
+            Some(reader) => {
+                let mut hasher = Sha256::new();
+                std::io::copy(&mut reader.map_err(io_error)?, &mut hasher)
+                    .map_err(|e| io_error(Box::new(e)))?;
+                Ok(Some(format!("{:x}", hasher.finalize())))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// sha256 sum of a file loaded in memory, if [GtfsReader::hash_files] is set
+    fn sha256_of_content(&self, content: &[u8]) -> Option<String> {
+        self.reader
+            .hash_files
+            .then(|| format!("{:x}", Sha256::digest(content)))
+    }
+
+    /// Reads the stop times one by one from a local zip archive or local directory, without keeping them all in memory
+    ///
+    /// The file is parsed in a background thread, a few thousands stop times ahead of the iteration.
+    /// The options of the reader apply, but the rows skipped with [GtfsReader::skip_invalid_rows] are not reported
+    /// ```
+    ///let stop_times = gtfs_structures::GtfsReader::default()
+    ///    .raw()
+    ///    .stop_times_iter("fixtures/zips/gtfs.zip")?;
+    ///let mut nb_stop_times = 0;
+    ///for stop_time in stop_times {
+    ///    assert_eq!("trip1", stop_time?.trip_id);
+    ///    nb_stop_times += 1;
+    ///}
+    ///assert_eq!(2, nb_stop_times);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    ///```
+    pub fn stop_times_iter<P: AsRef<Path>>(self, path: P) -> Result<StopTimesIter, Error> {
+        let path = path.as_ref().to_path_buf();
+        if !path.is_file() && !path.is_dir() {
+            return Err(Error::NotFileNorDirectory(format!("{}", path.display())));
+        }
+        let (sender, receiver) = std::sync::mpsc::sync_channel(STOP_TIMES_BUFFER);
+        std::thread::spawn(move || {
+            if let Err(e) = self.send_stop_times(&path, &sender) {
+                // The iteration might have been stopped, there is nobody to warn then
+                let _ = sender.send(Err(e));
+            }
+        });
+        Ok(StopTimesIter { receiver })
+    }
+
+    fn send_stop_times(
+        &self,
+        path: &Path,
+        sender: &SyncSender<Result<RawStopTime, Error>>,
+    ) -> Result<(), Error> {
+        // When the iterator is dropped, sending fails and we stop parsing
+        let mut send = |mut stop_time| match self.check_stop_time(&mut stop_time) {
+            Ok(()) => sender.send(Ok(stop_time)).is_ok(),
+            Err(e) => {
+                let _ = sender.send(Err(e));
+                false
+            }
+        };
+        self.for_each_record_of_path(path, TableName::StopTimes, &mut |headers, rec| {
+            self.parse_record(
+                headers,
+                rec,
+                self.file_name_of(TableName::StopTimes),
+                &mut send,
+            )
+        })
+    }
+
+    /// Gives the rows of the file of a table one by one to the visitor, without building any object.
+    /// The visitor returns false to stop the reading
+    ///
+    /// The values are borrowed from the buffer of the csv reader, so nothing is allocated for each row.
+    /// They can be deserialized in structures that borrow them, like [crate::RawStopTimeRef]:
+    /// ```
+    ///use gtfs_structures::{GtfsReader, RawStopTimeRef, TableName};
+    ///let mut nb_stop_times_of_stop2 = 0;
+    ///GtfsReader::default().raw().visit_records("fixtures/basic", TableName::StopTimes, |row| {
+    ///    let stop_time: RawStopTimeRef = row.deserialize().unwrap();
+    ///    if stop_time.stop_id == "stop2" {
+    ///        nb_stop_times_of_stop2 += 1;
+    ///    }
+    ///    true
+    ///})?;
+    ///assert_eq!(1, nb_stop_times_of_stop2);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    ///```
+    pub fn visit_records<P, F>(
+        &self,
+        path: P,
+        table: TableName,
+        mut visitor: F,
+    ) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&CsvRow) -> bool,
+    {
+        let path = path.as_ref();
+        if !path.is_file() && !path.is_dir() {
+            return Err(Error::NotFileNorDirectory(format!("{}", path.display())));
+        }
+        self.for_each_record_of_path(path, table, &mut |headers, record| {
+            if visitor(&CsvRow { headers, record }) {
+                Ok(Visit::Continue)
+            } else {
+                Ok(Visit::Stop)
+            }
+        })
+    }
+
+    /// Visits the records of all the files of a local archive or directory, opened only once.
+    /// Returns the measures of each file that was read
+    pub(crate) fn visit_all_records(
+        &self,
+        path: &Path,
+        visitor: &mut dyn FnMut(TableName, &CsvRow),
+    ) -> Result<HashMap<TableName, FileMetrics>, Error> {
+        if !path.is_file() && !path.is_dir() {
+            return Err(Error::NotFileNorDirectory(format!("{}", path.display())));
+        }
+        self.with_source_of_path(path, |source, _, _| {
+            for table in TableName::ALL {
+                if !self.must_read(table) {
+                    continue;
+                }
+                if self.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
+                let start = Instant::now();
+                let mut rows = 0;
+                let result = self.for_each_record_of_file(source, table, &mut |headers, record| {
+                    rows += 1;
+                    visitor(table, &CsvRow { headers, record });
+                    Ok(Visit::Continue)
+                });
+                if let Some(result) = result {
+                    let (bytes, sha256) = result?;
+                    self.add_metrics(table, start.elapsed(), rows, bytes, sha256);
+                }
+            }
+            Ok(self.take_metrics())
+        })
+    }
+
+    /// Reads the records of the file of a table from a local archive or directory, without reading the other files
+    fn for_each_record_of_path(
+        &self,
+        path: &Path,
+        table: TableName,
+        f: &mut RecordVisitor,
+    ) -> Result<(), Error> {
+        self.with_source_of_path(path, |source, _, _| {
+            self.for_each_record_of_file(source, table, f)
+                .unwrap_or_else(|| Err(self.missing_file(table)))
+                .map(|_| ())
+        })
+    }
+
+    /// Opens a local archive or directory, and gives its files to `f`
+    fn with_source_of_path<T, F>(&self, path: &Path, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut dyn FileSource, Vec<String>, crate::SourceFormat) -> Result<T, Error>,
+    {
+        if path.is_dir() {
+            let (mut source, files) = self.directory_source(path)?;
+            return f(&mut source, files, crate::SourceFormat::Directory);
+        }
+        #[cfg(feature = "mmap")]
+        #[cfg_attr(not(feature = "read-tar-gz"), allow(unused_mut))]
+        let mut reader = std::io::Cursor::new(map_file(path)?);
+        #[cfg(not(feature = "mmap"))]
+        #[cfg_attr(not(feature = "read-tar-gz"), allow(unused_mut))]
+        let mut reader = std::io::BufReader::new(File::open(path)?);
+        #[cfg(feature = "read-tar-gz")]
+        if is_gzip(&mut reader)? {
+            let (mut source, files) = self.tar_gz_source(reader)?;
+            return f(&mut source, files, crate::SourceFormat::TarGz);
+        }
+        let (mut source, files) = self.zip_source(reader, Some(path))?;
+        f(&mut source, files, crate::SourceFormat::Zip)
+    }
+
+    fn missing_file(&self, table: TableName) -> Error {
+        Error::MissingFile(self.file_name_of(table).to_owned())
+    }
+
+    /// Reads the raw GTFS from a reader that cannot seek, like the standard input or a pipe
+    ///
+    /// As a zip archive must be read from its end, the whole archive is first loaded in memory
+    pub fn read_from_stream<T: std::io::Read>(&self, reader: T) -> Result<RawGtfs, Error> {
+        self.read_stream(reader)
+    }
+
+    fn read_stream<R: ReadTarget, T: std::io::Read>(&self, mut reader: T) -> Result<R, Error> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        self.read_reader(std::io::Cursor::new(content))
+    }
+
+    /// Reads the raw GTFS from a zip archive (or a tar.gz archive with the feature `read-tar-gz`)
+    pub fn read_from_reader<T: std::io::Read + std::io::Seek>(
+        &self,
+        reader: T,
+    ) -> Result<RawGtfs, Error> {
+        self.read_reader(reader)
+    }
+
+    fn read_reader<R: ReadTarget, T: std::io::Read + std::io::Seek>(
+        &self,
+        reader: T,
+    ) -> Result<R, Error> {
+        self.read_archive(reader, None)
+    }
+
+    /// Reads an archive, from a local file if `path` is given
+    fn read_archive<R: ReadTarget, T: std::io::Read + std::io::Seek>(
+        &self,
+        reader: T,
+        path: Option<&Path>,
+    ) -> Result<R, Error> {
+        let start_of_read_instant = Instant::now();
+        let mut hasher = Sha256::new();
+        let mut buf_reader = std::io::BufReader::new(reader);
+        let _n = std::io::copy(&mut buf_reader, &mut hasher)?;
+        let hash = hasher.finalize();
+
+        #[cfg(feature = "read-tar-gz")]
+        {
+            if is_gzip(&mut buf_reader)? {
+                let (mut source, files) = self.tar_gz_source(buf_reader)?;
+                let mut result = R::build(self, &mut source, files, crate::SourceFormat::TarGz)?;
+                result.set_read_info(start_of_read_instant.elapsed(), Some(format!("{hash:x}")));
+                return Ok(result);
+            }
+        }
+
+        let (mut source, files) = self.zip_source(buf_reader, path)?;
+        let mut result = R::build(self, &mut source, files, crate::SourceFormat::Zip)?;
+        result.set_read_info(start_of_read_instant.elapsed(), Some(format!("{hash:x}")));
+        Ok(result)
+    }
+
+    fn zip_source<T: std::io::Read + std::io::Seek>(
+        &self,
+        reader: T,
+        path: Option<&Path>,
+    ) -> Result<(ZipSource<T>, Vec<String>), Error> {
+        let mut archive = zip::ZipArchive::new(reader)?;
+        let mut file_mapping = HashMap::new();
+        let mut files = Vec::new();
+
+        for i in 0..archive.len() {
             let archive_file = archive.by_index(i)?;
             files.push(archive_file.name().to_owned());
 
-            for gtfs_file in &[
-                "agency.txt",
-                "calendar.txt",
-                "calendar_dates.txt",
-                "routes.txt",
-                "stops.txt",
-                "stop_times.txt",
-                "trips.txt",
-                "fare_attributes.txt",
-                "fare_rules.txt",
-                "frequencies.txt",
-                "transfers.txt",
-                "pathways.txt",
-                "feed_info.txt",
-                "shapes.txt",
-            ] {
-                let path = std::path::Path::new(archive_file.name());
-                if path.file_name() == Some(std::ffi::OsStr::new(gtfs_file)) {
-                    file_mapping.insert(gtfs_file, i);
-                    break;
+            if let Some(table) = self.table_of_path(archive_file.name()) {
+                file_mapping.insert(table, i);
+            }
+        }
+
+        let source = ZipSource {
+            archive,
+            file_mapping,
+            background_path: path
+                .filter(|_| self.reader.background_decompression)
+                .map(Path::to_path_buf),
+        };
+        Ok((source, files))
+    }
+
+    /// A tar archive can only be read sequentially, so the GTFS files are decompressed in memory
+    #[cfg(feature = "read-tar-gz")]
+    fn tar_gz_source<T: std::io::Read>(
+        &self,
+        reader: T,
+    ) -> Result<(InMemorySource, Vec<String>), Error> {
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(reader));
+        let mut source = InMemorySource {
+            files: HashMap::new(),
+        };
+        let mut files = Vec::new();
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            if let Some(table) = self.table_of_path(&name) {
+                if self.must_read(table) {
+                    let mut content = Vec::new();
+                    entry.take(self.content_limit()).read_to_end(&mut content)?;
+                    source.files.insert(table, content);
                 }
             }
+            files.push(name);
         }
+        Ok((source, files))
+    }
 
-        let mut result = RawGtfs {
-            agencies: self.read_file(&file_mapping, &mut archive, "agency.txt"),
-            calendar: self.read_optional_file(&file_mapping, &mut archive, "calendar.txt"),
-            calendar_dates: self.read_optional_file(
-                &file_mapping,
-                &mut archive,
-                "calendar_dates.txt",
-            ),
-            routes: self.read_file(&file_mapping, &mut archive, "routes.txt"),
-            stops: self.read_file(&file_mapping, &mut archive, "stops.txt"),
-            stop_times: if self.reader.read_stop_times {
-                self.read_file(&file_mapping, &mut archive, "stop_times.txt")
-            } else {
+    fn read_tables(
+        &self,
+        source: &mut dyn FileSource,
+        files: Vec<String>,
+        source_format: crate::SourceFormat,
+        deferred: &[TableName],
+    ) -> Result<RawGtfs, Error> {
+        let mut result = self.read_all_files(source, files, source_format, deferred)?;
+        result.metrics.files = self.take_metrics();
+        result.invalid_rows = std::mem::take(&mut *self.invalid_rows.lock().unwrap());
+        result.defaulted_values = std::mem::take(&mut *self.defaulted_values.lock().unwrap());
+
+        match self.reader.parse_mode {
+            ParseMode::Strict => result.unknown_to_error(|table| self.file_name_of(table)),
+            ParseMode::Lenient if self.reader.unkown_enum_as_default => result.unknown_to_default(),
+            ParseMode::Lenient => (),
+        }
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        Ok(result)
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn read_all_files(
+        &self,
+        source: &mut dyn FileSource,
+        files: Vec<String>,
+        source_format: crate::SourceFormat,
+        deferred: &[TableName],
+    ) -> Result<RawGtfs, Error> {
+        Ok(RawGtfs {
+            agencies: self.read_file(source, TableName::Agency),
+            calendar: self.read_optional_file(source, TableName::Calendar),
+            calendar_dates: self.read_optional_file(source, TableName::CalendarDates),
+            routes: self.read_file(source, TableName::Routes),
+            stops: self.read_file(source, TableName::Stops),
+            stop_times: if deferred.contains(&TableName::StopTimes) {
                 Ok(Vec::new())
+            } else {
+                self.read_file(source, TableName::StopTimes)
             },
-            trips: self.read_file(&file_mapping, &mut archive, "trips.txt"),
-            fare_attributes: self.read_optional_file(
-                &file_mapping,
-                &mut archive,
-                "fare_attributes.txt",
-            ),
-            fare_rules: self.read_optional_file(&file_mapping, &mut archive, "fare_rules.txt"),
-            frequencies: self.read_optional_file(&file_mapping, &mut archive, "frequencies.txt"),
-            transfers: self.read_optional_file(&file_mapping, &mut archive, "transfers.txt"),
-            pathways: self.read_optional_file(&file_mapping, &mut archive, "pathways.txt"),
-            feed_info: self.read_optional_file(&file_mapping, &mut archive, "feed_info.txt"),
-            shapes: if self.reader.read_shapes {
-                self.read_optional_file(&file_mapping, &mut archive, "shapes.txt")
+            trips: self.read_file(source, TableName::Trips),
+            fare_attributes: self.read_optional_file(source, TableName::FareAttributes),
+            fare_rules: self.read_optional_file(source, TableName::FareRules),
+            frequencies: self.read_optional_file(source, TableName::Frequencies),
+            transfers: self.read_optional_file(source, TableName::Transfers),
+            pathways: self.read_optional_file(source, TableName::Pathways),
+            feed_info: self.read_optional_file(source, TableName::FeedInfo),
+            shapes: if deferred.contains(&TableName::Shapes) {
+                None
             } else {
-                Some(Ok(Vec::new()))
+                self.read_optional_file(source, TableName::Shapes)
             },
-            translations: self.read_optional_file(&file_mapping, &mut archive, "translations.txt"),
-            read_duration: start_of_read_instant.elapsed(),
+            translations: self.read_optional_file(source, TableName::Translations),
+            metrics: ParseMetrics::default(),
+            files,
+            source_format,
+            sha256: None,
+            invalid_rows: Vec::new(),
+            defaulted_values: Vec::new(),
+        })
+    }
+
+    #[cfg(feature = "parallel")]
+    fn read_all_files(
+        &self,
+        source: &mut dyn FileSource,
+        files: Vec<String>,
+        source_format: crate::SourceFormat,
+        deferred: &[TableName],
+    ) -> Result<RawGtfs, Error> {
+        // The files must be extracted one after the other, but they can then be parsed at the same time
+        let memory = &self.load_in_memory(source, deferred)?;
+        let mut result = RawGtfs {
+            agencies: Ok(Vec::new()),
+            calendar: None,
+            calendar_dates: None,
+            routes: Ok(Vec::new()),
+            stops: Ok(Vec::new()),
+            stop_times: Ok(Vec::new()),
+            trips: Ok(Vec::new()),
+            fare_attributes: None,
+            fare_rules: None,
+            frequencies: None,
+            transfers: None,
+            pathways: None,
+            feed_info: None,
+            shapes: None,
+            translations: None,
+            metrics: ParseMetrics::default(),
             files,
-            source_format: crate::SourceFormat::Zip,
-            sha256: Some(format!("{hash:x}")),
+            source_format,
+            sha256: None,
+            invalid_rows: Vec::new(),
+            defaulted_values: Vec::new(),
         };
+        let RawGtfs {
+            agencies,
+            calendar,
+            calendar_dates,
+            routes,
+            stops,
+            stop_times,
+            trips,
+            fare_attributes,
+            fare_rules,
+            frequencies,
+            transfers,
+            pathways,
+            feed_info,
+            shapes,
+            translations,
+            ..
+        } = &mut result;
+        rayon::scope(|s| {
+            s.spawn(|_| *agencies = self.read_file(&mut { memory }, TableName::Agency));
+            s.spawn(|_| *calendar = self.read_optional_file(&mut { memory }, TableName::Calendar));
+            s.spawn(|_| {
+                *calendar_dates = self.read_optional_file(&mut { memory }, TableName::CalendarDates)
+            });
+            s.spawn(|_| *routes = self.read_file(&mut { memory }, TableName::Routes));
+            s.spawn(|_| *stops = self.read_file(&mut { memory }, TableName::Stops));
+            if !deferred.contains(&TableName::StopTimes) {
+                s.spawn(|_| *stop_times = self.read_file(&mut { memory }, TableName::StopTimes));
+            }
+            s.spawn(|_| *trips = self.read_file(&mut { memory }, TableName::Trips));
+            s.spawn(|_| {
+                *fare_attributes =
+                    self.read_optional_file(&mut { memory }, TableName::FareAttributes)
+            });
+            s.spawn(|_| {
+                *fare_rules = self.read_optional_file(&mut { memory }, TableName::FareRules)
+            });
+            s.spawn(|_| {
+                *frequencies = self.read_optional_file(&mut { memory }, TableName::Frequencies)
+            });
+            s.spawn(|_| {
+                *transfers = self.read_optional_file(&mut { memory }, TableName::Transfers)
+            });
+            s.spawn(|_| *pathways = self.read_optional_file(&mut { memory }, TableName::Pathways));
+            s.spawn(|_| *feed_info = self.read_optional_file(&mut { memory }, TableName::FeedInfo));
+            if !deferred.contains(&TableName::Shapes) {
+                s.spawn(|_| *shapes = self.read_optional_file(&mut { memory }, TableName::Shapes));
+            }
+            s.spawn(|_| {
+                *translations = self.read_optional_file(&mut { memory }, TableName::Translations)
+            });
+        });
+        // The files were parsed in any order
+        self.invalid_rows
+            .lock()
+            .unwrap()
+            .sort_by(|a, b| (&a.file_name, a.line).cmp(&(&b.file_name, b.line)));
+        self.defaulted_values
+            .lock()
+            .unwrap()
+            .sort_by(|a, b| (&a.file_name, a.line).cmp(&(&b.file_name, b.line)));
+        Ok(result)
+    }
 
-        if self.reader.unkown_enum_as_default {
-            result.unknown_to_default();
+    /// Extracts all the files that must be read
+    #[cfg(feature = "parallel")]
+    fn load_in_memory(
+        &self,
+        source: &mut dyn FileSource,
+        deferred: &[TableName],
+    ) -> Result<InMemorySource, Error> {
+        let mut memory = InMemorySource {
+            files: HashMap::new(),
+        };
+        for table in TableName::ALL {
+            if !self.must_read(table) || deferred.contains(&table) {
+                continue;
+            }
+            let file_name = self.file_name_of(table);
+            if let Some(content) = source.content(table, file_name, self.content_limit()) {
+                let content = content.map_err(|e| Error::NamedFileIO {
+                    file_name: file_name.to_owned(),
+                    source: e,
+                })?;
+                memory.files.insert(table, content.into_owned());
+            }
         }
-        Ok(result)
+        Ok(memory)
+    }
+
+    /// We never keep more than the size limit, the error is then raised when parsing the file
+    #[cfg(any(feature = "parallel", feature = "read-tar-gz"))]
+    fn content_limit(&self) -> u64 {
+        self.reader
+            .file_size_limit
+            .map_or(u64::MAX, |l| l.saturating_add(1))
+    }
+
+    /// Builds the [Gtfs] directly: the stop times are linked to their trip as soon as they are parsed,
+    /// instead of being all kept in a [RawGtfs] first
+    fn read_gtfs<G: LinkedGtfs>(
+        &self,
+        source: &mut dyn FileSource,
+        files: Vec<String>,
+        source_format: crate::SourceFormat,
+    ) -> Result<G, Error> {
+        let deferred: &[TableName] = if self.reader.lazy_shapes {
+            &[TableName::StopTimes, TableName::Shapes]
+        } else {
+            &[TableName::StopTimes]
+        };
+        let raw = self.read_tables(source, files, source_format, deferred)?;
+        let mut gtfs = G::build(
+            raw,
+            self.reader.skip_broken_references,
+            self.reader.duplicate_policy,
+        )?;
+        if self.must_read(TableName::StopTimes) {
+            self.link_stop_times(source, &mut gtfs)?;
+            if self.reader.remove_duplicate_stop_times {
+                let removed = gtfs.take_duplicate_stop_times();
+                gtfs.validation_report().removed_stop_times = removed;
+            }
+        }
+        if self.reader.lazy_shapes && self.must_read(TableName::Shapes) {
+            if let Some(shapes) = self.index_shapes(source) {
+                gtfs.set_lazy_shapes(shapes?);
+            }
+        }
+        gtfs.metrics().files.extend(self.take_metrics());
+        if self.reader.sorted_storage {
+            gtfs.sort_by_id();
+        }
+
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        Ok(gtfs)
+    }
+
+    /// Parses the stop times and adds them to their trip
+    fn link_stop_times<G: LinkedGtfs>(
+        &self,
+        source: &mut dyn FileSource,
+        gtfs: &mut G,
+    ) -> Result<(), Error> {
+        let file_name = self.file_name_of(TableName::StopTimes);
+        let skip_broken_references = self.reader.skip_broken_references;
+        let mut link_error = None;
+        self.parse_file(source, TableName::StopTimes, &mut |mut stop_time| {
+            let result = self
+                .check_stop_time(&mut stop_time)
+                .and_then(|()| gtfs.add_stop_time(stop_time, skip_broken_references));
+            match result {
+                Ok(()) => true,
+                Err(e) => {
+                    link_error = Some(e);
+                    false
+                }
+            }
+        })
+        .unwrap_or_else(|| Err(Error::MissingFile(file_name.to_owned())))?;
+        if let Some(e) = link_error {
+            return Err(e);
+        }
+        gtfs.sort_stop_times();
+        Ok(())
+    }
+
+    /// Applies the [ParseMode] to the unknown values of a stop time, see [GtfsReader::parse_mode]
+    fn check_stop_time(&self, stop_time: &mut RawStopTime) -> Result<(), Error> {
+        match self.reader.parse_mode {
+            ParseMode::Strict => match stop_time_unknown_value(stop_time) {
+                Some((field_name, value)) => Err(Error::UnknownEnumValue {
+                    file_name: self.file_name_of(TableName::StopTimes).to_owned(),
+                    field_name: field_name.to_owned(),
+                    value: value.to_string(),
+                }),
+                None => Ok(()),
+            },
+            ParseMode::Lenient => {
+                if self.reader.unkown_enum_as_default {
+                    stop_time_unknown_to_default(stop_time);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Keeps the content of the shapes in memory and indexes the position of their records.
+    /// None if the file does not exist
+    fn index_shapes(&self, source: &mut dyn FileSource) -> Option<Result<LazyShapes, Error>> {
+        let start = Instant::now();
+        let table = TableName::Shapes;
+        let file_name = self.file_name_of(table);
+        let reader = source.open(table, file_name)?;
+        Some((|| {
+            let io_error = |e| Error::NamedFileIO {
+                file_name: file_name.to_owned(),
+                source: e,
+            };
+            let mut content = Vec::new();
+            let limit = self.reader.file_size_limit.unwrap_or(u64::MAX);
+            reader
+                .map_err(io_error)?
+                .take(limit.saturating_add(1))
+                .read_to_end(&mut content)
+                .map_err(|e| io_error(Box::new(e)))?;
+            if content.len() as u64 > limit {
+                return Err(Error::FileSizeLimitExceeded {
+                    file_name: file_name.to_owned(),
+                    limit,
+                });
+            }
+            let bytes = content.len() as u64;
+            let sha256 = self.sha256_of_content(&content);
+            // The positions of the records are given without the byte order mark
+            if content.starts_with(&[0xef, 0xbb, 0xbf]) {
+                content.drain(..3);
+            }
+
+            let delimiter = match self.reader.delimiter {
+                Delimiter::Comma => b',',
+                Delimiter::Custom(delimiter) => delimiter,
+                Delimiter::Detect => detect_delimiter(&content[..record_end(&content)]),
+            };
+            let mut starts = IdHashMap::<Vec<usize>>::default();
+            let mut shape_id_index = None;
+            let mut rows = 0;
+            self.for_each_record(
+                content.as_slice(),
+                table,
+                file_name,
+                0,
+                &mut |headers, rec| {
+                    let index = *shape_id_index
+                        .get_or_insert_with(|| headers.iter().position(|h| h == "shape_id"));
+                    if let (Some(shape_id), Some(position)) =
+                        (index.and_then(|i| rec.get(i)), rec.position())
+                    {
+                        let record_start = position.byte() as usize;
+                        match starts.get_mut(shape_id) {
+                            Some(shape_starts) => shape_starts.push(record_start),
+                            None => {
+                                starts.insert(shape_id.into(), vec![record_start]);
+                            }
+                        }
+                    }
+                    rows += 1;
+                    Ok(Visit::Continue)
+                },
+            )?;
+            self.add_metrics(table, start.elapsed(), rows, bytes, sha256);
+            let trim = self.csv_trim();
+            Ok(LazyShapes::new(content, starts, delimiter, trim, file_name))
+        })())
+    }
+
+    fn add_metrics(
+        &self,
+        table: TableName,
+        duration: Duration,
+        rows: usize,
+        bytes: u64,
+        sha256: Option<String>,
+    ) {
+        let metrics = FileMetrics {
+            file_name: self.file_name_of(table).to_owned(),
+            duration,
+            rows,
+            bytes,
+            skipped_rows: 0,
+            defaulted_values: 0,
+            sha256,
+            reused: false,
+        };
+        self.metrics.lock().unwrap().insert(table, metrics);
+    }
+
+    /// Takes the measures of the files read so far, with their number of skipped rows and defaulted values
+    fn take_metrics(&self) -> HashMap<TableName, FileMetrics> {
+        let mut files = std::mem::take(&mut *self.metrics.lock().unwrap());
+        let invalid_rows = self.invalid_rows.lock().unwrap();
+        let defaulted_values = self.defaulted_values.lock().unwrap();
+        for metrics in files.values_mut() {
+            metrics.skipped_rows = invalid_rows
+                .iter()
+                .filter(|r| r.file_name == metrics.file_name)
+                .count();
+            metrics.defaulted_values = defaulted_values
+                .iter()
+                .filter(|v| v.file_name == metrics.file_name)
+                .count();
+        }
+        files
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.reader
+            .cancel_flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Finds the table from the path of a file in an archive
+    fn table_of_path(&self, path: &str) -> Option<TableName> {
+        let path = Path::new(path);
+        if let Some(subdirectory) = &self.reader.archive_subdirectory {
+            if path.parent() != Some(Path::new(subdirectory)) {
+                return None;
+            }
+        }
+        let file_name = path.file_name()?.to_str()?;
+        TableName::ALL
+            .iter()
+            .find(|t| self.is_file_of(file_name, **t))
+            .copied()
+    }
+
+    fn contains_gtfs_file(&self, files: &[String]) -> bool {
+        files
+            .iter()
+            .any(|f| TableName::ALL.iter().any(|t| self.is_file_of(f, *t)))
+    }
+
+    fn is_file_of(&self, file_name: &str, table: TableName) -> bool {
+        if self.reader.case_insensitive_file_names {
+            file_name.eq_ignore_ascii_case(self.file_name_of(table))
+        } else {
+            file_name == self.file_name_of(table)
+        }
+    }
+
+    /// The name of the file of the table, unless a custom name was given
+    fn file_name_of(&self, table: TableName) -> &str {
+        self.reader
+            .file_names
+            .get(&table)
+            .map_or(table.file_name(), |f| f.as_str())
+    }
+
+    /// Some tables can be skipped to save time and memory
+    fn must_read(&self, table: TableName) -> bool {
+        if let Some(only_files) = &self.reader.only_files {
+            if !only_files.iter().any(|f| self.is_file_of(f, table)) {
+                return false;
+            }
+        }
+        match table {
+            TableName::StopTimes => self.reader.read_stop_times,
+            TableName::Shapes => self.reader.read_shapes,
+            TableName::Pathways => self.reader.read_pathways,
+            TableName::Transfers => self.reader.read_transfers,
+            TableName::Translations => self.reader.read_translations,
+            TableName::FareAttributes | TableName::FareRules => self.reader.read_fares,
+            _ => true,
+        }
+    }
+
+    /// Parses the objects of a file one by one, until `f` returns false
+    #[cfg(feature = "parallel")]
+    fn for_each_obj<T, O>(
+        &self,
+        reader: T,
+        table: TableName,
+        file_name: &str,
+        line_offset: u64,
+        f: &mut dyn FnMut(O) -> bool,
+    ) -> Result<(), Error>
+    where
+        for<'de> O: Deserialize<'de>,
+        T: std::io::Read,
+    {
+        self.for_each_record(
+            reader,
+            table,
+            file_name,
+            line_offset,
+            &mut |headers, rec| self.parse_record(headers, rec, file_name, f),
+        )
     }
 
-    fn read_objs<T, O>(&self, mut reader: T, file_name: &str) -> Result<Vec<O>, Error>
+    /// Deserializes a record and gives the object to `f`
+    fn parse_record<O>(
+        &self,
+        headers: &csv::StringRecord,
+        rec: &csv::StringRecord,
+        file_name: &str,
+        f: &mut dyn FnMut(O) -> bool,
+    ) -> Result<Visit, Error>
     where
         for<'de> O: Deserialize<'de>,
+    {
+        match rec.deserialize(Some(headers)) {
+            Ok(obj) => Ok(if f(obj) { Visit::Continue } else { Visit::Stop }),
+            Err(e) if self.reader.skip_invalid_rows => {
+                self.invalid_rows.lock().unwrap().push(InvalidRow {
+                    file_name: file_name.to_owned(),
+                    line: rec.position().map_or(0, |p| p.line()),
+                    headers: headers.iter().map(String::from).collect(),
+                    values: rec.iter().map(String::from).collect(),
+                    error: e,
+                });
+                Ok(Visit::Skipped)
+            }
+            Err(e) => Err(Error::CSVError {
+                file_name: file_name.to_owned(),
+                source: e,
+                line_in_error: Some(crate::error::LineError {
+                    line: rec.position().map_or(0, |p| p.line()),
+                    headers: headers.into_iter().map(String::from).collect(),
+                    values: rec.into_iter().map(String::from).collect(),
+                }),
+            }),
+        }
+    }
+
+    /// Reads the records of a file one by one, until `f` returns [Visit::Stop]
+    ///
+    /// `line_offset` is added to the line of each record, when the file is read in chunks
+    fn for_each_record<T>(
+        &self,
+        mut reader: T,
+        table: TableName,
+        file_name: &str,
+        line_offset: u64,
+        f: &mut RecordVisitor,
+    ) -> Result<(), Error>
+    where
         T: std::io::Read,
     {
         let mut bom = [0; 3];
@@ -341,31 +1791,66 @@ impl RawGtfsReader {
         } else {
             [].chain(reader)
         };
+        let mut chained = std::io::BufReader::new(chained);
+
+        // To detect the delimiter, the header line is read first, and then given back to the csv reader
+        let mut header_line = Vec::new();
+        let delimiter = match self.reader.delimiter {
+            Delimiter::Comma => b',',
+            Delimiter::Custom(delimiter) => delimiter,
+            Delimiter::Detect => {
+                chained
+                    .read_until(b'\n', &mut header_line)
+                    .map_err(|e| Error::NamedFileIO {
+                        file_name: file_name.to_owned(),
+                        source: Box::new(e),
+                    })?;
+                detect_delimiter(&header_line)
+            }
+        };
+        let chained = header_line.as_slice().chain(chained);
 
         let mut reader = csv::ReaderBuilder::new()
             .flexible(true)
-            .trim(if self.reader.trim_fields {
-                csv::Trim::Fields
-            } else {
-                csv::Trim::None
-            })
+            .delimiter(delimiter)
+            .trim(self.csv_trim())
             .from_reader(chained);
         // We store the headers to be able to return them in case of errors
-        let headers = reader
+        let mut headers = reader
             .headers()
             .map_err(|e| Error::CSVError {
                 file_name: file_name.to_owned(),
                 source: e,
                 line_in_error: None,
             })?
-            .clone()
-            .into_iter()
-            .map(|x| x.trim())
-            .collect::<csv::StringRecord>();
+            .clone();
+
+        let defaults: Vec<_> = self
+            .reader
+            .default_values
+            .iter()
+            .filter(|d| d.table == table)
+            .collect();
+        for default in &defaults {
+            if !headers.iter().any(|h| h == default.field_name) {
+                headers.push_field(&default.field_name);
+            }
+        }
+        let dropped: Vec<usize> = if self.reader.drop_descriptive_fields {
+            headers
+                .iter()
+                .enumerate()
+                .filter(|(_, h)| DESCRIPTIVE_FIELDS.contains(&(table, *h)))
+                .map(|(i, _)| i)
+                .collect()
+        } else {
+            Vec::new()
+        };
 
         // Pre-allocate a StringRecord for performance reasons
         let mut rec = csv::StringRecord::new();
-        let mut objs = Vec::new();
+        // The skipped invalid rows are counted too, to bound the work done on any file
+        let mut nb_records = 0;
 
         // Read each record into the pre-allocated StringRecord one at a time
         while reader.read_record(&mut rec).map_err(|e| Error::CSVError {
@@ -373,87 +1858,919 @@ impl RawGtfsReader {
             source: e,
             line_in_error: None,
         })? {
-            let obj = rec
-                .deserialize(Some(&headers))
-                .map_err(|e| Error::CSVError {
-                    file_name: file_name.to_owned(),
-                    source: e,
-                    line_in_error: Some(crate::error::LineError {
-                        headers: headers.into_iter().map(String::from).collect(),
-                        values: rec.into_iter().map(String::from).collect(),
-                    }),
-                })?;
-            objs.push(obj);
-        }
-        Ok(objs)
+            // Checking the flag for every row would be too costly
+            if nb_records % 10_000 == 0 && self.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            if Some(nb_records) == self.reader.max_rows_per_file {
+                break;
+            }
+            if let Some(limit) = self.reader.row_limit {
+                if nb_records >= limit {
+                    return Err(Error::RowLimitExceeded {
+                        file_name: file_name.to_owned(),
+                        limit,
+                    });
+                }
+            }
+            nb_records += 1;
+            if line_offset > 0 {
+                if let Some(mut position) = rec.position().cloned() {
+                    position.set_line(position.line() + line_offset);
+                    rec.set_position(Some(position));
+                }
+            }
+            if !defaults.is_empty() {
+                rec = self.with_default_values(rec, &headers, &defaults, file_name);
+            }
+            if !dropped.is_empty() {
+                rec = without_values(rec, &dropped);
+            }
+            if let Visit::Stop = f(&headers, &rec)? {
+                break;
+            }
+        }
+        Ok(())
     }
 
-    fn read_objs_from_path<O>(&self, path: std::path::PathBuf) -> Result<Vec<O>, Error>
-    where
-        for<'de> O: Deserialize<'de>,
-    {
-        let file_name = path
-            .file_name()
-            .and_then(|f| f.to_str())
-            .unwrap_or("invalid_file_name")
-            .to_string();
-        if path.exists() {
-            File::open(path)
-                .map_err(|e| Error::NamedFileIO {
-                    file_name: file_name.to_owned(),
-                    source: Box::new(e),
-                })
-                .and_then(|r| self.read_objs(r, &file_name))
-        } else {
-            Err(Error::MissingFile(file_name))
+    fn csv_trim(&self) -> csv::Trim {
+        #[allow(deprecated)]
+        let trim = match self.reader.trim {
+            TrimMode::All if !self.reader.trim_fields => TrimMode::Headers,
+            trim => trim,
+        };
+        match trim {
+            TrimMode::None => csv::Trim::None,
+            TrimMode::Headers => csv::Trim::Headers,
+            TrimMode::All => csv::Trim::All,
         }
     }
 
-    fn read_objs_from_optional_path<O>(
+    /// Replaces the blank or missing values by their default value
+    fn with_default_values(
         &self,
-        dir_path: &std::path::Path,
+        rec: csv::StringRecord,
+        headers: &csv::StringRecord,
+        defaults: &[&DefaultValue],
         file_name: &str,
+    ) -> csv::StringRecord {
+        let row = CsvRow {
+            headers,
+            record: &rec,
+        };
+        let mut patched = csv::StringRecord::with_capacity(rec.as_slice().len(), headers.len());
+        for (i, header) in headers.iter().enumerate() {
+            let value = rec.get(i).unwrap_or_default();
+            let default_value = defaults
+                .iter()
+                .filter(|d| d.field_name == header && value.trim().is_empty())
+                .find_map(|d| (d.value)(&row));
+            match default_value {
+                Some(default_value) => {
+                    patched.push_field(&default_value);
+                    self.defaulted_values.lock().unwrap().push(DefaultedValue {
+                        file_name: file_name.to_owned(),
+                        line: rec.position().map_or(0, |p| p.line()),
+                        field_name: header.to_owned(),
+                        value: default_value,
+                    });
+                }
+                None => patched.push_field(value),
+            }
+        }
+        patched.set_position(rec.position().cloned());
+        patched
+    }
+
+    fn read_file<O>(&self, source: &mut dyn FileSource, table: TableName) -> Result<Vec<O>, Error>
+    where
+        for<'de> O: Deserialize<'de> + Send,
+    {
+        self.read_optional_file(source, table)
+            .unwrap_or_else(|| Err(Error::MissingFile(self.file_name_of(table).to_owned())))
+    }
+
+    fn read_optional_file<O>(
+        &self,
+        source: &mut dyn FileSource,
+        table: TableName,
     ) -> Option<Result<Vec<O>, Error>>
     where
-        for<'de> O: Deserialize<'de>,
+        for<'de> O: Deserialize<'de> + Send,
+    {
+        if !self.must_read(table) {
+            return Some(Ok(Vec::new()));
+        }
+        if self.is_cancelled() {
+            return Some(Err(Error::Cancelled));
+        }
+        let mut objs = Vec::new();
+        // The hint is only an optimization, the vector grows as usual if it cannot be allocated
+        let _ = objs.try_reserve(self.capacity_hint(source, table));
+        self.parse_file(source, table, &mut |obj| {
+            objs.push(obj);
+            true
+        })
+        .map(|result| result.map(|()| objs))
+    }
+
+    /// Number of objects to allocate before parsing a file, to avoid copying large vectors while they grow
+    ///
+    /// The estimation comes from the size claimed by the source, so it is bounded by the limits of the reader and [MAX_CAPACITY_HINT]
+    fn capacity_hint(&self, source: &mut dyn FileSource, table: TableName) -> usize {
+        let file_name = self.file_name_of(table);
+        let rows = match source.estimated_rows(table, file_name) {
+            Some(rows) => rows,
+            None => return 0,
+        };
+        let size_limit = self
+            .reader
+            .file_size_limit
+            .and_then(|limit| usize::try_from(limit / typical_row_size(table)).ok());
+        [
+            self.reader.max_rows_per_file,
+            self.reader.row_limit,
+            size_limit,
+        ]
+        .iter()
+        .flatten()
+        .fold(rows.min(MAX_CAPACITY_HINT), |rows, limit| rows.min(*limit))
+    }
+
+    /// Parses the objects of the file of the table, using several threads with the feature `parallel`.
+    /// None if the file does not exist
+    fn parse_file<O>(
+        &self,
+        source: &mut dyn FileSource,
+        table: TableName,
+        f: &mut dyn FnMut(O) -> bool,
+    ) -> Option<Result<(), Error>>
+    where
+        for<'de> O: Deserialize<'de> + Send,
     {
-        File::open(dir_path.join(file_name))
-            .ok()
-            .map(|r| self.read_objs(r, file_name))
+        let start = Instant::now();
+        let mut rows = 0;
+        let mut count = |obj| {
+            rows += 1;
+            f(obj)
+        };
+        #[cfg(feature = "parallel")]
+        let result = self.for_each_obj_of_file_in_parallel(source, table, &mut count)?;
+        #[cfg(not(feature = "parallel"))]
+        let result = self.for_each_obj_of_file(source, table, &mut count)?;
+        Some(
+            result.map(|(bytes, sha256)| {
+                self.add_metrics(table, start.elapsed(), rows, bytes, sha256)
+            }),
+        )
     }
 
-    fn read_file<O, T>(
+    /// The file is loaded in memory and split in chunks that are parsed by several threads.
+    /// The objects are still given in the order of the file. Returns the size and the sha256 sum of the file
+    #[cfg(feature = "parallel")]
+    fn for_each_obj_of_file_in_parallel<O>(
         &self,
-        file_mapping: &HashMap<&&str, usize>,
-        archive: &mut zip::ZipArchive<T>,
-        file_name: &str,
-    ) -> Result<Vec<O>, Error>
+        source: &mut dyn FileSource,
+        table: TableName,
+        f: &mut dyn FnMut(O) -> bool,
+    ) -> Option<Result<(u64, Option<String>), Error>>
     where
-        for<'de> O: Deserialize<'de>,
-        T: std::io::Read + std::io::Seek,
+        for<'de> O: Deserialize<'de> + Send,
     {
-        self.read_optional_file(file_mapping, archive, file_name)
-            .unwrap_or_else(|| Err(Error::MissingFile(file_name.to_owned())))
+        use rayon::prelude::*;
+
+        let file_name = self.file_name_of(table);
+        let content = source.content(table, file_name, self.content_limit())?;
+        Some((|| {
+            let content = content.map_err(|e| Error::NamedFileIO {
+                file_name: file_name.to_owned(),
+                source: e,
+            })?;
+            if let Some(limit) = self.reader.file_size_limit {
+                if content.len() as u64 > limit {
+                    return Err(Error::FileSizeLimitExceeded {
+                        file_name: file_name.to_owned(),
+                        limit,
+                    });
+                }
+            }
+
+            // The limits on the number of rows apply to the whole file, that must then be parsed at once
+            let chunks =
+                if self.reader.row_limit.is_none() && self.reader.max_rows_per_file.is_none() {
+                    split_records(&content, PARALLEL_CHUNK_SIZE)
+                } else {
+                    vec![(0, &content[..])]
+                };
+            let size = (content.len() as u64, self.sha256_of_content(&content));
+            let headers = &content[..record_end(&content)];
+            let header_lines = headers.iter().filter(|b| **b == b'\n').count() as u64;
+
+            // Only a few chunks are parsed at the same time to limit the memory used
+            for window in chunks.chunks(rayon::current_num_threads()) {
+                let parsed: Vec<Result<Vec<O>, Error>> = window
+                    .par_iter()
+                    .map(|(lines_before, chunk)| {
+                        let mut objs = Vec::with_capacity(count_lines(chunk));
+                        let mut push = |obj| {
+                            objs.push(obj);
+                            true
+                        };
+                        if *lines_before == 0 {
+                            self.for_each_obj(*chunk, table, file_name, 0, &mut push)?;
+                        } else {
+                            let line_offset = lines_before - header_lines;
+                            let reader = headers.chain(*chunk);
+                            self.for_each_obj(reader, table, file_name, line_offset, &mut push)?;
+                        }
+                        Ok(objs)
+                    })
+                    .collect();
+                for objs in parsed {
+                    for obj in objs? {
+                        if !f(obj) {
+                            return Ok(size);
+                        }
+                    }
+                }
+            }
+            Ok(size)
+        })())
     }
 
-    fn read_optional_file<O, T>(
+    /// Parses the objects of the file of the table one by one. None if the file does not exist
+    #[cfg(not(feature = "parallel"))]
+    fn for_each_obj_of_file<O>(
         &self,
-        file_mapping: &HashMap<&&str, usize>,
-        archive: &mut zip::ZipArchive<T>,
-        file_name: &str,
-    ) -> Option<Result<Vec<O>, Error>>
+        source: &mut dyn FileSource,
+        table: TableName,
+        f: &mut dyn FnMut(O) -> bool,
+    ) -> Option<Result<(u64, Option<String>), Error>>
     where
         for<'de> O: Deserialize<'de>,
-        T: std::io::Read + std::io::Seek,
     {
-        file_mapping.get(&file_name).map(|i| {
-            self.read_objs(
-                archive.by_index(*i).map_err(|e| Error::NamedFileIO {
+        let file_name = self.file_name_of(table);
+        self.for_each_record_of_file(source, table, &mut |headers, rec| {
+            self.parse_record(headers, rec, file_name, f)
+        })
+    }
+
+    /// Reads the records of the file of the table one by one, and returns the number of bytes read and their sha256 sum if [GtfsReader::hash_files] is set.
+    /// None if the file does not exist
+    fn for_each_record_of_file(
+        &self,
+        source: &mut dyn FileSource,
+        table: TableName,
+        f: &mut RecordVisitor,
+    ) -> Option<Result<(u64, Option<String>), Error>> {
+        let file_name = self.file_name_of(table);
+        source.open(table, file_name).map(|reader| {
+            reader
+                .map_err(|e| Error::NamedFileIO {
                     file_name: file_name.to_owned(),
-                    source: Box::new(e),
-                })?,
-                file_name,
-            )
+                    source: e,
+                })
+                .and_then(|r| self.for_each_limited_record(r, table, file_name, f))
         })
     }
+
+    fn for_each_limited_record(
+        &self,
+        reader: Box<dyn Read + '_>,
+        table: TableName,
+        file_name: &str,
+        f: &mut RecordVisitor,
+    ) -> Result<(u64, Option<String>), Error> {
+        let mut limited = SizeLimitedReader {
+            inner: reader,
+            remaining: self.reader.file_size_limit.unwrap_or(u64::MAX),
+            read: 0,
+            exceeded: false,
+            hasher: self.reader.hash_files.then(Sha256::new),
+        };
+        let result = self.for_each_record(&mut limited, table, file_name, 0, f);
+        match self.reader.file_size_limit {
+            Some(limit) if limited.exceeded => Err(Error::FileSizeLimitExceeded {
+                file_name: file_name.to_owned(),
+                limit,
+            }),
+            _ => result.map(|()| {
+                let sha256 = limited.hasher.map(|h| format!("{:x}", h.finalize()));
+                (limited.read, sha256)
+            }),
+        }
+    }
+}
+
+/// What is built from the files: a [RawGtfs] or directly a [Gtfs]
+trait ReadTarget: Sized {
+    fn build(
+        reader: &RawGtfsReader,
+        source: &mut dyn FileSource,
+        files: Vec<String>,
+        source_format: crate::SourceFormat,
+    ) -> Result<Self, Error>;
+
+    fn set_read_info(&mut self, read_duration: Duration, sha256: Option<String>);
+}
+
+impl ReadTarget for RawGtfs {
+    fn build(
+        reader: &RawGtfsReader,
+        source: &mut dyn FileSource,
+        files: Vec<String>,
+        source_format: crate::SourceFormat,
+    ) -> Result<Self, Error> {
+        reader.read_tables(source, files, source_format, &[])
+    }
+
+    fn set_read_info(&mut self, read_duration: Duration, sha256: Option<String>) {
+        self.metrics.total_duration = read_duration;
+        self.sha256 = sha256;
+    }
+}
+
+/// A [Gtfs] layout to which the stop times can be added once the rest of the feed is read
+trait LinkedGtfs: Sized {
+    fn build(
+        raw: RawGtfs,
+        skip_broken_references: bool,
+        duplicate_policy: DuplicatePolicy,
+    ) -> Result<Self, Error>;
+
+    fn add_stop_time(
+        &mut self,
+        stop_time: RawStopTime,
+        skip_broken_references: bool,
+    ) -> Result<(), Error>;
+
+    fn sort_stop_times(&mut self);
+
+    fn take_duplicate_stop_times(&mut self) -> Vec<RemovedStopTime>;
+
+    fn metrics(&mut self) -> &mut ParseMetrics;
+
+    fn validation_report(&mut self) -> &mut ValidationReport;
+
+    fn sort_by_id(&mut self);
+
+    fn set_lazy_shapes(&mut self, shapes: LazyShapes);
+}
+
+impl LinkedGtfs for Gtfs {
+    fn build(
+        raw: RawGtfs,
+        skip_broken_references: bool,
+        duplicate_policy: DuplicatePolicy,
+    ) -> Result<Self, Error> {
+        Gtfs::build(raw, skip_broken_references, duplicate_policy)
+    }
+
+    fn add_stop_time(
+        &mut self,
+        stop_time: RawStopTime,
+        skip_broken_references: bool,
+    ) -> Result<(), Error> {
+        Gtfs::add_stop_time(self, stop_time, skip_broken_references)
+    }
+
+    fn sort_stop_times(&mut self) {
+        Gtfs::sort_stop_times(self)
+    }
+
+    fn take_duplicate_stop_times(&mut self) -> Vec<RemovedStopTime> {
+        Gtfs::take_duplicate_stop_times(self)
+    }
+
+    fn metrics(&mut self) -> &mut ParseMetrics {
+        &mut self.metrics
+    }
+
+    fn validation_report(&mut self) -> &mut ValidationReport {
+        &mut self.validation_report
+    }
+
+    fn sort_by_id(&mut self) {
+        Gtfs::sort_by_id(self)
+    }
+
+    fn set_lazy_shapes(&mut self, shapes: LazyShapes) {
+        self.lazy_shapes = shapes;
+    }
+}
+
+impl LinkedGtfs for CompactGtfs {
+    fn build(
+        raw: RawGtfs,
+        skip_broken_references: bool,
+        duplicate_policy: DuplicatePolicy,
+    ) -> Result<Self, Error> {
+        CompactGtfs::build(raw, skip_broken_references, duplicate_policy)
+    }
+
+    fn add_stop_time(
+        &mut self,
+        stop_time: RawStopTime,
+        skip_broken_references: bool,
+    ) -> Result<(), Error> {
+        CompactGtfs::add_stop_time(self, stop_time, skip_broken_references)
+    }
+
+    fn sort_stop_times(&mut self) {
+        CompactGtfs::sort_stop_times(self)
+    }
+
+    fn take_duplicate_stop_times(&mut self) -> Vec<RemovedStopTime> {
+        CompactGtfs::take_duplicate_stop_times(self)
+    }
+
+    fn metrics(&mut self) -> &mut ParseMetrics {
+        &mut self.metrics
+    }
+
+    fn validation_report(&mut self) -> &mut ValidationReport {
+        &mut self.validation_report
+    }
+
+    fn sort_by_id(&mut self) {
+        CompactGtfs::sort_by_id(self)
+    }
+
+    fn set_lazy_shapes(&mut self, shapes: LazyShapes) {
+        self.lazy_shapes = shapes;
+    }
+}
+
+impl<G: LinkedGtfs> ReadTarget for G {
+    fn build(
+        reader: &RawGtfsReader,
+        source: &mut dyn FileSource,
+        files: Vec<String>,
+        source_format: crate::SourceFormat,
+    ) -> Result<Self, Error> {
+        reader.read_gtfs(source, files, source_format)
+    }
+
+    fn set_read_info(&mut self, read_duration: Duration, _sha256: Option<String>) {
+        self.metrics().total_duration = read_duration;
+    }
+}
+
+/// Size in bytes of the parts of a file that are parsed in parallel
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Position just after the end of the first record
+///
+/// A new line ends a record only if it is not between quotes (the quotes being escaped as in the RFC 4180)
+pub(crate) fn record_end(content: &[u8]) -> usize {
+    let mut in_quotes = false;
+    for (i, b) in content.iter().enumerate() {
+        match b {
+            b'"' => in_quotes = !in_quotes,
+            b'\n' if !in_quotes => return i + 1,
+            _ => (),
+        }
+    }
+    content.len()
+}
+
+/// Splits the content of a csv file in chunks of whole records of about `chunk_size` bytes,
+/// each with the number of lines before it. The first chunk starts with the headers
+#[cfg(feature = "parallel")]
+fn split_records(content: &[u8], chunk_size: usize) -> Vec<(u64, &[u8])> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut lines = 0;
+    let mut lines_before_start = 0;
+    let mut in_quotes = false;
+    for (i, b) in content.iter().enumerate() {
+        match b {
+            b'"' => in_quotes = !in_quotes,
+            b'\n' => {
+                lines += 1;
+                if !in_quotes && i + 1 - start >= chunk_size {
+                    chunks.push((lines_before_start, &content[start..=i]));
+                    start = i + 1;
+                    lines_before_start = lines;
+                }
+            }
+            _ => (),
+        }
+    }
+    if start < content.len() || chunks.is_empty() {
+        chunks.push((lines_before_start, &content[start..]));
+    }
+    chunks
+}
+
+/// Number of lines of a csv content, an upper bound of its number of records
+#[cfg(any(feature = "read-tar-gz", feature = "parallel"))]
+fn count_lines(content: &[u8]) -> usize {
+    content.iter().filter(|b| **b == b'\n').count()
+}
+
+/// Typical size in bytes of a row, to estimate the number of rows of a file from its size
+///
+/// The estimation is better a bit too large than too small: the vector would grow to twice its size
+fn typical_row_size(table: TableName) -> u64 {
+    match table {
+        TableName::StopTimes => 40,
+        TableName::Shapes => 35,
+        TableName::CalendarDates => 25,
+        _ => 60,
+    }
+}
+
+/// Maximum number of objects allocated before parsing a file, whatever its size
+///
+/// Larger files are still read, their vector grows while they are parsed
+const MAX_CAPACITY_HINT: usize = 10_000_000;
+
+/// Number of stop times parsed in advance by [RawGtfsReader::stop_times_iter]
+const STOP_TIMES_BUFFER: usize = 10_000;
+
+/// Iterator over the stop times of a GTFS, built with [RawGtfsReader::stop_times_iter]
+///
+/// When a stop time cannot be read, the error is the last item
+pub struct StopTimesIter {
+    receiver: Receiver<Result<RawStopTime, Error>>,
+}
+
+impl Iterator for StopTimesIter {
+    type Item = Result<RawStopTime, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// The record with blank values at the given positions
+fn without_values(rec: csv::StringRecord, positions: &[usize]) -> csv::StringRecord {
+    let mut patched = csv::StringRecord::with_capacity(rec.as_slice().len(), rec.len());
+    for (i, value) in rec.iter().enumerate() {
+        patched.push_field(if positions.contains(&i) { "" } else { value });
+    }
+    patched.set_position(rec.position().cloned());
+    patched
+}
+
+/// Counts (and hashes if needed) the bytes read, and fails as soon as more than `remaining` bytes are read
+///
+/// As it wraps the decompressed stream, the limit holds whatever the archive claims the size is
+struct SizeLimitedReader<R> {
+    inner: R,
+    remaining: u64,
+    read: u64,
+    exceeded: bool,
+    hasher: Option<Sha256>,
+}
+
+impl<R: Read> Read for SizeLimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // We read one byte more than allowed to know if the limit is exceeded
+        let max = usize::try_from(self.remaining.saturating_add(1))
+            .map_or(buf.len(), |r| buf.len().min(r));
+        let n = self.inner.read(&mut buf[..max])?;
+        if n as u64 > self.remaining {
+            self.exceeded = true;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file size limit exceeded",
+            ));
+        }
+        self.remaining -= n as u64;
+        self.read += n as u64;
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+/// Checks the magic number of gzip, and rewinds the reader
+#[cfg(feature = "read-tar-gz")]
+fn is_gzip<T: std::io::Read + std::io::Seek>(reader: &mut T) -> Result<bool, Error> {
+    use std::io::SeekFrom;
+    reader.seek(SeekFrom::Start(0))?;
+    let mut magic = [0; 2];
+    let is_gzip = reader.read_exact(&mut magic).is_ok() && magic == [0x1f, 0x8b];
+    reader.seek(SeekFrom::Start(0))?;
+    Ok(is_gzip)
+}
+
+/// The most frequent delimiter in the header line, comma if there is none
+fn detect_delimiter(header: &[u8]) -> u8 {
+    [b',', b';', b'\t']
+        .iter()
+        .copied()
+        .max_by_key(|d| (header.iter().filter(|c| *c == d).count(), *d == b','))
+        .unwrap_or(b',')
+}
+
+/// Names of the entries of a directory
+fn list_files(p: &Path) -> Result<Vec<String>, Error> {
+    Ok(std::fs::read_dir(p)?
+        .filter_map(|d| {
+            d.ok().and_then(|e| {
+                e.path()
+                    .strip_prefix(p)
+                    .ok()
+                    .and_then(|f| f.to_str().map(|s| s.to_owned()))
+            })
+        })
+        .collect())
+}
+
+type OpenError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Where the files of a GTFS are read from
+trait FileSource {
+    /// Opens the file of the table. None if the file does not exist
+    fn open(
+        &mut self,
+        table: TableName,
+        file_name: &str,
+    ) -> Option<Result<Box<dyn Read + '_>, OpenError>>;
+
+    /// Estimated number of rows of the file of the table, from its size
+    fn estimated_rows(&mut self, _table: TableName, _file_name: &str) -> Option<usize> {
+        None
+    }
+
+    /// The whole content of the file of the table, but not more than `limit` bytes
+    #[cfg(feature = "parallel")]
+    fn content(
+        &mut self,
+        table: TableName,
+        file_name: &str,
+        limit: u64,
+    ) -> Option<Result<std::borrow::Cow<'_, [u8]>, OpenError>> {
+        self.open(table, file_name).map(|reader| {
+            let mut content = Vec::new();
+            reader?.take(limit).read_to_end(&mut content)?;
+            Ok(std::borrow::Cow::Owned(content))
+        })
+    }
+}
+
+struct DirectorySource {
+    path: std::path::PathBuf,
+    /// Only listed when the file names are case insensitive, to find the actual names
+    files: Vec<String>,
+}
+
+impl FileSource for DirectorySource {
+    fn open(
+        &mut self,
+        _table: TableName,
+        file_name: &str,
+    ) -> Option<Result<Box<dyn Read + '_>, OpenError>> {
+        let path = self.path_of(file_name);
+        if path.exists() {
+            #[cfg(feature = "mmap")]
+            let reader =
+                map_file(&path).map(|m| Box::new(std::io::Cursor::new(m)) as Box<dyn Read>);
+            #[cfg(not(feature = "mmap"))]
+            let reader = File::open(path).map(|f| Box::new(f) as Box<dyn Read>);
+            Some(reader.map_err(|e| e.into()))
+        } else {
+            None
+        }
+    }
+
+    fn estimated_rows(&mut self, table: TableName, file_name: &str) -> Option<usize> {
+        let size = std::fs::metadata(self.path_of(file_name)).ok()?.len();
+        usize::try_from(size / typical_row_size(table)).ok()
+    }
+}
+
+impl DirectorySource {
+    fn path_of(&self, file_name: &str) -> std::path::PathBuf {
+        let file_name = self
+            .files
+            .iter()
+            .find(|f| f.eq_ignore_ascii_case(file_name))
+            .map_or(file_name, |f| f.as_str());
+        self.path.join(file_name)
+    }
+}
+
+struct ZipSource<T> {
+    archive: zip::ZipArchive<T>,
+    file_mapping: HashMap<TableName, usize>,
+    /// Path of the archive, if its files must be decompressed on another thread
+    background_path: Option<std::path::PathBuf>,
+}
+
+impl<T: std::io::Read + std::io::Seek> FileSource for ZipSource<T> {
+    fn open(
+        &mut self,
+        table: TableName,
+        _file_name: &str,
+    ) -> Option<Result<Box<dyn Read + '_>, OpenError>> {
+        if let Some(path) = &self.background_path {
+            let i = *self.file_mapping.get(&table)?;
+            return Some(Ok(Box::new(decompress_in_background(path.clone(), i))));
+        }
+        let archive = &mut self.archive;
+        self.file_mapping.get(&table).map(move |i| {
+            archive
+                .by_index(*i)
+                .map(|f| Box::new(f) as Box<dyn Read>)
+                .map_err(|e| e.into())
+        })
+    }
+
+    fn estimated_rows(&mut self, table: TableName, _file_name: &str) -> Option<usize> {
+        let i = *self.file_mapping.get(&table)?;
+        let file = self.archive.by_index_raw(i).ok()?;
+        // The uncompressed size is only a claim of the archive, we do not trust a ratio higher than csv files usually have
+        let size = file.size().min(file.compressed_size().saturating_mul(20));
+        usize::try_from(size / typical_row_size(table)).ok()
+    }
+}
+
+/// Size of the parts of a file sent by the decompression thread
+const DECOMPRESSED_BUFFER_SIZE: u64 = 256 * 1024;
+
+/// Number of parts decompressed in advance, waiting to be parsed
+const DECOMPRESSED_BUFFERS: usize = 4;
+
+/// Decompresses a file of a local zip archive on another thread, that opens the archive again
+fn decompress_in_background(path: std::path::PathBuf, index: usize) -> ChannelReader {
+    let (sender, receiver) = std::sync::mpsc::sync_channel(DECOMPRESSED_BUFFERS);
+    std::thread::spawn(move || {
+        let result = (|| -> std::io::Result<()> {
+            #[cfg(feature = "mmap")]
+            let reader = std::io::Cursor::new(map_file(&path)?);
+            #[cfg(not(feature = "mmap"))]
+            let reader = std::io::BufReader::new(File::open(&path)?);
+            let mut archive = zip::ZipArchive::new(reader)?;
+            let mut file = archive.by_index(index)?;
+            loop {
+                let mut buffer = Vec::new();
+                (&mut file)
+                    .take(DECOMPRESSED_BUFFER_SIZE)
+                    .read_to_end(&mut buffer)?;
+                // An empty buffer marks the end of the file. If it cannot be sent, the reader was dropped
+                let end = buffer.is_empty();
+                if sender.send(Ok(buffer)).is_err() || end {
+                    return Ok(());
+                }
+            }
+        })();
+        if let Err(e) = result {
+            let _ = sender.send(Err(e));
+        }
+    });
+    ChannelReader {
+        receiver,
+        buffer: Vec::new(),
+        position: 0,
+        finished: false,
+    }
+}
+
+/// Reads the parts of a file sent by [decompress_in_background]
+struct ChannelReader {
+    receiver: Receiver<std::io::Result<Vec<u8>>>,
+    buffer: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.buffer.len() && !self.finished {
+            match self.receiver.recv() {
+                Ok(Ok(buffer)) => {
+                    self.finished = buffer.is_empty();
+                    self.buffer = buffer;
+                    self.position = 0;
+                }
+                Ok(Err(e)) => return Err(e),
+                Err(_) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "the decompression thread stopped",
+                    ))
+                }
+            }
+        }
+        let n = buf.len().min(self.buffer.len() - self.position);
+        buf[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Maps a local file in memory, to let the operating system load its pages only when they are read
+#[cfg(feature = "mmap")]
+fn map_file(path: &Path) -> std::io::Result<memmap2::Mmap> {
+    let file = File::open(path)?;
+    // SAFETY: the file must not be modified while it is mapped, which is a documented limitation of the feature `mmap`
+    unsafe { memmap2::Mmap::map(&file) }
+}
+
+/// Hides some files of another source, as if they did not exist
+struct SkippingSource<'a> {
+    inner: &'a mut dyn FileSource,
+    skipped: &'a [TableName],
+}
+
+impl FileSource for SkippingSource<'_> {
+    fn open(
+        &mut self,
+        table: TableName,
+        file_name: &str,
+    ) -> Option<Result<Box<dyn Read + '_>, OpenError>> {
+        if self.skipped.contains(&table) {
+            return None;
+        }
+        self.inner.open(table, file_name)
+    }
+
+    fn estimated_rows(&mut self, table: TableName, file_name: &str) -> Option<usize> {
+        self.inner.estimated_rows(table, file_name)
+    }
+
+    #[cfg(feature = "parallel")]
+    fn content(
+        &mut self,
+        table: TableName,
+        file_name: &str,
+        limit: u64,
+    ) -> Option<Result<std::borrow::Cow<'_, [u8]>, OpenError>> {
+        if self.skipped.contains(&table) {
+            return None;
+        }
+        self.inner.content(table, file_name, limit)
+    }
+}
+
+/// Files already loaded in memory
+#[cfg(any(feature = "read-tar-gz", feature = "parallel"))]
+struct InMemorySource {
+    files: HashMap<TableName, Vec<u8>>,
+}
+
+#[cfg(any(feature = "read-tar-gz", feature = "parallel"))]
+impl FileSource for InMemorySource {
+    fn open(
+        &mut self,
+        table: TableName,
+        _file_name: &str,
+    ) -> Option<Result<Box<dyn Read + '_>, OpenError>> {
+        self.files
+            .get(&table)
+            .map(|content| Ok(Box::new(content.as_slice()) as Box<dyn Read>))
+    }
+
+    /// As the content is in memory, counting its lines is cheap compared to the parsing
+    fn estimated_rows(&mut self, table: TableName, _file_name: &str) -> Option<usize> {
+        self.files.get(&table).map(|content| count_lines(content))
+    }
+
+    #[cfg(feature = "parallel")]
+    fn content(
+        &mut self,
+        table: TableName,
+        _file_name: &str,
+        _limit: u64,
+    ) -> Option<Result<std::borrow::Cow<'_, [u8]>, OpenError>> {
+        self.files
+            .get(&table)
+            .map(|content| Ok(std::borrow::Cow::Borrowed(content.as_slice())))
+    }
+}
+
+/// The files can be read from several threads at the same time
+#[cfg(feature = "parallel")]
+impl FileSource for &InMemorySource {
+    fn open(
+        &mut self,
+        table: TableName,
+        _file_name: &str,
+    ) -> Option<Result<Box<dyn Read + '_>, OpenError>> {
+        self.files
+            .get(&table)
+            .map(|content| Ok(Box::new(content.as_slice()) as Box<dyn Read>))
+    }
+
+    fn estimated_rows(&mut self, table: TableName, _file_name: &str) -> Option<usize> {
+        self.files.get(&table).map(|content| count_lines(content))
+    }
+
+    #[cfg(feature = "parallel")]
+    fn content(
+        &mut self,
+        table: TableName,
+        _file_name: &str,
+        _limit: u64,
+    ) -> Option<Result<std::borrow::Cow<'_, [u8]>, OpenError>> {
+        self.files
+            .get(&table)
+            .map(|content| Ok(std::borrow::Cow::Borrowed(content.as_slice())))
+    }
 }
@@ -0,0 +1,381 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::{objects::*, Error, Gtfs, IdMap};
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Allows to parameterize how a [Gtfs] is written back as GTFS files
+///
+/// ```
+///let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+///let output = gtfs_structures::GtfsWriter::default()
+///    .update_feed_info(true) // Bumps the feed_version and sets the feed dates from the calendars
+///    .write_to_writer(&gtfs, std::io::Cursor::new(Vec::new()))?;
+///let written = gtfs_structures::Gtfs::from_reader(output)?;
+///assert_eq!(Some("0.4".to_string()), written.feed_info[0].version);
+/// # Ok::<(), gtfs_structures::error::Error>(())
+///```
+#[derive(Derivative)]
+#[derivative(Default)]
+pub struct GtfsWriter {
+    /// Should the [FeedInfo] be updated to reflect the written data
+    #[derivative(Default(value = "false"))]
+    pub update_feed_info: bool,
+    /// Only those tables are written. All the tables are written if None
+    pub tables: Option<Vec<TableName>>,
+    /// Should the references between objects be checked before writing
+    #[derivative(Default(value = "false"))]
+    pub validate: bool,
+}
+
+impl GtfsWriter {
+    /// Configures the writer to update the feed information (default: false)
+    ///
+    /// When true, the `feed_version` of every [FeedInfo] is bumped (the trailing number is incremented)
+    /// and `feed_start_date`/`feed_end_date` are set from [Gtfs::service_span].
+    /// The [Gtfs] itself is not modified, only the written files
+    /// Returns Self and can be chained
+    pub fn update_feed_info(mut self, update_feed_info: bool) -> Self {
+        self.update_feed_info = update_feed_info;
+        self
+    }
+
+    /// Configures the writer to only write some of the tables (default: all tables)
+    ///
+    /// This can be useful to export only a part of the data, for instance the stops and pathways of a station.
+    /// The translations are not kept in a [Gtfs]: when writing all the tables, translations.txt is not written,
+    /// and asking for [TableName::Translations] makes the writing fail with [Error::UnwritableTable]
+    /// Returns Self and can be chained
+    pub fn tables(mut self, tables: &[TableName]) -> Self {
+        self.tables = Some(tables.to_vec());
+        self
+    }
+
+    /// Configures the writer to check the references before writing anything (default: false)
+    ///
+    /// When true, nothing is written if [Gtfs::broken_references] finds a problem
+    /// and an [Error::BrokenReferences] listing all of them is returned
+    /// Returns Self and can be chained
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Writes the GTFS as csv files in a local directory. The directory is created if needed
+    pub fn write_to_directory<P>(&self, gtfs: &Gtfs, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.check(gtfs)?;
+        let path = path.as_ref();
+        std::fs::create_dir_all(path)?;
+        self.write_files(
+            gtfs,
+            &mut DirectorySink {
+                path: path.to_path_buf(),
+            },
+        )
+    }
+
+    /// Writes the GTFS as a zip archive in any object implementing [std::io::Write] and [std::io::Seek]
+    ///
+    /// Returns the inner writer once the archive is finished
+    pub fn write_to_writer<W: Write + Seek>(&self, gtfs: &Gtfs, writer: W) -> Result<W, Error> {
+        self.check(gtfs)?;
+        let mut sink = ZipSink {
+            zip: zip::ZipWriter::new(writer),
+        };
+        self.write_files(gtfs, &mut sink)?;
+        Ok(sink.zip.finish()?)
+    }
+
+    fn check(&self, gtfs: &Gtfs) -> Result<(), Error> {
+        if let Some(tables) = &self.tables {
+            if tables.contains(&TableName::Translations) {
+                return Err(Error::UnwritableTable(TableName::Translations));
+            }
+        }
+        if self.validate {
+            let broken_references = gtfs.broken_references();
+            if !broken_references.is_empty() {
+                return Err(Error::BrokenReferences(broken_references));
+            }
+        }
+        Ok(())
+    }
+
+    fn write_files(&self, gtfs: &Gtfs, sink: &mut dyn FileSink) -> Result<(), Error> {
+        for table in TableName::ALL {
+            if self.tables.as_ref().is_none_or(|t| t.contains(&table)) {
+                self.write_table(gtfs, table, sink)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_table(
+        &self,
+        gtfs: &Gtfs,
+        table: TableName,
+        sink: &mut dyn FileSink,
+    ) -> Result<(), Error> {
+        match table {
+            TableName::Agency => write_objs(sink, table, &gtfs.agencies),
+            TableName::Stops => write_objs(sink, table, sorted(&gtfs.stops).map(|s| s.as_ref())),
+            TableName::Routes => write_objs(sink, table, sorted(&gtfs.routes)),
+            TableName::Trips => write_objs(sink, table, sorted(&gtfs.trips).map(raw_trip)),
+            TableName::StopTimes => write_objs(
+                sink,
+                table,
+                sorted(&gtfs.trips).flat_map(|trip| {
+                    trip.stop_times
+                        .iter()
+                        .map(move |stop_time| raw_stop_time(&trip.id, stop_time))
+                }),
+            ),
+            TableName::Calendar => write_optional_objs(sink, table, sorted(&gtfs.calendar)),
+            TableName::CalendarDates => {
+                write_optional_objs(sink, table, sorted(&gtfs.calendar_dates).flatten())
+            }
+            TableName::Shapes if !gtfs.lazy_shapes.is_empty() => {
+                let mut ids: Vec<&GtfsId> = gtfs.lazy_shapes.ids().collect();
+                ids.sort();
+                let shapes = ids
+                    .into_iter()
+                    .filter_map(|id| gtfs.lazy_shapes.get(id))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                write_optional_objs(sink, table, shapes.into_iter().flatten())
+            }
+            TableName::Shapes => write_optional_objs(sink, table, sorted(&gtfs.shapes).flatten()),
+            TableName::FareAttributes => {
+                write_optional_objs(sink, table, sorted(&gtfs.fare_attributes))
+            }
+            TableName::FareRules => {
+                write_optional_objs(sink, table, sorted(&gtfs.fare_rules).flatten())
+            }
+            TableName::Frequencies => write_optional_objs(
+                sink,
+                table,
+                sorted(&gtfs.trips).flat_map(|trip| {
+                    trip.frequencies
+                        .iter()
+                        .map(move |frequency| raw_frequency(&trip.id, frequency))
+                }),
+            ),
+            TableName::Transfers => write_optional_objs(
+                sink,
+                table,
+                sorted(&gtfs.stops).flat_map(|stop| {
+                    stop.transfers
+                        .iter()
+                        .map(move |transfer| raw_transfer(&stop.id, transfer))
+                }),
+            ),
+            TableName::Pathways => write_optional_objs(
+                sink,
+                table,
+                sorted(&gtfs.stops).flat_map(|stop| {
+                    stop.pathways
+                        .iter()
+                        .map(move |pathway| raw_pathway(&stop.id, pathway))
+                }),
+            ),
+            TableName::FeedInfo if self.update_feed_info => {
+                let span = gtfs.service_span();
+                write_optional_objs(
+                    sink,
+                    table,
+                    gtfs.feed_info.iter().map(|f| updated_feed_info(f, span)),
+                )
+            }
+            TableName::FeedInfo => write_optional_objs(sink, table, &gtfs.feed_info),
+            // The translations are not kept in a Gtfs, see [GtfsWriter::tables]
+            TableName::Translations => Ok(()),
+        }
+    }
+}
+
+/// Destination of the files written by a [GtfsWriter]
+trait FileSink {
+    fn create(&mut self, table: TableName) -> Result<Box<dyn Write + '_>, Error>;
+}
+
+struct DirectorySink {
+    path: PathBuf,
+}
+
+impl FileSink for DirectorySink {
+    fn create(&mut self, table: TableName) -> Result<Box<dyn Write + '_>, Error> {
+        let file_name = table.file_name();
+        let file = File::create(self.path.join(file_name)).map_err(|e| Error::NamedFileWrite {
+            file_name: file_name.to_owned(),
+            source: Box::new(e),
+        })?;
+        Ok(Box::new(BufWriter::new(file)))
+    }
+}
+
+struct ZipSink<W: Write + Seek> {
+    zip: zip::ZipWriter<W>,
+}
+
+impl<W: Write + Seek> FileSink for ZipSink<W> {
+    fn create(&mut self, table: TableName) -> Result<Box<dyn Write + '_>, Error> {
+        // The size is not known in advance, and without zip64 a file cannot exceed 4 GB.
+        // Only the stop times and shapes of very large datasets can reach that size
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .large_file(matches!(table, TableName::StopTimes | TableName::Shapes));
+        self.zip.start_file(table.file_name(), options)?;
+        Ok(Box::new(&mut self.zip))
+    }
+}
+
+fn write_objs<T, I>(sink: &mut dyn FileSink, table: TableName, objs: I) -> Result<(), Error>
+where
+    T: Serialize,
+    I: IntoIterator<Item = T>,
+{
+    let file_name = table.file_name();
+    let mk_err = |e: csv::Error| Error::NamedFileWrite {
+        file_name: file_name.to_owned(),
+        source: Box::new(e),
+    };
+    let mut writer = csv::Writer::from_writer(sink.create(table)?);
+    for obj in objs {
+        writer.serialize(obj).map_err(mk_err)?;
+    }
+    writer.flush().map_err(|e| mk_err(e.into()))
+}
+
+// Optional files are only written if there is something to write
+fn write_optional_objs<T, I>(
+    sink: &mut dyn FileSink,
+    table: TableName,
+    objs: I,
+) -> Result<(), Error>
+where
+    T: Serialize,
+    I: IntoIterator<Item = T>,
+{
+    let mut objs = objs.into_iter().peekable();
+    if objs.peek().is_some() {
+        write_objs(sink, table, objs)
+    } else {
+        Ok(())
+    }
+}
+
+// The objects are sorted by id to have a deterministic output
+fn sorted<T>(map: &IdMap<T>) -> impl Iterator<Item = &T> {
+    let mut objs: Vec<(&GtfsId, &T)> = map.iter().collect();
+    if !map.is_sorted_by_id() {
+        objs.sort_by(|a, b| a.0.cmp(b.0));
+    }
+    objs.into_iter().map(|(_, obj)| obj)
+}
+
+pub(crate) fn raw_trip(trip: &Trip) -> RawTrip {
+    RawTrip {
+        id: trip.id.clone(),
+        service_id: trip.service_id.clone(),
+        route_id: trip.route_id.clone(),
+        shape_id: trip.shape_id.clone(),
+        trip_headsign: trip.trip_headsign.clone(),
+        trip_short_name: trip.trip_short_name.clone(),
+        direction_id: trip.direction_id,
+        block_id: trip.block_id.clone(),
+        wheelchair_accessible: trip.wheelchair_accessible,
+        bikes_allowed: trip.bikes_allowed,
+    }
+}
+
+fn raw_stop_time(trip_id: &str, stop_time: &StopTime) -> RawStopTime {
+    RawStopTime {
+        trip_id: trip_id.into(),
+        arrival_time: stop_time.arrival_time,
+        departure_time: stop_time.departure_time,
+        stop_id: stop_time.stop.id.clone(),
+        stop_sequence: stop_time.stop_sequence,
+        stop_headsign: stop_time.stop_headsign.clone(),
+        pickup_type: stop_time.pickup_type,
+        drop_off_type: stop_time.drop_off_type,
+        continuous_pickup: stop_time.continuous_pickup,
+        continuous_drop_off: stop_time.continuous_drop_off,
+        shape_dist_traveled: stop_time.shape_dist_traveled,
+        timepoint: stop_time.timepoint,
+    }
+}
+
+fn raw_frequency(trip_id: &str, frequency: &Frequency) -> RawFrequency {
+    RawFrequency {
+        trip_id: trip_id.into(),
+        start_time: frequency.start_time,
+        end_time: frequency.end_time,
+        headway_secs: frequency.headway_secs,
+        exact_times: frequency.exact_times,
+    }
+}
+
+fn raw_transfer(from_stop_id: &str, transfer: &StopTransfer) -> RawTransfer {
+    RawTransfer {
+        from_stop_id: from_stop_id.into(),
+        to_stop_id: transfer.to_stop_id.clone(),
+        transfer_type: transfer.transfer_type,
+        min_transfer_time: transfer.min_transfer_time,
+    }
+}
+
+fn raw_pathway(from_stop_id: &str, pathway: &Pathway) -> RawPathway {
+    RawPathway {
+        id: pathway.id.clone(),
+        from_stop_id: from_stop_id.into(),
+        to_stop_id: pathway.to_stop_id.clone(),
+        mode: pathway.mode,
+        is_bidirectional: pathway.is_bidirectional,
+        length: pathway.length,
+        traversal_time: pathway.traversal_time,
+        stair_count: pathway.stair_count,
+        max_slope: pathway.max_slope,
+        min_width: pathway.min_width,
+        signposted_as: pathway.signposted_as.clone(),
+        reversed_signposted_as: pathway.reversed_signposted_as.clone(),
+    }
+}
+
+fn updated_feed_info(feed_info: &FeedInfo, span: Option<(NaiveDate, NaiveDate)>) -> FeedInfo {
+    let mut feed_info = feed_info.clone();
+    feed_info.version = Some(bump_version(feed_info.version.as_deref()));
+    if let Some((start_date, end_date)) = span {
+        feed_info.start_date = Some(start_date);
+        feed_info.end_date = Some(end_date);
+    }
+    feed_info
+}
+
+/// Increments the trailing number of a version (`"0.3"` becomes `"0.4"`, `"v9"` becomes `"v10"`)
+///
+/// If there is no trailing number, `".1"` is appended. A missing version becomes `"1"`
+fn bump_version(version: Option<&str>) -> String {
+    let version = match version {
+        Some(v) if !v.is_empty() => v,
+        _ => return "1".to_owned(),
+    };
+    let prefix = version.trim_end_matches(|c: char| c.is_ascii_digit());
+    let digits = &version[prefix.len()..];
+    match digits.parse::<u64>() {
+        Ok(n) => format!("{prefix}{:0width$}", n + 1, width = digits.len()),
+        Err(_) => format!("{version}.1"),
+    }
+}
+
+#[test]
+fn test_bump_version() {
+    assert_eq!("1", bump_version(None));
+    assert_eq!("0.4", bump_version(Some("0.3")));
+    assert_eq!("v10", bump_version(Some("v9")));
+    assert_eq!("2024-010", bump_version(Some("2024-009")));
+    assert_eq!("beta.1", bump_version(Some("beta")));
+}
@@ -0,0 +1,115 @@
+use crate::{Error, Gtfs};
+
+/// Rules applied in order to clean a headsign, see [Gtfs::clean_headsign]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadsignRules {
+    /// Uses the name of the last stop of the trip when it has no headsign
+    pub fallback_to_last_stop: bool,
+    /// Removes the `route_short_name` at the start of the headsign, like `12 - ` in `12 - Airport`
+    pub strip_route_name: bool,
+    /// Trims the whitespace, and replaces the consecutive whitespace characters by a single space
+    pub collapse_whitespace: bool,
+    /// Rewrites the headsigns without lowercase letters in title case: `GARE DE L'EST` becomes `Gare De L'Est`
+    pub fix_capitals: bool,
+}
+
+impl Default for HeadsignRules {
+    fn default() -> Self {
+        Self {
+            fallback_to_last_stop: true,
+            strip_route_name: true,
+            collapse_whitespace: true,
+            fix_capitals: true,
+        }
+    }
+}
+
+impl HeadsignRules {
+    /// Cleans a headsign of a route with the rules, except [HeadsignRules::fallback_to_last_stop]
+    ///
+    /// ```
+    /// use gtfs_structures::HeadsignRules;
+    /// let rules = HeadsignRules::default();
+    /// assert_eq!("Gare De L'Est", rules.clean("12 -  GARE DE  L'EST ", Some("12")));
+    /// assert_eq!("120 Avenue", rules.clean("120 Avenue", Some("12")));
+    /// ```
+    pub fn clean(&self, headsign: &str, route_short_name: Option<&str>) -> String {
+        let mut headsign = headsign.trim();
+        if self.strip_route_name {
+            if let Some(rest) = route_short_name
+                .filter(|name| !name.is_empty())
+                .and_then(|name| headsign.strip_prefix(name))
+                .filter(|rest| rest.starts_with(|c: char| !c.is_alphanumeric()))
+            {
+                let rest = rest.trim_start_matches(|c: char| {
+                    c.is_whitespace() || matches!(c, '-' | '–' | ':' | '>' | '→')
+                });
+                if !rest.is_empty() {
+                    headsign = rest;
+                }
+            }
+        }
+        let mut headsign = if self.collapse_whitespace {
+            headsign.split_whitespace().collect::<Vec<_>>().join(" ")
+        } else {
+            headsign.to_owned()
+        };
+        if self.fix_capitals && !headsign.chars().any(char::is_lowercase) {
+            headsign = title_case(&headsign);
+        }
+        headsign
+    }
+}
+
+/// Capitalizes the first letter of each word, and writes the other letters in lowercase
+fn title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut word_start = true;
+    for c in text.chars() {
+        if word_start {
+            result.extend(c.to_uppercase());
+        } else {
+            result.extend(c.to_lowercase());
+        }
+        word_start = !c.is_alphanumeric();
+    }
+    result
+}
+
+impl Gtfs {
+    /// Gives the cleaned headsign of a trip, see [HeadsignRules]. None if the trip has no headsign left
+    ///
+    /// ```
+    /// use gtfs_structures::HeadsignRules;
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// // Without headsign, tripA2 is going to its last stop platform1
+    /// gtfs.trips.get_mut("tripA2").unwrap().trip_headsign = None;
+    /// let headsign = gtfs.clean_headsign("tripA2", &HeadsignRules::default())?;
+    /// assert_eq!(Some("Platform 1".to_owned()), headsign);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn clean_headsign(
+        &self,
+        trip_id: &str,
+        rules: &HeadsignRules,
+    ) -> Result<Option<String>, Error> {
+        let trip = self.get_trip(trip_id)?;
+        let headsign = trip
+            .trip_headsign
+            .as_deref()
+            .filter(|headsign| !headsign.trim().is_empty())
+            .or_else(|| {
+                trip.stop_times
+                    .last()
+                    .filter(|_| rules.fallback_to_last_stop)
+                    .and_then(|st| st.stop.name.as_deref())
+            });
+        let route_short_name = self
+            .routes
+            .get(&trip.route_id)
+            .and_then(|route| route.short_name.as_deref());
+        Ok(headsign
+            .map(|headsign| rules.clean(headsign, route_short_name))
+            .filter(|headsign| !headsign.is_empty()))
+    }
+}
@@ -0,0 +1,228 @@
+use crate::objects::GtfsId;
+use itertools::Either;
+use std::collections::{hash_map, HashMap};
+use std::hash::BuildHasher;
+use std::iter::FromIterator;
+
+/// Hasher of the maps by identifier, [ahash](https://docs.rs/ahash) with the feature `ahash-maps`
+#[cfg(feature = "ahash-maps")]
+pub type IdHasher = ahash::RandomState;
+/// Hasher of the maps by identifier, [fxhash](https://docs.rs/fxhash) with the feature `fxhash-maps`
+///
+/// It is faster but not resistant to collision attacks, only use it with trusted data
+#[cfg(all(feature = "fxhash-maps", not(feature = "ahash-maps")))]
+pub type IdHasher = fxhash::FxBuildHasher;
+/// Hasher of the maps by identifier, the one of the standard library unless the feature `ahash-maps` or `fxhash-maps` is enabled
+#[cfg(not(any(feature = "ahash-maps", feature = "fxhash-maps")))]
+pub type IdHasher = std::collections::hash_map::RandomState;
+
+/// Map by identifier using [IdHasher]
+pub(crate) type IdHashMap<T> = HashMap<GtfsId, T, IdHasher>;
+
+/// Objects by their identifier, used for the tables of [crate::Gtfs]
+///
+/// The objects are in a [HashMap], or in a vector sorted by identifier once [IdMap::sort_by_id] is called
+/// (see [crate::GtfsReader::sorted_storage]). The sorted vector is smaller and more cache-friendly,
+/// the objects are then found by binary search, but inserting or removing an object is slower
+///
+/// ```
+/// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+/// gtfs.sort_by_id();
+/// assert_eq!("Stop Area", gtfs.stops["stop1"].name.as_deref().unwrap());
+/// # Ok::<(), gtfs_structures::error::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct IdMap<T> {
+    storage: Storage<T>,
+}
+
+#[derive(Debug, Clone)]
+enum Storage<T> {
+    Hash(IdHashMap<T>),
+    Sorted(Vec<(GtfsId, T)>),
+}
+
+impl<T> Default for IdMap<T> {
+    fn default() -> Self {
+        IdMap {
+            storage: Storage::Hash(IdHashMap::default()),
+        }
+    }
+}
+
+impl<T> IdMap<T> {
+    /// Creates an empty map, using a [HashMap] with [IdHasher]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the objects in a vector sorted by identifier
+    pub fn sort_by_id(&mut self) {
+        if let Storage::Hash(map) = &mut self.storage {
+            let mut objs: Vec<_> = map.drain().collect();
+            objs.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            self.storage = Storage::Sorted(objs);
+        }
+    }
+
+    /// True if the objects are stored in a vector sorted by identifier
+    pub fn is_sorted_by_id(&self) -> bool {
+        matches!(self.storage, Storage::Sorted(_))
+    }
+
+    /// Gets an object by its identifier
+    pub fn get(&self, id: &str) -> Option<&T> {
+        match &self.storage {
+            Storage::Hash(map) => map.get(id),
+            Storage::Sorted(objs) => position(objs, id).ok().map(|i| &objs[i].1),
+        }
+    }
+
+    /// Gets a mutable reference to an object by its identifier
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut T> {
+        match &mut self.storage {
+            Storage::Hash(map) => map.get_mut(id),
+            Storage::Sorted(objs) => position(objs, id).ok().map(move |i| &mut objs[i].1),
+        }
+    }
+
+    /// True if there is an object with this identifier
+    pub fn contains_key(&self, id: &str) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Inserts an object, and returns the object that had the same identifier
+    pub fn insert(&mut self, id: GtfsId, obj: T) -> Option<T> {
+        match &mut self.storage {
+            Storage::Hash(map) => map.insert(id, obj),
+            Storage::Sorted(objs) => match position(objs, &id) {
+                Ok(i) => Some(std::mem::replace(&mut objs[i].1, obj)),
+                Err(i) => {
+                    objs.insert(i, (id, obj));
+                    None
+                }
+            },
+        }
+    }
+
+    /// Removes an object by its identifier, and returns it
+    pub fn remove(&mut self, id: &str) -> Option<T> {
+        match &mut self.storage {
+            Storage::Hash(map) => map.remove(id),
+            Storage::Sorted(objs) => position(objs, id).ok().map(|i| objs.remove(i).1),
+        }
+    }
+
+    /// Number of objects
+    pub fn len(&self) -> usize {
+        match &self.storage {
+            Storage::Hash(map) => map.len(),
+            Storage::Sorted(objs) => objs.len(),
+        }
+    }
+
+    /// True if there is no object
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All the identifiers and objects, sorted by identifier only with the sorted storage
+    pub fn iter(&self) -> impl Iterator<Item = (&GtfsId, &T)> {
+        self.into_iter()
+    }
+
+    /// All the identifiers and mutable references to the objects
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&GtfsId, &mut T)> {
+        match &mut self.storage {
+            Storage::Hash(map) => Either::Left(map.iter_mut()),
+            Storage::Sorted(objs) => Either::Right(objs.iter_mut().map(|(id, obj)| (&*id, obj))),
+        }
+    }
+
+    /// All the identifiers
+    pub fn keys(&self) -> impl Iterator<Item = &GtfsId> {
+        self.iter().map(|(id, _)| id)
+    }
+
+    /// All the objects
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter().map(|(_, obj)| obj)
+    }
+
+    /// Mutable references to all the objects
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.iter_mut().map(|(_, obj)| obj)
+    }
+}
+
+fn position<T>(objs: &[(GtfsId, T)], id: &str) -> Result<usize, usize> {
+    objs.binary_search_by(|(obj_id, _)| (**obj_id).cmp(id))
+}
+
+impl<T> std::ops::Index<&str> for IdMap<T> {
+    type Output = T;
+
+    /// Panics if there is no object with this identifier
+    fn index(&self, id: &str) -> &T {
+        self.get(id).expect("no object with this identifier")
+    }
+}
+
+/// Two maps are equal if they have the same objects, whatever their storage
+impl<T: PartialEq> PartialEq for IdMap<T> {
+    fn eq(&self, other: &IdMap<T>) -> bool {
+        self.len() == other.len() && self.iter().all(|(id, obj)| other.get(id) == Some(obj))
+    }
+}
+
+impl<T: PartialEq, S: BuildHasher> PartialEq<HashMap<GtfsId, T, S>> for IdMap<T> {
+    fn eq(&self, other: &HashMap<GtfsId, T, S>) -> bool {
+        self.len() == other.len() && self.iter().all(|(id, obj)| other.get(id) == Some(obj))
+    }
+}
+
+impl<T> FromIterator<(GtfsId, T)> for IdMap<T> {
+    fn from_iter<I: IntoIterator<Item = (GtfsId, T)>>(iter: I) -> Self {
+        IdMap {
+            storage: Storage::Hash(iter.into_iter().collect()),
+        }
+    }
+}
+
+impl<T> From<IdHashMap<T>> for IdMap<T> {
+    fn from(map: IdHashMap<T>) -> Self {
+        IdMap {
+            storage: Storage::Hash(map),
+        }
+    }
+}
+
+impl<T> IntoIterator for IdMap<T> {
+    type Item = (GtfsId, T);
+    type IntoIter = Either<hash_map::IntoIter<GtfsId, T>, std::vec::IntoIter<(GtfsId, T)>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.storage {
+            Storage::Hash(map) => Either::Left(map.into_iter()),
+            Storage::Sorted(objs) => Either::Right(objs.into_iter()),
+        }
+    }
+}
+
+type SortedIter<'a, T> =
+    std::iter::Map<std::slice::Iter<'a, (GtfsId, T)>, fn(&'a (GtfsId, T)) -> (&'a GtfsId, &'a T)>;
+
+impl<'a, T> IntoIterator for &'a IdMap<T> {
+    type Item = (&'a GtfsId, &'a T);
+    type IntoIter = Either<hash_map::Iter<'a, GtfsId, T>, SortedIter<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match &self.storage {
+            Storage::Hash(map) => Either::Left(map.iter()),
+            Storage::Sorted(objs) => {
+                let split: fn(&'a (GtfsId, T)) -> (&'a GtfsId, &'a T) = |(id, obj)| (id, obj);
+                Either::Right(objs.iter().map(split))
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::objects::*;
+
+impl Trip {
+    /// Fills the missing arrival and departure times of the stop times, see [Trip::interpolated_stop_times]
+    ///
+    /// The stop times must be sorted by `stop_sequence`, as they are once the GTFS is read
+    pub fn interpolate_stop_times(&mut self) {
+        interpolate(&mut self.stop_times);
+    }
+
+    /// Copies the stop times with their missing arrival and departure times filled
+    ///
+    /// The times between two stops with times are interpolated linearly over the `shape_dist_traveled`
+    /// when all the stop times between them have one, and over the `stop_sequence` otherwise.
+    /// A stop time with only one of the times gets the other one. The interpolated stop times are marked as [TimepointType::Approximate].
+    /// The stop times before the first time or after the last one are left without times
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let trip = gtfs.trips.get_mut("trip1").unwrap();
+    /// trip.stop_times[1].arrival_time = None;
+    /// trip.stop_times[1].departure_time = None;
+    /// assert_eq!(Some(15 * 3600), trip.interpolated_stop_times()[1].arrival_time);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn interpolated_stop_times(&self) -> Vec<StopTime> {
+        let mut stop_times = self.stop_times.clone();
+        interpolate(&mut stop_times);
+        stop_times
+    }
+}
+
+fn interpolate(stop_times: &mut [StopTime]) {
+    let known: Vec<usize> = (0..stop_times.len())
+        .filter(|&i| {
+            stop_times[i]
+                .arrival_time
+                .or(stop_times[i].departure_time)
+                .is_some()
+        })
+        .collect();
+    for st in stop_times.iter_mut() {
+        st.arrival_time = st.arrival_time.or(st.departure_time);
+        st.departure_time = st.departure_time.or(st.arrival_time);
+    }
+    for pair in known.windows(2) {
+        let (first, last) = (pair[0], pair[1]);
+        let (start, end) = match (
+            stop_times[first].departure_time,
+            stop_times[last].arrival_time,
+        ) {
+            (Some(start), Some(end)) if last > first + 1 => (f64::from(start), f64::from(end)),
+            _ => continue,
+        };
+        let by_distance = stop_times[first..=last]
+            .iter()
+            .all(|st| st.shape_dist_traveled.is_some());
+        let position = |st: &StopTime| match st.shape_dist_traveled {
+            Some(distance) if by_distance => f64::from(distance),
+            _ => f64::from(st.stop_sequence),
+        };
+        let origin = position(&stop_times[first]);
+        let span = position(&stop_times[last]) - origin;
+        for st in &mut stop_times[first + 1..last] {
+            let ratio = if span > 0.0 {
+                ((position(st) - origin) / span).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let time = (start + ratio * (end - start)).round() as u32;
+            st.arrival_time = Some(time);
+            st.departure_time = Some(time);
+            st.timepoint = TimepointType::Approximate;
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use crate::error::LanguageTagError;
+use crate::RawGtfs;
+
+/// Common values that are not BCP 47 language tags, with the tag that should be used
+const NON_STANDARD_LANGUAGES: &[(&str, &str)] = &[
+    // Country codes used as language codes
+    ("cn", "zh"),
+    ("cz", "cs"),
+    ("dk", "da"),
+    ("gr", "el"),
+    ("jp", "ja"),
+    ("ua", "uk"),
+    // ISO 639-2 codes that have a shorter ISO 639-1 code
+    ("deu", "de"),
+    ("dut", "nl"),
+    ("eng", "en"),
+    ("fra", "fr"),
+    ("fre", "fr"),
+    ("ger", "de"),
+    ("ita", "it"),
+    ("nld", "nl"),
+    ("por", "pt"),
+    ("spa", "es"),
+    // English names
+    ("dutch", "nl"),
+    ("english", "en"),
+    ("french", "fr"),
+    ("german", "de"),
+    ("greek", "el"),
+    ("italian", "it"),
+    ("portuguese", "pt"),
+    ("spanish", "es"),
+];
+
+/// Language of a translation that is not a valid language tag, see [RawGtfs::invalid_languages]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidLanguage<'a> {
+    /// Index of the row in translations.txt, the first row after the headers being 0
+    pub row: usize,
+    /// The invalid language
+    pub value: &'a str,
+    /// Why it is invalid
+    pub error: LanguageTagError,
+}
+
+/// Checks that the language is a well-formed IETF BCP 47 language tag, like `fr` or `zh-Hant-TW`
+///
+/// Only the syntax is checked, the subtags are not compared to the IANA registry.
+/// The common values of [normalize_language] are rejected, even when well-formed (like `gr`)
+///
+/// ```
+/// use gtfs_structures::{check_language_tag, error::LanguageTagError};
+/// assert!(check_language_tag("pt-BR").is_ok());
+/// assert_eq!(
+///     Err(LanguageTagError::InvalidSubtag("português".to_owned())),
+///     check_language_tag("português")
+/// );
+/// assert_eq!(Err(LanguageTagError::NonStandard("el".to_owned())), check_language_tag("gr"));
+/// ```
+pub fn check_language_tag(value: &str) -> Result<(), LanguageTagError> {
+    if value.is_empty() {
+        return Err(LanguageTagError::Empty);
+    }
+    if let Some(replacement) = normalize_language(value) {
+        return Err(LanguageTagError::NonStandard(replacement));
+    }
+    let invalid = |subtag: &str| Err(LanguageTagError::InvalidSubtag(subtag.to_owned()));
+    let alpha = |s: &str, min, max| {
+        (min..=max).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_alphabetic())
+    };
+    let alphanum = |s: &str, min, max| {
+        (min..=max).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_alphanumeric())
+    };
+
+    let mut subtags = value.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    if primary.eq_ignore_ascii_case("x") || primary.eq_ignore_ascii_case("i") {
+        // Private use and grandfathered tags
+        let mut empty = true;
+        for subtag in subtags {
+            if !alphanum(subtag, 1, 8) {
+                return invalid(subtag);
+            }
+            empty = false;
+        }
+        return if empty { invalid(primary) } else { Ok(()) };
+    }
+    if !alpha(primary, 2, 8) || primary.len() == 4 {
+        return invalid(primary);
+    }
+
+    // Position of the next subtag: 0 extlang, 1 script, 2 region, 3 variants, 4 extensions
+    let mut position = if primary.len() <= 3 { 0 } else { 1 };
+    let mut extlangs = 0;
+    let mut singleton: Option<&str> = None;
+    let mut private_use = false;
+    for subtag in subtags {
+        if private_use {
+            if !alphanum(subtag, 1, 8) {
+                return invalid(subtag);
+            }
+            singleton = None;
+            continue;
+        }
+        if subtag.len() == 1 {
+            if singleton.is_some() || !alphanum(subtag, 1, 1) {
+                return invalid(subtag);
+            }
+            private_use = subtag.eq_ignore_ascii_case("x");
+            singleton = Some(subtag);
+            position = 4;
+            continue;
+        }
+        if position == 4 {
+            if singleton.is_none() || !alphanum(subtag, 2, 8) {
+                return invalid(subtag);
+            }
+            singleton = None;
+            continue;
+        }
+        let is_region = alpha(subtag, 2, 2)
+            || (subtag.len() == 3 && subtag.bytes().all(|b| b.is_ascii_digit()));
+        let is_variant = alphanum(subtag, 5, 8)
+            || (subtag.len() == 4
+                && subtag.as_bytes()[0].is_ascii_digit()
+                && alphanum(subtag, 4, 4));
+        if position == 0 && extlangs < 3 && alpha(subtag, 3, 3) {
+            extlangs += 1;
+        } else if position <= 1 && alpha(subtag, 4, 4) {
+            position = 2;
+        } else if (position <= 2 && is_region) || (position <= 3 && is_variant) {
+            position = 3;
+        } else {
+            return invalid(subtag);
+        }
+    }
+    match singleton {
+        Some(singleton) => invalid(singleton),
+        None => Ok(()),
+    }
+}
+
+/// Returns the language tag to use instead of a common non-standard value, like `el` for `gr` or `en` for `english`
+///
+/// The comparison ignores the case. A `_` separator, like in `en_US`, is replaced by `-`
+///
+/// ```
+/// use gtfs_structures::normalize_language;
+/// assert_eq!(Some("el".to_owned()), normalize_language("GR"));
+/// assert_eq!(Some("en-US".to_owned()), normalize_language("en_US"));
+/// assert_eq!(None, normalize_language("fr"));
+/// ```
+pub fn normalize_language(value: &str) -> Option<String> {
+    let lowercase = value.trim().to_ascii_lowercase();
+    if let Some((_, tag)) = NON_STANDARD_LANGUAGES
+        .iter()
+        .find(|(non_standard, _)| *non_standard == lowercase)
+    {
+        return Some((*tag).to_owned());
+    }
+    if value.contains('_') {
+        let replaced = value.trim().replace('_', "-");
+        if check_language_tag(&replaced).is_ok() {
+            return Some(replaced);
+        }
+    }
+    None
+}
+
+impl RawGtfs {
+    /// Lists the translations whose language is not a valid IETF BCP 47 language tag (see [check_language_tag])
+    ///
+    /// With `accept_non_standard`, the common values that [normalize_language] can replace are not listed
+    ///
+    /// ```
+    /// use gtfs_structures::error::LanguageTagError;
+    /// let mut gtfs = gtfs_structures::RawGtfs::from_path("fixtures/basic")?;
+    /// assert!(gtfs.invalid_languages(false).is_empty());
+    /// if let Some(Ok(translations)) = &mut gtfs.translations {
+    ///     translations[0].language = "Dutch".to_owned();
+    /// }
+    /// let invalid = gtfs.invalid_languages(false);
+    /// assert_eq!(0, invalid[0].row);
+    /// assert_eq!(LanguageTagError::NonStandard("nl".to_owned()), invalid[0].error);
+    /// assert!(gtfs.invalid_languages(true).is_empty());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn invalid_languages(&self, accept_non_standard: bool) -> Vec<InvalidLanguage<'_>> {
+        let translations = match &self.translations {
+            Some(Ok(translations)) => translations,
+            _ => return Vec::new(),
+        };
+        translations
+            .iter()
+            .enumerate()
+            .filter_map(|(row, translation)| {
+                let error = check_language_tag(&translation.language).err()?;
+                if accept_non_standard && matches!(error, LanguageTagError::NonStandard(_)) {
+                    return None;
+                }
+                Some(InvalidLanguage {
+                    row,
+                    value: &translation.language,
+                    error,
+                })
+            })
+            .collect()
+    }
+
+    /// Replaces the common non-standard languages of the translations by their language tag (see [normalize_language])
+    ///
+    /// Returns the number of translations modified
+    pub fn normalize_languages(&mut self) -> usize {
+        let mut count = 0;
+        if let Some(Ok(translations)) = &mut self.translations {
+            for translation in translations {
+                if let Some(tag) = normalize_language(&translation.language) {
+                    translation.language = tag;
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+}
@@ -0,0 +1,149 @@
+use crate::error::LineError;
+use crate::gtfs_reader::record_end;
+use crate::id_map::IdHashMap;
+use crate::{objects::*, Error};
+use std::sync::OnceLock;
+
+/// Points of a shape that are parsed the first time they are requested
+struct LazyShape {
+    /// Byte position of each record of the shape in the content
+    starts: Vec<usize>,
+    points: OnceLock<Vec<Shape>>,
+}
+
+/// Shapes kept as csv text and parsed one by one when they are requested,
+/// built with [crate::GtfsReader::lazy_shapes]
+///
+/// Reading `shapes.txt` only indexes the position of the rows of each `shape_id`
+///
+/// The default values and [crate::GtfsReader::skip_invalid_rows] are not applied to those shapes:
+/// an invalid row is an error when its shape is requested
+#[derive(Default)]
+pub struct LazyShapes {
+    /// Content of the file, without its byte order mark
+    content: Vec<u8>,
+    shapes: IdHashMap<LazyShape>,
+    delimiter: u8,
+    trim: csv::Trim,
+    file_name: String,
+}
+
+impl LazyShapes {
+    /// `starts` are the byte positions in `content` of the records of each shape
+    pub(crate) fn new(
+        content: Vec<u8>,
+        starts: IdHashMap<Vec<usize>>,
+        delimiter: u8,
+        trim: csv::Trim,
+        file_name: &str,
+    ) -> Self {
+        let shapes = starts
+            .into_iter()
+            .map(|(id, starts)| {
+                let shape = LazyShape {
+                    starts,
+                    points: OnceLock::new(),
+                };
+                (id, shape)
+            })
+            .collect();
+        LazyShapes {
+            content,
+            shapes,
+            delimiter,
+            trim,
+            file_name: file_name.to_owned(),
+        }
+    }
+
+    /// Gets all the points of a shape, sorted by `shape_pt_sequence`. None if the shape does not exist
+    ///
+    /// The shape is parsed at the first call, the following calls return the same points
+    pub fn get(&self, id: &str) -> Option<Result<&Vec<Shape>, Error>> {
+        let shape = self.shapes.get(id)?;
+        if let Some(points) = shape.points.get() {
+            return Some(Ok(points));
+        }
+        Some(
+            self.parse(&shape.starts)
+                .map(|points| shape.points.get_or_init(|| points)),
+        )
+    }
+
+    /// True if the shape exists, without parsing it
+    pub fn contains(&self, id: &str) -> bool {
+        self.shapes.contains_key(id)
+    }
+
+    /// All the `shape_id`, in no particular order
+    pub fn ids(&self) -> impl Iterator<Item = &GtfsId> {
+        self.shapes.keys()
+    }
+
+    /// Number of shapes
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    /// True if there is no shape, in particular when the shapes were not read lazily
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    fn parse(&self, starts: &[usize]) -> Result<Vec<Shape>, Error> {
+        let mut csv_content = self.content[..record_end(&self.content)].to_vec();
+        for start in starts {
+            let record = &self.content[*start..];
+            // With `\r\n` line endings, the position of a record can be on the `\n` ending the previous one
+            let record = &record[record.iter().take_while(|b| **b == b'\n').count()..];
+            csv_content.extend_from_slice(&record[..record_end(record)]);
+            if csv_content.last() != Some(&b'\n') {
+                csv_content.push(b'\n');
+            }
+        }
+
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .delimiter(self.delimiter)
+            .trim(self.trim)
+            .from_reader(csv_content.as_slice());
+        let headers = reader
+            .headers()
+            .map_err(|e| self.csv_error(e, None))?
+            .clone();
+        let mut points = Vec::with_capacity(starts.len());
+        for (rec, start) in reader.records().zip(starts) {
+            let rec = rec.map_err(|e| self.csv_error(e, None))?;
+            let point: Shape = rec.deserialize(Some(&headers)).map_err(|e| {
+                let line = LineError {
+                    line: self.line_at(*start),
+                    headers: headers.iter().map(String::from).collect(),
+                    values: rec.iter().map(String::from).collect(),
+                };
+                self.csv_error(e, Some(line))
+            })?;
+            points.push(point);
+        }
+        points.sort_by_key(|p| p.sequence);
+        Ok(points)
+    }
+
+    /// Line number in the file of the record at this byte position
+    fn line_at(&self, start: usize) -> u64 {
+        let record = &self.content[start..];
+        let skipped = record.iter().take_while(|b| **b == b'\n').count();
+        let newlines = self.content[..start + skipped]
+            .iter()
+            .filter(|b| **b == b'\n')
+            .count();
+        newlines as u64 + 1
+    }
+
+    fn csv_error(&self, source: csv::Error, line_in_error: Option<LineError>) -> Error {
+        Error::CSVError {
+            file_name: self.file_name.clone(),
+            source,
+            line_in_error,
+        }
+    }
+}
@@ -43,19 +43,100 @@ extern crate derivative;
 #[macro_use]
 extern crate serde_derive;
 
+mod accessibility;
+#[cfg(feature = "rkyv")]
+mod archive;
+mod blocks;
+#[cfg(feature = "cache")]
+mod cache;
+mod clusters;
+mod compact_gtfs;
+mod connections;
+mod coordinates;
+mod decreasing_times;
+mod departures;
+mod directions;
+mod duplicate_stop_times;
 mod enums;
 pub mod error;
+mod fares;
+mod feed_validity;
+mod field_formats;
+mod frequencies;
+mod geometry;
 mod gtfs;
 mod gtfs_reader;
+mod gtfs_writer;
+mod headsigns;
+mod id_map;
+mod interpolation;
+mod languages;
+mod lazy_shapes;
 pub(crate) mod objects;
+mod pathways;
+mod patterns;
 mod raw_gtfs;
+mod scanner;
 mod serde_helpers;
+mod service_levels;
+mod service_time;
+mod services;
+#[cfg(feature = "spatial")]
+mod spatial;
+mod speeds;
+mod stats;
+mod stop_sequences;
+mod stop_time_columns;
+mod timetable;
+#[cfg(feature = "timezones")]
+mod timezones;
+mod transfers;
+mod trip_times;
 
 #[cfg(test)]
 mod tests;
 
+pub use accessibility::{RouteAccessibility, StopAccessibility};
+#[cfg(feature = "rkyv")]
+pub use archive::{
+    archived_date, ArchivedGtfs, ColorAsArray, DateAsDays, FlatGtfs, IdAsString, MappedGtfs,
+};
+pub use blocks::{Block, Continuation};
+pub use clusters::{normalize_name, ClusterParams, StopCluster, StopClusters};
+pub use compact_gtfs::CompactGtfs;
+pub use connections::{Connection, DirectTrip};
+pub use coordinates::{CoordinateIssue, InvalidCoordinates};
+pub use decreasing_times::{DecreasingTime, DecreasingTimeKind};
+pub use departures::{Departure, FrequencyProfile, Headways};
+pub use directions::InferredDirection;
+pub use duplicate_stop_times::{DuplicateStopTime, DuplicateStopTimeKind};
 pub use error::Error;
+pub use feed_validity::FeedDateIssue;
+pub use field_formats::{FieldFormat, InvalidField};
+pub use geometry::{haversine, polyline_length, ShapeProjection, StopFarFromShape, TripStats};
 pub use gtfs::Gtfs;
-pub use gtfs_reader::GtfsReader;
+pub use gtfs_reader::{
+    CsvRow, DefaultValue, DefaultedValue, Delimiter, DuplicatePolicy, FileMetrics, GtfsReader,
+    ParseMetrics, ParseMode, StopTimesIter, TrimMode, DESCRIPTIVE_FIELDS,
+};
+pub use gtfs_writer::GtfsWriter;
+pub use headsigns::HeadsignRules;
+pub use id_map::{IdHasher, IdMap};
+pub use languages::{check_language_tag, normalize_language, InvalidLanguage};
+pub use lazy_shapes::LazyShapes;
 pub use objects::*;
+pub use pathways::{PathwayConstraints, PathwayPath, PathwayStep};
+pub use patterns::{StopPattern, StopPatterns};
 pub use raw_gtfs::RawGtfs;
+pub use scanner::{BoundingBox, FeedStatistics, GtfsScanner};
+pub use service_levels::{RouteServiceLevels, ServiceLevel, ServiceLevelRules};
+pub use service_time::ServiceTime;
+pub use services::ServiceException;
+#[cfg(feature = "spatial")]
+pub use spatial::StopPosition;
+pub use speeds::{ImplausibleSpeed, SpeedIssue, SpeedRules};
+pub use stats::FeedStats;
+pub use stop_sequences::StopSequenceIssue;
+pub use stop_time_columns::StopTimeColumns;
+pub use timetable::Timetable;
+pub use transfers::{Transfer, TransferGeneration};
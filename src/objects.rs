@@ -1,12 +1,30 @@
+#[cfg(feature = "rkyv")]
+use crate::archive::{ColorAsArray, DateAsDays, IdAsString};
 pub use crate::enums::*;
 use crate::serde_helpers::*;
 use chrono::{Datelike, NaiveDate, Weekday};
 use rgb::RGB8;
+#[cfg(feature = "rkyv")]
+use rkyv::with::Map;
 
 use std::fmt;
 use std::hash::Hash;
 use std::sync::Arc;
 
+/// Type of the identifiers of the objects, and of the references to them
+///
+/// It is a [String], or with the feature `compact-ids` a [smol_str::SmolStr] that stores the short identifiers inline without allocation.
+/// Both dereference to [str]
+#[cfg(not(feature = "compact-ids"))]
+pub type GtfsId = String;
+
+/// Type of the identifiers of the objects, and of the references to them
+///
+/// It is a [String], or with the feature `compact-ids` a [smol_str::SmolStr] that stores the short identifiers inline without allocation.
+/// Both dereference to [str]
+#[cfg(feature = "compact-ids")]
+pub type GtfsId = smol_str::SmolStr;
+
 /// Objects that have an identifier implement this trait
 ///
 /// Those identifier are technical and should not be shown to travellers
@@ -35,10 +53,15 @@ impl<T: Type> Type for Arc<T> {
 
 /// A calender describes on which days the vehicle runs. See <https://gtfs.org/reference/static/#calendartxt>
 #[derive(Debug, Clone, Deserialize, Serialize, Hash, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Calendar {
     /// Unique technical identifier (not for the traveller) of this calendar
     #[serde(rename = "service_id")]
-    pub id: String,
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub id: GtfsId,
     /// Does the service run on mondays
     #[serde(
         deserialize_with = "deserialize_bool",
@@ -86,12 +109,14 @@ pub struct Calendar {
         deserialize_with = "deserialize_date",
         serialize_with = "serialize_date"
     )]
+    #[cfg_attr(feature = "rkyv", rkyv(with = DateAsDays))]
     pub start_date: NaiveDate,
     /// End service day for the service interval. This service day is included in the interval
     #[serde(
         deserialize_with = "deserialize_date",
         serialize_with = "serialize_date"
     )]
+    #[cfg_attr(feature = "rkyv", rkyv(with = DateAsDays))]
     pub end_date: NaiveDate,
 }
 
@@ -126,18 +151,41 @@ impl Calendar {
             Weekday::Sun => self.sunday,
         }
     }
+
+    /// Iterates over the days the service runs, from `start_date` to `end_date` (both included), on the valid weekdays.
+    /// The [CalendarDate] are not applied, see [crate::Gtfs::service_dates]
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// // service1 runs on the weekends from 2017-01-01 (a Sunday) to 2017-01-15
+    /// let dates: Vec<_> = gtfs.calendar["service1"].dates().map(|d| d.to_string()).collect();
+    /// assert_eq!(vec!["2017-01-01", "2017-01-07", "2017-01-08", "2017-01-14", "2017-01-15"], dates);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.start_date
+            .iter_days()
+            .take_while(move |day| *day <= self.end_date)
+            .filter(move |day| self.valid_weekday(*day))
+    }
 }
 
 /// Defines a specific date that can be added or removed from a [Calendar]. See <https://gtfs.org/reference/static/#calendar_datestxt>
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct CalendarDate {
     /// Identifier of the service that is modified at this date
-    pub service_id: String,
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub service_id: GtfsId,
     #[serde(
         deserialize_with = "deserialize_date",
         serialize_with = "serialize_date"
     )]
     /// Date where the service will be added or deleted
+    #[cfg_attr(feature = "rkyv", rkyv(with = DateAsDays))]
     pub date: NaiveDate,
     /// Is the service added or deleted
     pub exception_type: Exception,
@@ -145,29 +193,36 @@ pub struct CalendarDate {
 
 /// A physical stop, station or area. See <https://gtfs.org/reference/static/#stopstxt>
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Stop {
     /// Unique technical identifier (not for the traveller) of the stop
     #[serde(rename = "stop_id")]
-    pub id: String,
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub id: GtfsId,
     /// Short text or a number that identifies the location for riders
     #[serde(rename = "stop_code")]
-    pub code: Option<String>,
+    pub code: Option<Box<str>>,
     ///Name of the location. Use a name that people will understand in the local and tourist vernacular
     #[serde(rename = "stop_name")]
     pub name: Option<String>,
     /// Description of the location that provides useful, quality information
     #[serde(default, rename = "stop_desc")]
-    pub description: Option<String>,
+    pub description: Option<Box<str>>,
     /// Type of the location
     #[serde(default)]
     pub location_type: LocationType,
     /// Defines hierarchy between the different locations
-    pub parent_station: Option<String>,
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub parent_station: Option<GtfsId>,
     /// Identifies the fare zone for a stop
-    pub zone_id: Option<String>,
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub zone_id: Option<GtfsId>,
     /// URL of a web page about the location
     #[serde(rename = "stop_url")]
-    pub url: Option<String>,
+    pub url: Option<Box<str>>,
     /// Longitude of the stop
     #[serde(deserialize_with = "de_with_optional_float")]
     #[serde(serialize_with = "serialize_float_as_str")]
@@ -180,14 +235,15 @@ pub struct Stop {
     pub latitude: Option<f64>,
     /// Timezone of the location
     #[serde(rename = "stop_timezone")]
-    pub timezone: Option<String>,
+    pub timezone: Option<Box<str>>,
     /// Indicates whether wheelchair boardings are possible from the location
     #[serde(deserialize_with = "de_with_empty_default", default)]
     pub wheelchair_boarding: Availability,
     /// Level of the location. The same level can be used by multiple unlinked stations
-    pub level_id: Option<String>,
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub level_id: Option<GtfsId>,
     /// Platform identifier for a platform stop (a stop belonging to a station)
-    pub platform_code: Option<String>,
+    pub platform_code: Option<Box<str>>,
     /// Transfers from this Stop
     #[serde(skip)]
     pub transfers: Vec<StopTransfer>,
@@ -196,7 +252,7 @@ pub struct Stop {
     pub pathways: Vec<Pathway>,
     /// Text to speech readable version of the stop_name
     #[serde(rename = "tts_stop_name")]
-    pub tts_name: Option<String>,
+    pub tts_name: Option<Box<str>>,
 }
 
 impl Type for Stop {
@@ -221,7 +277,7 @@ impl fmt::Display for Stop {
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct RawStopTime {
     /// [Trip] to which this stop time belongs to
-    pub trip_id: String,
+    pub trip_id: GtfsId,
     /// Arrival time of the stop time.
     /// It's an option since the intermediate stops can have have no arrival
     /// and this arrival needs to be interpolated
@@ -239,11 +295,52 @@ pub struct RawStopTime {
     )]
     pub departure_time: Option<u32>,
     /// Identifier of the [Stop] where the vehicle stops
-    pub stop_id: String,
+    pub stop_id: GtfsId,
     /// Order of stops for a particular trip. The values must increase along the trip but do not need to be consecutive
     pub stop_sequence: u16,
     /// Text that appears on signage identifying the trip's destination to riders
-    pub stop_headsign: Option<String>,
+    pub stop_headsign: Option<Box<str>>,
+    /// Indicates pickup method
+    #[serde(default)]
+    pub pickup_type: PickupDropOffType,
+    /// Indicates drop off method
+    #[serde(default)]
+    pub drop_off_type: PickupDropOffType,
+    /// Indicates whether a rider can board the transit vehicle anywhere along the vehicle’s travel path
+    #[serde(default)]
+    pub continuous_pickup: ContinuousPickupDropOff,
+    /// Indicates whether a rider can alight from the transit vehicle at any point along the vehicle’s travel path
+    #[serde(default)]
+    pub continuous_drop_off: ContinuousPickupDropOff,
+    /// Actual distance traveled along the associated shape, from the first stop to the stop specified in this record. This field specifies how much of the shape to draw between any two stops during a trip
+    pub shape_dist_traveled: Option<f32>,
+    /// Indicates if arrival and departure times for a stop are strictly adhered to by the vehicle or if they are instead approximate and/or interpolated times
+    #[serde(default)]
+    pub timepoint: TimepointType,
+}
+
+/// A [RawStopTime] that borrows its text values instead of allocating them, see [crate::RawGtfsReader::visit_records]
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawStopTimeRef<'a> {
+    /// [Trip] to which this stop time belongs to
+    pub trip_id: &'a str,
+    /// Arrival time of the stop time.
+    /// It's an option since the intermediate stops can have have no arrival
+    /// and this arrival needs to be interpolated
+    #[serde(deserialize_with = "deserialize_optional_time")]
+    pub arrival_time: Option<u32>,
+    /// Departure time of the stop time.
+    /// It's an option since the intermediate stops can have have no departure
+    /// and this departure needs to be interpolated
+    #[serde(deserialize_with = "deserialize_optional_time")]
+    pub departure_time: Option<u32>,
+    /// Identifier of the [Stop] where the vehicle stops
+    pub stop_id: &'a str,
+    /// Order of stops for a particular trip. The values must increase along the trip but do not need to be consecutive
+    pub stop_sequence: u16,
+    /// Text that appears on signage identifying the trip's destination to riders
+    #[serde(borrow, default)]
+    pub stop_headsign: Option<&'a str>,
     /// Indicates pickup method
     #[serde(default)]
     pub pickup_type: PickupDropOffType,
@@ -283,7 +380,7 @@ pub struct StopTime {
     /// Order of stops for a particular trip. The values must increase along the trip but do not need to be consecutive
     pub stop_sequence: u16,
     /// Text that appears on signage identifying the trip's destination to riders
-    pub stop_headsign: Option<String>,
+    pub stop_headsign: Option<Box<str>>,
     /// Indicates whether a rider can board the transit vehicle anywhere along the vehicle’s travel path
     pub continuous_pickup: ContinuousPickupDropOff,
     /// Indicates whether a rider can alight from the transit vehicle at any point along the vehicle’s travel path
@@ -313,12 +410,90 @@ impl StopTime {
     }
 }
 
+/// A [StopTime] that references its [Stop] by its index in [crate::CompactGtfs::stops] instead of an [Arc]
+///
+/// Use [crate::CompactGtfs::stop_of] to get the [Stop]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct CompactStopTime {
+    /// Arrival time of the stop time.
+    /// It's an option since the intermediate stops can have have no arrival
+    /// and this arrival needs to be interpolated
+    pub arrival_time: Option<u32>,
+    /// Index of the [Stop] in [crate::CompactGtfs::stops]
+    pub stop_index: u32,
+    /// Departure time of the stop time.
+    /// It's an option since the intermediate stops can have have no departure
+    /// and this departure needs to be interpolated
+    pub departure_time: Option<u32>,
+    /// Indicates pickup method
+    pub pickup_type: PickupDropOffType,
+    /// Indicates drop off method
+    pub drop_off_type: PickupDropOffType,
+    /// Order of stops for a particular trip. The values must increase along the trip but do not need to be consecutive
+    pub stop_sequence: u16,
+    /// Text that appears on signage identifying the trip's destination to riders
+    pub stop_headsign: Option<Box<str>>,
+    /// Indicates whether a rider can board the transit vehicle anywhere along the vehicle’s travel path
+    pub continuous_pickup: ContinuousPickupDropOff,
+    /// Indicates whether a rider can alight from the transit vehicle at any point along the vehicle’s travel path
+    pub continuous_drop_off: ContinuousPickupDropOff,
+    /// Actual distance traveled along the associated shape, from the first stop to the stop specified in this record. This field specifies how much of the shape to draw between any two stops during a trip
+    pub shape_dist_traveled: Option<f32>,
+    /// Indicates if arrival and departure times for a stop are strictly adhered to by the vehicle or if they are instead approximate and/or interpolated times
+    pub timepoint: TimepointType,
+}
+
+impl CompactStopTime {
+    /// Creates [CompactStopTime] from a [RawStopTime] and the index of its [Stop]
+    pub fn from(stop_time_gtfs: RawStopTime, stop_index: u32) -> Self {
+        Self {
+            arrival_time: stop_time_gtfs.arrival_time,
+            departure_time: stop_time_gtfs.departure_time,
+            stop_index,
+            pickup_type: stop_time_gtfs.pickup_type,
+            drop_off_type: stop_time_gtfs.drop_off_type,
+            stop_sequence: stop_time_gtfs.stop_sequence,
+            stop_headsign: stop_time_gtfs.stop_headsign,
+            continuous_pickup: stop_time_gtfs.continuous_pickup,
+            continuous_drop_off: stop_time_gtfs.continuous_drop_off,
+            shape_dist_traveled: stop_time_gtfs.shape_dist_traveled,
+            timepoint: stop_time_gtfs.timepoint,
+        }
+    }
+
+    /// Creates [CompactStopTime] from a [StopTime] and the index of its [Stop]
+    pub fn from_stop_time(stop_time: StopTime, stop_index: u32) -> Self {
+        Self {
+            arrival_time: stop_time.arrival_time,
+            departure_time: stop_time.departure_time,
+            stop_index,
+            pickup_type: stop_time.pickup_type,
+            drop_off_type: stop_time.drop_off_type,
+            stop_sequence: stop_time.stop_sequence,
+            stop_headsign: stop_time.stop_headsign,
+            continuous_pickup: stop_time.continuous_pickup,
+            continuous_drop_off: stop_time.continuous_drop_off,
+            shape_dist_traveled: stop_time.shape_dist_traveled,
+            timepoint: stop_time.timepoint,
+        }
+    }
+}
+
 /// A route is a commercial line (there can be various stop sequences for a same line). See <https://gtfs.org/reference/static/#routestxt>
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Route {
     /// Unique technical (not for the traveller) identifier for the route
     #[serde(rename = "route_id")]
-    pub id: String,
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub id: GtfsId,
     /// Short name of a route. This will often be a short, abstract identifier like "32", "100X", or "Green" that riders use to identify a route, but which doesn't give any indication of what places the route serves
     #[serde(rename = "route_short_name", default)]
     pub short_name: Option<String>,
@@ -334,7 +509,8 @@ pub struct Route {
     #[serde(rename = "route_url")]
     pub url: Option<String>,
     /// Agency for the specified route
-    pub agency_id: Option<String>,
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub agency_id: Option<GtfsId>,
     /// Orders the routes in a way which is ideal for presentation to customers. Routes with smaller route_sort_order values should be displayed first.
     #[serde(rename = "route_sort_order")]
     pub order: Option<u32>,
@@ -345,6 +521,7 @@ pub struct Route {
         rename = "route_color",
         default = "default_route_color"
     )]
+    #[cfg_attr(feature = "rkyv", rkyv(with = ColorAsArray))]
     pub color: RGB8,
     /// Legible color to use for text drawn against a background of [Route::route_color]
     #[serde(
@@ -353,6 +530,7 @@ pub struct Route {
         rename = "route_text_color",
         default
     )]
+    #[cfg_attr(feature = "rkyv", rkyv(with = ColorAsArray))]
     pub text_color: RGB8,
     /// Indicates whether a rider can board the transit vehicle anywhere along the vehicle’s travel path
     #[serde(default)]
@@ -410,21 +588,21 @@ pub struct RawTranslation {
 pub struct RawTrip {
     /// Unique technical (not for the traveller) identifier for the Trip
     #[serde(rename = "trip_id")]
-    pub id: String,
+    pub id: GtfsId,
     /// References the [Calendar] on which this trip runs
-    pub service_id: String,
+    pub service_id: GtfsId,
     /// References along which [Route] this trip runs
-    pub route_id: String,
+    pub route_id: GtfsId,
     /// Shape of the trip
-    pub shape_id: Option<String>,
+    pub shape_id: Option<GtfsId>,
     /// Text that appears on signage identifying the trip's destination to riders
     pub trip_headsign: Option<String>,
     /// Public facing text used to identify the trip to riders, for instance, to identify train numbers for commuter rail trips
-    pub trip_short_name: Option<String>,
+    pub trip_short_name: Option<Box<str>>,
     /// Indicates the direction of travel for a trip. This field is not used in routing; it provides a way to separate trips by direction when publishing time tables
     pub direction_id: Option<DirectionType>,
     /// Identifies the block to which the trip belongs. A block consists of a single trip or many sequential trips made using the same vehicle, defined by shared service days and block_id. A block_id can have trips with different service days, making distinct blocks
-    pub block_id: Option<String>,
+    pub block_id: Option<GtfsId>,
     /// Indicates wheelchair accessibility
     #[serde(default)]
     pub wheelchair_accessible: Availability,
@@ -459,23 +637,23 @@ impl fmt::Display for RawTrip {
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Trip {
     /// Unique technical identifier (not for the traveller) for the Trip
-    pub id: String,
+    pub id: GtfsId,
     /// References the [Calendar] on which this trip runs
-    pub service_id: String,
+    pub service_id: GtfsId,
     /// References along which [Route] this trip runs
-    pub route_id: String,
+    pub route_id: GtfsId,
     /// All the [StopTime] that define the trip
     pub stop_times: Vec<StopTime>,
     /// Unique technical (not for the traveller) identifier for the Shape
-    pub shape_id: Option<String>,
+    pub shape_id: Option<GtfsId>,
     /// Text that appears on signage identifying the trip's destination to riders
     pub trip_headsign: Option<String>,
     /// Public facing text used to identify the trip to riders, for instance, to identify train numbers for commuter rail trips
-    pub trip_short_name: Option<String>,
+    pub trip_short_name: Option<Box<str>>,
     /// Indicates the direction of travel for a trip. This field is not used in routing; it provides a way to separate trips by direction when publishing time tables
     pub direction_id: Option<DirectionType>,
     /// Identifies the block to which the trip belongs. A block consists of a single trip or many sequential trips made using the same vehicle, defined by shared service days and block_id. A block_id can have trips with different service days, making distinct blocks
-    pub block_id: Option<String>,
+    pub block_id: Option<GtfsId>,
     /// Indicates wheelchair accessibility
     pub wheelchair_accessible: Availability,
     /// Indicates whether bikes are allowed
@@ -484,6 +662,50 @@ pub struct Trip {
     pub frequencies: Vec<Frequency>,
 }
 
+/// A [Trip] whose stop times are [CompactStopTime], see [crate::CompactGtfs]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct CompactTrip {
+    /// Unique technical identifier (not for the traveller) for the Trip
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub id: GtfsId,
+    /// References the [Calendar] on which this trip runs
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub service_id: GtfsId,
+    /// References along which [Route] this trip runs
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub route_id: GtfsId,
+    /// All the [CompactStopTime] that define the trip
+    pub stop_times: Vec<CompactStopTime>,
+    /// Unique technical (not for the traveller) identifier for the Shape
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub shape_id: Option<GtfsId>,
+    /// Text that appears on signage identifying the trip's destination to riders
+    pub trip_headsign: Option<String>,
+    /// Public facing text used to identify the trip to riders, for instance, to identify train numbers for commuter rail trips
+    pub trip_short_name: Option<Box<str>>,
+    /// Indicates the direction of travel for a trip. This field is not used in routing; it provides a way to separate trips by direction when publishing time tables
+    pub direction_id: Option<DirectionType>,
+    /// Identifies the block to which the trip belongs. A block consists of a single trip or many sequential trips made using the same vehicle, defined by shared service days and block_id. A block_id can have trips with different service days, making distinct blocks
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub block_id: Option<GtfsId>,
+    /// Indicates wheelchair accessibility
+    pub wheelchair_accessible: Availability,
+    /// Indicates whether bikes are allowed
+    pub bikes_allowed: BikesAllowedType,
+    /// During which periods the trip runs by frequency and not by fixed timetable
+    pub frequencies: Vec<Frequency>,
+}
+
+impl Id for CompactTrip {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
 impl Type for Trip {
     fn object_type(&self) -> ObjectType {
         ObjectType::Trip
@@ -508,10 +730,15 @@ impl fmt::Display for Trip {
 
 /// General informations about the agency running the network. See <https://gtfs.org/reference/static/#agencytxt>
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Agency {
     /// Unique technical (not for the traveller) identifier for the Agency
     #[serde(rename = "agency_id")]
-    pub id: Option<String>,
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub id: Option<GtfsId>,
     ///Full name of the transit agency
     #[serde(rename = "agency_name")]
     pub name: String,
@@ -558,10 +785,15 @@ impl fmt::Display for Agency {
 
 /// A single geographical point decribing the shape of a [Trip]. See <https://gtfs.org/reference/static/#shapestxt>
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Shape {
     /// Unique technical (not for the traveller) identifier for the Shape
     #[serde(rename = "shape_id")]
-    pub id: String,
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub id: GtfsId,
     #[serde(rename = "shape_pt_lat", default)]
     /// Latitude of a shape point
     pub latitude: f64,
@@ -590,10 +822,15 @@ impl Id for Shape {
 
 /// Defines one possible fare. See <https://gtfs.org/reference/static/#fare_attributestxt>
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct FareAttribute {
     /// Unique technical (not for the traveller) identifier for the FareAttribute
     #[serde(rename = "fare_id")]
-    pub id: String,
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub id: GtfsId,
     /// Fare price, in the unit specified by [FareAttribute::currency]
     pub price: String,
     /// Currency used to pay the fare.
@@ -604,7 +841,8 @@ pub struct FareAttribute {
     /// Indicates the number of transfers permitted on this fare
     pub transfers: Transfers,
     /// Identifies the relevant agency for a fare
-    pub agency_id: Option<String>,
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub agency_id: Option<GtfsId>,
     /// Length of time in seconds before a transfer expires
     pub transfer_duration: Option<usize>,
 }
@@ -623,24 +861,33 @@ impl Type for FareAttribute {
 
 /// Defines one possible fare. See <https://gtfs.org/schedule/reference/#fare_rulestxt>
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct FareRule {
     /// ID of the referenced FareAttribute.
-    pub fare_id: String,
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub fare_id: GtfsId,
     /// ID of a [Route] associated with the fare class
-    pub route_id: Option<String>,
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub route_id: Option<GtfsId>,
     /// Identifies an origin zone. References a [Stop].zone_id
-    pub origin_id: Option<String>,
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub origin_id: Option<GtfsId>,
     /// Identifies an destination zone. References a [Stop].zone_id
-    pub destination_id: Option<String>,
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub destination_id: Option<GtfsId>,
     /// Identifies the zones that a rider will enter while using a given fare class. References a [Stop].zone_id
-    pub contains_id: Option<String>,
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<IdAsString>))]
+    pub contains_id: Option<GtfsId>,
 }
 
 /// A [Frequency] before being merged into the corresponding [Trip]
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct RawFrequency {
     /// References the [Trip] that uses frequency
-    pub trip_id: String,
+    pub trip_id: GtfsId,
     /// Time at which the first vehicle departs from the first stop of the trip
     #[serde(
         deserialize_with = "deserialize_time",
@@ -661,6 +908,10 @@ pub struct RawFrequency {
 
 /// Timetables can be defined by the frequency of their vehicles. See <<https://gtfs.org/reference/static/#frequenciestxt>>
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Frequency {
     /// Time at which the first vehicle departs from the first stop of the trip
     pub start_time: u32,
@@ -688,9 +939,9 @@ impl Frequency {
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct RawTransfer {
     /// Stop from which to leave
-    pub from_stop_id: String,
+    pub from_stop_id: GtfsId,
     /// Stop which to transfer to
-    pub to_stop_id: String,
+    pub to_stop_id: GtfsId,
     /// Type of the transfer
     pub transfer_type: TransferType,
     /// Minimum time needed to make the transfer in seconds
@@ -699,9 +950,14 @@ pub struct RawTransfer {
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 /// Transfer information between stops
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct StopTransfer {
     /// Stop which to transfer to
-    pub to_stop_id: String,
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub to_stop_id: GtfsId,
     /// Type of the transfer
     pub transfer_type: TransferType,
     /// Minimum time needed to make the transfer in seconds
@@ -721,6 +977,10 @@ impl From<RawTransfer> for StopTransfer {
 
 /// Meta-data about the feed. See <https://gtfs.org/reference/static/#feed_infotxt>
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct FeedInfo {
     /// Full name of the organization that publishes the dataset.
     #[serde(rename = "feed_publisher_name")]
@@ -740,6 +1000,7 @@ pub struct FeedInfo {
         rename = "feed_start_date",
         default
     )]
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<DateAsDays>))]
     pub start_date: Option<NaiveDate>,
     ///The dataset provides complete and reliable schedule information for service in the period until this date
     #[serde(
@@ -748,6 +1009,7 @@ pub struct FeedInfo {
         rename = "feed_end_date",
         default
     )]
+    #[cfg_attr(feature = "rkyv", rkyv(with = Map<DateAsDays>))]
     pub end_date: Option<NaiveDate>,
     /// String that indicates the current version of their GTFS dataset
     #[serde(rename = "feed_version")]
@@ -771,11 +1033,11 @@ impl fmt::Display for FeedInfo {
 pub struct RawPathway {
     /// Uniquely identifies the pathway
     #[serde(rename = "pathway_id")]
-    pub id: String,
+    pub id: GtfsId,
     /// Location at which the pathway begins
-    pub from_stop_id: String,
+    pub from_stop_id: GtfsId,
     /// Location at which the pathway ends
-    pub to_stop_id: String,
+    pub to_stop_id: GtfsId,
     /// Type of pathway between the specified (from_stop_id, to_stop_id) pair
     #[serde(rename = "pathway_mode")]
     pub mode: PathwayMode,
@@ -792,9 +1054,9 @@ pub struct RawPathway {
     /// Minimum width of the pathway in meters
     pub min_width: Option<f32>,
     /// String of text from physical signage visible to transit riders
-    pub signposted_as: Option<String>,
+    pub signposted_as: Option<Box<str>>,
     /// Same than the signposted_as field, but when the pathways is used backward
-    pub reversed_signposted_as: Option<String>,
+    pub reversed_signposted_as: Option<Box<str>>,
 }
 
 impl Id for RawPathway {
@@ -811,11 +1073,17 @@ impl Type for RawPathway {
 
 /// Pathway going from a stop to another.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Pathway {
     /// Uniquely identifies the pathway
-    pub id: String,
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub id: GtfsId,
     /// Location at which the pathway ends
-    pub to_stop_id: String,
+    #[cfg_attr(feature = "rkyv", rkyv(with = IdAsString))]
+    pub to_stop_id: GtfsId,
     /// Type of pathway between the specified (from_stop_id, to_stop_id) pair
     pub mode: PathwayMode,
     /// Indicates in which direction the pathway can be used
@@ -831,9 +1099,9 @@ pub struct Pathway {
     /// Minimum width of the pathway in meters
     pub min_width: Option<f32>,
     /// String of text from physical signage visible to transit riders
-    pub signposted_as: Option<String>,
+    pub signposted_as: Option<Box<str>>,
     /// Same than the signposted_as field, but when the pathways is used backward
-    pub reversed_signposted_as: Option<String>,
+    pub reversed_signposted_as: Option<Box<str>>,
 }
 
 impl Id for Pathway {
@@ -874,4 +1142,6 @@ pub enum SourceFormat {
     Directory,
     /// `Zip` means the data were read from a zip
     Zip,
+    /// `TarGz` means the data were read from a gzipped tar archive (feature `read-tar-gz`)
+    TarGz,
 }
@@ -0,0 +1,158 @@
+use crate::{objects::*, Gtfs};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+
+/// Walking speed in meters per second, used for the pathways with a `length` but no `traversal_time`
+const WALKING_SPEED: f32 = 1.2;
+
+/// Restrictions on the pathways that can be used by [Gtfs::pathway_path]
+///
+/// A pathway without the measure of a restriction (like a missing `max_slope`) is considered as satisfying it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PathwayConstraints {
+    /// Excludes the stairs, and the pathways with a `stair_count`
+    pub avoid_stairs: bool,
+    /// Excludes the pathways with a steeper slope, up or down
+    pub max_slope: Option<f32>,
+    /// Excludes the pathways narrower than this width in meters
+    pub min_width: Option<f32>,
+}
+
+impl PathwayConstraints {
+    fn allow(&self, pathway: &Pathway) -> bool {
+        let stairs = pathway.mode == PathwayMode::Stairs
+            || pathway.stair_count.is_some_and(|count| count != 0);
+        !(self.avoid_stairs && stairs)
+            && match (self.max_slope, pathway.max_slope) {
+                (Some(max), Some(slope)) => slope.abs() <= max,
+                _ => true,
+            }
+            && match (self.min_width, pathway.min_width) {
+                (Some(min), Some(width)) => width >= min,
+                _ => true,
+            }
+    }
+}
+
+/// A pathway used by a [PathwayPath], in the direction it is walked
+#[derive(Debug, Clone)]
+pub struct PathwayStep<'a> {
+    /// The pathway
+    pub pathway: &'a Pathway,
+    /// Location where the step starts
+    pub from_stop: &'a Arc<Stop>,
+    /// Location where the step ends
+    pub to_stop: &'a Arc<Stop>,
+    /// True if the bidirectional pathway is walked from its `to_stop_id`, then the signage is its `reversed_signposted_as`
+    pub reversed: bool,
+    /// Time in seconds to walk through the pathway, see [Gtfs::pathway_path]
+    pub traversal_time: u32,
+}
+
+/// Path through the pathways of a station, found by [Gtfs::pathway_path]
+#[derive(Debug, Clone, Default)]
+pub struct PathwayPath<'a> {
+    /// Pathways to walk through, in order. Empty if the path goes from a location to itself
+    pub steps: Vec<PathwayStep<'a>>,
+    /// Total time in seconds to walk through all the pathways
+    pub traversal_time: u32,
+}
+
+impl Gtfs {
+    /// Finds the fastest path through the pathways from a location to another one, like from an entrance to a platform
+    ///
+    /// The pathways are only used in their direction, unless they are bidirectional, and must satisfy the constraints.
+    /// The time of a pathway is its `traversal_time`, or else its `length` walked at 1.2 m/s, or else 0.
+    /// None if no path exists
+    ///
+    /// ```
+    /// use gtfs_structures::PathwayConstraints;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let path = gtfs.pathway_path("entrance1", "platform1", &PathwayConstraints::default()).unwrap();
+    /// assert_eq!(60, path.traversal_time);
+    ///
+    /// let step_free = PathwayConstraints { avoid_stairs: true, ..Default::default() };
+    /// let path = gtfs.pathway_path("entrance1", "platform1", &step_free).unwrap();
+    /// let steps: Vec<_> = path.steps.iter().map(|s| s.pathway.id.as_str()).collect();
+    /// assert_eq!(vec!["corridor1", "elevator1"], steps);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn pathway_path(
+        &self,
+        from_stop_id: &str,
+        to_stop_id: &str,
+        constraints: &PathwayConstraints,
+    ) -> Option<PathwayPath<'_>> {
+        let from_stop = self.stops.get(from_stop_id)?;
+        self.stops.get(to_stop_id)?;
+
+        let mut edges: HashMap<&str, Vec<PathwayStep>> = HashMap::new();
+        for stop in self.stops.values() {
+            for pathway in stop.pathways.iter().filter(|p| constraints.allow(p)) {
+                let to_stop = match self.stops.get(&pathway.to_stop_id) {
+                    Some(to_stop) => to_stop,
+                    None => continue,
+                };
+                let traversal_time = pathway.traversal_time.unwrap_or_else(|| {
+                    pathway
+                        .length
+                        .map_or(0, |length| (length / WALKING_SPEED).round() as u32)
+                });
+                let step = PathwayStep {
+                    pathway,
+                    from_stop: stop,
+                    to_stop,
+                    reversed: false,
+                    traversal_time,
+                };
+                if pathway.is_bidirectional == PathwayDirectionType::Bidirectional {
+                    edges.entry(&to_stop.id).or_default().push(PathwayStep {
+                        from_stop: to_stop,
+                        to_stop: stop,
+                        reversed: true,
+                        ..step.clone()
+                    });
+                }
+                edges.entry(&stop.id).or_default().push(step);
+            }
+        }
+
+        // Dijkstra's algorithm, keeping the step that reached each location first
+        let mut times: HashMap<&str, u32> = HashMap::new();
+        let mut reached_by: HashMap<&str, &PathwayStep> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        times.insert(&from_stop.id, 0);
+        queue.push(Reverse((0, from_stop.id.as_str())));
+        while let Some(Reverse((time, stop_id))) = queue.pop() {
+            if stop_id == to_stop_id {
+                break;
+            }
+            if times.get(stop_id).is_some_and(|t| *t < time) {
+                continue;
+            }
+            for step in edges.get(stop_id).into_iter().flatten() {
+                let next_time = time + step.traversal_time;
+                let next_id = step.to_stop.id.as_str();
+                if times.get(next_id).is_none_or(|t| next_time < *t) {
+                    times.insert(next_id, next_time);
+                    reached_by.insert(next_id, step);
+                    queue.push(Reverse((next_time, next_id)));
+                }
+            }
+        }
+
+        let traversal_time = *times.get(to_stop_id)?;
+        let mut steps = Vec::new();
+        let mut stop_id = to_stop_id;
+        while let Some(step) = reached_by.get(stop_id) {
+            steps.push((*step).clone());
+            stop_id = &step.from_stop.id;
+        }
+        steps.reverse();
+        Some(PathwayPath {
+            steps,
+            traversal_time,
+        })
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{objects::*, Gtfs};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Ordered stops shared by trips of a route, also called a variant or a journey pattern, see [Gtfs::stop_patterns]
+#[derive(Debug, Clone)]
+pub struct StopPattern<'a> {
+    /// Identifier of the pattern, `{route_id}:{number}` with the patterns of a route numbered from 1
+    pub id: String,
+    /// `route_id` of the trips
+    pub route_id: &'a str,
+    /// Stops visited by the trips, in order
+    pub stops: Vec<&'a Arc<Stop>>,
+    /// Trips following the pattern, sorted by `trip_id`
+    pub trips: Vec<&'a Trip>,
+}
+
+/// The distinct stop patterns of the routes, found by [Gtfs::stop_patterns]
+#[derive(Debug, Clone, Default)]
+pub struct StopPatterns<'a> {
+    /// Patterns, sorted by `route_id`, then by number of trips from the most common one
+    pub patterns: Vec<StopPattern<'a>>,
+    trip_patterns: HashMap<&'a str, usize>,
+}
+
+impl<'a> StopPatterns<'a> {
+    /// The pattern followed by a trip. None if the trip does not exist
+    pub fn pattern_of(&self, trip_id: &str) -> Option<&StopPattern<'a>> {
+        self.trip_patterns
+            .get(trip_id)
+            .map(|index| &self.patterns[*index])
+    }
+}
+
+impl Gtfs {
+    /// Groups the trips of each route visiting the same stops in the same order
+    ///
+    /// The patterns of a route are sorted by decreasing number of trips, then by stop ids,
+    /// so the most common pattern of a route is `{route_id}:1`
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let patterns = gtfs.stop_patterns();
+    /// assert_eq!(4, patterns.patterns.len());
+    /// let pattern = patterns.pattern_of("tripB1").unwrap();
+    /// assert_eq!("routeB:1", pattern.id);
+    /// assert_eq!(2, pattern.stops.len());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn stop_patterns(&self) -> StopPatterns<'_> {
+        let mut trips_by_pattern: HashMap<(&str, Vec<&str>), Vec<&Trip>> = HashMap::new();
+        for trip in self.trips.values() {
+            let stops = trip.stop_times.iter().map(|st| &*st.stop.id).collect();
+            trips_by_pattern
+                .entry((&trip.route_id, stops))
+                .or_default()
+                .push(trip);
+        }
+        let mut patterns: Vec<_> = trips_by_pattern
+            .into_iter()
+            .map(|((route_id, _), mut trips)| {
+                trips.sort_by(|a, b| a.id.cmp(&b.id));
+                StopPattern {
+                    id: String::new(),
+                    route_id,
+                    stops: trips[0].stop_times.iter().map(|st| &st.stop).collect(),
+                    trips,
+                }
+            })
+            .collect();
+        patterns.sort_by(|a, b| {
+            a.route_id
+                .cmp(b.route_id)
+                .then_with(|| b.trips.len().cmp(&a.trips.len()))
+                .then_with(|| {
+                    let a_stops = a.stops.iter().map(|s| &s.id);
+                    a_stops.cmp(b.stops.iter().map(|s| &s.id))
+                })
+        });
+
+        let mut trip_patterns = HashMap::new();
+        let mut number = 0;
+        for index in 0..patterns.len() {
+            if index == 0 || patterns[index - 1].route_id != patterns[index].route_id {
+                number = 0;
+            }
+            number += 1;
+            let pattern = &mut patterns[index];
+            pattern.id = format!("{}:{}", pattern.route_id, number);
+            for trip in &pattern.trips {
+                trip_patterns.insert(trip.id.as_str(), index);
+            }
+        }
+        StopPatterns {
+            patterns,
+            trip_patterns,
+        }
+    }
+}
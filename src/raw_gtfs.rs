@@ -1,8 +1,12 @@
+use crate::error::InvalidRow;
 use crate::objects::*;
+use crate::DefaultedValue;
 use crate::Error;
 use crate::GtfsReader;
+use crate::ParseMetrics;
+use crate::StopTimesIter;
+use crate::TableName;
 use std::path::Path;
-use std::time::Duration;
 
 /// Data structure that map the GTFS csv with little intelligence
 ///
@@ -10,8 +14,8 @@ use std::time::Duration;
 /// To manipulate the transit data, maybe [crate::Gtfs] will be more convienient
 #[derive(Debug)]
 pub struct RawGtfs {
-    /// Time needed to read and parse the archive
-    pub read_duration: Duration,
+    /// Time needed to read and parse the archive, and of each of its files
+    pub metrics: ParseMetrics,
     /// All Calendar, None if the file was absent as it is not mandatory
     pub calendar: Option<Result<Vec<Calendar>, Error>>,
     /// All Calendar dates, None if the file was absent as it is not mandatory
@@ -48,13 +52,17 @@ pub struct RawGtfs {
     pub sha256: Option<String>,
     /// All translations, None if the file was absent as it is not mandatory
     pub translations: Option<Result<Vec<RawTranslation>, Error>>,
+    /// Rows that could not be parsed and were skipped (only with [crate::GtfsReader::skip_invalid_rows])
+    pub invalid_rows: Vec<InvalidRow>,
+    /// Values that were blank or missing and set by [crate::GtfsReader::default_value]
+    pub defaulted_values: Vec<DefaultedValue>,
 }
 
 impl RawGtfs {
     /// Prints on stdout some basic statistics about the GTFS file (numbers of elements for each object). Mostly to be sure that everything was read
     pub fn print_stats(&self) {
         println!("GTFS data:");
-        println!("  Read in {:?}", self.read_duration);
+        println!("  Read in {:?}", self.metrics.total_duration);
         println!("  Stops: {}", mandatory_file_summary(&self.stops));
         println!("  Routes: {}", mandatory_file_summary(&self.routes));
         println!("  Trips: {}", mandatory_file_summary(&self.trips));
@@ -114,6 +122,125 @@ impl RawGtfs {
         GtfsReader::default().raw().read_from_reader(reader)
     }
 
+    /// Reads for any object implementing [std::io::Read], even if it cannot seek (e.g. the standard input)
+    ///
+    /// The whole archive is first loaded in memory
+    pub fn from_stream<T: std::io::Read>(reader: T) -> Result<Self, Error> {
+        GtfsReader::default().raw().read_from_stream(reader)
+    }
+
+    /// Reads the stop times of a local zip archive or local directory one by one, without keeping them all in memory
+    ///
+    /// Use [crate::GtfsReader::read_stop_times] to read the other files without the stop times
+    pub fn stop_times_iter<P: AsRef<Path>>(path: P) -> Result<StopTimesIter, Error> {
+        GtfsReader::default().stop_times_iter(path)
+    }
+
+    /// Reads a zipped GTFS already loaded in memory (e.g. from a database or a cache)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_reader(std::io::Cursor::new(bytes))
+    }
+
+    /// Replaces the files with an unknown enumeration value by an [Error::UnknownEnumValue]
+    ///
+    /// `file_name_of` gives the name of the file of each table, that can be customized with [GtfsReader::file_name]
+    pub(crate) fn unknown_to_error<'a>(&mut self, file_name_of: impl Fn(TableName) -> &'a str) {
+        unknown_to_error(
+            &mut self.stops,
+            file_name_of(TableName::Stops),
+            |stop| match (stop.location_type, stop.wheelchair_boarding) {
+                (LocationType::Unknown(i), _) => Some(("location_type", i)),
+                (_, Availability::Unknown(i)) => Some(("wheelchair_boarding", i)),
+                _ => None,
+            },
+        );
+        unknown_to_error(
+            &mut self.routes,
+            file_name_of(TableName::Routes),
+            |route| match (
+                route.route_type,
+                route.continuous_pickup,
+                route.continuous_drop_off,
+            ) {
+                (RouteType::Other(i), _, _) if !is_specified_route_type(i) => {
+                    Some(("route_type", i))
+                }
+                (_, ContinuousPickupDropOff::Unknown(i), _) => Some(("continuous_pickup", i)),
+                (_, _, ContinuousPickupDropOff::Unknown(i)) => Some(("continuous_drop_off", i)),
+                _ => None,
+            },
+        );
+        unknown_to_error(
+            &mut self.stop_times,
+            file_name_of(TableName::StopTimes),
+            stop_time_unknown_value,
+        );
+        unknown_to_error(
+            &mut self.trips,
+            file_name_of(TableName::Trips),
+            |trip| match (
+                trip.wheelchair_accessible,
+                trip.bikes_allowed,
+                trip.direction_id,
+            ) {
+                (Availability::Unknown(i), _, _) => Some(("wheelchair_accessible", i)),
+                (_, BikesAllowedType::Unknown(i), _) => Some(("bikes_allowed", i)),
+                (_, _, Some(DirectionType::Unknown(i))) => Some(("direction_id", i)),
+                _ => None,
+            },
+        );
+        if let Some(calendar_dates) = &mut self.calendar_dates {
+            unknown_to_error(
+                calendar_dates,
+                file_name_of(TableName::CalendarDates),
+                |date| match date.exception_type {
+                    Exception::Unknown(i) => Some(("exception_type", i)),
+                    _ => None,
+                },
+            );
+        }
+        if let Some(fare_attributes) = &mut self.fare_attributes {
+            unknown_to_error(
+                fare_attributes,
+                file_name_of(TableName::FareAttributes),
+                |fare| match (fare.transfers, fare.payment_method) {
+                    (Transfers::Other(i), _) => Some(("transfers", i)),
+                    (_, PaymentMethod::Unknown(i)) => Some(("payment_method", i)),
+                    _ => None,
+                },
+            );
+        }
+        if let Some(frequencies) = &mut self.frequencies {
+            unknown_to_error(
+                frequencies,
+                file_name_of(TableName::Frequencies),
+                |frequency| match frequency.exact_times {
+                    Some(ExactTimes::Unknown(i)) => Some(("exact_times", i)),
+                    _ => None,
+                },
+            );
+        }
+        if let Some(transfers) = &mut self.transfers {
+            unknown_to_error(transfers, file_name_of(TableName::Transfers), |transfer| {
+                match transfer.transfer_type {
+                    TransferType::Unknown(i) => Some(("transfer_type", i)),
+                    _ => None,
+                }
+            });
+        }
+        if let Some(pathways) = &mut self.pathways {
+            unknown_to_error(
+                pathways,
+                file_name_of(TableName::Pathways),
+                |pathway| match (pathway.mode, pathway.is_bidirectional) {
+                    (PathwayMode::Unknown(i), _) => Some(("pathway_mode", i)),
+                    (_, PathwayDirectionType::Unknown(i)) => Some(("is_bidirectional", i)),
+                    _ => None,
+                },
+            );
+        }
+    }
+
     pub(crate) fn unknown_to_default(&mut self) {
         if let Ok(stops) = &mut self.stops {
             for stop in stops.iter_mut() {
@@ -126,20 +253,7 @@ impl RawGtfs {
             }
         }
         if let Ok(stop_times) = &mut self.stop_times {
-            for stop_time in stop_times.iter_mut() {
-                if let PickupDropOffType::Unknown(_) = stop_time.pickup_type {
-                    stop_time.pickup_type = PickupDropOffType::default();
-                }
-                if let PickupDropOffType::Unknown(_) = stop_time.drop_off_type {
-                    stop_time.drop_off_type = PickupDropOffType::default();
-                }
-                if let ContinuousPickupDropOff::Unknown(_) = stop_time.continuous_pickup {
-                    stop_time.continuous_pickup = ContinuousPickupDropOff::default();
-                }
-                if let ContinuousPickupDropOff::Unknown(_) = stop_time.continuous_drop_off {
-                    stop_time.continuous_drop_off = ContinuousPickupDropOff::default();
-                }
-            }
+            stop_times.iter_mut().for_each(stop_time_unknown_to_default);
         }
         if let Ok(trips) = &mut self.trips {
             for trip in trips.iter_mut() {
@@ -149,11 +263,168 @@ impl RawGtfs {
                 if let BikesAllowedType::Unknown(_) = trip.bikes_allowed {
                     trip.bikes_allowed = BikesAllowedType::default();
                 }
+                if let Some(DirectionType::Unknown(_)) = trip.direction_id {
+                    trip.direction_id = None;
+                }
+            }
+        }
+        if let Some(Ok(frequencies)) = &mut self.frequencies {
+            for frequency in frequencies.iter_mut() {
+                if let Some(ExactTimes::Unknown(_)) = frequency.exact_times {
+                    frequency.exact_times = None;
+                }
+            }
+        }
+        // Exception and PaymentMethod have no default value: their unknown values are kept
+        if let Some(Ok(transfers)) = &mut self.transfers {
+            for transfer in transfers.iter_mut() {
+                if let TransferType::Unknown(_) = transfer.transfer_type {
+                    transfer.transfer_type = TransferType::default();
+                }
+            }
+        }
+        if let Some(Ok(pathways)) = &mut self.pathways {
+            for pathway in pathways.iter_mut() {
+                if let PathwayMode::Unknown(_) = pathway.mode {
+                    pathway.mode = PathwayMode::default();
+                }
+                if let PathwayDirectionType::Unknown(_) = pathway.is_bidirectional {
+                    pathway.is_bidirectional = PathwayDirectionType::default();
+                }
+            }
+        }
+    }
+
+    /// True if the file of the table was read without error (an absent optional file has no error)
+    pub(crate) fn has_table(&self, table: TableName) -> bool {
+        match table {
+            TableName::Agency => self.agencies.is_ok(),
+            TableName::Stops => self.stops.is_ok(),
+            TableName::Routes => self.routes.is_ok(),
+            TableName::Trips => self.trips.is_ok(),
+            TableName::StopTimes => self.stop_times.is_ok(),
+            TableName::Calendar => !matches!(self.calendar, Some(Err(_))),
+            TableName::CalendarDates => !matches!(self.calendar_dates, Some(Err(_))),
+            TableName::Shapes => !matches!(self.shapes, Some(Err(_))),
+            TableName::FareAttributes => !matches!(self.fare_attributes, Some(Err(_))),
+            TableName::FareRules => !matches!(self.fare_rules, Some(Err(_))),
+            TableName::Frequencies => !matches!(self.frequencies, Some(Err(_))),
+            TableName::Transfers => !matches!(self.transfers, Some(Err(_))),
+            TableName::Pathways => !matches!(self.pathways, Some(Err(_))),
+            TableName::FeedInfo => !matches!(self.feed_info, Some(Err(_))),
+            TableName::Translations => !matches!(self.translations, Some(Err(_))),
+        }
+    }
+
+    /// Copies the objects of the table from `other`, unless its file could not be read (see [RawGtfs::has_table])
+    pub(crate) fn copy_table(&mut self, other: &RawGtfs, table: TableName) {
+        match table {
+            TableName::Agency => copy_file(&mut self.agencies, &other.agencies),
+            TableName::Stops => copy_file(&mut self.stops, &other.stops),
+            TableName::Routes => copy_file(&mut self.routes, &other.routes),
+            TableName::Trips => copy_file(&mut self.trips, &other.trips),
+            TableName::StopTimes => copy_file(&mut self.stop_times, &other.stop_times),
+            TableName::Calendar => copy_optional_file(&mut self.calendar, &other.calendar),
+            TableName::CalendarDates => {
+                copy_optional_file(&mut self.calendar_dates, &other.calendar_dates)
+            }
+            TableName::Shapes => copy_optional_file(&mut self.shapes, &other.shapes),
+            TableName::FareAttributes => {
+                copy_optional_file(&mut self.fare_attributes, &other.fare_attributes)
+            }
+            TableName::FareRules => copy_optional_file(&mut self.fare_rules, &other.fare_rules),
+            TableName::Frequencies => copy_optional_file(&mut self.frequencies, &other.frequencies),
+            TableName::Transfers => copy_optional_file(&mut self.transfers, &other.transfers),
+            TableName::Pathways => copy_optional_file(&mut self.pathways, &other.pathways),
+            TableName::FeedInfo => copy_optional_file(&mut self.feed_info, &other.feed_info),
+            TableName::Translations => {
+                copy_optional_file(&mut self.translations, &other.translations)
             }
         }
     }
 }
 
+/// The first field of the stop time with a value not defined by the specification
+///
+/// The stop times are checked one by one when they are not kept in a [RawGtfs]
+pub(crate) fn stop_time_unknown_value(stop_time: &RawStopTime) -> Option<(&'static str, i16)> {
+    match (
+        stop_time.pickup_type,
+        stop_time.drop_off_type,
+        stop_time.continuous_pickup,
+        stop_time.continuous_drop_off,
+        stop_time.timepoint,
+    ) {
+        (PickupDropOffType::Unknown(i), _, _, _, _) => Some(("pickup_type", i)),
+        (_, PickupDropOffType::Unknown(i), _, _, _) => Some(("drop_off_type", i)),
+        (_, _, ContinuousPickupDropOff::Unknown(i), _, _) => Some(("continuous_pickup", i)),
+        (_, _, _, ContinuousPickupDropOff::Unknown(i), _) => Some(("continuous_drop_off", i)),
+        (_, _, _, _, TimepointType::Unknown(i)) => Some(("timepoint", i)),
+        _ => None,
+    }
+}
+
+pub(crate) fn stop_time_unknown_to_default(stop_time: &mut RawStopTime) {
+    if let PickupDropOffType::Unknown(_) = stop_time.pickup_type {
+        stop_time.pickup_type = PickupDropOffType::default();
+    }
+    if let PickupDropOffType::Unknown(_) = stop_time.drop_off_type {
+        stop_time.drop_off_type = PickupDropOffType::default();
+    }
+    if let ContinuousPickupDropOff::Unknown(_) = stop_time.continuous_pickup {
+        stop_time.continuous_pickup = ContinuousPickupDropOff::default();
+    }
+    if let ContinuousPickupDropOff::Unknown(_) = stop_time.continuous_drop_off {
+        stop_time.continuous_drop_off = ContinuousPickupDropOff::default();
+    }
+    if let TimepointType::Unknown(_) = stop_time.timepoint {
+        stop_time.timepoint = TimepointType::default();
+    }
+}
+
+/// Replaces the file by an [Error::UnknownEnumValue] if an object has an unknown value
+///
+/// `find_unknown` returns the name of the field and the value if the object has an unknown value
+fn unknown_to_error<T>(
+    objs: &mut Result<Vec<T>, Error>,
+    file_name: &str,
+    find_unknown: impl Fn(&T) -> Option<(&'static str, i16)>,
+) {
+    let unknown = match objs {
+        Ok(objs) => objs.iter().find_map(find_unknown),
+        Err(_) => None,
+    };
+    if let Some((field_name, value)) = unknown {
+        *objs = Err(Error::UnknownEnumValue {
+            file_name: file_name.to_owned(),
+            field_name: field_name.to_owned(),
+            value: value.to_string(),
+        });
+    }
+}
+
+/// The route types of the specification without their own [RouteType]: 11, 12 and the extended ones
+fn is_specified_route_type(route_type: i16) -> bool {
+    matches!(route_type, 11 | 12 | 100..=1799)
+}
+
+fn copy_file<T: Clone>(objs: &mut Result<Vec<T>, Error>, other: &Result<Vec<T>, Error>) {
+    if let Ok(other) = other {
+        *objs = Ok(other.clone());
+    }
+}
+
+fn copy_optional_file<T: Clone>(
+    objs: &mut Option<Result<Vec<T>, Error>>,
+    other: &Option<Result<Vec<T>, Error>>,
+) {
+    match other {
+        Some(Ok(other)) => *objs = Some(Ok(other.clone())),
+        Some(Err(_)) => (),
+        None => *objs = None,
+    }
+}
+
 fn mandatory_file_summary<T>(objs: &Result<Vec<T>, Error>) -> String {
     match objs {
         Ok(vec) => format!("{} objects", vec.len()),
@@ -0,0 +1,154 @@
+use crate::gtfs_reader::RawGtfsReader;
+use crate::{CsvRow, Error, FileMetrics, GtfsReader, TableName};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Smallest rectangle containing a set of coordinates, in degrees
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BoundingBox {
+    /// Westernmost longitude
+    pub min_longitude: f64,
+    /// Southernmost latitude
+    pub min_latitude: f64,
+    /// Easternmost longitude
+    pub max_longitude: f64,
+    /// Northernmost latitude
+    pub max_latitude: f64,
+}
+
+impl BoundingBox {
+    /// True if the coordinates are inside the rectangle or on its border.
+    /// The rectangle crosses the antimeridian when `min_longitude` is greater than `max_longitude`
+    pub fn contains(&self, longitude: f64, latitude: f64) -> bool {
+        let longitude_inside = if self.min_longitude <= self.max_longitude {
+            self.min_longitude <= longitude && longitude <= self.max_longitude
+        } else {
+            self.min_longitude <= longitude || longitude <= self.max_longitude
+        };
+        longitude_inside && self.min_latitude <= latitude && latitude <= self.max_latitude
+    }
+
+    pub(crate) fn extend(bbox: Option<Self>, longitude: f64, latitude: f64) -> Self {
+        match bbox {
+            None => BoundingBox {
+                min_longitude: longitude,
+                min_latitude: latitude,
+                max_longitude: longitude,
+                max_latitude: latitude,
+            },
+            Some(b) => BoundingBox {
+                min_longitude: b.min_longitude.min(longitude),
+                min_latitude: b.min_latitude.min(latitude),
+                max_longitude: b.max_longitude.max(longitude),
+                max_latitude: b.max_latitude.max(latitude),
+            },
+        }
+    }
+}
+
+/// Statistics of a GTFS gathered by [GtfsScanner], without building its objects
+#[derive(Debug, Clone, Default)]
+pub struct FeedStatistics {
+    /// Measures of each file that was read, with its number of rows and its size
+    pub files: HashMap<TableName, FileMetrics>,
+    /// First and last days (both included) covered by the calendar and the added calendar dates,
+    /// like [crate::Gtfs::service_span]. None if there is no calendar at all
+    pub service_span: Option<(NaiveDate, NaiveDate)>,
+    /// Rectangle containing all the stops with coordinates. None if no stop has coordinates
+    pub bounding_box: Option<BoundingBox>,
+}
+
+impl FeedStatistics {
+    /// Number of rows of the file of the table, 0 if it is absent
+    pub fn rows(&self, table: TableName) -> usize {
+        self.files.get(&table).map_or(0, |f| f.rows)
+    }
+
+    /// Number of bytes of the file of the table (once uncompressed), 0 if it is absent
+    pub fn bytes(&self, table: TableName) -> u64 {
+        self.files.get(&table).map_or(0, |f| f.bytes)
+    }
+
+    fn add_record(&mut self, table: TableName, row: &CsvRow) {
+        let date = |field_name| {
+            row.get(field_name)
+                .and_then(|d| NaiveDate::parse_from_str(d.trim(), "%Y%m%d").ok())
+        };
+        match table {
+            TableName::Stops => {
+                let coordinate = |field_name| {
+                    row.get(field_name)
+                        .and_then(|c| c.trim().parse::<f64>().ok())
+                };
+                if let (Some(longitude), Some(latitude)) =
+                    (coordinate("stop_lon"), coordinate("stop_lat"))
+                {
+                    self.bounding_box =
+                        Some(BoundingBox::extend(self.bounding_box, longitude, latitude));
+                }
+            }
+            TableName::Calendar => {
+                for date in [date("start_date"), date("end_date")].iter().flatten() {
+                    self.add_service_date(*date);
+                }
+            }
+            TableName::CalendarDates if row.get("exception_type").map(str::trim) == Some("1") => {
+                if let Some(date) = date("date") {
+                    self.add_service_date(date);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn add_service_date(&mut self, date: NaiveDate) {
+        self.service_span = Some(match self.service_span {
+            None => (date, date),
+            Some((start, end)) => (start.min(date), end.max(date)),
+        });
+    }
+}
+
+/// Reads the files of a GTFS in a single pass to gather [FeedStatistics], without keeping any object in memory
+///
+/// It is built with [GtfsReader::scanner], and the options of the reader like the file names or the delimiter apply.
+/// The values that cannot be parsed are ignored instead of making the file invalid
+///
+/// ```
+/// let stats = gtfs_structures::GtfsScanner::default().scan("fixtures/basic")?;
+/// assert_eq!(6, stats.rows(gtfs_structures::TableName::Stops));
+/// let (start, end) = stats.service_span.unwrap();
+/// assert!(start < end);
+/// # Ok::<(), gtfs_structures::error::Error>(())
+/// ```
+pub struct GtfsScanner {
+    reader: RawGtfsReader,
+}
+
+impl Default for GtfsScanner {
+    fn default() -> Self {
+        GtfsReader::default().scanner()
+    }
+}
+
+impl GtfsReader {
+    /// Builds a [GtfsScanner] with the options of this reader
+    pub fn scanner(self) -> GtfsScanner {
+        GtfsScanner { reader: self.raw() }
+    }
+}
+
+impl GtfsScanner {
+    /// Scans a local zip archive or local directory
+    pub fn scan<P: AsRef<Path>>(&self, path: P) -> Result<FeedStatistics, Error> {
+        let mut stats = FeedStatistics::default();
+        let files = self
+            .reader
+            .visit_all_records(path.as_ref(), &mut |table, row| {
+                stats.add_record(table, row)
+            })?;
+        stats.files = files;
+        Ok(stats)
+    }
+}
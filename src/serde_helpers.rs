@@ -37,38 +37,36 @@ where
 {
     match date {
         None => serializer.serialize_none(),
-        Some(d) => serialize_date(d, serializer),
+        Some(d) => serializer.serialize_some(&d.format("%Y%m%d").to_string()),
     }
 }
 
-pub fn parse_time_impl(h: &str, m: &str, s: &str) -> Result<u32, std::num::ParseIntError> {
-    let hours: u32 = h.parse()?;
-    let minutes: u32 = m.parse()?;
-    let seconds: u32 = s.parse()?;
-    Ok(hours * 3600 + minutes * 60 + seconds)
-}
-
+/// Number of seconds of a time in the `H:MM:SS` format, the hours having any number of digits (e.g. `172:35:42`)
+///
+/// It is parsed twice for each stop time, so the digits are read directly, without splitting the string
 pub fn parse_time(s: &str) -> Result<u32, crate::Error> {
-    let mk_err = || crate::Error::InvalidTime(s.to_owned());
-
-    if s.len() < 7 {
-        Err(mk_err())
-    } else {
-        let mut parts = s.split(':');
-
-        let hour = parts.next().ok_or_else(mk_err)?;
-        let min = parts.next().ok_or_else(mk_err)?;
-        let sec = parts.next().ok_or_else(mk_err)?;
-        if parts.next().is_some() {
-            return Err(mk_err());
-        }
-
-        if min.len() != 2 || sec.len() != 2 {
-            return Err(mk_err());
-        }
+    parse_seconds(s.as_bytes()).ok_or_else(|| crate::Error::InvalidTime(s.to_owned()))
+}
 
-        parse_time_impl(hour, min, sec).map_err(|_| mk_err())
+fn parse_seconds(s: &[u8]) -> Option<u32> {
+    // The minutes and seconds always have two digits, separated by `:`
+    let (hours, min_sec) = s.split_at(s.len().checked_sub(6)?);
+    let [b':', m1, m2, b':', s1, s2] = *min_sec else {
+        return None;
+    };
+    if hours.is_empty() {
+        return None;
     }
+    let hours = hours
+        .iter()
+        .try_fold(0u32, |acc, b| acc.checked_mul(10)?.checked_add(digit(*b)?))?;
+    let minutes = digit(m1)? * 10 + digit(m2)?;
+    let seconds = digit(s1)? * 10 + digit(s2)?;
+    hours.checked_mul(3600)?.checked_add(minutes * 60 + seconds)
+}
+
+fn digit(b: u8) -> Option<u32> {
+    b.is_ascii_digit().then(|| u32::from(b - b'0'))
 }
 
 pub fn deserialize_time<'de, D>(deserializer: D) -> Result<u32, D::Error>
@@ -83,14 +81,15 @@ pub fn serialize_time<S>(time: &u32, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    serializer.serialize_str(
-        format!(
-            "{:02}:{:02}:{:02}",
-            time / 3600,
-            time % 3600 / 60,
-            time % 60
-        )
-        .as_str(),
+    serializer.serialize_str(&format_time(*time))
+}
+
+pub(crate) fn format_time(time: u32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
     )
 }
 
@@ -112,7 +111,7 @@ where
 {
     match time {
         None => serializer.serialize_none(),
-        Some(t) => serialize_time(t, serializer),
+        Some(t) => serializer.serialize_some(&format_time(*t)),
     }
 }
 
@@ -213,6 +212,30 @@ where
     serializer.serialize_u8(u8::from(*value))
 }
 
+#[test]
+fn test_parse_time() {
+    assert_eq!(3600 + 60 + 1, parse_time("01:01:01").unwrap());
+    assert_eq!(8 * 3600 + 5 * 60, parse_time("8:05:00").unwrap());
+    assert_eq!(172 * 3600 + 35 * 60 + 42, parse_time("172:35:42").unwrap());
+    for invalid in [
+        "",
+        "8:05",
+        ":05:00",
+        "8:5:00",
+        "08:05:0",
+        "08h05:00",
+        "+8:05:00",
+        "08:05:00:00",
+        "4294967295:00:00",
+    ] {
+        assert!(
+            parse_time(invalid).is_err(),
+            "{} should be invalid",
+            invalid
+        );
+    }
+}
+
 #[test]
 fn test_serialize_time() {
     #[derive(Serialize, Deserialize)]
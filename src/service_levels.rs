@@ -0,0 +1,149 @@
+use crate::{Error, Gtfs};
+use chrono::{Datelike, NaiveDate, TimeDelta, Weekday};
+use std::ops::Range;
+
+/// Intensity of the service of a route on a day, see [Gtfs::route_service_level]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceLevel {
+    /// At least [ServiceLevelRules::frequent_trips_per_hour] trips in each hour of the day
+    Frequent,
+    /// At least one trip in each hour of the day
+    Standard,
+    /// All the trips leave during the peak hours
+    PeakOnly,
+    /// Some trips, with hours of the day without any
+    Infrequent,
+    /// No trip at all
+    NoService,
+}
+
+/// Hours and thresholds used to find the [ServiceLevel] of a route
+///
+/// The hours are counted from the midnight of the service day, and the number of trips of an hour
+/// is the one of the busiest direction, see [Gtfs::frequency_profile]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceLevelRules {
+    /// Hours that must have trips for a [ServiceLevel::Frequent] or [ServiceLevel::Standard] service
+    pub day_hours: Range<u32>,
+    /// Number of trips in each hour of the day for a [ServiceLevel::Frequent] service, 4 for a trip every 15 minutes
+    pub frequent_trips_per_hour: u32,
+    /// Morning and evening hours of a [ServiceLevel::PeakOnly] service
+    pub peak_hours: [Range<u32>; 2],
+}
+
+impl Default for ServiceLevelRules {
+    fn default() -> Self {
+        Self {
+            day_hours: 7..19,
+            frequent_trips_per_hour: 4,
+            peak_hours: [6..10, 15..19],
+        }
+    }
+}
+
+/// [ServiceLevel] of a route on each type of day of a week, see [Gtfs::route_service_levels]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RouteServiceLevels {
+    /// Level on the weekday from Monday to Friday with the most trips
+    pub weekday: ServiceLevel,
+    /// Level on Saturday
+    pub saturday: ServiceLevel,
+    /// Level on Sunday
+    pub sunday: ServiceLevel,
+}
+
+impl Gtfs {
+    /// Classifies the service of a route on a day, from the number of trips leaving in each hour
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use gtfs_structures::{ServiceLevel, ServiceLevelRules};
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    /// // routeA has a trip at 08:00, 09:00 and 10:00
+    /// let level = gtfs.route_service_level("routeA", monday, &ServiceLevelRules::default())?;
+    /// assert_eq!(ServiceLevel::Infrequent, level);
+    /// // routeB only has a trip at 08:30
+    /// let level = gtfs.route_service_level("routeB", monday, &ServiceLevelRules::default())?;
+    /// assert_eq!(ServiceLevel::PeakOnly, level);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn route_service_level(
+        &self,
+        route_id: &str,
+        date: NaiveDate,
+        rules: &ServiceLevelRules,
+    ) -> Result<ServiceLevel, Error> {
+        let profiles = self.frequency_profile(route_id, date)?;
+        let trips = |hour: u32| {
+            profiles
+                .iter()
+                .filter_map(|p| p.trips_per_hour.get(hour as usize))
+                .max()
+                .copied()
+                .unwrap_or(0)
+        };
+        let hours = profiles
+            .iter()
+            .map(|p| p.trips_per_hour.len() as u32)
+            .max()
+            .unwrap_or(0);
+        let every_hour = |min_trips: u32| rules.day_hours.clone().all(|h| trips(h) >= min_trips);
+        Ok(if (0..hours).all(|h| trips(h) == 0) {
+            ServiceLevel::NoService
+        } else if every_hour(rules.frequent_trips_per_hour.max(1)) {
+            ServiceLevel::Frequent
+        } else if every_hour(1) {
+            ServiceLevel::Standard
+        } else if (0..hours)
+            .filter(|h| trips(*h) > 0)
+            .all(|h| rules.peak_hours.iter().any(|peak| peak.contains(&h)))
+        {
+            ServiceLevel::PeakOnly
+        } else {
+            ServiceLevel::Infrequent
+        })
+    }
+
+    /// Classifies the service of a route on the weekdays, Saturday and Sunday of the week (from Monday) containing `date`,
+    /// see [Gtfs::route_service_level]
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use gtfs_structures::{ServiceLevel, ServiceLevelRules};
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let thursday = NaiveDate::from_ymd_opt(2024, 3, 7).unwrap();
+    /// let levels = gtfs.route_service_levels("routeB", thursday, &ServiceLevelRules::default())?;
+    /// assert_eq!(ServiceLevel::PeakOnly, levels.weekday);
+    /// assert_eq!(ServiceLevel::NoService, levels.sunday);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn route_service_levels(
+        &self,
+        route_id: &str,
+        date: NaiveDate,
+        rules: &ServiceLevelRules,
+    ) -> Result<RouteServiceLevels, Error> {
+        let monday = date - TimeDelta::days(i64::from(date.weekday().num_days_from_monday()));
+        let day =
+            |weekday: Weekday| monday + TimeDelta::days(i64::from(weekday.num_days_from_monday()));
+        let weekday = (0..5)
+            .map(|i| monday + TimeDelta::days(i))
+            .max_by_key(|day| {
+                (
+                    self.trips_for_route(route_id)
+                        .filter(|trip| self.is_service_active(&trip.service_id, *day))
+                        .count(),
+                    // The earliest weekday in case of a tie
+                    std::cmp::Reverse(*day),
+                )
+            })
+            .unwrap_or(monday);
+        Ok(RouteServiceLevels {
+            weekday: self.route_service_level(route_id, weekday, rules)?,
+            saturday: self.route_service_level(route_id, day(Weekday::Sat), rules)?,
+            sunday: self.route_service_level(route_id, day(Weekday::Sun), rules)?,
+        })
+    }
+}
@@ -0,0 +1,163 @@
+use crate::serde_helpers::{format_time, parse_time};
+use crate::trip_times::trip_span;
+use crate::{objects::*, Error, Gtfs};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+const SECONDS_PER_DAY: u32 = 24 * 3600;
+
+/// Time of a [StopTime], in seconds since the midnight of its service day
+///
+/// It goes past 24:00:00 for the trips running after midnight: 25:30:00 is at 01:30 on the day after the service date.
+/// The times are local, regardless of daylight saving time changes
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use gtfs_structures::ServiceTime;
+/// let time: ServiceTime = "25:30:00".parse()?;
+/// let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+/// assert_eq!(NaiveDate::from_ymd_opt(2024, 3, 5).unwrap().and_hms_opt(1, 30, 0).unwrap(), time.on(monday));
+/// assert_eq!(1, time.day_offset());
+/// assert_eq!("25:30:00", time.to_string());
+/// # Ok::<(), gtfs_structures::error::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServiceTime(pub u32);
+
+impl ServiceTime {
+    /// Number of seconds since the midnight of the service day
+    pub fn seconds(self) -> u32 {
+        self.0
+    }
+
+    /// Number of days between the service date and the day of the time, 1 for 25:30:00
+    pub fn day_offset(self) -> u32 {
+        self.0 / SECONDS_PER_DAY
+    }
+
+    /// Wall-clock time of the day, 01:30 for 25:30:00
+    pub fn time_of_day(self) -> NaiveTime {
+        NaiveTime::MIN + TimeDelta::seconds(i64::from(self.0 % SECONDS_PER_DAY))
+    }
+
+    /// Date and time of this time on a service date
+    pub fn on(self, service_date: NaiveDate) -> NaiveDateTime {
+        service_date.and_time(NaiveTime::MIN) + TimeDelta::seconds(i64::from(self.0))
+    }
+
+    /// Time of a date and time counted from the midnight of a service date.
+    /// None if the date and time is before the service date
+    pub fn since(service_date: NaiveDate, datetime: NaiveDateTime) -> Option<Self> {
+        let seconds = (datetime - service_date.and_time(NaiveTime::MIN)).num_seconds();
+        u32::try_from(seconds).ok().map(ServiceTime)
+    }
+}
+
+impl From<u32> for ServiceTime {
+    fn from(seconds: u32) -> Self {
+        ServiceTime(seconds)
+    }
+}
+
+impl From<ServiceTime> for u32 {
+    fn from(time: ServiceTime) -> Self {
+        time.0
+    }
+}
+
+impl FromStr for ServiceTime {
+    type Err = Error;
+
+    /// Parses a time in the `H:MM:SS` format, like the times of `stop_times.txt`
+    fn from_str(s: &str) -> Result<Self, Error> {
+        parse_time(s).map(ServiceTime)
+    }
+}
+
+impl fmt::Display for ServiceTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_time(self.0))
+    }
+}
+
+impl StopTime {
+    /// Date and time of the arrival when the trip runs on `service_date`. None if the stop time has no arrival time
+    pub fn arrival_datetime(&self, service_date: NaiveDate) -> Option<NaiveDateTime> {
+        self.arrival_time.map(|t| ServiceTime(t).on(service_date))
+    }
+
+    /// Date and time of the departure when the trip runs on `service_date`. None if the stop time has no departure time
+    pub fn departure_datetime(&self, service_date: NaiveDate) -> Option<NaiveDateTime> {
+        self.departure_time.map(|t| ServiceTime(t).on(service_date))
+    }
+}
+
+impl Gtfs {
+    /// Lists the service dates whose day can include a wall-clock date and time, with the time counted from each of them,
+    /// from the latest service date
+    ///
+    /// The date of `datetime` is always listed. The previous days are listed as long as the time counted from them
+    /// is not after the latest time of the stop times and [Frequency] of the GTFS
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// // No trip of the GTFS runs after midnight, so only the date itself can include 01:30
+    /// let datetime = NaiveDate::from_ymd_opt(2017, 1, 8).unwrap().and_hms_opt(1, 30, 0).unwrap();
+    /// assert_eq!(1, gtfs.service_dates_at(datetime).len());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn service_dates_at(&self, datetime: NaiveDateTime) -> Vec<(NaiveDate, ServiceTime)> {
+        let latest = self
+            .trips
+            .values()
+            .filter_map(|trip| trip_span(trip).map(|(_, end)| end))
+            .max()
+            .unwrap_or_default();
+        let mut dates = Vec::new();
+        for service_date in datetime.date().iter_days().rev() {
+            match ServiceTime::since(service_date, datetime) {
+                Some(time) if dates.is_empty() || time.0 <= latest => {
+                    dates.push((service_date, time))
+                }
+                _ => break,
+            }
+        }
+        dates
+    }
+
+    /// Finds the service date of the run of a trip that is under way at a wall-clock date and time,
+    /// between its first and its last stop time. None if the trip does not run at that time
+    ///
+    /// A trip with [Frequency] is under way from the departure of its first vehicle to the arrival of its last one.
+    /// With several matching service dates, the latest is returned
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// // trip1 runs every 8 minutes from 05:30:00 to 05:38:00, and lasts 2 hours
+    /// let saturday = NaiveDate::from_ymd_opt(2017, 1, 7).unwrap();
+    /// assert_eq!(Some(saturday), gtfs.trip_service_date("trip1", saturday.and_hms_opt(7, 0, 0).unwrap())?);
+    /// assert_eq!(None, gtfs.trip_service_date("trip1", saturday.and_hms_opt(8, 0, 0).unwrap())?);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn trip_service_date(
+        &self,
+        trip_id: &str,
+        datetime: NaiveDateTime,
+    ) -> Result<Option<NaiveDate>, Error> {
+        let trip = self.get_trip(trip_id)?;
+        let (start, end) = match trip_span(trip) {
+            Some(span) => span,
+            None => return Ok(None),
+        };
+        Ok(self
+            .service_dates_at(datetime)
+            .into_iter()
+            .filter(|(_, time)| start <= time.0 && time.0 <= end)
+            .map(|(service_date, _)| service_date)
+            .find(|service_date| self.is_service_active(&trip.service_id, *service_date)))
+    }
+}
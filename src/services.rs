@@ -0,0 +1,274 @@
+use crate::{objects::*, Error, Gtfs};
+use chrono::{Datelike, NaiveDate};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/// Days when a service differs from its [Calendar], summarizing its [CalendarDate], see [Gtfs::service_exceptions]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceException {
+    /// The service does not run from `start` to `end` (both included), on the days its [Calendar] would run
+    NoService {
+        /// First day without service
+        start: NaiveDate,
+        /// Last day without service
+        end: NaiveDate,
+    },
+    /// The service runs on all the days from `start` to `end` (both included), while its [Calendar] would not
+    ExtraService {
+        /// First day of extra service
+        start: NaiveDate,
+        /// Last day of extra service
+        end: NaiveDate,
+    },
+}
+
+impl fmt::Display for ServiceException {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (statement, start, end) = match self {
+            ServiceException::NoService { start, end } => ("no service", start, end),
+            ServiceException::ExtraService { start, end } => ("extra service", start, end),
+        };
+        if start == end {
+            write!(f, "{statement} on {start}")
+        } else {
+            write!(f, "{statement} from {start} to {end}")
+        }
+    }
+}
+
+impl Gtfs {
+    /// Replaces the [CalendarDate] of the services without [Calendar] by a [Calendar] and fewer exceptions,
+    /// and returns the `service_id` of these services, sorted
+    ///
+    /// The [Calendar] runs from the first to the last date of the service, on the days of the week
+    /// when the service runs more often than not. The dates that differ become [CalendarDate] exceptions.
+    /// A service is only replaced when the [Calendar] with its exceptions takes fewer rows than its dates,
+    /// and it then runs on exactly the same days
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// // service2 is only given by calendar_dates.txt, with a single date
+    /// assert!(gtfs.normalize_calendars().is_empty());
+    /// assert!(!gtfs.calendar.contains_key("service2"));
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn normalize_calendars(&mut self) -> Vec<GtfsId> {
+        let mut normalized = Vec::new();
+        for (service_id, dates) in self.calendar_dates.iter_mut() {
+            if self.calendar.contains_key(service_id) {
+                continue;
+            }
+            let active: BTreeSet<NaiveDate> = dates
+                .iter()
+                .filter(|d| d.exception_type == Exception::Added)
+                .map(|d| d.date)
+                .collect();
+            let (start_date, end_date) = match (active.first(), active.last()) {
+                (Some(first), Some(last)) => (*first, *last),
+                _ => continue,
+            };
+            let days = || start_date.iter_days().take_while(|day| *day <= end_date);
+
+            let mut weekdays = [(0, 0); 7];
+            for day in days() {
+                let (total, running) = &mut weekdays[day.weekday().num_days_from_monday() as usize];
+                *total += 1;
+                if active.contains(&day) {
+                    *running += 1;
+                }
+            }
+            let runs = |i: usize| weekdays[i].1 * 2 > weekdays[i].0;
+            let calendar = Calendar {
+                id: service_id.clone(),
+                monday: runs(0),
+                tuesday: runs(1),
+                wednesday: runs(2),
+                thursday: runs(3),
+                friday: runs(4),
+                saturday: runs(5),
+                sunday: runs(6),
+                start_date,
+                end_date,
+            };
+            let exceptions: Vec<CalendarDate> = days()
+                .filter_map(|day| {
+                    let running = active.contains(&day);
+                    let exception_type = match (running, calendar.valid_weekday(day)) {
+                        (true, false) => Exception::Added,
+                        (false, true) => Exception::Deleted,
+                        _ => return None,
+                    };
+                    Some(CalendarDate {
+                        service_id: service_id.clone(),
+                        date: day,
+                        exception_type,
+                    })
+                })
+                .collect();
+            if 1 + exceptions.len() >= active.len() {
+                continue;
+            }
+            *dates = exceptions;
+            normalized.push(calendar);
+        }
+
+        normalized.sort_by(|a, b| a.id.cmp(&b.id));
+        let service_ids = normalized.iter().map(|c| c.id.clone()).collect();
+        for calendar in normalized {
+            if self.calendar_dates[&calendar.id].is_empty() {
+                self.calendar_dates.remove(&calendar.id);
+            }
+            self.calendar.insert(calendar.id.clone(), calendar);
+        }
+        service_ids
+    }
+}
+
+impl Gtfs {
+    /// Merges the services running on exactly the same days, and returns the `service_id` of the removed services
+    /// with the `service_id` that replaces them, sorted
+    ///
+    /// The service with the smallest `service_id` is kept. The trips of the other services are moved to it,
+    /// and their [Calendar] and [CalendarDate] are removed
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let mut calendar = gtfs.calendar["service1"].clone();
+    /// calendar.id = "service2".into();
+    /// gtfs.calendar.insert(calendar.id.clone(), calendar);
+    /// gtfs.trips.get_mut("tripB1").unwrap().service_id = "service2".into();
+    ///
+    /// let merged = gtfs.merge_equal_services();
+    /// assert_eq!(vec![("service2".into(), "service1".into())], merged);
+    /// assert_eq!("service1", gtfs.trips["tripB1"].service_id);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn merge_equal_services(&mut self) -> Vec<(GtfsId, GtfsId)> {
+        let mut service_ids: Vec<&GtfsId> = self
+            .calendar
+            .keys()
+            .chain(self.calendar_dates.keys())
+            .collect();
+        service_ids.sort();
+        service_ids.dedup();
+        let mut kept: HashMap<BTreeSet<NaiveDate>, &GtfsId> = HashMap::new();
+        let mut merged = Vec::new();
+        for service_id in service_ids {
+            match kept.entry(self.service_dates(service_id).collect()) {
+                Entry::Occupied(entry) => merged.push((service_id.clone(), (*entry.get()).clone())),
+                Entry::Vacant(entry) => {
+                    entry.insert(service_id);
+                }
+            }
+        }
+
+        let replacements: HashMap<&str, &GtfsId> = merged
+            .iter()
+            .map(|(removed, kept)| (removed.as_str(), kept))
+            .collect();
+        for trip in self.trips.values_mut() {
+            if let Some(kept) = replacements.get(&*trip.service_id) {
+                trip.service_id = (*kept).clone();
+            }
+        }
+        for (removed, _) in &merged {
+            self.calendar.remove(removed);
+            self.calendar_dates.remove(removed);
+        }
+        merged
+    }
+
+    /// Summarizes the [CalendarDate] of a service into the ranges of days when it differs from its [Calendar], sorted by day
+    ///
+    /// The removed days are grouped when the service does not run at all in between: the removal of all the weekdays
+    /// of two weeks gives a single [ServiceException::NoService] over the two weeks. The added days are grouped when they follow each other.
+    /// The exceptions that do not change anything, like removing a day the [Calendar] does not run, are ignored.
+    /// A service without [Calendar] only has [ServiceException::ExtraService]
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use gtfs_structures::ServiceException;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let exceptions = gtfs.service_exceptions("service2")?;
+    /// assert_eq!(1, exceptions.len());
+    /// assert_eq!("extra service on 2017-01-01", exceptions[0].to_string());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn service_exceptions(&self, service_id: &str) -> Result<Vec<ServiceException>, Error> {
+        let calendar = self.calendar.get(service_id);
+        let dates = self.calendar_dates.get(service_id);
+        if calendar.is_none() && dates.is_none() {
+            return Err(Error::ReferenceError(service_id.to_owned()));
+        }
+        let scheduled = |day: NaiveDate| {
+            calendar
+                .is_some_and(|c| c.start_date <= day && day <= c.end_date && c.valid_weekday(day))
+        };
+        let changes: BTreeMap<NaiveDate, bool> = dates
+            .into_iter()
+            .flatten()
+            .filter_map(|d| match d.exception_type {
+                Exception::Added if !scheduled(d.date) => Some((d.date, true)),
+                Exception::Deleted if scheduled(d.date) => Some((d.date, false)),
+                _ => None,
+            })
+            .collect();
+
+        let mut exceptions = Vec::new();
+        let mut current: Option<(NaiveDate, NaiveDate, bool)> = None;
+        for (date, added) in changes {
+            if let Some((start, end, current_added)) = current {
+                let extends = current_added == added
+                    && end
+                        .iter_days()
+                        .skip(1)
+                        .take_while(|day| *day < date)
+                        .all(|day| !added && !scheduled(day));
+                if extends {
+                    current = Some((start, date, added));
+                    continue;
+                }
+                exceptions.push(exception(start, end, current_added));
+            }
+            current = Some((date, date, added));
+        }
+        exceptions.extend(current.map(|(start, end, added)| exception(start, end, added)));
+        Ok(exceptions)
+    }
+
+    /// Iterates over the days a service runs, sorted, from its [Calendar::dates] with its [CalendarDate] applied.
+    /// Empty if the service does not exist
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// // 2017-01-01 is removed from service1
+    /// let dates: Vec<_> = gtfs.service_dates("service1").map(|d| d.to_string()).collect();
+    /// assert_eq!(vec!["2017-01-07", "2017-01-08", "2017-01-14", "2017-01-15"], dates);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn service_dates(&self, service_id: &str) -> impl Iterator<Item = NaiveDate> {
+        let mut dates: BTreeSet<NaiveDate> = self
+            .calendar
+            .get(service_id)
+            .into_iter()
+            .flat_map(Calendar::dates)
+            .collect();
+        for date in self.calendar_dates.get(service_id).into_iter().flatten() {
+            match date.exception_type {
+                Exception::Added => dates.insert(date.date),
+                Exception::Deleted => dates.remove(&date.date),
+                Exception::Unknown(_) => false,
+            };
+        }
+        dates.into_iter()
+    }
+}
+
+fn exception(start: NaiveDate, end: NaiveDate, added: bool) -> ServiceException {
+    if added {
+        ServiceException::ExtraService { start, end }
+    } else {
+        ServiceException::NoService { start, end }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::geometry::EARTH_RADIUS;
+use crate::{objects::*, BoundingBox, Gtfs, IdMap};
+use rstar::primitives::GeomWithData;
+use rstar::{RTree, AABB};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Position of a stop on a sphere of radius 1, with its `stop_id`
+///
+/// The straight distance between two positions grows with the distance on the surface of the Earth,
+/// so the nearest positions are the nearest stops, at any latitude
+pub type StopPosition = GeomWithData<[f64; 3], GtfsId>;
+
+fn position(latitude: f64, longitude: f64) -> [f64; 3] {
+    let (latitude, longitude) = (latitude.to_radians(), longitude.to_radians());
+    [
+        latitude.cos() * longitude.cos(),
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+    ]
+}
+
+/// Smallest and largest values of the cosine over the angles in `[min, max]`, in radians
+fn cos_range(min: f64, max: f64) -> (f64, f64) {
+    // The cosine is 1 at the multiples of 2π, and -1 at π plus a multiple of 2π
+    let has_angle =
+        |offset: f64| ((min - offset) / (2. * PI)).ceil() <= ((max - offset) / (2. * PI)).floor();
+    let (low, high) = (min.cos().min(max.cos()), min.cos().max(max.cos()));
+    (
+        if has_angle(PI) { -1. } else { low },
+        if has_angle(0.) { 1. } else { high },
+    )
+}
+
+/// Smallest and largest products of a value in each range
+fn product_range((a_min, a_max): (f64, f64), (b_min, b_max): (f64, f64)) -> (f64, f64) {
+    let products = [a_min * b_min, a_min * b_max, a_max * b_min, a_max * b_max];
+    (
+        products.iter().copied().fold(f64::INFINITY, f64::min),
+        products.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+    )
+}
+
+/// Box containing the positions of all the coordinates of the rectangle. It can also contain positions outside of it
+pub(crate) fn envelope(bbox: &BoundingBox) -> AABB<[f64; 3]> {
+    let (min_latitude, max_latitude) = (
+        bbox.min_latitude.to_radians(),
+        bbox.max_latitude.to_radians(),
+    );
+    let (mut min_longitude, max_longitude) = (
+        bbox.min_longitude.to_radians(),
+        bbox.max_longitude.to_radians(),
+    );
+    if min_longitude > max_longitude {
+        min_longitude -= 2. * PI;
+    }
+    let latitude_cos = cos_range(min_latitude, max_latitude);
+    let longitude_cos = cos_range(min_longitude, max_longitude);
+    // sin(x) = cos(x - π/2)
+    let longitude_sin = cos_range(min_longitude - PI / 2., max_longitude - PI / 2.);
+    let (x_min, x_max) = product_range(latitude_cos, longitude_cos);
+    let (y_min, y_max) = product_range(latitude_cos, longitude_sin);
+    AABB::from_corners(
+        [x_min, y_min, min_latitude.sin()],
+        [x_max, y_max, max_latitude.sin()],
+    )
+}
+
+/// The stops whose position is at most `meters` away from the coordinates, and maybe a bit farther
+pub(crate) fn stops_around(
+    gtfs: &Gtfs,
+    latitude: f64,
+    longitude: f64,
+    meters: f64,
+) -> impl Iterator<Item = &Arc<Stop>> {
+    // Straight distance between two positions separated by this distance along the surface, with a margin for the rounding
+    let angle = (meters / EARTH_RADIUS).min(PI);
+    let chord = 2. * (angle / 2.).sin() * (1. + 1e-9) + 1e-12;
+    gtfs.stop_tree
+        .locate_within_distance(position(latitude, longitude), chord * chord)
+        .filter_map(move |position| gtfs.stops.get(&position.data))
+}
+
+/// Indexes the stops with coordinates by their position
+pub(crate) fn to_stop_tree(stops: &IdMap<Arc<Stop>>) -> RTree<StopPosition> {
+    RTree::bulk_load(
+        stops
+            .values()
+            .filter_map(|stop| match (stop.latitude, stop.longitude) {
+                (Some(latitude), Some(longitude)) => Some(StopPosition::new(
+                    position(latitude, longitude),
+                    stop.id.clone(),
+                )),
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
+impl Gtfs {
+    /// Finds the `k` stops nearest to a position, with their distance in meters, from the nearest to the farthest
+    ///
+    /// Requires the feature `spatial`. The stops without coordinates are ignored.
+    /// The distance is along the surface of the Earth, considered as a sphere
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let nearest = gtfs.nearest_stops(48.869, 2.369, 2);
+    /// assert_eq!("stop2", nearest[0].0.id);
+    /// assert_eq!("stop1", nearest[1].0.id);
+    /// assert!(nearest[0].1 < 150.);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn nearest_stops(&self, latitude: f64, longitude: f64, k: usize) -> Vec<(&Arc<Stop>, f64)> {
+        let point = position(latitude, longitude);
+        self.stop_tree
+            .nearest_neighbor_iter_with_distance_2(&point)
+            .filter_map(|(position, distance_2)| {
+                let chord = distance_2.sqrt().min(2.);
+                let distance = 2. * EARTH_RADIUS * (chord / 2.).asin();
+                self.stops.get(&position.data).map(|stop| (stop, distance))
+            })
+            .take(k)
+            .collect()
+    }
+}
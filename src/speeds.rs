@@ -0,0 +1,182 @@
+use crate::geometry::{point_lengths, project, stop_positions};
+use crate::{haversine, objects::*, Error, Gtfs};
+use std::collections::HashMap;
+
+/// Why the speed between two stops is implausible, see [Gtfs::implausible_speeds]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpeedIssue {
+    /// Faster than [SpeedRules::max_speed] for the type of the route
+    TooFast,
+    /// Slower than [SpeedRules::min_speed]
+    TooSlow,
+}
+
+/// Speeds, in meters per second, used to find the implausible speeds between stops
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeedRules {
+    /// Maximum speed for each type of route
+    pub max_speeds: HashMap<RouteType, f64>,
+    /// Maximum speed for the types of route not in `max_speeds`
+    pub default_max_speed: f64,
+    /// Minimum speed between two stops at a distance, whatever the type of route
+    pub min_speed: f64,
+}
+
+impl Default for SpeedRules {
+    fn default() -> Self {
+        let max_speeds = [
+            (RouteType::Tramway, 100.),
+            (RouteType::Subway, 150.),
+            (RouteType::Rail, 500.),
+            (RouteType::Bus, 150.),
+            (RouteType::Ferry, 80.),
+            (RouteType::CableCar, 30.),
+            (RouteType::Gondola, 50.),
+            (RouteType::Funicular, 50.),
+            (RouteType::Coach, 150.),
+            (RouteType::Air, 1000.),
+            (RouteType::Taxi, 150.),
+        ]
+        .iter()
+        .map(|(route_type, km_h)| (*route_type, km_h / 3.6))
+        .collect();
+        Self {
+            max_speeds,
+            default_max_speed: 200. / 3.6,
+            min_speed: 1. / 3.6,
+        }
+    }
+}
+
+impl SpeedRules {
+    /// Maximum speed for the type of route
+    pub fn max_speed(&self, route_type: RouteType) -> f64 {
+        self.max_speeds
+            .get(&route_type)
+            .copied()
+            .unwrap_or(self.default_max_speed)
+    }
+}
+
+/// Travel between two stop times of a trip at an implausible speed, see [Gtfs::implausible_speeds]
+#[derive(Debug, Clone, Copy)]
+pub struct ImplausibleSpeed<'a> {
+    /// The trip
+    pub trip: &'a Trip,
+    /// Index in [Trip::stop_times] of the stop time the travel starts from
+    pub from_index: usize,
+    /// Index in [Trip::stop_times] of the stop time the travel ends at
+    pub to_index: usize,
+    /// Distance in meters between both stops
+    pub distance: f64,
+    /// Seconds between the departure and the arrival
+    pub duration: u32,
+    /// Speed in meters per second, counting a duration of 0 as one minute
+    pub speed: f64,
+    /// Whether it is too fast or too slow
+    pub issue: SpeedIssue,
+}
+
+impl Gtfs {
+    /// Lists the travels between consecutive stop times with a time whose speed is too high for the type of route, or absurdly low
+    ///
+    /// The distance follows the shape of the trip if it has one, or else goes straight between the stops.
+    /// The times are often rounded to the minute, so a duration of 0 seconds is counted as one minute.
+    /// The stops without coordinates are ignored. The travels are sorted by `trip_id`, then by their order in the trip
+    ///
+    /// ```
+    /// use gtfs_structures::{SpeedIssue, SpeedRules};
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// assert!(gtfs.implausible_speeds(&SpeedRules::default())?.is_empty());
+    /// // The bus now takes four hours to drive the 1.3 km from platform1 to stop1
+    /// gtfs.trips.get_mut("tripA1").unwrap().stop_times[1].arrival_time = Some(12 * 3600);
+    /// let speeds = gtfs.implausible_speeds(&SpeedRules::default())?;
+    /// assert_eq!(SpeedIssue::TooSlow, speeds[0].issue);
+    /// assert_eq!((0, 1), (speeds[0].from_index, speeds[0].to_index));
+    /// assert_eq!(4 * 3600, speeds[0].duration);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn implausible_speeds(
+        &self,
+        rules: &SpeedRules,
+    ) -> Result<Vec<ImplausibleSpeed<'_>>, Error> {
+        let mut trips: Vec<&Trip> = self.trips.values().collect();
+        trips.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut result = Vec::new();
+        for trip in trips {
+            let max_speed = match self.routes.get(trip.route_id.as_str()) {
+                Some(route) => rules.max_speed(route.route_type),
+                None => rules.default_max_speed,
+            };
+            let distances = self.stop_time_distances(trip)?;
+            let mut previous: Option<(usize, u32, f64)> = None;
+            for (index, stop_time) in trip.stop_times.iter().enumerate() {
+                let distance = match distances[index] {
+                    Some(distance) => distance,
+                    None => continue,
+                };
+                if let (Some((from_index, departure, from_distance)), Some(arrival)) = (
+                    previous,
+                    stop_time.arrival_time.or(stop_time.departure_time),
+                ) {
+                    let duration = arrival.saturating_sub(departure);
+                    let distance = distance - from_distance;
+                    let speed = distance / f64::from(duration.max(60));
+                    let issue = if speed > max_speed {
+                        Some(SpeedIssue::TooFast)
+                    } else if distance > 0. && speed < rules.min_speed {
+                        Some(SpeedIssue::TooSlow)
+                    } else {
+                        None
+                    };
+                    if let Some(issue) = issue {
+                        result.push(ImplausibleSpeed {
+                            trip,
+                            from_index,
+                            to_index: index,
+                            distance,
+                            duration,
+                            speed,
+                            issue,
+                        });
+                    }
+                }
+                if let Some(departure) = stop_time.departure_time.or(stop_time.arrival_time) {
+                    previous = Some((index, departure, distance));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Distance in meters of each stop time from the first one, along the shape of the trip if it has one
+    fn stop_time_distances(&self, trip: &Trip) -> Result<Vec<Option<f64>>, Error> {
+        if let Some(points) = trip.shape_id.as_deref().and_then(|id| {
+            self.lazy_shapes
+                .get(id)
+                .or_else(|| self.shapes.get(id).map(Ok))
+        }) {
+            let points = points?;
+            if points.len() >= 2 {
+                return Ok(
+                    project(points, &point_lengths(points), stop_positions(trip))
+                        .into_iter()
+                        .map(|p| p.map(|p| p.distance))
+                        .collect(),
+                );
+            }
+        }
+        let mut length = 0.;
+        let mut last: Option<(f64, f64)> = None;
+        Ok(stop_positions(trip)
+            .map(|position| {
+                let (latitude, longitude) = position?;
+                if let Some((last_latitude, last_longitude)) = last {
+                    length += haversine(last_latitude, last_longitude, latitude, longitude);
+                }
+                last = Some((latitude, longitude));
+                Some(length)
+            })
+            .collect())
+    }
+}
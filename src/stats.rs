@@ -0,0 +1,107 @@
+use crate::serde_helpers::serialize_option_date;
+use crate::{objects::*, BoundingBox, Gtfs};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+/// Numbers describing a [Gtfs], that can be serialized for monitoring, see [Gtfs::stats]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FeedStats {
+    /// Number of [Agency]
+    pub agencies: usize,
+    /// Number of [Stop], including the stations, entrances and nodes
+    pub stops: usize,
+    /// Number of [Route]
+    pub routes: usize,
+    /// Number of [Trip]
+    pub trips: usize,
+    /// Number of [StopTime] of all the trips
+    pub stop_times: usize,
+    /// Number of [Frequency] of all the trips
+    pub frequencies: usize,
+    /// Number of [Calendar]
+    pub calendars: usize,
+    /// Number of [CalendarDate] of all the services
+    pub calendar_dates: usize,
+    /// Number of shapes, including the ones read lazily
+    pub shapes: usize,
+    /// Number of [FareAttribute]
+    pub fare_attributes: usize,
+    /// Number of [FareRule]
+    pub fare_rules: usize,
+    /// Number of [StopTransfer] of all the stops
+    pub transfers: usize,
+    /// Number of [Pathway] of all the stops
+    pub pathways: usize,
+    /// Number of [FeedInfo]
+    pub feed_info: usize,
+    /// First day covered by the services, see [Gtfs::service_span]
+    #[serde(serialize_with = "serialize_option_date")]
+    pub start_date: Option<NaiveDate>,
+    /// Last day covered by the services, see [Gtfs::service_span]
+    #[serde(serialize_with = "serialize_option_date")]
+    pub end_date: Option<NaiveDate>,
+    /// Rectangle containing all the stops with coordinates. None if no stop has coordinates
+    pub bounding_box: Option<BoundingBox>,
+    /// Number of routes of each [RouteType]
+    pub route_types: HashMap<RouteType, usize>,
+    /// Languages of the agencies and of the feed info, sorted and without duplicates
+    pub languages: Vec<String>,
+}
+
+impl Gtfs {
+    /// Gathers the numbers describing the GTFS, like the ones printed by [Gtfs::print_stats]
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let stats = gtfs.stats();
+    /// assert_eq!(6, stats.stops);
+    /// assert_eq!(vec!["fr"], stats.languages);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn stats(&self) -> FeedStats {
+        let service_span = self.service_span();
+        let mut route_types = HashMap::new();
+        for route in self.routes.values() {
+            *route_types.entry(route.route_type).or_insert(0) += 1;
+        }
+        let mut languages: Vec<String> = self
+            .agencies
+            .iter()
+            .filter_map(|agency| agency.lang.clone())
+            .chain(self.feed_info.iter().flat_map(|info| {
+                std::iter::once(info.lang.clone()).chain(info.default_lang.clone())
+            }))
+            .filter(|lang| !lang.is_empty())
+            .collect();
+        languages.sort();
+        languages.dedup();
+
+        FeedStats {
+            agencies: self.agencies.len(),
+            stops: self.stops.len(),
+            routes: self.routes.len(),
+            trips: self.trips.len(),
+            stop_times: self.trips.values().map(|t| t.stop_times.len()).sum(),
+            frequencies: self.trips.values().map(|t| t.frequencies.len()).sum(),
+            calendars: self.calendar.len(),
+            calendar_dates: self.calendar_dates.values().map(Vec::len).sum(),
+            shapes: self.shapes.len() + self.lazy_shapes.len(),
+            fare_attributes: self.fare_attributes.len(),
+            fare_rules: self.fare_rules.values().map(Vec::len).sum(),
+            transfers: self.stops.values().map(|s| s.transfers.len()).sum(),
+            pathways: self.stops.values().map(|s| s.pathways.len()).sum(),
+            feed_info: self.feed_info.len(),
+            start_date: service_span.map(|(start, _)| start),
+            end_date: service_span.map(|(_, end)| end),
+            bounding_box: self
+                .stops
+                .values()
+                .filter_map(|stop| Some((stop.longitude?, stop.latitude?)))
+                .fold(None, |bbox, (longitude, latitude)| {
+                    Some(BoundingBox::extend(bbox, longitude, latitude))
+                }),
+            route_types,
+            languages,
+        }
+    }
+}
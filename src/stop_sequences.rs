@@ -0,0 +1,129 @@
+use crate::decreasing_times::arrivals_before_previous_departure;
+use crate::{objects::*, Gtfs};
+
+/// Stop times of a trip whose `stop_sequence` can not give their order, see [Gtfs::stop_sequence_issues]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopSequenceIssue<'a> {
+    /// Several stop times of the trip have this `stop_sequence`, their order is the one of the file
+    Repeated {
+        /// `trip_id` of the trip
+        trip_id: &'a str,
+        /// The repeated `stop_sequence`
+        stop_sequence: u16,
+    },
+    /// Once sorted by `stop_sequence`, the stop time is earlier than the previous one:
+    /// the `stop_sequence` decrease along the trip
+    TimeDecreasing {
+        /// `trip_id` of the trip
+        trip_id: &'a str,
+        /// `stop_sequence` of the stop time earlier than the previous one
+        stop_sequence: u16,
+    },
+}
+
+impl Gtfs {
+    /// Lists the trips whose stop times can not be ordered by their `stop_sequence`, sorted by `trip_id`
+    ///
+    /// The stop times are sorted by `stop_sequence` when the GTFS is read, which silently gives a wrong order
+    /// when the `stop_sequence` repeat, or decrease while the times increase. See [Gtfs::repair_stop_sequences]
+    ///
+    /// ```
+    /// use gtfs_structures::StopSequenceIssue;
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// assert!(gtfs.stop_sequence_issues().is_empty());
+    /// gtfs.trips.get_mut("trip1").unwrap().stop_times[1].stop_sequence = 0;
+    /// assert_eq!(
+    ///     vec![StopSequenceIssue::Repeated { trip_id: "trip1", stop_sequence: 0 }],
+    ///     gtfs.stop_sequence_issues()
+    /// );
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn stop_sequence_issues(&self) -> Vec<StopSequenceIssue<'_>> {
+        let mut trips: Vec<&Trip> = self.trips.values().collect();
+        trips.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut issues = Vec::new();
+        for trip in trips {
+            let trip_id = trip.id.as_str();
+            let mut early_arrivals =
+                arrivals_before_previous_departure(&trip.stop_times).peekable();
+            for (i, stop_time) in trip.stop_times.iter().enumerate() {
+                if i > 0 && trip.stop_times[i - 1].stop_sequence == stop_time.stop_sequence {
+                    let issue = StopSequenceIssue::Repeated {
+                        trip_id,
+                        stop_sequence: stop_time.stop_sequence,
+                    };
+                    if issues.last() != Some(&issue) {
+                        issues.push(issue);
+                    }
+                }
+                if early_arrivals
+                    .next_if(|(early, _, _)| *early == i)
+                    .is_some()
+                {
+                    issues.push(StopSequenceIssue::TimeDecreasing {
+                        trip_id,
+                        stop_sequence: stop_time.stop_sequence,
+                    });
+                }
+            }
+        }
+        issues
+    }
+
+    /// Reorders the stop times of the trips listed by [Gtfs::stop_sequence_issues], and returns the number of repaired trips
+    ///
+    /// When all the stop times of a trip have a time, they are sorted by time (the stop times at the same time keep their order).
+    /// The `stop_sequence` of the trip are then renumbered from 1 if they are not strictly increasing
+    ///
+    /// ```
+    /// let mut gtfs = gtfs_structures::Gtfs::from_path("fixtures/basic")?;
+    /// let trip = gtfs.trips.get_mut("trip1").unwrap();
+    /// // The first stop (14:00:00) is given a larger stop_sequence than the second one (15:00:00)
+    /// trip.stop_times[0].stop_sequence = 1;
+    /// trip.stop_times[1].stop_sequence = 0;
+    /// trip.stop_times.sort_by_key(|st| st.stop_sequence);
+    /// assert_eq!(1, gtfs.repair_stop_sequences());
+    /// let trip = gtfs.get_trip("trip1")?;
+    /// assert_eq!("stop2", trip.stop_times[0].stop.id);
+    /// assert_eq!(vec![1, 2, 3], trip.stop_times.iter().map(|st| st.stop_sequence).collect::<Vec<_>>());
+    /// assert!(gtfs.stop_sequence_issues().is_empty());
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn repair_stop_sequences(&mut self) -> usize {
+        let trip_ids: Vec<GtfsId> = self
+            .stop_sequence_issues()
+            .iter()
+            .map(|issue| match issue {
+                StopSequenceIssue::Repeated { trip_id, .. }
+                | StopSequenceIssue::TimeDecreasing { trip_id, .. } => (*trip_id).into(),
+            })
+            .collect();
+        let mut repaired = 0;
+        for (i, trip_id) in trip_ids.iter().enumerate() {
+            if i > 0 && trip_ids[i - 1] == *trip_id {
+                continue;
+            }
+            let trip = match self.trips.get_mut(trip_id) {
+                Some(trip) => trip,
+                None => continue,
+            };
+            let stop_times = &mut trip.stop_times;
+            if stop_times
+                .iter()
+                .all(|st| st.arrival_time.or(st.departure_time).is_some())
+            {
+                stop_times.sort_by_key(|st| st.arrival_time.or(st.departure_time));
+            }
+            if stop_times
+                .windows(2)
+                .any(|w| w[0].stop_sequence >= w[1].stop_sequence)
+            {
+                for (sequence, stop_time) in stop_times.iter_mut().enumerate() {
+                    stop_time.stop_sequence = (sequence + 1) as u16;
+                }
+            }
+            repaired += 1;
+        }
+        repaired
+    }
+}
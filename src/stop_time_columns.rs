@@ -0,0 +1,196 @@
+use crate::{objects::*, CompactGtfs};
+use std::ops::Range;
+
+/// Stop times of a [CompactGtfs] stored by column: one vector per field instead of one struct per stop time
+///
+/// The stop times of each trip are contiguous and sorted by `stop_sequence`, the trips are sorted by `trip_id`.
+/// Scanning a single field, like all the departure times, only reads that field from memory
+///
+/// ```
+/// let gtfs = gtfs_structures::CompactGtfs::from_path("fixtures/basic")?;
+/// let columns = gtfs.stop_time_columns();
+/// let last_departure = columns.departure_times.iter().flatten().max();
+/// assert_eq!(Some(&(16 * 3600)), last_departure);
+/// let range = columns.trip_range("trip1").unwrap();
+/// assert_eq!("stop2", gtfs.stops[columns.stop_indices[range.start] as usize].id);
+/// # Ok::<(), gtfs_structures::error::Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StopTimeColumns {
+    /// `trip_id` of each trip, sorted
+    trip_ids: Vec<GtfsId>,
+    /// Position of the first stop time of each trip, followed by the number of stop times
+    trip_starts: Vec<usize>,
+    /// Arrival time of each stop time, see [CompactStopTime::arrival_time]
+    pub arrival_times: Vec<Option<u32>>,
+    /// Departure time of each stop time, see [CompactStopTime::departure_time]
+    pub departure_times: Vec<Option<u32>>,
+    /// Index in [CompactGtfs::stops] of the [Stop] of each stop time
+    pub stop_indices: Vec<u32>,
+    /// `stop_sequence` of each stop time
+    pub stop_sequences: Vec<u16>,
+    /// Pickup method of each stop time
+    pub pickup_types: Vec<PickupDropOffType>,
+    /// Drop off method of each stop time
+    pub drop_off_types: Vec<PickupDropOffType>,
+    /// Continuous pickup of each stop time
+    pub continuous_pickups: Vec<ContinuousPickupDropOff>,
+    /// Continuous drop off of each stop time
+    pub continuous_drop_offs: Vec<ContinuousPickupDropOff>,
+    /// Timepoint of each stop time
+    pub timepoints: Vec<TimepointType>,
+    /// Distance traveled along the shape at each stop time
+    pub shape_dist_traveled: Vec<Option<f32>>,
+    /// Headsign of each stop time
+    pub stop_headsigns: Vec<Option<Box<str>>>,
+}
+
+impl StopTimeColumns {
+    fn with_capacity(trips: usize, stop_times: usize) -> Self {
+        let mut trip_starts = Vec::with_capacity(trips + 1);
+        trip_starts.push(0);
+        StopTimeColumns {
+            trip_ids: Vec::with_capacity(trips),
+            trip_starts,
+            arrival_times: Vec::with_capacity(stop_times),
+            departure_times: Vec::with_capacity(stop_times),
+            stop_indices: Vec::with_capacity(stop_times),
+            stop_sequences: Vec::with_capacity(stop_times),
+            pickup_types: Vec::with_capacity(stop_times),
+            drop_off_types: Vec::with_capacity(stop_times),
+            continuous_pickups: Vec::with_capacity(stop_times),
+            continuous_drop_offs: Vec::with_capacity(stop_times),
+            timepoints: Vec::with_capacity(stop_times),
+            shape_dist_traveled: Vec::with_capacity(stop_times),
+            stop_headsigns: Vec::with_capacity(stop_times),
+        }
+    }
+
+    /// Builds the columns from the stop times of each trip
+    fn from_trips<'a, I>(trips: impl Iterator<Item = (&'a GtfsId, I)>) -> Self
+    where
+        I: ExactSizeIterator<Item = CompactStopTime>,
+    {
+        let mut trips: Vec<_> = trips.collect();
+        trips.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        let stop_times = trips.iter().map(|(_, stop_times)| stop_times.len()).sum();
+        let mut columns = Self::with_capacity(trips.len(), stop_times);
+        for (id, stop_times) in trips {
+            columns.trip_ids.push(id.clone());
+            for stop_time in stop_times {
+                columns.push(stop_time);
+            }
+            columns.trip_starts.push(columns.len());
+        }
+        columns
+    }
+
+    fn push(&mut self, stop_time: CompactStopTime) {
+        self.arrival_times.push(stop_time.arrival_time);
+        self.departure_times.push(stop_time.departure_time);
+        self.stop_indices.push(stop_time.stop_index);
+        self.stop_sequences.push(stop_time.stop_sequence);
+        self.pickup_types.push(stop_time.pickup_type);
+        self.drop_off_types.push(stop_time.drop_off_type);
+        self.continuous_pickups.push(stop_time.continuous_pickup);
+        self.continuous_drop_offs
+            .push(stop_time.continuous_drop_off);
+        self.timepoints.push(stop_time.timepoint);
+        self.shape_dist_traveled.push(stop_time.shape_dist_traveled);
+        self.stop_headsigns.push(stop_time.stop_headsign);
+    }
+
+    /// Number of stop times
+    pub fn len(&self) -> usize {
+        self.stop_sequences.len()
+    }
+
+    /// True if there is no stop time
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Positions of the stop times of a trip in the columns. None if the trip does not exist
+    pub fn trip_range(&self, trip_id: &str) -> Option<Range<usize>> {
+        let index = self
+            .trip_ids
+            .binary_search_by(|id| (**id).cmp(trip_id))
+            .ok()?;
+        Some(self.trip_starts[index]..self.trip_starts[index + 1])
+    }
+
+    /// All the trips, sorted by `trip_id`, with the positions of their stop times
+    pub fn trips(&self) -> impl Iterator<Item = (&GtfsId, Range<usize>)> {
+        self.trip_ids
+            .iter()
+            .zip(self.trip_starts.windows(2))
+            .map(|(id, starts)| (id, starts[0]..starts[1]))
+    }
+
+    /// `trip_id` of the stop time at this position
+    ///
+    /// Panics if the position is out of bounds
+    pub fn trip_id(&self, position: usize) -> &GtfsId {
+        assert!(position < self.len(), "stop time out of bounds");
+        &self.trip_ids[self.trip_starts.partition_point(|start| *start <= position) - 1]
+    }
+
+    /// Rebuilds the [CompactStopTime] at this position. None if the position is out of bounds
+    pub fn get(&self, position: usize) -> Option<CompactStopTime> {
+        if position >= self.len() {
+            return None;
+        }
+        Some(CompactStopTime {
+            arrival_time: self.arrival_times[position],
+            stop_index: self.stop_indices[position],
+            departure_time: self.departure_times[position],
+            pickup_type: self.pickup_types[position],
+            drop_off_type: self.drop_off_types[position],
+            stop_sequence: self.stop_sequences[position],
+            stop_headsign: self.stop_headsigns[position].clone(),
+            continuous_pickup: self.continuous_pickups[position],
+            continuous_drop_off: self.continuous_drop_offs[position],
+            shape_dist_traveled: self.shape_dist_traveled[position],
+            timepoint: self.timepoints[position],
+        })
+    }
+
+    /// Rebuilds all the [CompactStopTime] of a trip. Empty if the trip does not exist
+    pub fn stop_times_of_trip<'a>(
+        &'a self,
+        trip_id: &str,
+    ) -> impl Iterator<Item = CompactStopTime> + 'a {
+        self.trip_range(trip_id)
+            .unwrap_or_default()
+            .filter_map(move |position| self.get(position))
+    }
+
+    /// Rebuilds all the [CompactStopTime] with their `trip_id`, trip by trip
+    pub fn iter(&self) -> impl Iterator<Item = (&GtfsId, CompactStopTime)> {
+        self.trips().flat_map(move |(id, range)| {
+            range.filter_map(move |position| self.get(position).map(|st| (id, st)))
+        })
+    }
+}
+
+impl CompactGtfs {
+    /// Copies all the stop times in a [StopTimeColumns]
+    pub fn stop_time_columns(&self) -> StopTimeColumns {
+        StopTimeColumns::from_trips(
+            self.trips
+                .values()
+                .map(|trip| (&trip.id, trip.stop_times.iter().cloned())),
+        )
+    }
+
+    /// Moves all the stop times in a [StopTimeColumns]. The stop times of the trips are then empty
+    ///
+    /// Unlike [CompactGtfs::stop_time_columns], the stop times are not kept twice in memory
+    pub fn take_stop_time_columns(&mut self) -> StopTimeColumns {
+        StopTimeColumns::from_trips(self.trips.values_mut().map(|trip| {
+            let CompactTrip { id, stop_times, .. } = trip;
+            let id: &GtfsId = id;
+            (id, std::mem::take(stop_times).into_iter())
+        }))
+    }
+}
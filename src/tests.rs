@@ -1,10 +1,23 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use crate::objects::*;
+use crate::BoundingBox;
+use crate::Delimiter;
 use crate::Gtfs;
+use crate::GtfsReader;
+use crate::GtfsScanner;
+use crate::GtfsWriter;
+use crate::ParseMode;
 use crate::RawGtfs;
-use chrono::NaiveDate;
+use crate::TableName;
+use crate::TrimMode;
+use crate::{PathwayConstraints, PathwayPath, RouteAccessibility, ServiceTime};
+use chrono::{Datelike, NaiveDate, NaiveTime, TimeDelta};
 use rgb::RGB8;
+use sha2::{Digest, Sha256};
 
 #[test]
 fn serialization_deserialization() {
@@ -69,7 +82,7 @@ fn read_stop() {
     );
     assert_eq!(Some(48.796_058), gtfs.get_stop("stop2").unwrap().latitude);
     assert_eq!(
-        Some("1".to_owned()),
+        Some("1".into()),
         gtfs.get_stop("stop3").unwrap().parent_station
     );
     assert_eq!(
@@ -169,7 +182,7 @@ fn read_fare_attributes() {
         gtfs.get_fare_attributes("50").unwrap().transfers
     );
     assert_eq!(
-        Some("1".to_string()),
+        Some("1".into()),
         gtfs.get_fare_attributes("50").unwrap().agency_id
     );
     assert_eq!(
@@ -249,6 +262,65 @@ fn read_translations() {
     assert_eq!(translation.field_value, None);
 }
 
+#[test]
+fn invalid_languages() {
+    use crate::{check_language_tag, error::LanguageTagError};
+    for tag in [
+        "en",
+        "EN-us",
+        "zh-Hant-TW",
+        "zh-yue-HK",
+        "sl-rozaj-1994",
+        "es-419",
+        "de-CH-x-phonebk",
+        "en-a-bbb-x-a-ccc",
+        "x-whatever",
+        "i-klingon",
+    ] {
+        assert_eq!(Ok(()), check_language_tag(tag), "{}", tag);
+    }
+    for (tag, subtag) in [
+        ("e", "e"),
+        ("fr-", ""),
+        ("en--US", ""),
+        ("fr-FR-CH", "CH"),
+        ("en-US-Latn", "Latn"),
+        ("de-a", "a"),
+        ("fr FR", "fr FR"),
+        ("x", "x"),
+    ] {
+        assert_eq!(
+            Err(LanguageTagError::InvalidSubtag(subtag.to_owned())),
+            check_language_tag(tag),
+            "{}",
+            tag
+        );
+    }
+    assert_eq!(Err(LanguageTagError::Empty), check_language_tag(""));
+
+    let mut gtfs = RawGtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    if let Some(Ok(translations)) = &mut gtfs.translations {
+        translations[0].language = "english".to_owned();
+        translations[1].language = "fr_BE".to_owned();
+        translations.push(RawTranslation {
+            language: "nl/BE".to_owned(),
+            ..translations[0].clone()
+        });
+    }
+    let invalid: Vec<_> = gtfs
+        .invalid_languages(true)
+        .into_iter()
+        .map(|i| (i.row, i.value))
+        .collect();
+    assert_eq!(vec![(2, "nl/BE")], invalid);
+    assert_eq!(3, gtfs.invalid_languages(false).len());
+
+    assert_eq!(2, gtfs.normalize_languages());
+    let translations = gtfs.translations.unwrap().unwrap();
+    let languages: Vec<_> = translations.iter().map(|t| t.language.as_str()).collect();
+    assert_eq!(vec!["en", "fr-BE", "nl/BE"], languages);
+}
+
 #[test]
 fn read_feed_info() {
     let gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
@@ -273,254 +345,3523 @@ fn trip_days() {
 }
 
 #[test]
-fn trip_clone() {
+fn services_active_on() {
     let gtfs = Gtfs::from_path("fixtures/basic/").unwrap();
-    let _: Trip = gtfs.trips.get("trip1").unwrap().clone();
+    let start = NaiveDate::from_ymd_opt(2017, 1, 1).unwrap();
+    for service_id in ["service1", "service2"] {
+        let days = gtfs.trip_days(service_id, start);
+        for offset in 0..20u16 {
+            let date = start + chrono::TimeDelta::days(offset.into());
+            assert_eq!(
+                days.contains(&offset),
+                gtfs.services_active_on(date).contains(service_id)
+            );
+        }
+    }
+    assert!(gtfs
+        .services_active_on(NaiveDate::from_ymd_opt(2017, 1, 9).unwrap())
+        .is_empty());
 }
 
 #[test]
-fn read_from_gtfs() {
-    let gtfs = Gtfs::from_path("fixtures/zips/gtfs.zip").unwrap();
-    assert_eq!(1, gtfs.calendar.len());
-    assert_eq!(2, gtfs.calendar_dates.len());
-    assert_eq!(5, gtfs.stops.len());
-    assert_eq!(1, gtfs.routes.len());
-    assert_eq!(1, gtfs.trips.len());
-    assert_eq!(1, gtfs.shapes.len());
-    assert_eq!(1, gtfs.fare_attributes.len());
-    assert_eq!(1, gtfs.feed_info.len());
-    assert_eq!(2, gtfs.get_trip("trip1").unwrap().stop_times.len());
+fn trips_active_on() {
+    let mut gtfs = Gtfs::from_path("fixtures/basic/").unwrap();
+    let date = |d| NaiveDate::from_ymd_opt(2017, 1, d).unwrap();
+    let trips = |gtfs: &Gtfs, d, previous| -> Vec<(NaiveDate, String)> {
+        gtfs.trips_active_on(date(d), previous)
+            .map(|(day, trip)| (day, trip.id.to_string()))
+            .collect()
+    };
+    assert_eq!(vec![(date(7), "trip1".to_string())], trips(&gtfs, 7, true));
+    assert!(trips(&gtfs, 9, true).is_empty());
 
-    assert!(gtfs.get_calendar("service1").is_ok());
-    assert!(gtfs.get_calendar_date("service1").is_ok());
-    assert!(gtfs.get_stop("stop1").is_ok());
-    assert!(gtfs.get_route("1").is_ok());
-    assert!(gtfs.get_trip("trip1").is_ok());
-    assert!(gtfs.get_fare_attributes("50").is_ok());
+    // Without frequency, the trip ends at 25:00 and is still running on Monday
+    let trip = gtfs.trips.get_mut("trip1").unwrap();
+    trip.frequencies.clear();
+    trip.stop_times[2].arrival_time = Some(25 * 3600);
+    assert_eq!(vec![(date(8), "trip1".to_string())], trips(&gtfs, 9, true));
+    assert!(trips(&gtfs, 9, false).is_empty());
+    assert_eq!(
+        vec![
+            (date(8), "trip1".to_string()),
+            (date(7), "trip1".to_string())
+        ],
+        trips(&gtfs, 8, true)
+    );
+}
 
-    assert!(gtfs.get_stop("Utopia").is_err());
+#[test]
+fn expand_frequencies() {
+    let mut gtfs = Gtfs::from_path("fixtures/basic/").unwrap();
+    let trip = gtfs.trips.get_mut("trip1").unwrap();
+    trip.frequencies[0].end_time = 6 * 3600;
+    trip.frequencies.push(Frequency {
+        start_time: 7 * 3600,
+        end_time: 7 * 3600 + 1,
+        headway_secs: 600,
+        exact_times: Some(ExactTimes::ScheduleBased),
+    });
+    gtfs.expand_frequencies();
+
+    assert!(gtfs.trips.get("trip1").is_none());
+    let mut ids: Vec<_> = gtfs.trips.keys().map(|id| id.to_string()).collect();
+    ids.sort();
+    assert_eq!(
+        vec![
+            "trip1_05:30:00",
+            "trip1_05:38:00",
+            "trip1_05:46:00",
+            "trip1_05:54:00",
+            "trip1_07:00:00",
+        ],
+        ids
+    );
+    let trip = &gtfs.trips["trip1_05:46:00"];
+    assert!(trip.frequencies.is_empty());
+    assert_eq!(Some(7 * 3600 + 46 * 60), trip.stop_times[2].departure_time);
+    assert_eq!(TimepointType::Approximate, trip.stop_times[0].timepoint);
+    let trip = &gtfs.trips["trip1_07:00:00"];
+    assert_eq!(Some(7 * 3600), trip.stop_times[0].arrival_time);
+    assert_eq!(TimepointType::Exact, trip.stop_times[0].timepoint);
+
+    // The identifiers already used get a suffix
+    let mut gtfs = Gtfs::from_path("fixtures/basic/").unwrap();
+    let mut trip = gtfs.trips["trip1"].clone();
+    let frequency = trip.frequencies[0].clone();
+    trip.frequencies.push(frequency);
+    gtfs.trips.insert("trip1".into(), trip);
+    let mut scheduled = gtfs.trips["trip1"].clone();
+    scheduled.id = "trip1_05:30:00".into();
+    scheduled.frequencies.clear();
+    gtfs.trips.insert(scheduled.id.clone(), scheduled);
+    let expected = gtfs.trips.len() - 1 + gtfs.trips["trip1"].materialized_departures().len();
+    gtfs.expand_frequencies();
+    assert_eq!(expected, gtfs.trips.len());
+    assert!(gtfs.trips["trip1_05:30:00"].frequencies.is_empty());
+    assert_eq!(
+        Some(6 * 3600 + 30 * 60),
+        gtfs.trips["trip1_05:30:00_2"].stop_times[1].arrival_time
+    );
+    assert!(gtfs.trips.contains_key("trip1_05:30:00_2_2"));
 }
 
 #[test]
-fn read_from_subdirectory() {
-    let gtfs = Gtfs::from_path("fixtures/zips/subdirectory.zip").unwrap();
-    assert_eq!(1, gtfs.calendar.len());
-    assert_eq!(2, gtfs.calendar_dates.len());
-    assert_eq!(5, gtfs.stops.len());
-    assert_eq!(1, gtfs.routes.len());
-    assert_eq!(1, gtfs.trips.len());
-    assert_eq!(1, gtfs.shapes.len());
-    assert_eq!(1, gtfs.fare_attributes.len());
-    assert_eq!(2, gtfs.get_trip("trip1").unwrap().stop_times.len());
+fn interpolate_stop_times() {
+    let mut gtfs = Gtfs::from_path("fixtures/basic/").unwrap();
+    let trip = gtfs.trips.get_mut("trip1").unwrap();
+    trip.stop_times[1].arrival_time = None;
+    trip.stop_times[1].departure_time = None;
+    trip.stop_times[2].arrival_time = None;
+    for (st, distance) in trip.stop_times.iter_mut().zip([0., 10., 40.]) {
+        st.shape_dist_traveled = Some(distance);
+    }
+
+    let stop_times = trip.interpolated_stop_times();
+    assert_eq!(None, trip.stop_times[1].arrival_time);
+    assert_eq!(Some(14 * 3600 + 30 * 60), stop_times[1].arrival_time);
+    assert_eq!(Some(14 * 3600 + 30 * 60), stop_times[1].departure_time);
+    assert_eq!(TimepointType::Approximate, stop_times[1].timepoint);
+    assert_eq!(Some(16 * 3600), stop_times[2].arrival_time);
+
+    // Without all the distances, the interpolation is over the stop_sequence
+    trip.stop_times[2].shape_dist_traveled = None;
+    trip.interpolate_stop_times();
+    assert_eq!(Some(15 * 3600), trip.stop_times[1].arrival_time);
 }
 
 #[test]
-fn display() {
+fn headways() {
+    let mut gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    let monday = NaiveDate::from_ymd_opt(2024, 1, 8).unwrap();
+    let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+    let headways = gtfs.headways("routeA", "stop1", monday, time(0, 0)..time(23, 0));
+    assert_eq!(vec![TimeDelta::minutes(70)], headways.headways);
+    let last = headways.departures[1].time();
+    let headways = gtfs.headways("routeA", "stop1", monday, time(0, 0)..last);
+    assert_eq!(1, headways.departures.len());
+    assert!(gtfs
+        .headways("routeA", "stop1", monday, time(9, 30)..time(23, 0))
+        .mean()
+        .is_none());
+    let saturday = NaiveDate::from_ymd_opt(2024, 1, 6).unwrap();
+    assert!(gtfs
+        .headways("routeA", "stop1", saturday, time(0, 0)..time(23, 0))
+        .departures
+        .is_empty());
+
+    gtfs.trips.get_mut("tripA2").unwrap().frequencies = vec![Frequency {
+        start_time: 6 * 3600,
+        end_time: 7 * 3600,
+        headway_secs: 1200,
+        exact_times: None,
+    }];
+    let headways = gtfs.headways("routeA", "stop1", monday, time(0, 0)..time(23, 0));
     assert_eq!(
-        "Sorano".to_owned(),
-        format!(
-            "{}",
-            Stop {
-                name: Some("Sorano".to_owned()),
-                ..Stop::default()
-            }
-        )
+        vec![time(6, 0), time(6, 20), time(6, 40), time(10, 10)],
+        headways
+            .departures
+            .iter()
+            .map(|d| d.time())
+            .collect::<Vec<_>>()
     );
+    assert_eq!(Some(TimeDelta::minutes(20)), headways.min());
+    assert_eq!(Some(TimeDelta::minutes(210)), headways.max());
+    assert_eq!(Some(TimeDelta::minutes(250) / 3), headways.mean());
+}
+
+#[test]
+fn transfers_graph() {
+    let gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    let transfer = gtfs.transfer_between("platform1", "platform2").unwrap();
+    assert_eq!(TransferType::Timed, transfer.transfer.transfer_type);
 
+    // platform2 inherits the transfer of its station to stop1
+    let transfer = gtfs.transfer_between("platform2", "stop1").unwrap();
+    assert_eq!("platform2", transfer.from_stop.id);
+    assert_eq!(TransferType::MinTime, transfer.transfer.transfer_type);
+    // The transfer of platform1 is more specific than the one of its station
     assert_eq!(
-        "Long route name".to_owned(),
-        format!(
-            "{}",
-            Route {
-                long_name: Some("Long route name".to_owned()),
-                short_name: None,
-                ..Route::default()
-            }
-        )
+        TransferType::Recommended,
+        gtfs.transfer_between("platform1", "stop1")
+            .unwrap()
+            .transfer
+            .transfer_type
+    );
+    // The transfer to the station applies to its platforms
+    assert_eq!(
+        TransferType::Impossible,
+        gtfs.transfer_between("stop2", "platform2")
+            .unwrap()
+            .transfer
+            .transfer_type
     );
+    assert!(gtfs.transfer_between("stop1", "platform1").is_none());
+    assert!(gtfs.transfer_between("stop2", "unknown").is_none());
 
+    let mut edges: Vec<_> = gtfs
+        .transfers()
+        .map(|t| format!("{}-{}", t.from_stop.id, t.to_stop.id))
+        .collect();
+    edges.sort();
     assert_eq!(
-        "Short route name".to_owned(),
-        format!(
-            "{}",
-            Route {
-                short_name: Some("Short route name".to_owned()),
-                long_name: None,
-                ..Route::default()
-            }
-        )
+        vec![
+            "platform1-platform2",
+            "platform1-stop1",
+            "platform2-stop1",
+            "station1-stop1",
+            "stop2-platform1",
+            "stop2-platform2",
+            "stop2-station1",
+        ],
+        edges
     );
 }
 
 #[test]
-fn path_files() {
-    let gtfs = RawGtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
-    assert_eq!(gtfs.files.len(), 14);
-    assert_eq!(gtfs.source_format, SourceFormat::Directory);
-    assert!(gtfs.files.contains(&"agency.txt".to_owned()));
-}
+fn pathway_path() {
+    let gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    let ids = |path: &PathwayPath| -> Vec<String> {
+        path.steps
+            .iter()
+            .map(|s| format!("{}:{}-{}", s.pathway.id, s.from_stop.id, s.to_stop.id))
+            .collect()
+    };
+    let any = PathwayConstraints::default();
+    let step_free = PathwayConstraints {
+        avoid_stairs: true,
+        ..PathwayConstraints::default()
+    };
 
-#[test]
-fn subdirectory_files() {
-    // reading subdirectory does not work when reading from a path (it's useless since the path can be given explicitly)
-    // Note: if its needed, an issue can be opened to discuss it
-    let gtfs = RawGtfs::from_path("fixtures/subdirectory").expect("impossible to read gtfs");
-    // no files can be read
-    assert!(gtfs.stops.is_err());
-    assert!(gtfs.routes.is_err());
-    assert!(gtfs.agencies.is_err());
+    // The escalator only goes from platform1 to platform2, the stairs are walked backward
+    let path = gtfs.pathway_path("platform2", "platform1", &any).unwrap();
+    assert_eq!(
+        vec!["walk1:platform2-entrance1", "stairs1:entrance1-platform1"],
+        ids(&path)
+    );
+    assert_eq!(105, path.traversal_time);
+    assert!(!path.steps[1].reversed);
+    let path = gtfs.pathway_path("platform1", "entrance1", &any).unwrap();
+    assert!(path.steps[0].reversed);
+    assert_eq!(
+        Some("Exit"),
+        path.steps[0].pathway.reversed_signposted_as.as_deref()
+    );
 
-    assert_eq!(gtfs.files, vec!["gtfs".to_string()]);
+    // The corridor of 50m is walked in 42s
+    let path = gtfs
+        .pathway_path("platform2", "platform1", &step_free)
+        .unwrap();
+    assert_eq!(
+        vec![
+            "walk1:platform2-entrance1",
+            "corridor1:entrance1-node1",
+            "elevator1:node1-platform1",
+        ],
+        ids(&path)
+    );
+    assert_eq!(177, path.traversal_time);
+
+    let wide = PathwayConstraints {
+        min_width: Some(1.),
+        ..PathwayConstraints::default()
+    };
+    assert!(gtfs.pathway_path("platform2", "platform1", &wide).is_none());
+    let flat = PathwayConstraints {
+        max_slope: Some(0.01),
+        ..step_free
+    };
+    assert!(gtfs.pathway_path("entrance1", "platform1", &flat).is_none());
+    assert!(gtfs
+        .pathway_path("platform1", "platform1", &flat)
+        .unwrap()
+        .steps
+        .is_empty());
+    assert!(gtfs.pathway_path("platform1", "unknown", &any).is_none());
 }
 
 #[test]
-fn zip_files() {
-    let gtfs = RawGtfs::from_path("fixtures/zips/gtfs.zip").expect("impossible to read gtfs");
-    assert_eq!(gtfs.files.len(), 10);
-    assert_eq!(gtfs.source_format, SourceFormat::Zip);
-    assert!(gtfs.files.contains(&"agency.txt".to_owned()));
+fn stops_in_bbox() {
+    let gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    let ids = |bbox: BoundingBox| -> Vec<String> {
+        gtfs.stops_in_bbox(&bbox)
+            .iter()
+            .map(|s| s.id.to_string())
+            .collect()
+    };
+    let bbox = BoundingBox {
+        min_longitude: 2.35,
+        min_latitude: 48.85,
+        max_longitude: 2.36,
+        max_latitude: 48.86,
+    };
+    assert_eq!(
+        vec![
+            "entrance1",
+            "node1",
+            "platform1",
+            "platform2",
+            "station1",
+            "stop1"
+        ],
+        ids(bbox)
+    );
+    assert!(ids(BoundingBox {
+        max_latitude: 48.855,
+        min_latitude: 48.851,
+        ..bbox
+    })
+    .is_empty());
+    // Crossing the antimeridian, from 2.36 to 180 and from -180 to 2.35
+    assert_eq!(
+        vec![
+            "entrance1",
+            "node1",
+            "platform1",
+            "platform2",
+            "station1",
+            "stop1"
+        ],
+        ids(BoundingBox {
+            min_longitude: 2.36,
+            max_longitude: 2.35,
+            ..bbox
+        })
+    );
+    assert_eq!(
+        vec!["stop1", "stop2"],
+        ids(BoundingBox {
+            min_longitude: 2.36,
+            max_longitude: -170.,
+            min_latitude: -90.,
+            max_latitude: 90.,
+        })
+    );
 }
 
 #[test]
-fn zip_subdirectory_files() {
-    let gtfs =
-        RawGtfs::from_path("fixtures/zips/subdirectory.zip").expect("impossible to read gtfs");
-    assert_eq!(gtfs.files.len(), 11);
-    assert_eq!(gtfs.source_format, SourceFormat::Zip);
-    assert!(gtfs.files.contains(&"subdirectory/agency.txt".to_owned()));
+fn stops_within() {
+    let gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    let stops = gtfs.stops_within(48.86, 2.36, 2000., None);
+    let ids: Vec<_> = stops.iter().map(|(s, _)| s.id.as_str()).collect();
+    assert_eq!(
+        vec![
+            "stop1",
+            "stop2",
+            "entrance1",
+            "node1",
+            "platform1",
+            "platform2",
+            "station1",
+        ],
+        ids
+    );
+    assert_eq!(0., stops[0].1);
+    assert!((stops[1].1 - 1331.).abs() < 1.);
+    assert_eq!(1, gtfs.stops_within(48.86, 2.36, 1000., None).len());
+    let platforms = gtfs.stops_within(48.86, 2.36, 2000., Some(LocationType::StopPoint));
+    assert_eq!(4, platforms.len());
+    assert!(gtfs.stops_within(0., 0., 1000., None).is_empty());
+
+    // Across the antimeridian, 1° of longitude at the equator is about 111km
+    let distance = crate::haversine(0., 179.5, 0., -179.5);
+    assert!((distance - 111_195.).abs() < 1., "{}", distance);
 }
 
 #[test]
-fn compute_sha256() {
-    let gtfs = RawGtfs::from_path("fixtures/zips/gtfs.zip").expect("impossible to read gtfs");
+fn shape_length() {
+    let gtfs = Gtfs::from_path("fixtures/basic/").unwrap();
+    // The points of Unordered_shp are sorted when they are read
     assert_eq!(
-        gtfs.sha256,
-        Some("4a262ae109101ffbd1629b67e080a2b074afdaa60d57684db0e1a31c0a1e75b0".to_owned())
+        gtfs.shape_length("A_shp").unwrap(),
+        gtfs.shape_length("Unordered_shp").unwrap()
+    );
+    assert!(gtfs.shape_length("unknown").is_err());
+    assert_eq!(
+        0.,
+        crate::polyline_length(&gtfs.get_shape("A_shp").unwrap()[..1])
+    );
+
+    let gtfs = GtfsReader::default()
+        .lazy_shapes(true)
+        .read_from_path("fixtures/basic/")
+        .unwrap();
+    assert_eq!(15_970., gtfs.shape_length("A_shp").unwrap().round());
+}
+
+/// The basic GTFS with trip1 following A_shp, and its stops moved along the shape
+fn trip_on_shape() -> Gtfs {
+    let mut gtfs = Gtfs::from_path("fixtures/basic/").unwrap();
+    let stop = |latitude, longitude| {
+        Arc::new(Stop {
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            ..Stop::default()
+        })
+    };
+    let trip = gtfs.trips.get_mut("trip1").unwrap();
+    trip.shape_id = Some("A_shp".into());
+    let mut last = trip.stop_times[2].clone();
+    trip.stop_times[0].stop = stop(37.61956, -122.48161);
+    // Halfway between the last two points, a bit aside
+    trip.stop_times[1].stop = stop(
+        (37.64430 + 37.65863) / 2. + 0.0001,
+        (-122.41070 - 122.30839) / 2.,
     );
+    trip.stop_times[2].stop = stop(37.65863, -122.30839);
+    // The trip can not go back to the start of the shape
+    last.stop = stop(37.61956, -122.48161);
+    last.stop_sequence = 3;
+    trip.stop_times.push(last);
+    gtfs
 }
 
 #[test]
-fn test_bom() {
-    let gtfs =
-        RawGtfs::from_path("fixtures/zips/gtfs_with_bom.zip").expect("impossible to read gtfs");
-    assert_eq!(gtfs.agencies.expect("agencies missing").len(), 2);
+fn fill_shape_dist_traveled() {
+    let mut gtfs = trip_on_shape();
+    let projections = gtfs.snap_stop_times_to_shape("trip1").unwrap();
+    assert_eq!(4, projections.len());
+    assert_eq!((0, 0.), {
+        let p = projections[0].unwrap();
+        (p.point_index, p.offset)
+    });
+    let halfway = projections[1].unwrap();
+    assert_eq!(1, halfway.point_index);
+    assert!((halfway.offset - 0.5).abs() < 0.01);
+    assert_eq!((1, 1.), {
+        let p = projections[3].unwrap();
+        (p.point_index, p.offset)
+    });
+    assert!(gtfs.snap_stop_times_to_shape("unknown").is_err());
+    gtfs.fill_shape_dist_traveled().unwrap();
+
+    let distances: Vec<_> = gtfs.trips["trip1"]
+        .stop_times
+        .iter()
+        .map(|st| st.shape_dist_traveled.unwrap())
+        .collect();
+    assert_eq!(0., distances[0]);
+    assert!(
+        (distances[1] - (6.831 + 15.8765) / 2.).abs() < 0.01,
+        "{:?}",
+        distances
+    );
+    assert_eq!(15.8765, distances[2]);
+    assert_eq!(15.8765, distances[3]);
+
+    // The points of a shape without distance get their distance in meters
+    for point in gtfs.shapes.get_mut("A_shp").unwrap() {
+        point.dist_traveled = None;
+    }
+    for stop_time in &mut gtfs.trips.get_mut("trip1").unwrap().stop_times {
+        stop_time.shape_dist_traveled = None;
+    }
+    gtfs.fill_shape_dist_traveled().unwrap();
+    let length = gtfs.shape_length("A_shp").unwrap() as f32;
+    assert_eq!(Some(length), gtfs.shapes["A_shp"][2].dist_traveled);
+    assert_eq!(
+        Some(length),
+        gtfs.trips["trip1"].stop_times[2].shape_dist_traveled
+    );
 }
 
 #[test]
-fn test_macosx() {
-    let gtfs = RawGtfs::from_path("fixtures/zips/macosx.zip").expect("impossible to read gtfs");
-    assert_eq!(gtfs.agencies.expect("agencies missing").len(), 2);
-    assert_eq!(gtfs.stops.expect("stops missing").len(), 5);
+fn segment_geometry() {
+    let mut gtfs = trip_on_shape();
+    let line = gtfs.segment_geometry("trip1", 0, 1).unwrap();
+    assert_eq!(3, line.len());
+    assert_eq!((37.61956, -122.48161), line[0]);
+    assert_eq!((37.64430, -122.41070), line[1]);
+    assert!((line[2].1 - (-122.41070 - 122.30839) / 2.).abs() < 0.001);
+    assert_eq!(
+        vec![(37.65863, -122.30839)],
+        gtfs.segment_geometry("trip1", 2, 3).unwrap()
+    );
+    assert!(gtfs.segment_geometry("trip1", 1, 0).unwrap().is_empty());
+    assert!(gtfs.segment_geometry("trip1", 0, 9).is_err());
+    assert_eq!(3, gtfs.trip_geometry("trip1").unwrap().len());
+
+    // Without shape, the line goes straight between the stops
+    gtfs.trips.get_mut("trip1").unwrap().shape_id = None;
+    let line = gtfs.segment_geometry("trip1", 0, 1).unwrap();
+    assert_eq!(2, line.len());
+    assert_eq!((37.61956, -122.48161), line[0]);
+    assert_eq!(4, gtfs.trip_geometry("trip1").unwrap().len());
 }
 
 #[test]
-fn read_missing_feed_dates() {
-    let gtfs = Gtfs::from_path("fixtures/missing_feed_date").expect("impossible to read gtfs");
-    assert_eq!(1, gtfs.feed_info.len());
-    assert!(gtfs.feed_info[0].start_date.is_none());
+fn trip_stats() {
+    let mut gtfs = trip_on_shape();
+    let trip = gtfs.trips.get_mut("trip1").unwrap();
+    trip.stop_times[1].departure_time = Some(15 * 3600 + 120);
+    trip.stop_times[3].arrival_time = Some(17 * 3600);
+    trip.stop_times[3].departure_time = None;
+    let stats = gtfs.trips["trip1"].stats(&gtfs).unwrap();
+    assert_eq!(Some(3 * 3600), stats.duration);
+    assert_eq!(4, stats.stops);
+    assert_eq!(vec![Some(0), Some(120), Some(0), None], stats.dwell_times);
+    // The last stop does not go back along the shape
+    let length = gtfs.shape_length("A_shp").unwrap();
+    assert!((stats.distance - length).abs() < 1.);
+    assert_eq!(Some(stats.distance / 10_800.), stats.average_speed);
+
+    // Without shape, the line goes back to the first stop
+    gtfs.trips.get_mut("trip1").unwrap().shape_id = None;
+    let stats = gtfs.trips["trip1"].stats(&gtfs).unwrap();
+    assert!(stats.distance > 2. * length - 1000.);
 }
 
 #[test]
-fn read_interpolated_stops() {
-    let gtfs =
-        Gtfs::from_path("fixtures/interpolated_stop_times").expect("impossible to read gtfs");
-    assert_eq!(1, gtfs.feed_info.len());
-    // the second stop have no departure/arrival, it should not cause any problems
+fn blocks() {
+    let mut gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    for trip_id in ["tripA1", "tripA2", "tripA3", "tripB1"] {
+        gtfs.trips.get_mut(trip_id).unwrap().block_id = Some("block1".into());
+    }
+    let ids = |trips: &[&Trip]| -> Vec<String> { trips.iter().map(|t| t.id.to_string()).collect() };
+    let blocks = gtfs.blocks();
+    assert_eq!(1, blocks.len());
+    assert_eq!("block1", blocks[0].id);
     assert_eq!(
-        gtfs.trips["trip1"].stop_times[1].stop.name,
-        Some("Stop Point child of 1".to_owned())
+        vec!["tripA1", "tripB1", "tripA2", "tripA3"],
+        ids(&blocks[0].trips)
     );
-    assert!(gtfs.trips["trip1"].stop_times[1].arrival_time.is_none());
-}
+    assert!(blocks[0].overlaps.is_empty());
 
-#[test]
-fn read_only_required_fields() {
-    let gtfs = Gtfs::from_path("fixtures/only_required_fields").expect("impossible to read gtfs");
-    let route = gtfs.routes.get("1").unwrap();
-    let fare_attribute = gtfs.fare_attributes.get("50").unwrap();
-    let feed = &gtfs.feed_info[0];
-    let shape = &gtfs.shapes.get("A_shp").unwrap()[0];
-    assert_eq!(route.color, RGB8::new(255, 255, 255));
-    assert_eq!(route.text_color, RGB8::new(0, 0, 0));
-    assert_eq!(fare_attribute.transfer_duration, None);
-    assert_eq!(feed.start_date, None);
-    assert_eq!(feed.end_date, None);
-    assert_eq!(shape.dist_traveled, None);
+    // tripA2 now runs from 08:40 to 09:10, at the same time as tripB1
+    gtfs.trips.get_mut("tripA2").unwrap().stop_times[0].departure_time = Some(8 * 3600 + 2400);
+    let blocks = gtfs.blocks();
     assert_eq!(
-        TimepointType::Exact,
-        gtfs.trips["trip1"].stop_times[0].timepoint
-    )
+        vec!["tripB1", "tripA2"],
+        ids(&[blocks[0].overlaps[0].0, blocks[0].overlaps[0].1])
+    );
+
+    // A trip on other days does not overlap
+    gtfs.calendar_dates.insert(
+        "saturday".into(),
+        vec![CalendarDate {
+            service_id: "saturday".into(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 6).unwrap(),
+            exception_type: Exception::Added,
+        }],
+    );
+    gtfs.trips.get_mut("tripA2").unwrap().service_id = "saturday".into();
+    assert!(gtfs.blocks()[0].overlaps.is_empty());
 }
 
 #[test]
-fn metra_gtfs() {
-    let gtfs = Gtfs::from_path("fixtures/zips/metra.zip");
-
-    if let Err(err) = &gtfs {
-        eprintln!("{:#?}", err);
+fn infer_direction_ids() {
+    let mut gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    for trip in gtfs.trips.values_mut() {
+        if trip.route_id == "routeA" {
+            trip.direction_id = None;
+        }
+    }
+    // tripA1 goes through the stops of tripA3 backward, and is the reference as the first of the longest trips
+    let mut stop_times = gtfs.trips["tripA3"].stop_times.clone();
+    for (stop_time, other) in stop_times
+        .iter_mut()
+        .zip(gtfs.trips["tripA3"].stop_times.iter().rev())
+    {
+        stop_time.stop = other.stop.clone();
     }
+    gtfs.trips.get_mut("tripA1").unwrap().stop_times = stop_times;
 
-    assert!(gtfs.is_ok());
+    let inferred: Vec<_> = gtfs
+        .infer_direction_ids()
+        .into_iter()
+        .map(|d| {
+            (
+                d.trip_id.to_string(),
+                d.direction_id,
+                (d.confidence * 100.).round(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            ("tripA1".to_string(), DirectionType::Outbound, 100.),
+            ("tripA2".to_string(), DirectionType::Outbound, 100.),
+            ("tripA3".to_string(), DirectionType::Inbound, 100.),
+        ],
+        inferred
+    );
+    assert_eq!(
+        Some(DirectionType::Inbound),
+        gtfs.trips["tripA3"].direction_id
+    );
+    assert!(gtfs.infer_direction_ids().is_empty());
 }
 
 #[test]
-fn sorted_shapes() {
-    let gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
-    let shape = &gtfs.shapes.get("Unordered_shp").unwrap();
+fn stop_patterns() {
+    let mut gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    let mut trip = gtfs.trips["tripA1"].clone();
+    trip.id = "tripA4".into();
+    gtfs.trips.insert(trip.id.clone(), trip);
 
-    let points = shape
+    let patterns = gtfs.stop_patterns();
+    let ids: Vec<_> = patterns
+        .patterns
         .iter()
-        .map(|s| (s.sequence, s.latitude, s.longitude))
-        .collect::<Vec<_>>();
+        .map(|p| {
+            let trips: Vec<_> = p.trips.iter().map(|t| t.id.as_str()).collect();
+            (p.id.as_str(), trips)
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            ("routeA:1", vec!["tripA1", "tripA4"]),
+            ("routeA:2", vec!["tripA3"]),
+            ("routeA:3", vec!["tripA2"]),
+            ("routeB:1", vec!["tripB1"]),
+        ],
+        ids
+    );
+    let pattern = patterns.pattern_of("tripA4").unwrap();
+    assert_eq!("routeA:1", pattern.id);
+    let stops: Vec<_> = pattern.stops.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(vec!["platform1", "stop1"], stops);
+    assert!(patterns.pattern_of("unknown").is_none());
+}
 
+#[test]
+fn timetable() {
+    let gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    let timetable = gtfs
+        .timetable("routeA", Some(DirectionType::Outbound), monday)
+        .unwrap();
+    let stops: Vec<_> = timetable.stops.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(vec!["platform2", "platform1", "stop1", "stop2"], stops);
+    let trips: Vec<_> = timetable.trips.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(vec!["tripA1", "tripA3"], trips);
+    let hour = |h: u32, m: u32| Some(h * 3600 + m * 60);
     assert_eq!(
-        points,
         vec![
-            (0, 37.61956, -122.48161),
-            (6, 37.64430, -122.41070),
-            (11, 37.65863, -122.30839),
-        ]
+            vec![None, hour(10, 0)],
+            vec![hour(8, 0), None],
+            vec![hour(8, 10), hour(10, 10)],
+            vec![None, hour(10, 20)],
+        ],
+        timetable.times
+    );
+    assert_eq!(
+        Some(vec![None, hour(8, 0), hour(8, 10), None]),
+        timetable.trip_times("tripA1")
     );
+    assert_eq!(None, timetable.trip_times("tripA2"));
+
+    let saturday = NaiveDate::from_ymd_opt(2024, 3, 2).unwrap();
+    let timetable = gtfs
+        .timetable("routeA", Some(DirectionType::Outbound), saturday)
+        .unwrap();
+    assert_eq!(4, timetable.stops.len());
+    assert!(timetable.trips.is_empty());
+    assert!(gtfs.timetable("unknown", None, monday).is_err());
 }
 
 #[test]
-fn fare_v1() {
-    let gtfs = Gtfs::from_path("fixtures/fares_v1").expect("impossible to read gtfs");
+fn normalize_calendars() {
+    let mut gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    let day = |d: u32| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+    let added = |service_id: &str, days: Vec<u32>| -> Vec<CalendarDate> {
+        days.into_iter()
+            .map(|d| CalendarDate {
+                service_id: service_id.into(),
+                date: day(d),
+                exception_type: Exception::Added,
+            })
+            .collect()
+    };
+    // The weekdays of three weeks, except a wednesday, and a saturday
+    let weekdays = (4..=22)
+        .filter(|d| day(*d).weekday().num_days_from_monday() < 5 && *d != 6)
+        .chain([9])
+        .collect();
+    gtfs.calendar_dates
+        .insert("weekdays".into(), added("weekdays", weekdays));
+    gtfs.calendar_dates
+        .insert("scattered".into(), added("scattered", vec![4, 12]));
+    let before: Vec<Vec<bool>> = ["weekdays", "scattered"]
+        .iter()
+        .map(|s| {
+            (1..=31)
+                .map(|d| gtfs.is_service_active(s, day(d)))
+                .collect()
+        })
+        .collect();
 
-    let mut expected_attributes = HashMap::new();
-    expected_attributes.insert(
-        "presto_fare".to_string(),
-        FareAttribute {
-            id: "presto_fare".to_string(),
-            currency: "CAD".to_string(),
-            price: "3.2".to_string(),
-            payment_method: PaymentMethod::PreBoarding,
-            transfer_duration: Some(7200),
-            agency_id: None,
-            transfers: Transfers::Unlimited,
-        },
+    assert_eq!(vec!["weekdays"], gtfs.normalize_calendars());
+    let calendar = &gtfs.calendar["weekdays"];
+    assert_eq!((day(4), day(22)), (calendar.start_date, calendar.end_date));
+    assert!(calendar.monday && calendar.wednesday && calendar.friday);
+    assert!(!calendar.saturday && !calendar.sunday);
+    let exceptions: Vec<_> = gtfs.calendar_dates["weekdays"]
+        .iter()
+        .map(|d| (d.date, d.exception_type))
+        .collect();
+    assert_eq!(
+        vec![(day(6), Exception::Deleted), (day(9), Exception::Added)],
+        exceptions
+    );
+    assert!(!gtfs.calendar.contains_key("scattered"));
+    assert_eq!(2, gtfs.calendar_dates["scattered"].len());
+
+    let after: Vec<Vec<bool>> = ["weekdays", "scattered"]
+        .iter()
+        .map(|s| {
+            (1..=31)
+                .map(|d| gtfs.is_service_active(s, day(d)))
+                .collect()
+        })
+        .collect();
+    assert_eq!(before, after);
+    assert!(gtfs.normalize_calendars().is_empty());
+}
+
+#[test]
+fn merge_equal_services() {
+    let mut gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    let mut calendar = gtfs.calendar["service1"].clone();
+    calendar.id = "a_service".into();
+    gtfs.calendar.insert(calendar.id.clone(), calendar);
+    // The same days, given by calendar_dates.txt only
+    let dates = NaiveDate::from_ymd_opt(2024, 1, 1)
+        .unwrap()
+        .iter_days()
+        .take_while(|d| d.year() == 2024)
+        .filter(|d| d.weekday().num_days_from_monday() < 5)
+        .map(|date| CalendarDate {
+            service_id: "service3".into(),
+            date,
+            exception_type: Exception::Added,
+        })
+        .collect();
+    gtfs.calendar_dates.insert("service3".into(), dates);
+    gtfs.trips.get_mut("tripB1").unwrap().service_id = "service3".into();
+
+    assert_eq!(
+        vec![
+            ("service1".into(), "a_service".into()),
+            ("service3".into(), "a_service".into()),
+        ],
+        gtfs.merge_equal_services()
+    );
+    assert!(gtfs.trips.values().all(|t| t.service_id == "a_service"));
+    assert_eq!(1, gtfs.calendar.len());
+    assert!(gtfs.calendar_dates.is_empty());
+    assert!(gtfs.merge_equal_services().is_empty());
+}
+
+#[test]
+fn fare_for() {
+    let gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    let fare_ids = |from: &str, to: &str, route_id: &str| -> Vec<String> {
+        gtfs.fare_for(from, to, route_id)
+            .unwrap()
+            .iter()
+            .map(|f| f.id.to_string())
+            .collect()
+    };
+    assert_eq!(
+        vec!["short", "fareA"],
+        fare_ids("platform1", "stop1", "routeA")
+    );
+    // tripA3 goes through the 3 zones
+    assert_eq!(
+        vec!["fareA", "through"],
+        fare_ids("platform2", "stop2", "routeA")
+    );
+    assert_eq!(vec!["fareA"], fare_ids("stop1", "stop2", "routeA"));
+    assert!(fare_ids("platform2", "stop2", "routeB").is_empty());
+    assert!(gtfs.fare_for("unknown", "stop2", "routeB").is_err());
+}
+
+#[test]
+fn accessibility() {
+    let mut gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    Arc::make_mut(gtfs.stops.get_mut("station1").unwrap()).wheelchair_boarding =
+        Availability::Available;
+    Arc::make_mut(gtfs.stops.get_mut("stop2").unwrap()).wheelchair_boarding =
+        Availability::NotAvailable;
+    gtfs.trips.get_mut("tripA1").unwrap().wheelchair_accessible = Availability::Available;
+    gtfs.trips.get_mut("tripA2").unwrap().wheelchair_accessible = Availability::NotAvailable;
+
+    // platform1 inherits the wheelchair boarding of station1
+    let platform1 = gtfs.stop_accessibility("platform1").unwrap();
+    assert_eq!(Availability::Available, platform1.wheelchair_boarding);
+    assert_eq!(Some(true), platform1.step_free_access);
+    assert_eq!((2, 1), (platform1.trips, platform1.accessible_trips));
+    assert_eq!(Some(0.5), platform1.accessible_trips_ratio());
+
+    let stop1 = gtfs.stop_accessibility("stop1").unwrap();
+    assert_eq!(
+        Availability::InformationNotAvailable,
+        stop1.wheelchair_boarding
+    );
+    assert_eq!(None, stop1.step_free_access);
+
+    let route = gtfs.route_accessibility("routeA").unwrap();
+    assert_eq!(
+        RouteAccessibility {
+            trips: 3,
+            accessible_trips: 1,
+            inaccessible_trips: 1,
+            stops: 4,
+            accessible_stops: 2,
+        },
+        route
+    );
+
+    // Without the elevator, the only way to platform1 has stairs
+    for stop in gtfs.stops.values_mut() {
+        Arc::make_mut(stop).pathways.retain(|p| p.id != "elevator1");
+    }
+    let platform1 = gtfs.stop_accessibility("platform1").unwrap();
+    assert_eq!(Some(false), platform1.step_free_access);
+    assert!(gtfs.stop_accessibility("unknown").is_err());
+    assert!(gtfs.route_accessibility("unknown").is_err());
+}
+
+#[test]
+fn frequency_profile() {
+    let gtfs = Gtfs::from_path("fixtures/stations/").unwrap();
+    let monday = NaiveDate::from_ymd_opt(2024, 1, 8).unwrap();
+    let profiles = gtfs.frequency_profile("routeA", monday).unwrap();
+    let hours = |profile: &crate::FrequencyProfile| -> Vec<(usize, u32)> {
+        profile
+            .trips_per_hour
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| **n > 0)
+            .map(|(hour, n)| (hour, *n))
+            .collect()
+    };
+    assert_eq!(2, profiles.len());
+    assert_eq!(Some(DirectionType::Outbound), profiles[0].direction_id);
+    assert_eq!(vec![(8, 1), (10, 1)], hours(&profiles[0]));
+    assert_eq!(Some(DirectionType::Inbound), profiles[1].direction_id);
+    assert_eq!(vec![(9, 1)], hours(&profiles[1]));
+    assert_eq!(24, profiles[1].trips_per_hour.len());
+
+    let saturday = NaiveDate::from_ymd_opt(2024, 1, 6).unwrap();
+    assert!(gtfs
+        .frequency_profile("routeA", saturday)
+        .unwrap()
+        .is_empty());
+    assert!(gtfs.frequency_profile("unknown", monday).is_err());
+
+    // The frequency of trip1 gives departures at 05:30 and after midnight at 24:30
+    let mut gtfs = Gtfs::from_path("fixtures/basic/").unwrap();
+    let mut route = gtfs.routes["1"].clone();
+    route.id = "route1".into();
+    gtfs.routes.insert(route.id.clone(), route);
+    let trip = gtfs.trips.get_mut("trip1").unwrap();
+    let mut frequency = trip.frequencies[0].clone();
+    frequency.start_time = 24 * 3600 + 30 * 60;
+    frequency.end_time = frequency.start_time + 480;
+    trip.frequencies.push(frequency);
+    let sunday = NaiveDate::from_ymd_opt(2017, 1, 8).unwrap();
+    let profiles = gtfs.frequency_profile("route1", sunday).unwrap();
+    assert_eq!(1, profiles.len());
+    assert_eq!(vec![(5, 1), (24, 1)], hours(&profiles[0]));
+    assert_eq!(25, profiles[0].trips_per_hour.len());
+}
+
+#[test]
+fn stats() {
+    let gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    let stats = gtfs.stats();
+    assert_eq!(2, stats.agencies);
+    assert_eq!(3, stats.routes);
+    assert_eq!(3, stats.stop_times);
+    assert_eq!(Some(&2), stats.route_types.get(&RouteType::Bus));
+    assert_eq!(Some(&1), stats.route_types.get(&RouteType::Other(42)));
+    assert_eq!(vec!["fr"], stats.languages);
+    assert_eq!(NaiveDate::from_ymd_opt(2017, 1, 1), stats.start_date);
+    assert!(stats.bounding_box.is_some());
+
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!("20170101", json["start_date"]);
+    assert_eq!(2, json["route_types"]["3"]);
+}
+
+#[test]
+fn after_midnight_service_day() {
+    let mut gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    // tripA1 leaves at 25:30:00 and arrives at 25:40:00
+    let trip = gtfs.trips.get_mut("tripA1").unwrap();
+    trip.stop_times[0].departure_time = Some(25 * 3600 + 30 * 60);
+    trip.stop_times[1].arrival_time = Some(25 * 3600 + 40 * 60);
+
+    let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    let tuesday = monday.succ_opt().unwrap();
+    assert_eq!(
+        Some(tuesday.and_hms_opt(1, 30, 0).unwrap()),
+        gtfs.trips["tripA1"].stop_times[0].departure_datetime(monday)
+    );
+
+    let datetime = tuesday.and_hms_opt(1, 35, 0).unwrap();
+    let dates = gtfs.service_dates_at(datetime);
+    assert_eq!(
+        vec![
+            (tuesday, ServiceTime(5700)),
+            (monday, ServiceTime(25 * 3600 + 35 * 60))
+        ],
+        dates
+    );
+    assert_eq!("25:35:00", dates[1].1.to_string());
+    assert_eq!(
+        NaiveTime::from_hms_opt(1, 35, 0),
+        Some(dates[1].1.time_of_day())
+    );
+
+    assert_eq!(
+        Some(monday),
+        gtfs.trip_service_date("tripA1", datetime).unwrap()
+    );
+    // On Sunday at 01:35, the trip would run on Saturday, when service1 does not run
+    let sunday = NaiveDate::from_ymd_opt(2024, 3, 3).unwrap();
+    assert_eq!(
+        None,
+        gtfs.trip_service_date("tripA1", sunday.and_hms_opt(1, 35, 0).unwrap())
+            .unwrap()
+    );
+    assert_eq!(
+        None,
+        gtfs.trip_service_date("tripA1", tuesday.and_hms_opt(1, 45, 0).unwrap())
+            .unwrap()
+    );
+
+    // The last vehicle leaves at 25:20:00, as the end of the frequency is excluded, and arrives at 25:30:00
+    gtfs.trips.get_mut("tripA1").unwrap().frequencies = vec![Frequency {
+        start_time: 25 * 3600,
+        end_time: 25 * 3600 + 30 * 60,
+        headway_secs: 600,
+        exact_times: None,
+    }];
+    let under_way = |h, m| {
+        gtfs.trip_service_date("tripA1", tuesday.and_hms_opt(h, m, 0).unwrap())
+            .unwrap()
+    };
+    assert_eq!(Some(monday), under_way(1, 25));
+    assert_eq!(None, under_way(1, 35));
+    let connections = gtfs.connections(tuesday);
+    let departures: Vec<_> = connections
+        .iter()
+        .filter(|c| c.trip.id == "tripA1")
+        .map(|c| (c.service_date, c.departure_time))
+        .collect();
+    assert_eq!(
+        vec![(monday, 3600), (monday, 3600 + 600), (monday, 3600 + 1200)],
+        departures
+    );
+    // The connections after midnight are not listed with their service day
+    assert!(gtfs
+        .connections(monday)
+        .iter()
+        .all(|c| c.trip.id != "tripA1"));
+}
+
+#[test]
+fn agency_for_route() {
+    let mut gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    assert_eq!(
+        Some("agency1"),
+        gtfs.agency_for_trip("tripB1").unwrap().id.as_deref()
+    );
+    assert!(matches!(
+        gtfs.agency_for_trip("unknown"),
+        Err(crate::Error::ReferenceError(_))
+    ));
+
+    // Without agency_id, the route is operated by the only agency
+    gtfs.routes.get_mut("routeB").unwrap().agency_id = None;
+    assert_eq!("Agency", gtfs.agency_for_route("routeB").unwrap().name);
+
+    let mut other = gtfs.agencies[0].clone();
+    other.id = Some("agency2".into());
+    other.name = "Other agency".to_owned();
+    gtfs.agencies.push(other);
+    assert_eq!("Agency", gtfs.agency_for_route("routeA").unwrap().name);
+    assert!(matches!(
+        gtfs.agency_for_route("routeB"),
+        Err(crate::Error::MissingAgencyId(route_id)) if route_id == "routeB"
+    ));
+
+    // The agencies of fixtures/basic have no agency_id, unlike its routes
+    let gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    assert!(matches!(
+        gtfs.agency_for_route("1"),
+        Err(crate::Error::ReferenceError(agency_id)) if agency_id == "848"
+    ));
+}
+
+#[test]
+fn trips_between() {
+    let gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    let trips = gtfs.trips_between("platform2", "stop2", None);
+    assert_eq!(2, trips.len());
+    assert_eq!("tripB1", trips[0].trip.id);
+    assert_eq!(Some(15 * 60), trips[0].duration());
+    assert_eq!("tripA3", trips[1].trip.id);
+    assert_eq!(1, trips[1].from.stop_sequence);
+    assert_eq!(3, trips[1].to.stop_sequence);
+
+    // The trips do not serve the stops in the other order
+    assert!(gtfs.trips_between("stop2", "platform2", None).is_empty());
+    assert!(gtfs.trips_between("unknown", "stop2", None).is_empty());
+
+    let saturday = NaiveDate::from_ymd_opt(2024, 3, 2).unwrap();
+    let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    assert!(gtfs
+        .trips_between("station1", "stop2", Some(saturday))
+        .is_empty());
+    assert_eq!(
+        2,
+        gtfs.trips_between("station1", "stop2", Some(monday)).len()
+    );
+}
+
+#[test]
+fn connections() {
+    // trip1 runs every 8 minutes from 05:30:00 to 05:38:00, so only once, and lasts 2 hours
+    let gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    let saturday = NaiveDate::from_ymd_opt(2017, 1, 7).unwrap();
+    let connections: Vec<_> = gtfs
+        .connections(saturday)
+        .iter()
+        .map(|c| {
+            (
+                c.departure.stop.id.as_str(),
+                c.departure_time,
+                c.arrival.stop.id.as_str(),
+                c.arrival_time,
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            ("stop2", 5 * 3600 + 1800, "stop3", 6 * 3600 + 1800),
+            ("stop3", 6 * 3600 + 1800, "stop4", 7 * 3600 + 1800)
+        ],
+        connections
+    );
+
+    // tripA3 leaves platform2 at 23:50:00, and calls at stop1 at 24:00:00 and stop2 at 24:10:00
+    let mut gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    let trip = gtfs.trips.get_mut("tripA3").unwrap();
+    for (stop_time, time) in
+        trip.stop_times
+            .iter_mut()
+            .zip([23 * 3600 + 3000, 24 * 3600, 24 * 3600 + 600])
+    {
+        stop_time.arrival_time = Some(time);
+        stop_time.departure_time = Some(time);
+    }
+    let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    // The ride from stop1 at 24:00:00 is only listed on Tuesday
+    let connections = gtfs.connections(monday);
+    assert_eq!(4, connections.len());
+    let last = connections.last().unwrap();
+    assert_eq!(
+        ("tripA3", 23 * 3600 + 3000),
+        (last.trip.id.as_str(), last.departure_time)
+    );
+    assert!(connections
+        .windows(2)
+        .all(|pair| pair[0].departure_time <= pair[1].departure_time));
+
+    // On Tuesday, the ride of Monday from stop1 to stop2 leaves at midnight
+    let tuesday = monday.succ_opt().unwrap();
+    let connections = gtfs.connections(tuesday);
+    assert_eq!(5, connections.len());
+    let first = &connections[0];
+    assert_eq!(
+        ("tripA3", monday),
+        (first.trip.id.as_str(), first.service_date)
+    );
+    assert_eq!((0, 600), (first.departure_time, first.arrival_time));
+    assert_eq!("stop1", first.departure.stop.id);
+}
+
+#[test]
+fn generate_transfers() {
+    let gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    // stop1 is about 1.3 km away from the platforms and from stop2
+    let params = crate::TransferGeneration {
+        max_distance: 1500.,
+        walking_speed: 1.,
+    };
+    let transfers = gtfs.generate_transfers(&params);
+    let pairs: Vec<_> = transfers
+        .iter()
+        .map(|t| (t.from_stop_id.as_str(), t.to_stop_id.as_str()))
+        .collect();
+    // The transfers from the platforms to stop1 are given for platform1 and station1,
+    // and the one from stop2 to the platforms for station1
+    assert_eq!(
+        vec![
+            ("platform2", "platform1"),
+            ("stop1", "platform1"),
+            ("stop1", "platform2"),
+            ("stop1", "stop2"),
+            ("stop2", "stop1")
+        ],
+        pairs
+    );
+    let distance = crate::haversine(48.86, 2.36, 48.87, 2.37);
+    assert_eq!(Some(distance.ceil() as u32), transfers[3].min_transfer_time);
+    assert!(transfers
+        .iter()
+        .all(|t| t.transfer_type == TransferType::MinTime));
+
+    // Once added to the GTFS, the transfers are not generated again
+    let mut raw = RawGtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    if let Some(Ok(raw_transfers)) = &mut raw.transfers {
+        raw_transfers.extend(transfers);
+    }
+    let gtfs = Gtfs::try_from(raw).expect("impossible to build gtfs");
+    let transfer = gtfs.transfer_between("stop1", "platform1").unwrap();
+    assert_eq!(TransferType::MinTime, transfer.transfer.transfer_type);
+    assert!(gtfs.generate_transfers(&params).is_empty());
+}
+
+#[test]
+fn cluster_stops() {
+    assert_eq!("gare de l est", crate::normalize_name("  Gare de l’Est"));
+
+    let mut gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    let params = crate::ClusterParams::default();
+    // The platforms have the same position, but not the same name
+    assert_eq!(4, gtfs.cluster_stops(&params).clusters.len());
+
+    let mut platform = (*gtfs.stops["platform2"]).clone();
+    platform.name = Some("PLATFORM-1".to_owned());
+    gtfs.stops.insert("platform2".into(), Arc::new(platform));
+    let clusters = gtfs.cluster_stops(&params);
+    assert_eq!(3, clusters.clusters.len());
+    let cluster = &clusters.clusters[0];
+    assert_eq!("platform1", cluster.id);
+    assert_eq!(2, cluster.stops.len());
+    assert_eq!(Some("PLATFORM-1"), cluster.name.as_deref());
+    assert_eq!(
+        Some("platform1"),
+        clusters.mapping().get("platform2").copied()
+    );
+    assert!(clusters.cluster_of("station1").is_none());
+
+    let station = cluster.to_station("cluster1");
+    assert_eq!(LocationType::StopArea, station.location_type);
+    assert_eq!(Some(48.85), station.latitude);
+
+    // stop1 and stop2 are about 1.3 km apart, and are grouped with the platforms transitively
+    let params = crate::ClusterParams {
+        max_distance: 1500.,
+        same_name: false,
+    };
+    let clusters = gtfs.cluster_stops(&params);
+    assert_eq!(1, clusters.clusters.len());
+    assert_eq!("platform1", clusters.cluster_of("stop2").unwrap().id);
+}
+
+#[test]
+fn clean_headsign() {
+    let rules = crate::HeadsignRules::default();
+    assert_eq!("Airport", rules.clean("A > Airport", Some("A")));
+    // A headsign that is only the route name is kept
+    assert_eq!("A", rules.clean("A", Some("A")));
+    assert_eq!("Été-Nord", rules.clean("ÉTÉ-NORD", None));
+    assert_eq!("Gare de l'Est", rules.clean(" Gare de  l'Est", Some("")));
+
+    let mut gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    let trip = gtfs.trips.get_mut("tripA1").unwrap();
+    trip.trip_headsign = Some("A  -  STOP  ".to_owned());
+    assert_eq!(
+        Some("Stop".to_owned()),
+        gtfs.clean_headsign("tripA1", &rules).unwrap()
+    );
+    let unchanged = crate::HeadsignRules {
+        fallback_to_last_stop: false,
+        strip_route_name: false,
+        collapse_whitespace: false,
+        fix_capitals: false,
+    };
+    assert_eq!(
+        Some("A  -  STOP".to_owned()),
+        gtfs.clean_headsign("tripA1", &unchanged).unwrap()
+    );
+
+    gtfs.trips.get_mut("tripA1").unwrap().trip_headsign = Some(" ".to_owned());
+    assert_eq!(None, gtfs.clean_headsign("tripA1", &unchanged).unwrap());
+    assert_eq!(
+        Some("Stop".to_owned()),
+        gtfs.clean_headsign("tripA1", &rules).unwrap()
+    );
+    assert!(gtfs.clean_headsign("unknown", &rules).is_err());
+}
+
+#[test]
+fn continuations_of() {
+    let mut gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    assert!(gtfs.continuations_of("tripA1").unwrap().is_empty());
+
+    // On Mondays, the vehicle of tripA1 runs tripB1 from platform2, and tripA2 from stop1 the other days
+    let mut calendar = gtfs.calendar["service1"].clone();
+    calendar.id = "mondays".into();
+    calendar.tuesday = false;
+    calendar.wednesday = false;
+    calendar.thursday = false;
+    calendar.friday = false;
+    gtfs.calendar.insert(calendar.id.clone(), calendar);
+    gtfs.trips.get_mut("tripB1").unwrap().service_id = "mondays".into();
+    for trip_id in ["tripA1", "tripA2", "tripB1"] {
+        gtfs.trips.get_mut(trip_id).unwrap().block_id = Some("block1".into());
+    }
+
+    let continuations = gtfs.continuations_of("tripA1").unwrap();
+    let next: Vec<_> = continuations
+        .iter()
+        .map(|c| (c.trip.id.as_str(), c.layover, c.same_stop))
+        .collect();
+    assert_eq!(
+        vec![("tripB1", 20 * 60, false), ("tripA2", 50 * 60, true)],
+        next
+    );
+    assert_eq!("routeB", continuations[0].route.unwrap().id);
+    // tripA2 is the last trip of the block
+    assert!(gtfs.continuations_of("tripA2").unwrap().is_empty());
+    assert!(gtfs.continuations_of("unknown").is_err());
+}
+
+#[test]
+fn service_exceptions() {
+    use crate::ServiceException;
+    let mut gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    assert!(gtfs.service_exceptions("service1").unwrap().is_empty());
+    assert!(gtfs.service_exceptions("unknown").is_err());
+
+    let day = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+    let calendar_date = |date, exception_type| CalendarDate {
+        service_id: "service1".into(),
+        date,
+        exception_type,
+    };
+    // service1 runs on the weekdays: the weekend of 2024-07-13 is added, and the two following weeks removed.
+    // 2024-12-28 is a Saturday, removing it changes nothing
+    let mut dates: Vec<_> = (13..=26)
+        .map(|d| {
+            let exception_type = if d < 15 {
+                Exception::Added
+            } else {
+                Exception::Deleted
+            };
+            calendar_date(day(7, d), exception_type)
+        })
+        .collect();
+    dates.push(calendar_date(day(12, 25), Exception::Deleted));
+    dates.push(calendar_date(day(12, 28), Exception::Deleted));
+    gtfs.calendar_dates.insert("service1".into(), dates);
+
+    let exceptions = gtfs.service_exceptions("service1").unwrap();
+    assert_eq!(
+        vec![
+            ServiceException::ExtraService {
+                start: day(7, 13),
+                end: day(7, 14)
+            },
+            ServiceException::NoService {
+                start: day(7, 15),
+                end: day(7, 26)
+            },
+            ServiceException::NoService {
+                start: day(12, 25),
+                end: day(12, 25)
+            },
+        ],
+        exceptions
+    );
+    assert_eq!(
+        "no service from 2024-07-15 to 2024-07-26",
+        exceptions[1].to_string()
+    );
+    assert_eq!("no service on 2024-12-25", exceptions[2].to_string());
+}
+
+#[test]
+fn calendar_dates_iterator() {
+    let mut gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    // 2024 has 262 weekdays, from Monday 2024-01-01 to Tuesday 2024-12-31
+    let dates: Vec<_> = gtfs.calendar["service1"].dates().collect();
+    assert_eq!(262, dates.len());
+    assert_eq!(NaiveDate::from_ymd_opt(2024, 1, 1), dates.first().copied());
+    assert_eq!(NaiveDate::from_ymd_opt(2024, 12, 31), dates.last().copied());
+    assert!(dates.iter().all(|d| d.weekday().num_days_from_monday() < 5));
+
+    let saturday = NaiveDate::from_ymd_opt(2024, 3, 2).unwrap();
+    let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    gtfs.calendar_dates.insert(
+        "service1".into(),
+        vec![
+            CalendarDate {
+                service_id: "service1".into(),
+                date: saturday,
+                exception_type: Exception::Added,
+            },
+            CalendarDate {
+                service_id: "service1".into(),
+                date: monday,
+                exception_type: Exception::Deleted,
+            },
+        ],
+    );
+    let dates: Vec<_> = gtfs.service_dates("service1").collect();
+    assert_eq!(262, dates.len());
+    assert!(dates.contains(&saturday));
+    assert!(!dates.contains(&monday));
+    assert!(dates.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(0, gtfs.service_dates("unknown").count());
+}
+
+#[test]
+fn route_service_levels() {
+    use crate::{ServiceLevel, ServiceLevelRules};
+    let mut gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    let rules = ServiceLevelRules::default();
+    let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    assert!(gtfs.route_service_level("unknown", monday, &rules).is_err());
+
+    // tripB1 runs every 15 minutes from 05:00 to 21:00
+    gtfs.trips.get_mut("tripB1").unwrap().frequencies = vec![Frequency {
+        start_time: 5 * 3600,
+        end_time: 21 * 3600,
+        headway_secs: 900,
+        exact_times: None,
+    }];
+    assert_eq!(
+        ServiceLevel::Frequent,
+        gtfs.route_service_level("routeB", monday, &rules).unwrap()
+    );
+    let rules = ServiceLevelRules {
+        frequent_trips_per_hour: 6,
+        ..Default::default()
+    };
+    assert_eq!(
+        ServiceLevel::Standard,
+        gtfs.route_service_level("routeB", monday, &rules).unwrap()
+    );
+
+    let levels = gtfs.route_service_levels("routeB", monday, &rules).unwrap();
+    assert_eq!(ServiceLevel::Standard, levels.weekday);
+    assert_eq!(ServiceLevel::NoService, levels.saturday);
+    assert_eq!(
+        r#"{"weekday":"standard","saturday":"no_service","sunday":"no_service"}"#,
+        serde_json::to_string(&levels).unwrap()
+    );
+}
+
+#[test]
+fn stop_sequence_issues() {
+    use crate::StopSequenceIssue;
+    let mut gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    {
+        let stop_times = &mut gtfs.trips.get_mut("trip1").unwrap().stop_times;
+        stop_times[1].stop_sequence = 0;
+        stop_times[2].stop_sequence = 0;
+        // Without time, the stop times can not be reordered
+        stop_times[2].arrival_time = None;
+        stop_times[2].departure_time = None;
+    }
+    assert_eq!(
+        vec![StopSequenceIssue::Repeated {
+            trip_id: "trip1",
+            stop_sequence: 0
+        }],
+        gtfs.stop_sequence_issues()
+    );
+
+    assert_eq!(1, gtfs.repair_stop_sequences());
+    let trip = gtfs.get_trip("trip1").unwrap();
+    assert_eq!(
+        vec![("stop2", 1), ("stop3", 2), ("stop4", 3)],
+        trip.stop_times
+            .iter()
+            .map(|st| (st.stop.id.as_str(), st.stop_sequence))
+            .collect::<Vec<_>>()
+    );
+    assert!(gtfs.stop_sequence_issues().is_empty());
+    assert_eq!(0, gtfs.repair_stop_sequences());
+}
+
+#[test]
+fn decreasing_times() {
+    use crate::DecreasingTimeKind;
+    let mut gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    {
+        let stop_times = &mut gtfs.trips.get_mut("trip1").unwrap().stop_times;
+        // 15:00:00 -> 14:30:00
+        stop_times[1].departure_time = Some(14 * 3600 + 1800);
+        // Without arrival time, the second stop is only compared by its departure:
+        // the arrival at the third stop at 14:10:00 is before it
+        stop_times[1].arrival_time = None;
+        stop_times[2].arrival_time = Some(14 * 3600 + 600);
+        stop_times[2].departure_time = Some(14 * 3600 + 600);
+    }
+    let decreasing: Vec<_> = gtfs
+        .decreasing_times()
+        .iter()
+        .map(|d| (d.trip_id, d.index, d.stop_sequence, d.kind))
+        .collect();
+    assert_eq!(
+        vec![(
+            "trip1",
+            2,
+            2,
+            DecreasingTimeKind::ArrivalBeforePreviousDeparture
+        )],
+        decreasing
+    );
+
+    let stop_times = &mut gtfs.trips.get_mut("trip1").unwrap().stop_times;
+    stop_times[1].arrival_time = Some(15 * 3600);
+    stop_times[2].arrival_time = Some(16 * 3600);
+    stop_times[2].departure_time = Some(16 * 3600);
+    let decreasing = gtfs.decreasing_times();
+    assert_eq!(1, decreasing.len());
+    assert_eq!(
+        DecreasingTimeKind::DepartureBeforeArrival,
+        decreasing[0].kind
+    );
+    assert_eq!(15 * 3600, decreasing[0].previous_time);
+    assert_eq!(14 * 3600 + 1800, decreasing[0].time);
+}
+
+#[test]
+fn invalid_coordinates() {
+    use crate::CoordinateIssue;
+    let mut gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    // stop6 is a generic node, its coordinates are optional
+    assert!(gtfs.invalid_coordinates().is_empty());
+
+    let mut set = |id: &str, latitude, longitude| {
+        let stop = std::sync::Arc::make_mut(gtfs.stops.get_mut(id).unwrap());
+        stop.latitude = latitude;
+        stop.longitude = longitude;
+    };
+    set("stop1", None, None);
+    set("stop2", Some(0.0), Some(0.0));
+    set("stop3", Some(48.8), Some(-181.0));
+    set("stop4", Some(48.8), None);
+    set("stop6", Some(f64::NAN), Some(2.4));
+    let invalid: Vec<_> = gtfs
+        .invalid_coordinates()
+        .iter()
+        .map(|i| (i.stop.id.as_str(), i.issue))
+        .collect();
+    assert_eq!(
+        vec![
+            ("stop1", CoordinateIssue::Missing),
+            ("stop2", CoordinateIssue::NullIsland),
+            ("stop3", CoordinateIssue::OutOfRange),
+            ("stop4", CoordinateIssue::Missing),
+            ("stop6", CoordinateIssue::OutOfRange),
+        ],
+        invalid
+    );
+
+    // The findings are in the report of the built GTFS
+    let mut raw = RawGtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    for stop in raw.stops.as_mut().unwrap() {
+        if stop.id == "stop2" {
+            stop.latitude = Some(91.0);
+        }
+    }
+    let gtfs = Gtfs::try_from(raw).unwrap();
+    assert!(!gtfs.validation_report.is_valid());
+    assert_eq!(
+        vec!["stops.txt: the coordinates of 'stop2' are out of range"],
+        gtfs.validation_report
+            .invalid_coordinates
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn stops_far_from_shape() {
+    let mut gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    // A shape going north from the stops of the fixture
+    let point = |sequence, latitude| Shape {
+        id: "north".into(),
+        latitude,
+        longitude: 2.449386,
+        sequence,
+        dist_traveled: None,
+    };
+    gtfs.shapes.insert(
+        "north".into(),
+        vec![point(0, 48.796058), point(1, 48.806058)],
+    );
+    gtfs.trips.get_mut("trip1").unwrap().shape_id = Some("north".into());
+    assert!(gtfs.stops_far_from_shape(10.).unwrap().is_empty());
+
+    // 0.01° of longitude east of the shape, and 0.01° of latitude north of its end
+    let stop = std::sync::Arc::make_mut(gtfs.stops.get_mut("stop4").unwrap());
+    stop.longitude = Some(2.459386);
+    let stop = std::sync::Arc::make_mut(gtfs.stops.get_mut("stop3").unwrap());
+    stop.latitude = Some(48.816058);
+    gtfs.trips.get_mut("trip1").unwrap().stop_times = gtfs.trips["trip1"]
+        .stop_times
+        .iter()
+        .map(|st| StopTime {
+            stop: gtfs.stops[st.stop.id.as_str()].clone(),
+            ..st.clone()
+        })
+        .collect();
+    let far: Vec<_> = gtfs
+        .stops_far_from_shape(500.)
+        .unwrap()
+        .iter()
+        .map(|f| (f.index, f.distance.round()))
+        .collect();
+    assert_eq!(vec![(1, 1112.), (2, 732.)], far);
+    assert_eq!(1, gtfs.stops_far_from_shape(1000.).unwrap().len());
+}
+
+#[test]
+fn implausible_speeds() {
+    use crate::{SpeedIssue, SpeedRules};
+    let mut gtfs = Gtfs::from_path("fixtures/stations").expect("impossible to read gtfs");
+    // stop2 is moved 1° north, more than 100 km away from the other stops
+    for trip in gtfs.trips.values_mut() {
+        for stop_time in trip.stop_times.iter_mut() {
+            if stop_time.stop.id == "stop2" {
+                Arc::make_mut(&mut stop_time.stop).latitude = Some(49.87);
+            }
+        }
+    }
+    // Without time at stop1, tripA3 goes from platform2 to stop2 in 20 minutes
+    let trip = gtfs.trips.get_mut("tripA3").unwrap();
+    trip.stop_times[1].arrival_time = None;
+    trip.stop_times[1].departure_time = None;
+
+    let speeds: Vec<_> = gtfs
+        .implausible_speeds(&SpeedRules::default())
+        .unwrap()
+        .iter()
+        .map(|s| {
+            (
+                s.trip.id.as_str(),
+                s.from_index,
+                s.to_index,
+                s.duration,
+                s.issue,
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            ("tripA3", 0, 2, 1200, SpeedIssue::TooFast),
+            ("tripB1", 0, 1, 900, SpeedIssue::TooFast),
+        ],
+        speeds
+    );
+    let distance = gtfs.implausible_speeds(&SpeedRules::default()).unwrap()[0].distance;
+    // Through stop1, even without time
+    assert_eq!(114., (distance / 1000.).round());
+
+    // A plane would fly that fast
+    let mut rules = SpeedRules::default();
+    rules.max_speeds.insert(RouteType::Bus, 1000. / 3.6);
+    assert!(gtfs.implausible_speeds(&rules).unwrap().is_empty());
+}
+
+#[test]
+fn feed_date_issues() {
+    use crate::FeedDateIssue;
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    let mut gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    gtfs.feed_info[0].start_date = Some(date(2017, 1, 10));
+    gtfs.feed_info[0].end_date = Some(date(2017, 1, 31));
+    assert_eq!(
+        vec![
+            FeedDateIssue::Expired {
+                end: date(2017, 1, 31)
+            },
+            // 2017-01-01 and 2017-01-02 are removed
+            FeedDateIssue::ServiceOutsideFeed {
+                service_id: "service1",
+                first: date(2017, 1, 7),
+                last: date(2017, 1, 8)
+            },
+            FeedDateIssue::ServiceOutsideFeed {
+                service_id: "service2",
+                first: date(2017, 1, 1),
+                last: date(2017, 1, 1)
+            },
+        ],
+        gtfs.feed_date_issues(date(2018, 1, 1), 30)
+    );
+
+    // Without feed dates, the feed ends with the last day of service1
+    gtfs.feed_info.clear();
+    assert_eq!(
+        vec![FeedDateIssue::ExpiresSoon {
+            end: date(2017, 1, 15),
+            days: 14
+        }],
+        gtfs.feed_date_issues(date(2017, 1, 1), 30)
+    );
+    assert!(gtfs.feed_date_issues(date(2017, 1, 1), 14).is_empty());
+}
+
+#[test]
+fn invalid_fields() {
+    use crate::FieldFormat;
+    for url in ["http://example.com", "https://a.example.com:8080/x?y=1#z"] {
+        assert!(FieldFormat::Url.is_valid(url), "{}", url);
+    }
+    for url in [
+        "example.com",
+        "https://",
+        "https://exa mple.com",
+        "ftp://example.com",
+    ] {
+        assert!(!FieldFormat::Url.is_valid(url), "{}", url);
+    }
+    assert!(!FieldFormat::Email.is_valid("contact@localhost"));
+    assert!(FieldFormat::Phone.is_valid("+33 (0)1 23-45-67-89"));
+    assert!(FieldFormat::Phone.is_valid("1-800-FLOWERS"));
+    assert!(!FieldFormat::Phone.is_valid("unknown"));
+    assert!(FieldFormat::Timezone.is_valid("America/Argentina/Buenos_Aires"));
+    assert!(FieldFormat::Timezone.is_valid("UTC"));
+    assert!(!FieldFormat::Timezone.is_valid("Paris"));
+    assert!(!FieldFormat::Timezone.is_valid("+01:00"));
+
+    let mut gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    gtfs.agencies[1].email = Some("ter.sncf.com".to_owned());
+    gtfs.routes.get_mut("1").unwrap().url = Some("".to_owned());
+    std::sync::Arc::make_mut(gtfs.stops.get_mut("stop1").unwrap()).timezone =
+        Some("Europe Paris".into());
+    gtfs.fare_attributes.get_mut("50").unwrap().currency = "eur".to_owned();
+    let invalid: Vec<_> = gtfs
+        .invalid_fields()
+        .iter()
+        .map(|i| (i.object_id, i.field_name, i.format))
+        .collect();
+    assert_eq!(
+        vec![
+            ("Ter", "agency_email", FieldFormat::Email),
+            ("stop1", "stop_timezone", FieldFormat::Timezone),
+            ("50", "currency_type", FieldFormat::Currency),
+        ],
+        invalid
+    );
+}
+
+#[test]
+fn trip_clone() {
+    let gtfs = Gtfs::from_path("fixtures/basic/").unwrap();
+    let _: Trip = gtfs.trips.get("trip1").unwrap().clone();
+}
+
+#[test]
+fn read_from_gtfs() {
+    let gtfs = Gtfs::from_path("fixtures/zips/gtfs.zip").unwrap();
+    assert_eq!(1, gtfs.calendar.len());
+    assert_eq!(2, gtfs.calendar_dates.len());
+    assert_eq!(5, gtfs.stops.len());
+    assert_eq!(1, gtfs.routes.len());
+    assert_eq!(1, gtfs.trips.len());
+    assert_eq!(1, gtfs.shapes.len());
+    assert_eq!(1, gtfs.fare_attributes.len());
+    assert_eq!(1, gtfs.feed_info.len());
+    assert_eq!(2, gtfs.get_trip("trip1").unwrap().stop_times.len());
+
+    assert!(gtfs.get_calendar("service1").is_ok());
+    assert!(gtfs.get_calendar_date("service1").is_ok());
+    assert!(gtfs.get_stop("stop1").is_ok());
+    assert!(gtfs.get_route("1").is_ok());
+    assert!(gtfs.get_trip("trip1").is_ok());
+    assert!(gtfs.get_fare_attributes("50").is_ok());
+
+    assert!(gtfs.get_stop("Utopia").is_err());
+}
+
+#[test]
+fn read_from_subdirectory() {
+    let gtfs = Gtfs::from_path("fixtures/zips/subdirectory.zip").unwrap();
+    assert_eq!(1, gtfs.calendar.len());
+    assert_eq!(2, gtfs.calendar_dates.len());
+    assert_eq!(5, gtfs.stops.len());
+    assert_eq!(1, gtfs.routes.len());
+    assert_eq!(1, gtfs.trips.len());
+    assert_eq!(1, gtfs.shapes.len());
+    assert_eq!(1, gtfs.fare_attributes.len());
+    assert_eq!(2, gtfs.get_trip("trip1").unwrap().stop_times.len());
+}
+
+#[test]
+fn read_archive_subdirectory() {
+    let brest = GtfsReader::default()
+        .archive_subdirectory("brest/")
+        .read_from_path("fixtures/zips/multiple_datasets.zip")
+        .expect("impossible to read gtfs");
+    assert_eq!(5, brest.stops.len());
+    assert!(brest.get_route("1").is_ok());
+
+    let basic = GtfsReader::default()
+        .archive_subdirectory("basic")
+        .raw()
+        .read_from_path("fixtures/zips/multiple_datasets.zip")
+        .expect("impossible to read gtfs");
+    assert_eq!(6, basic.stops.unwrap().len());
+    assert_eq!(1, basic.pathways.unwrap().unwrap().len());
+
+    let missing = GtfsReader::default()
+        .archive_subdirectory("paris/")
+        .raw()
+        .read_from_path("fixtures/zips/multiple_datasets.zip")
+        .expect("impossible to read gtfs");
+    assert!(matches!(missing.stops, Err(crate::Error::MissingFile(_))));
+}
+
+#[test]
+fn case_insensitive_file_names() {
+    let gtfs = RawGtfs::from_path("fixtures/zips/uppercase.zip").expect("impossible to read gtfs");
+    assert!(matches!(gtfs.stops, Err(crate::Error::MissingFile(_))));
+
+    let gtfs = GtfsReader::default()
+        .case_insensitive_file_names(true)
+        .read_from_path("fixtures/zips/uppercase.zip")
+        .expect("impossible to read gtfs");
+    assert_eq!(5, gtfs.stops.len());
+    assert_eq!(1, gtfs.routes.len());
+    assert_eq!(2, gtfs.get_trip("trip1").unwrap().stop_times.len());
+}
+
+#[test]
+fn custom_file_names() {
+    let gtfs = RawGtfs::from_path("fixtures/zips/renamed.zip").expect("impossible to read gtfs");
+    assert!(matches!(gtfs.stops, Err(crate::Error::MissingFile(_))));
+
+    let gtfs = GtfsReader::default()
+        .file_name(TableName::Stops, "arrets.txt")
+        .file_name(TableName::Routes, "routes.csv")
+        .read_from_path("fixtures/zips/renamed.zip")
+        .expect("impossible to read gtfs");
+    assert_eq!(5, gtfs.stops.len());
+    assert_eq!(1, gtfs.routes.len());
+
+    let gtfs = GtfsReader::default()
+        .file_name(TableName::Stops, "arrets.txt")
+        .raw()
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    match gtfs.stops {
+        Err(crate::Error::MissingFile(file_name)) => assert_eq!("arrets.txt", file_name),
+        _ => panic!("arrets.txt does not exist in fixtures/basic"),
+    }
+}
+
+#[test]
+fn only_custom_file_names() {
+    let gtfs = GtfsReader::default()
+        .file_name(TableName::Stops, "arrets.txt")
+        .only(&["arrets.txt"])
+        .raw()
+        .read_from_path("fixtures/zips/renamed.zip")
+        .expect("impossible to read gtfs");
+    assert_eq!(5, gtfs.stops.unwrap().len());
+    assert!(gtfs.trips.unwrap().is_empty());
+
+    // The default name of a renamed table is not read
+    let gtfs = GtfsReader::default()
+        .file_name(TableName::Routes, "routes.csv")
+        .only(&["stops.txt", "routes.txt"])
+        .raw()
+        .read_from_path("fixtures/zips/gtfs.zip")
+        .expect("impossible to read gtfs");
+    assert_eq!(5, gtfs.stops.unwrap().len());
+    assert!(gtfs.routes.unwrap().is_empty());
+}
+
+#[test]
+fn semicolon_delimiter() {
+    let gtfs = RawGtfs::from_path("fixtures/semicolon").expect("impossible to read gtfs");
+    assert!(gtfs.stops.is_err());
+
+    for delimiter in [Delimiter::Custom(b';'), Delimiter::Detect] {
+        let gtfs = GtfsReader::default()
+            .delimiter(delimiter)
+            .raw()
+            .read_from_path("fixtures/semicolon")
+            .expect("impossible to read gtfs");
+        let stops = gtfs.stops.expect("impossible to read stops");
+        assert_eq!(6, stops.len());
+        assert_eq!("Stop Area", stops[0].name.as_ref().unwrap());
+        assert_eq!(2, gtfs.agencies.unwrap().len());
+    }
+
+    // Detecting the delimiter of a standard GTFS keeps the comma
+    let gtfs = GtfsReader::default()
+        .delimiter(Delimiter::Detect)
+        .read_from_path("fixtures/zips/gtfs.zip")
+        .expect("impossible to read gtfs");
+    assert_eq!(5, gtfs.stops.len());
+}
+
+#[test]
+fn cancel_reading() {
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    let reader = GtfsReader::default().cancel_flag(cancel_flag.clone());
+    cancel_flag.store(true, Ordering::Relaxed);
+    assert!(matches!(
+        reader.read_from_path("fixtures/basic"),
+        Err(crate::Error::Cancelled)
+    ));
+
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    let gtfs = GtfsReader::default()
+        .cancel_flag(cancel_flag)
+        .read_from_path("fixtures/basic");
+    assert!(gtfs.is_ok());
+}
+
+#[test]
+fn read_from_stream() {
+    // A reader that can only be read, like a pipe
+    struct Pipe(std::fs::File);
+    impl std::io::Read for Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+    let pipe = Pipe(std::fs::File::open("fixtures/zips/gtfs.zip").unwrap());
+    let gtfs = Gtfs::from_stream(pipe).expect("impossible to read gtfs");
+    assert_eq!(5, gtfs.stops.len());
+    assert_eq!(2, gtfs.get_trip("trip1").unwrap().stop_times.len());
+}
+
+#[test]
+fn read_zip64() {
+    let gtfs = Gtfs::from_path("fixtures/zips/zip64.zip").expect("impossible to read gtfs");
+    assert_eq!(5, gtfs.stops.len());
+    assert_eq!(2, gtfs.get_trip("trip1").unwrap().stop_times.len());
+
+    // The stop times are written with zip64 extensions
+    let gtfs = Gtfs::from_path("fixtures/basic").unwrap();
+    let archive = GtfsWriter::default()
+        .write_to_writer(&gtfs, std::io::Cursor::new(Vec::new()))
+        .unwrap();
+    // The local header of a zip64 file has a zip64 extra field (id 0x0001) after its name
+    let bytes = archive.into_inner();
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(&bytes)).unwrap();
+    let mut zip64_extra_field = |name: &str| {
+        let header_start = zip.by_name(name).unwrap().header_start() as usize;
+        let header = &bytes[header_start..];
+        let name_len = u16::from_le_bytes([header[26], header[27]]) as usize;
+        header[30 + name_len..].starts_with(&[0x01, 0x00])
+    };
+    assert!(zip64_extra_field("stop_times.txt"));
+    assert!(!zip64_extra_field("stops.txt"));
+    let written =
+        Gtfs::from_reader(std::io::Cursor::new(bytes)).expect("impossible to read written gtfs");
+    assert_eq!(
+        gtfs.get_trip("trip1").unwrap().stop_times.len(),
+        written.get_trip("trip1").unwrap().stop_times.len()
+    );
+}
+
+#[test]
+fn stop_times_iter() {
+    let stop_times: Vec<_> = RawGtfs::stop_times_iter("fixtures/basic")
+        .expect("impossible to read stop times")
+        .collect::<Result<_, _>>()
+        .expect("invalid stop time");
+    let raw = RawGtfs::from_path("fixtures/basic").unwrap();
+    assert_eq!(raw.stop_times.unwrap().len(), stop_times.len());
+    assert_eq!("stop2", stop_times[0].stop_id);
+
+    // Stopping the iteration early is fine
+    let mut stop_times = RawGtfs::stop_times_iter("fixtures/zips/gtfs.zip").unwrap();
+    assert!(stop_times.next().unwrap().is_ok());
+    drop(stop_times);
+
+    let errors: Vec<_> = RawGtfs::stop_times_iter("fixtures/invalid_rows")
+        .unwrap()
+        .collect();
+    assert!(matches!(errors[..], [Err(crate::Error::MissingFile(_))]));
+
+    // The parse mode applies to the unknown values
+    let stop_times: Vec<_> = GtfsReader::default()
+        .parse_mode(ParseMode::Strict)
+        .stop_times_iter("fixtures/unknown_enum_values")
+        .unwrap()
+        .collect();
+    assert!(matches!(
+        stop_times[..],
+        [Ok(_), Err(crate::Error::UnknownEnumValue { .. })]
+    ));
+    let stop_times: Vec<_> = GtfsReader::default()
+        .unkown_enum_as_default(true)
+        .stop_times_iter("fixtures/unknown_enum_values")
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(PickupDropOffType::Regular, stop_times[1].pickup_type);
+}
+
+#[test]
+fn visit_records() {
+    let mut stop_ids = Vec::new();
+    GtfsReader::default()
+        .visit_records("fixtures/basic", TableName::Stops, |row| {
+            stop_ids.push(row.get("stop_id").unwrap().to_owned());
+            true
+        })
+        .unwrap();
+    let raw = RawGtfs::from_path("fixtures/basic").unwrap();
+    assert_eq!(raw.stops.unwrap().len(), stop_ids.len());
+
+    let mut stop_times = Vec::new();
+    GtfsReader::default()
+        .visit_records("fixtures/zips/gtfs.zip", TableName::StopTimes, |row| {
+            let stop_time: RawStopTimeRef = row.deserialize().unwrap();
+            stop_times.push((stop_time.trip_id.to_owned(), stop_time.arrival_time));
+            false
+        })
+        .unwrap();
+    assert_eq!(vec![("trip1".to_owned(), Some(14 * 3600))], stop_times);
+
+    let missing =
+        GtfsReader::default().visit_records("fixtures/basic", TableName::FareRules, |_| true);
+    assert!(matches!(missing, Err(crate::Error::MissingFile(_))));
+}
+
+#[test]
+fn read_stop_times_into_trips() {
+    let gtfs = Gtfs::from_path("fixtures/basic").unwrap();
+    let converted = Gtfs::try_from(RawGtfs::from_path("fixtures/basic").unwrap()).unwrap();
+    assert_eq!(gtfs.trips.len(), converted.trips.len());
+    for (id, trip) in &gtfs.trips {
+        let stop_ids = |t: &Trip| -> Vec<GtfsId> {
+            t.stop_times.iter().map(|st| st.stop.id.clone()).collect()
+        };
+        assert_eq!(stop_ids(trip), stop_ids(&converted.trips[id]));
+    }
+
+    // The parse mode is also applied to the stop times that are directly linked to the trips
+    let gtfs = GtfsReader::default()
+        .unkown_enum_as_default(true)
+        .read_from_path("fixtures/basic")
+        .unwrap();
+    assert_eq!(
+        PickupDropOffType::Regular,
+        gtfs.trips["trip1"].stop_times[2].drop_off_type
+    );
+}
+
+#[test]
+fn read_compact() {
+    let gtfs = Gtfs::from_path("fixtures/basic").unwrap();
+    let compact = crate::CompactGtfs::from_path("fixtures/basic").unwrap();
+    assert_eq!(gtfs.stops.len(), compact.stops.len());
+    assert_eq!(gtfs.trips.len(), compact.trips.len());
+    for (id, trip) in &gtfs.trips {
+        let compact_trip = compact.get_trip(id).unwrap();
+        assert_eq!(trip.stop_times.len(), compact_trip.stop_times.len());
+        for (st, compact_st) in trip.stop_times.iter().zip(&compact_trip.stop_times) {
+            assert_eq!(st.stop.id, compact.stop_of(compact_st).id);
+            assert_eq!(st.arrival_time, compact_st.arrival_time);
+        }
+    }
+    assert_eq!(1, compact.get_stop("stop1").unwrap().pathways.len());
+
+    let converted = crate::CompactGtfs::from(gtfs);
+    assert_eq!(compact.stop_indices, converted.stop_indices);
+    assert_eq!(3, converted.get_trip("trip1").unwrap().stop_times.len());
+}
+
+#[test]
+fn stop_time_columns() {
+    let mut compact = crate::CompactGtfs::from_path("fixtures/basic").unwrap();
+    let columns = compact.stop_time_columns();
+    let stop_times: usize = compact.trips.values().map(|t| t.stop_times.len()).sum();
+    assert_eq!(stop_times, columns.len());
+    assert_eq!(stop_times, columns.iter().count());
+    for (id, range) in columns.trips() {
+        let trip = compact.get_trip(id).unwrap();
+        assert_eq!(trip.stop_times.len(), range.len());
+        for (st, position) in trip.stop_times.iter().zip(range) {
+            assert_eq!(id, columns.trip_id(position));
+            assert_eq!(st.stop_index, columns.stop_indices[position]);
+            assert_eq!(st.departure_time, columns.departure_times[position]);
+            assert_eq!(
+                st.stop_sequence,
+                columns.get(position).unwrap().stop_sequence
+            );
+        }
+    }
+    assert_eq!(0, columns.stop_times_of_trip("unknown").count());
+    assert!(columns.get(columns.len()).is_none());
+
+    let taken = compact.take_stop_time_columns();
+    assert!(compact.trips.values().all(|t| t.stop_times.is_empty()));
+    assert_eq!(columns.stop_indices, taken.stop_indices);
+    assert_eq!(columns.arrival_times, taken.arrival_times);
+    assert_eq!(
+        columns.trips().collect::<Vec<_>>(),
+        taken.trips().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn sorted_storage() {
+    let gtfs = Gtfs::from_path("fixtures/basic").unwrap();
+    let sorted = GtfsReader::default()
+        .sorted_storage(true)
+        .read_from_path("fixtures/basic")
+        .unwrap();
+    assert!(sorted.stops.is_sorted_by_id());
+    assert_eq!(gtfs.calendar, sorted.calendar);
+    assert_eq!(gtfs.fare_attributes, sorted.fare_attributes);
+    let stop_ids: Vec<_> = sorted.stops.keys().collect();
+    assert!(stop_ids.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(3, sorted.get_trip("trip1").unwrap().stop_times.len());
+    assert!(sorted.get_stop("unknown").is_err());
+
+    let mut routes = sorted.routes.clone();
+    let mut route = routes.remove("1").unwrap();
+    assert!(!routes.contains_key("1"));
+    route.id = "0".into();
+    routes.insert(route.id.clone(), route);
+    assert_eq!("0", routes.keys().next().unwrap().as_str());
+
+    let compact = GtfsReader::default()
+        .sorted_storage(true)
+        .read_compact_from_path("fixtures/basic")
+        .unwrap();
+    assert!(compact.trips.is_sorted_by_id());
+    assert_eq!("stop1", compact.get_stop("stop1").unwrap().id);
+}
+
+#[test]
+fn display() {
+    assert_eq!(
+        "Sorano".to_owned(),
+        format!(
+            "{}",
+            Stop {
+                name: Some("Sorano".to_owned()),
+                ..Stop::default()
+            }
+        )
+    );
+
+    assert_eq!(
+        "Long route name".to_owned(),
+        format!(
+            "{}",
+            Route {
+                long_name: Some("Long route name".to_owned()),
+                short_name: None,
+                ..Route::default()
+            }
+        )
+    );
+
+    assert_eq!(
+        "Short route name".to_owned(),
+        format!(
+            "{}",
+            Route {
+                short_name: Some("Short route name".to_owned()),
+                long_name: None,
+                ..Route::default()
+            }
+        )
+    );
+}
+
+#[test]
+fn path_files() {
+    let gtfs = RawGtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    assert_eq!(gtfs.files.len(), 14);
+    assert_eq!(gtfs.source_format, SourceFormat::Directory);
+    assert!(gtfs.files.contains(&"agency.txt".to_owned()));
+}
+
+#[test]
+fn subdirectory_files() {
+    // by default, subdirectories are not searched when reading from a path (the path can be given explicitly)
+    // see GtfsReader::search_subdirectories
+    let gtfs = RawGtfs::from_path("fixtures/subdirectory").expect("impossible to read gtfs");
+    // no files can be read
+    assert!(gtfs.stops.is_err());
+    assert!(gtfs.routes.is_err());
+    assert!(gtfs.agencies.is_err());
+
+    assert_eq!(gtfs.files, vec!["gtfs".to_string()]);
+}
+
+#[test]
+fn search_subdirectories() {
+    let gtfs = GtfsReader::default()
+        .search_subdirectories(true)
+        .raw()
+        .read_from_path("fixtures/subdirectory")
+        .expect("impossible to read gtfs");
+    assert!(gtfs.stops.is_ok());
+    assert!(gtfs.agencies.is_ok());
+    assert!(gtfs.files.contains(&"gtfs/stops.txt".to_owned()));
+
+    // A directory with GTFS files is read as usual
+    let gtfs = GtfsReader::default()
+        .search_subdirectories(true)
+        .raw()
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    assert!(gtfs.files.contains(&"stops.txt".to_owned()));
+}
+
+#[test]
+fn zip_files() {
+    let gtfs = RawGtfs::from_path("fixtures/zips/gtfs.zip").expect("impossible to read gtfs");
+    assert_eq!(gtfs.files.len(), 10);
+    assert_eq!(gtfs.source_format, SourceFormat::Zip);
+    assert!(gtfs.files.contains(&"agency.txt".to_owned()));
+}
+
+#[test]
+fn zip_subdirectory_files() {
+    let gtfs =
+        RawGtfs::from_path("fixtures/zips/subdirectory.zip").expect("impossible to read gtfs");
+    assert_eq!(gtfs.files.len(), 11);
+    assert_eq!(gtfs.source_format, SourceFormat::Zip);
+    assert!(gtfs.files.contains(&"subdirectory/agency.txt".to_owned()));
+}
+
+#[test]
+fn compute_sha256() {
+    let gtfs = RawGtfs::from_path("fixtures/zips/gtfs.zip").expect("impossible to read gtfs");
+    assert_eq!(
+        gtfs.sha256,
+        Some("4a262ae109101ffbd1629b67e080a2b074afdaa60d57684db0e1a31c0a1e75b0".to_owned())
+    );
+}
+
+#[test]
+fn mmap_sha256_matches_reader() {
+    // With the feature `mmap`, the archive is mapped in memory instead of being read
+    let gtfs = RawGtfs::from_path("fixtures/zips/gtfs.zip").expect("impossible to read gtfs");
+    let file = std::fs::File::open("fixtures/zips/gtfs.zip").unwrap();
+    assert_eq!(gtfs.sha256, RawGtfs::from_reader(file).unwrap().sha256);
+}
+
+#[test]
+fn test_bom() {
+    let gtfs =
+        RawGtfs::from_path("fixtures/zips/gtfs_with_bom.zip").expect("impossible to read gtfs");
+    assert_eq!(gtfs.agencies.expect("agencies missing").len(), 2);
+}
+
+#[test]
+fn test_macosx() {
+    let gtfs = RawGtfs::from_path("fixtures/zips/macosx.zip").expect("impossible to read gtfs");
+    assert_eq!(gtfs.agencies.expect("agencies missing").len(), 2);
+    assert_eq!(gtfs.stops.expect("stops missing").len(), 5);
+}
+
+#[test]
+fn read_missing_feed_dates() {
+    let gtfs = Gtfs::from_path("fixtures/missing_feed_date").expect("impossible to read gtfs");
+    assert_eq!(1, gtfs.feed_info.len());
+    assert!(gtfs.feed_info[0].start_date.is_none());
+}
+
+#[test]
+fn read_interpolated_stops() {
+    let gtfs =
+        Gtfs::from_path("fixtures/interpolated_stop_times").expect("impossible to read gtfs");
+    assert_eq!(1, gtfs.feed_info.len());
+    // the second stop have no departure/arrival, it should not cause any problems
+    assert_eq!(
+        gtfs.trips["trip1"].stop_times[1].stop.name,
+        Some("Stop Point child of 1".to_owned())
+    );
+    assert!(gtfs.trips["trip1"].stop_times[1].arrival_time.is_none());
+}
+
+#[test]
+fn read_only_required_fields() {
+    let gtfs = Gtfs::from_path("fixtures/only_required_fields").expect("impossible to read gtfs");
+    let route = gtfs.routes.get("1").unwrap();
+    let fare_attribute = gtfs.fare_attributes.get("50").unwrap();
+    let feed = &gtfs.feed_info[0];
+    let shape = &gtfs.shapes.get("A_shp").unwrap()[0];
+    assert_eq!(route.color, RGB8::new(255, 255, 255));
+    assert_eq!(route.text_color, RGB8::new(0, 0, 0));
+    assert_eq!(fare_attribute.transfer_duration, None);
+    assert_eq!(feed.start_date, None);
+    assert_eq!(feed.end_date, None);
+    assert_eq!(shape.dist_traveled, None);
+    assert_eq!(
+        TimepointType::Exact,
+        gtfs.trips["trip1"].stop_times[0].timepoint
+    )
+}
+
+#[test]
+fn metra_gtfs() {
+    let gtfs = Gtfs::from_path("fixtures/zips/metra.zip");
+
+    if let Err(err) = &gtfs {
+        eprintln!("{:#?}", err);
+    }
+
+    assert!(gtfs.is_ok());
+}
+
+#[test]
+fn sorted_shapes() {
+    let gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    let shape = &gtfs.shapes.get("Unordered_shp").unwrap();
+
+    let points = shape
+        .iter()
+        .map(|s| (s.sequence, s.latitude, s.longitude))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        points,
+        vec![
+            (0, 37.61956, -122.48161),
+            (6, 37.64430, -122.41070),
+            (11, 37.65863, -122.30839),
+        ]
+    );
+}
+
+#[test]
+fn fare_v1() {
+    let gtfs = Gtfs::from_path("fixtures/fares_v1").expect("impossible to read gtfs");
+
+    let mut expected_attributes = HashMap::<GtfsId, _>::new();
+    expected_attributes.insert(
+        "presto_fare".into(),
+        FareAttribute {
+            id: "presto_fare".into(),
+            currency: "CAD".to_string(),
+            price: "3.2".to_string(),
+            payment_method: PaymentMethod::PreBoarding,
+            transfer_duration: Some(7200),
+            agency_id: None,
+            transfers: Transfers::Unlimited,
+        },
     );
     assert_eq!(gtfs.fare_attributes, expected_attributes);
 
-    let mut expected_rules = HashMap::new();
-    expected_rules.insert(
-        "presto_fare".to_string(),
+    let mut expected_rules = HashMap::<GtfsId, _>::new();
+    expected_rules.insert(
+        "presto_fare".into(),
+        vec![
+            FareRule {
+                fare_id: "presto_fare".into(),
+                route_id: Some("line1".into()),
+                origin_id: Some("ttc_subway_stations".into()),
+                destination_id: Some("ttc_subway_stations".into()),
+                contains_id: None,
+            },
+            FareRule {
+                fare_id: "presto_fare".into(),
+                route_id: Some("line2".into()),
+                origin_id: Some("ttc_subway_stations".into()),
+                destination_id: Some("ttc_subway_stations".into()),
+                contains_id: None,
+            },
+        ],
+    );
+    assert_eq!(gtfs.fare_rules, expected_rules);
+}
+
+#[test]
+fn write_and_read_back() {
+    let gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    let dir = std::env::temp_dir().join("gtfs_structures_write_and_read_back");
+    GtfsWriter::default()
+        .write_to_directory(&gtfs, &dir)
+        .expect("impossible to write gtfs");
+    let written = Gtfs::from_path(&dir).expect("impossible to read written gtfs");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(gtfs.stops.len(), written.stops.len());
+    assert_eq!(gtfs.routes.len(), written.routes.len());
+    assert_eq!(gtfs.calendar, written.calendar);
+    assert_eq!(gtfs.fare_attributes, written.fare_attributes);
+    let trip = written.get_trip("trip1").unwrap();
+    assert_eq!(3, trip.stop_times.len());
+    assert_eq!(1, trip.frequencies.len());
+    assert_eq!(TimepointType::Approximate, trip.stop_times[1].timepoint);
+    assert_eq!(2, written.get_stop("stop5").unwrap().transfers.len());
+    assert_eq!(1, written.get_stop("stop1").unwrap().pathways.len());
+    assert_eq!(gtfs.feed_info[0].version, written.feed_info[0].version);
+}
+
+#[test]
+fn read_large_file() {
+    // Large enough to be parsed in several chunks with the feature `parallel`
+    let dir = std::env::temp_dir().join("gtfs_structures_read_large_file");
+    std::fs::create_dir_all(&dir).unwrap();
+    for file in std::fs::read_dir("fixtures/basic").unwrap() {
+        let file = file.unwrap();
+        std::fs::copy(file.path(), dir.join(file.file_name())).unwrap();
+    }
+    let mut stop_times =
+        String::from("trip_id,arrival_time,departure_time,stop_id,stop_sequence,stop_headsign\n");
+    let mut line = 1;
+    let mut invalid_line = 0;
+    for i in 0..150_000 {
+        let sequence = i % 50_000;
+        line += 1;
+        if i == 120_000 {
+            invalid_line = line;
+            stop_times.push_str("trip1,14:00:00,14:00:00,stop2,invalid,\n");
+        } else if i % 1000 == 0 {
+            // A new line between quotes does not end the record
+            stop_times.push_str(&format!(
+                "trip1,14:00:00,14:00:00,stop2,{sequence},\"a\nb\"\n"
+            ));
+            line += 1;
+        } else {
+            stop_times.push_str(&format!("trip1,14:00:00,14:00:00,stop2,{sequence},\n"));
+        }
+    }
+    std::fs::write(dir.join("stop_times.txt"), stop_times).unwrap();
+
+    let raw = GtfsReader::default()
+        .skip_invalid_rows(true)
+        .raw()
+        .read_from_path(&dir);
+    let gtfs = Gtfs::from_path(&dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let raw = raw.unwrap();
+    let stop_times = raw.stop_times.unwrap();
+    assert_eq!(149_999, stop_times.len());
+    assert_eq!(20_000, stop_times[70_000].stop_sequence);
+    assert_eq!(49_999, stop_times[149_998].stop_sequence);
+    assert_eq!(Some("a\nb"), stop_times[1000].stop_headsign.as_deref());
+    assert_eq!(1, raw.invalid_rows.len());
+    assert_eq!(invalid_line, raw.invalid_rows[0].line);
+    match gtfs {
+        Err(crate::Error::CSVError {
+            line_in_error: Some(line),
+            ..
+        }) => assert_eq!(invalid_line, line.line),
+        _ => panic!("the invalid row should be an error"),
+    }
+}
+
+#[test]
+fn background_decompression() {
+    use std::io::Write;
+    // Large enough to be sent by the decompression thread in several parts
+    let path = std::env::temp_dir().join("gtfs_structures_background_decompression.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+    for file in std::fs::read_dir("fixtures/basic").unwrap() {
+        let file = file.unwrap();
+        let name = file.file_name().into_string().unwrap();
+        zip.start_file(name.as_str(), zip::write::SimpleFileOptions::default())
+            .unwrap();
+        if name == "stop_times.txt" {
+            zip.write_all(b"trip_id,arrival_time,departure_time,stop_id,stop_sequence\n")
+                .unwrap();
+            for i in 0..50_000 {
+                writeln!(zip, "trip1,14:00:00,14:00:00,stop2,{i}").unwrap();
+            }
+        } else {
+            zip.write_all(&std::fs::read(file.path()).unwrap()).unwrap();
+        }
+    }
+    zip.finish().unwrap();
+
+    let gtfs = GtfsReader::default()
+        .background_decompression(true)
+        .read_from_path(&path);
+    let raw = GtfsReader::default()
+        .background_decompression(true)
+        .raw()
+        .read_from_path(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let gtfs = gtfs.unwrap();
+    assert_eq!(50_000, gtfs.get_trip("trip1").unwrap().stop_times.len());
+    assert_eq!(49_999, gtfs.trips["trip1"].stop_times[49_999].stop_sequence);
+    assert_eq!(6, gtfs.stops.len());
+    assert_eq!(50_000, raw.unwrap().stop_times.unwrap().len());
+}
+
+#[test]
+#[cfg(feature = "cache")]
+fn save_and_load_cache() {
+    let gtfs = Gtfs::from_path("fixtures/basic").unwrap();
+    let dir = std::env::temp_dir().join("gtfs_structures_cache");
+    std::fs::create_dir_all(&dir).unwrap();
+    let cache = dir.join("gtfs.cache");
+    gtfs.save_cache(&cache).unwrap();
+    let cached = Gtfs::load_cache(&cache).unwrap();
+    assert!(matches!(
+        Gtfs::load_cache("fixtures/basic/stops.txt"),
+        Err(crate::Error::InvalidCache)
+    ));
+
+    // The written files are the same, whatever the GTFS was parsed or loaded from the cache
+    let writer = GtfsWriter::default();
+    writer
+        .write_to_directory(&gtfs, dir.join("parsed"))
+        .unwrap();
+    writer
+        .write_to_directory(&cached, dir.join("cached"))
+        .unwrap();
+    for table in TableName::ALL {
+        let parsed = std::fs::read_to_string(dir.join("parsed").join(table.file_name()));
+        let cached = std::fs::read_to_string(dir.join("cached").join(table.file_name()));
+        assert_eq!(parsed.ok(), cached.ok(), "{}", table);
+    }
+    assert!(Arc::ptr_eq(
+        &cached.trips["trip1"].stop_times[0].stop,
+        &cached.stops["stop2"]
+    ));
+    assert_eq!(1, cached.get_stop("stop1").unwrap().pathways.len());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg(feature = "rkyv")]
+fn archived_gtfs() {
+    let compact = crate::CompactGtfs::from_path("fixtures/basic").unwrap();
+    let path = std::env::temp_dir().join("gtfs_structures_archived_gtfs.rkyv");
+    crate::FlatGtfs::try_from(crate::CompactGtfs::from_path("fixtures/basic").unwrap())
+        .unwrap()
+        .save(&path)
+        .unwrap();
+    let archived = crate::MappedGtfs::open(&path).unwrap();
+
+    assert_eq!(compact.stops.len(), archived.stops.len());
+    assert_eq!(compact.trips.len(), archived.trips.len());
+    for (id, trip) in &compact.trips {
+        let archived_trip = archived.trips.get(id.as_str()).unwrap();
+        assert_eq!(trip.stop_times.len(), archived_trip.stop_times.len());
+        for (st, archived_st) in trip.stop_times.iter().zip(archived_trip.stop_times.iter()) {
+            let stop = &archived.stops[archived_st.stop_index.to_native() as usize];
+            assert_eq!(compact.stop_of(st).id.as_str(), stop.id.as_str());
+            assert_eq!(
+                st.arrival_time,
+                archived_st.arrival_time.as_ref().map(|t| t.to_native())
+            );
+        }
+    }
+    let calendar = archived.calendar.get("service1").unwrap();
+    assert_eq!(
+        compact.calendar["service1"].start_date,
+        crate::archived_date(&calendar.start_date).unwrap()
+    );
+    let route = archived.routes.get("1").unwrap();
+    assert_eq!(255, route.text_color[0]);
+
+    let deserialized: crate::FlatGtfs =
+        rkyv::deserialize::<_, rkyv::rancor::Error>(&*archived).unwrap();
+    assert_eq!(compact.routes.len(), deserialized.routes.len());
+    assert_eq!(
+        compact.calendar["service1"].end_date,
+        deserialized.calendar["service1"].end_date
+    );
+    drop(archived);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        crate::MappedGtfs::open("fixtures/basic/stops.txt"),
+        Err(crate::Error::Archive(_))
+    ));
+
+    // The lazy shapes are parsed to be archived
+    let lazy = GtfsReader::default()
+        .lazy_shapes(true)
+        .read_compact_from_path("fixtures/basic")
+        .unwrap();
+    let flat = crate::FlatGtfs::try_from(lazy).unwrap();
+    assert_eq!(compact.shapes.len(), flat.shapes.len());
+    assert_eq!(compact.shapes["A_shp"].len(), flat.shapes["A_shp"].len());
+}
+
+#[test]
+#[cfg(feature = "timezones")]
+fn stop_time_utc() {
+    use chrono::{TimeZone, Utc};
+    let mut gtfs = Gtfs::from_path("fixtures/stations").unwrap();
+    // tripA1 leaves platform1 at 01:00:00 and arrives at stop1 at 25:30:00
+    let trip = gtfs.trips.get_mut("tripA1").unwrap();
+    trip.stop_times[0].departure_time = Some(3600);
+    trip.stop_times[1].departure_time = None;
+    trip.stop_times[1].arrival_time = Some(25 * 3600 + 30 * 60);
+
+    // Summer time in Europe/Paris, two hours ahead of UTC
+    let monday = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+    assert_eq!(
+        Utc.with_ymd_and_hms(2024, 3, 31, 23, 0, 0).unwrap(),
+        gtfs.stop_time_utc("tripA1", 1, monday).unwrap()
+    );
+    assert_eq!(
+        Utc.with_ymd_and_hms(2024, 4, 1, 23, 30, 0).unwrap(),
+        gtfs.stop_time_utc("tripA1", 2, monday).unwrap()
+    );
+    // Summer time starts on 2024-03-31 at 02:00, and the times are counted from noon minus 12 hours,
+    // that is from 23:00 the day before in winter time
+    let change = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+    assert_eq!(
+        Utc.with_ymd_and_hms(2024, 3, 30, 23, 0, 0).unwrap(),
+        gtfs.stop_time_utc("tripA1", 1, change).unwrap()
+    );
+
+    assert!(matches!(
+        gtfs.stop_time_utc("tripA1", 3, monday),
+        Err(crate::Error::UnknownStopTime { .. })
+    ));
+    gtfs.agencies[0].timezone = "Mars/Olympus_Mons".to_owned();
+    assert!(matches!(
+        gtfs.stop_time_utc("tripA1", 1, monday),
+        Err(crate::Error::InvalidTimezone(_))
+    ));
+}
+
+#[test]
+#[cfg(feature = "spatial")]
+fn nearest_stops() {
+    let gtfs = Gtfs::from_path("fixtures/stations").unwrap();
+    let nearest = gtfs.nearest_stops(48.86, 2.36, 2);
+    assert_eq!("stop1", nearest[0].0.id);
+    assert_eq!(0., nearest[0].1);
+    assert!(nearest[1].1 > 1320. && nearest[1].1 < 1340.);
+    assert_eq!(7, gtfs.nearest_stops(48.86, 2.36, 100).len());
+    assert!(gtfs.nearest_stops(48.86, 2.36, 0).is_empty());
+
+    // The stops without coordinates are not indexed
+    let gtfs = Gtfs::from_path("fixtures/basic").unwrap();
+    assert_eq!(5, gtfs.nearest_stops(0., 0., 100).len());
+}
+
+#[test]
+fn write_updated_feed_info() {
+    let gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    let zip = GtfsWriter::default()
+        .update_feed_info(true)
+        .write_to_writer(&gtfs, std::io::Cursor::new(Vec::new()))
+        .expect("impossible to write gtfs");
+    let written = Gtfs::from_reader(zip).expect("impossible to read written gtfs");
+    let feed = &written.feed_info[0];
+    assert_eq!(Some("0.4".to_string()), feed.version);
+    assert_eq!(
+        gtfs.service_span(),
+        Some((feed.start_date.unwrap(), feed.end_date.unwrap()))
+    );
+}
+
+#[test]
+fn export_some_tables() {
+    let gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    let dir = std::env::temp_dir().join("gtfs_structures_export_some_tables");
+    GtfsWriter::default()
+        .tables(&[TableName::Stops, TableName::Pathways])
+        .write_to_directory(&gtfs, &dir)
+        .expect("impossible to write gtfs");
+    let written = RawGtfs::from_path(&dir).expect("impossible to read written gtfs");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let mut files = written.files.clone();
+    files.sort();
+    assert_eq!(vec!["pathways.txt", "stops.txt"], files);
+    assert_eq!(6, written.stops.unwrap().len());
+    assert!(written.routes.is_err());
+
+    let result = gtfs.export(
+        &[TableName::Stops, TableName::Translations],
+        std::io::Cursor::new(Vec::new()),
+    );
+    assert!(matches!(
+        result,
+        Err(crate::Error::UnwritableTable(TableName::Translations))
+    ));
+}
+
+#[test]
+fn secondary_broken_references() {
+    let mut gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    gtfs.trips.get_mut("trip1").unwrap().shape_id = Some("unknown_shape".into());
+    gtfs.fare_rules.insert(
+        "50".into(),
+        vec![FareRule {
+            fare_id: "50".into(),
+            route_id: None,
+            origin_id: Some("unknown_zone".into()),
+            destination_id: None,
+            contains_id: None,
+        }],
+    );
+    let broken: Vec<_> = gtfs
+        .broken_references()
+        .iter()
+        .map(|b| b.to_string())
+        .collect();
+    assert_eq!(
         vec![
-            FareRule {
-                fare_id: "presto_fare".to_string(),
-                route_id: Some("line1".to_string()),
-                origin_id: Some("ttc_subway_stations".to_string()),
-                destination_id: Some("ttc_subway_stations".to_string()),
-                contains_id: None,
+            "trips.txt: 'trip1' references an unknown route_id 'route1'",
+            "trips.txt: 'trip1' references an unknown shape_id 'unknown_shape'",
+            "stops.txt: 'stop3' references an unknown parent_station '1'",
+            "stops.txt: 'stop5' references an unknown parent_station '1'",
+            "stops.txt: 'stop6' references an unknown parent_station '1'",
+            "routes.txt: '1' references an unknown agency_id '848'",
+            "routes.txt: 'default_colors' references an unknown agency_id '848'",
+            "routes.txt: 'invalid_type' references an unknown agency_id '848'",
+            "fare_rules.txt: '50' references an unknown origin_id 'unknown_zone'",
+        ],
+        broken
+    );
+
+    gtfs.trips.get_mut("trip1").unwrap().shape_id = Some("A_shp".into());
+    assert_eq!(8, gtfs.broken_references().len());
+}
+
+#[test]
+fn validate_before_writing() {
+    let mut gtfs = Gtfs::from_path("fixtures/basic").expect("impossible to read gtfs");
+    // the unknown agency of the routes and parent station of the stops are checked by `secondary_broken_references`
+    for route in gtfs.routes.values_mut() {
+        route.agency_id = None;
+    }
+    for stop in gtfs.stops.values_mut() {
+        std::sync::Arc::make_mut(stop).parent_station = None;
+    }
+    // the route of the trip is not in routes.txt
+    let broken = gtfs.broken_references();
+    assert_eq!(1, broken.len());
+    assert_eq!(TableName::Trips, broken[0].table);
+    assert_eq!("route_id", broken[0].field_name);
+    assert_eq!("route1", broken[0].value);
+
+    gtfs.trips.get_mut("trip1").unwrap().stop_times[0].stop = std::sync::Arc::new(Stop {
+        id: "unknown_stop".into(),
+        ..Stop::default()
+    });
+    let result = GtfsWriter::default()
+        .validate(true)
+        .write_to_writer(&gtfs, std::io::Cursor::new(Vec::new()));
+    match result {
+        Err(crate::Error::BrokenReferences(broken)) => {
+            assert_eq!(2, broken.len());
+            assert_eq!(TableName::StopTimes, broken[1].table);
+            assert_eq!("trip1", broken[1].object_id);
+            assert_eq!("unknown_stop", broken[1].value);
+        }
+        _ => panic!("the invalid references should prevent the writing"),
+    }
+
+    gtfs.trips.get_mut("trip1").unwrap().route_id = "1".into();
+    gtfs.trips.get_mut("trip1").unwrap().stop_times.remove(0);
+    assert!(GtfsWriter::default()
+        .validate(true)
+        .write_to_writer(&gtfs, std::io::Cursor::new(Vec::new()))
+        .is_ok());
+}
+
+#[test]
+fn read_without_shapes() {
+    let gtfs = GtfsReader::default()
+        .read_shapes(false)
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    assert!(gtfs.shapes.is_empty());
+
+    let gtfs = GtfsReader::default()
+        .read_shapes(false)
+        .read_from_path("fixtures/zips/gtfs.zip")
+        .expect("impossible to read gtfs");
+    assert!(gtfs.shapes.is_empty());
+}
+
+#[test]
+fn read_lazy_shapes() {
+    let gtfs = GtfsReader::default()
+        .lazy_shapes(true)
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    assert!(gtfs.shapes.is_empty());
+    assert_eq!(2, gtfs.lazy_shapes.len());
+
+    let points = gtfs
+        .get_shape("Unordered_shp")
+        .expect("impossible to parse the shape")
+        .iter()
+        .map(|s| (s.sequence, s.latitude, s.longitude))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        points,
+        vec![
+            (0, 37.61956, -122.48161),
+            (6, 37.64430, -122.41070),
+            (11, 37.65863, -122.30839),
+        ]
+    );
+    assert_eq!(
+        Some(6.831),
+        gtfs.get_shape("A_shp").unwrap()[1].dist_traveled
+    );
+    assert!(gtfs.get_shape("unknown").is_err());
+
+    let compact = GtfsReader::default()
+        .lazy_shapes(true)
+        .read_compact_from_path("fixtures/zips/gtfs.zip")
+        .expect("impossible to read gtfs");
+    assert!(compact.shapes.is_empty());
+    assert_eq!(3, compact.get_shape("A_shp").unwrap().len());
+}
+
+#[test]
+fn read_without_optional_tables() {
+    let gtfs = GtfsReader::default()
+        .read_pathways(false)
+        .read_transfers(false)
+        .read_translations(false)
+        .read_fares(false)
+        .raw()
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    assert!(gtfs.pathways.unwrap().unwrap().is_empty());
+    assert!(gtfs.transfers.unwrap().unwrap().is_empty());
+    assert!(gtfs.translations.unwrap().unwrap().is_empty());
+    assert!(gtfs.fare_attributes.unwrap().unwrap().is_empty());
+    assert!(gtfs.fare_rules.unwrap().unwrap().is_empty());
+    assert_eq!(1, gtfs.frequencies.unwrap().unwrap().len());
+}
+
+#[test]
+fn read_only_some_files() {
+    let gtfs = GtfsReader::default()
+        .only(&["stops.txt", "routes.txt"])
+        .read_from_path("fixtures/zips/gtfs.zip")
+        .expect("impossible to read gtfs");
+    assert_eq!(5, gtfs.stops.len());
+    assert_eq!(1, gtfs.routes.len());
+    assert!(gtfs.trips.is_empty());
+    assert!(gtfs.agencies.is_empty());
+    assert!(gtfs.calendar.is_empty());
+    assert!(gtfs.shapes.is_empty());
+}
+
+#[test]
+fn size_and_row_limits() {
+    let gtfs = GtfsReader::default()
+        .file_size_limit(300)
+        .raw()
+        .read_from_path("fixtures/zips/gtfs.zip")
+        .expect("impossible to read gtfs");
+    assert!(gtfs.agencies.is_ok());
+    match gtfs.stops {
+        Err(crate::Error::FileSizeLimitExceeded { file_name, limit }) => {
+            assert_eq!("stops.txt", file_name);
+            assert_eq!(300, limit);
+        }
+        _ => panic!("stops.txt should exceed the size limit"),
+    }
+
+    // The archive claims stops.txt is 4 GB, nothing that large is allocated in advance
+    let mut bytes = std::fs::read("fixtures/zips/gtfs.zip").unwrap();
+    let entry = bytes
+        .windows(46 + 9)
+        .position(|w| w.starts_with(b"PK\x01\x02") && &w[46..] == b"stops.txt")
+        .unwrap();
+    bytes[entry + 20..entry + 28]
+        .copy_from_slice(&[0xf0, 0xff, 0xff, 0xff, 0xf0, 0xff, 0xff, 0xff]);
+    let gtfs = GtfsReader::default()
+        .file_size_limit(300)
+        .raw()
+        .read_from_reader(std::io::Cursor::new(bytes))
+        .expect("impossible to read gtfs");
+    assert!(matches!(
+        gtfs.stops,
+        Err(crate::Error::FileSizeLimitExceeded { limit: 300, .. })
+    ));
+
+    let gtfs = GtfsReader::default()
+        .row_limit(3)
+        .raw()
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    assert_eq!(2, gtfs.agencies.unwrap().len());
+    assert!(matches!(
+        gtfs.stops,
+        Err(crate::Error::RowLimitExceeded { limit: 3, .. })
+    ));
+}
+
+#[test]
+#[cfg(feature = "read-tar-gz")]
+fn read_tar_gz() {
+    let raw = RawGtfs::new("fixtures/zips/gtfs.tar.gz").expect("impossible to read gtfs");
+    assert_eq!(raw.source_format, SourceFormat::TarGz);
+    assert_eq!(10, raw.files.len());
+    assert!(raw.sha256.is_some());
+
+    let gtfs = Gtfs::new("fixtures/zips/gtfs.tar.gz").expect("impossible to read gtfs");
+    assert_eq!(5, gtfs.stops.len());
+    assert_eq!(1, gtfs.trips.len());
+    assert_eq!(2, gtfs.get_trip("trip1").unwrap().stop_times.len());
+}
+
+#[test]
+fn max_rows_per_file() {
+    let gtfs = GtfsReader::default()
+        .max_rows_per_file(2)
+        .raw()
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    let stops = gtfs.stops.unwrap();
+    assert_eq!(2, stops.len());
+    assert_eq!("stop1", stops[0].id);
+    assert_eq!(2, gtfs.shapes.unwrap().unwrap().len());
+    assert_eq!(1, gtfs.trips.unwrap().len());
+
+    // The files are truncated before reaching the row limit
+    let gtfs = GtfsReader::default()
+        .max_rows_per_file(2)
+        .row_limit(2)
+        .raw()
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    assert!(gtfs.stops.is_ok());
+}
+
+#[test]
+fn skip_invalid_rows() {
+    let gtfs = RawGtfs::from_path("fixtures/invalid_rows").expect("impossible to read gtfs");
+    match gtfs.stops {
+        Err(crate::Error::CSVError {
+            line_in_error: Some(line),
+            ..
+        }) => {
+            assert_eq!(3, line.line);
+            assert_eq!("stop2", line.values[0]);
+        }
+        _ => panic!("the invalid row should be an error"),
+    }
+    assert!(gtfs.invalid_rows.is_empty());
+
+    let gtfs = GtfsReader::default()
+        .skip_invalid_rows(true)
+        .raw()
+        .read_from_path("fixtures/invalid_rows")
+        .expect("impossible to read gtfs");
+    let stops = gtfs.stops.expect("invalid rows should be skipped");
+    assert_eq!(
+        vec!["stop1", "stop4"],
+        stops.iter().map(|s| &s.id).collect::<Vec<_>>()
+    );
+    assert_eq!(2, gtfs.invalid_rows.len());
+    assert_eq!("stops.txt", gtfs.invalid_rows[0].file_name);
+    assert_eq!(3, gtfs.invalid_rows[0].line);
+    assert_eq!("stop2", gtfs.invalid_rows[0].values[0]);
+    assert_eq!("stop_lat", gtfs.invalid_rows[0].headers[2]);
+    assert_eq!(4, gtfs.invalid_rows[1].line);
+}
+
+#[test]
+fn row_limit_with_invalid_rows() {
+    // The skipped rows count toward the limits
+    let gtfs = GtfsReader::default()
+        .skip_invalid_rows(true)
+        .row_limit(3)
+        .raw()
+        .read_from_path("fixtures/invalid_rows")
+        .expect("impossible to read gtfs");
+    assert!(matches!(
+        gtfs.stops,
+        Err(crate::Error::RowLimitExceeded { limit: 3, .. })
+    ));
+
+    let gtfs = GtfsReader::default()
+        .skip_invalid_rows(true)
+        .max_rows_per_file(2)
+        .raw()
+        .read_from_path("fixtures/invalid_rows")
+        .expect("impossible to read gtfs");
+    assert_eq!(1, gtfs.stops.unwrap().len());
+    assert_eq!(1, gtfs.invalid_rows.len());
+}
+
+#[test]
+fn skip_broken_references() {
+    match Gtfs::from_path("fixtures/broken_references") {
+        Err(crate::Error::ReferenceError(id)) => assert_eq!("'unknown_stop' in transfers.txt", id),
+        _ => panic!("the broken references should be an error"),
+    }
+
+    let gtfs = GtfsReader::default()
+        .skip_broken_references(true)
+        .read_from_path("fixtures/broken_references")
+        .expect("the broken references should be skipped");
+    let trip = gtfs.get_trip("trip1").unwrap();
+    assert_eq!(
+        vec!["stop1", "stop2"],
+        trip.stop_times
+            .iter()
+            .map(|st| st.stop.id.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(1, gtfs.get_stop("stop1").unwrap().transfers.len());
+    let broken: Vec<_> = gtfs
+        .validation_report
+        .broken_references
+        .iter()
+        .map(|b| b.to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "transfers.txt: 'stop1' references an unknown to_stop_id 'unknown_stop'",
+            "frequencies.txt: 'unknown_trip' references an unknown trip_id 'unknown_trip'",
+            "stop_times.txt: 'trip1' references an unknown stop_id 'unknown_stop'",
+            "stop_times.txt: 'unknown_trip' references an unknown trip_id 'unknown_trip'",
+        ],
+        broken
+    );
+
+    let compact = GtfsReader::default()
+        .skip_broken_references(true)
+        .read_compact_from_path("fixtures/broken_references")
+        .expect("the broken references should be skipped");
+    assert_eq!(2, compact.get_trip("trip1").unwrap().stop_times.len());
+    assert_eq!(
+        gtfs.validation_report.broken_references,
+        compact.validation_report.broken_references
+    );
+    assert!(Gtfs::from_path("fixtures/basic")
+        .unwrap()
+        .validation_report
+        .is_valid());
+}
+
+#[test]
+fn duplicate_policy() {
+    use crate::DuplicatePolicy;
+    let read = |policy| {
+        GtfsReader::default()
+            .duplicate_policy(policy)
+            .read_from_path("fixtures/duplicates")
+    };
+    let gtfs = Gtfs::from_path("fixtures/duplicates").unwrap();
+    assert_eq!(Some("Second"), gtfs.stops["stop1"].name.as_deref());
+    assert_eq!(Some("Second"), gtfs.trips["trip1"].trip_headsign.as_deref());
+    assert!(gtfs.validation_report.is_valid());
+
+    let gtfs = read(DuplicatePolicy::KeepFirst).unwrap();
+    assert_eq!(Some("First"), gtfs.stops["stop1"].name.as_deref());
+    assert_eq!(Some("First"), gtfs.trips["trip1"].trip_headsign.as_deref());
+    assert_eq!(2, gtfs.trips["trip1"].stop_times.len());
+    assert!(gtfs.validation_report.is_valid());
+
+    let gtfs = read(DuplicatePolicy::Report).unwrap();
+    assert_eq!(Some("First"), gtfs.stops["stop1"].name.as_deref());
+    assert_eq!(
+        vec![
+            "stops.txt: 'stop1' is not unique",
+            "trips.txt: 'trip1' is not unique"
+        ],
+        gtfs.validation_report
+            .duplicates
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>()
+    );
+
+    match read(DuplicatePolicy::Error) {
+        Err(crate::Error::DuplicateId { table, id }) => {
+            assert_eq!(TableName::Stops, table);
+            assert_eq!("stop1", id);
+        }
+        _ => panic!("the duplicated stop should be an error"),
+    }
+}
+
+#[test]
+fn duplicate_stop_times() {
+    use crate::{DuplicateStopTime, DuplicateStopTimeKind};
+    let gtfs = Gtfs::from_path("fixtures/duplicate_stop_times").expect("impossible to read gtfs");
+    let duplicate = |trip_id, first_index, index, kind| DuplicateStopTime {
+        trip_id,
+        first_index,
+        index,
+        kind,
+    };
+    assert_eq!(
+        vec![
+            duplicate("trip1", 1, 2, DuplicateStopTimeKind::SameStopAndTimes),
+            duplicate("trip2", 1, 2, DuplicateStopTimeKind::SameStopSequence),
+            // The second passage at stop2 at 09:20:00 is not a duplicate
+            duplicate("trip2", 1, 4, DuplicateStopTimeKind::SameStopAndTimes),
+        ],
+        gtfs.duplicate_stop_times()
+    );
+
+    let gtfs = GtfsReader::default()
+        .remove_duplicate_stop_times(true)
+        .read_from_path("fixtures/duplicate_stop_times")
+        .expect("impossible to read gtfs");
+    assert_eq!(3, gtfs.trips["trip1"].stop_times.len());
+    assert_eq!(4, gtfs.trips["trip2"].stop_times.len());
+    let removed = |trip_id: &str, stop_sequence| crate::error::RemovedStopTime {
+        trip_id: trip_id.to_owned(),
+        stop_sequence,
+    };
+    assert_eq!(
+        vec![removed("trip1", 2), removed("trip2", 4)],
+        gtfs.validation_report.removed_stop_times
+    );
+    assert!(!gtfs.validation_report.is_valid());
+    assert_eq!(
+        vec![duplicate(
+            "trip2",
+            1,
+            2,
+            DuplicateStopTimeKind::SameStopSequence
+        )],
+        gtfs.duplicate_stop_times()
+    );
+
+    let mut compact = GtfsReader::default()
+        .read_compact_from_path("fixtures/duplicate_stop_times")
+        .expect("impossible to read gtfs");
+    assert_eq!(2, compact.remove_duplicate_stop_times());
+    assert_eq!(4, compact.trips["trip2"].stop_times.len());
+    assert!(compact.validation_report.is_valid());
+
+    let compact = GtfsReader::default()
+        .remove_duplicate_stop_times(true)
+        .read_compact_from_path("fixtures/duplicate_stop_times")
+        .expect("impossible to read gtfs");
+    assert_eq!(
+        gtfs.validation_report.removed_stop_times,
+        compact.validation_report.removed_stop_times
+    );
+}
+
+#[test]
+fn parse_metrics() {
+    let gtfs = GtfsReader::default()
+        .skip_invalid_rows(true)
+        .raw()
+        .read_from_path("fixtures/invalid_rows")
+        .expect("impossible to read gtfs");
+    let stops = &gtfs.metrics.files[&TableName::Stops];
+    assert_eq!("stops.txt", stops.file_name);
+    assert_eq!(2, stops.rows);
+    assert_eq!(2, stops.skipped_rows);
+    assert_eq!(
+        std::fs::metadata("fixtures/invalid_rows/stops.txt")
+            .unwrap()
+            .len(),
+        stops.bytes
+    );
+
+    let gtfs = GtfsReader::default()
+        .default_value(TableName::Stops, "wheelchair_boarding", "1")
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    assert!(gtfs.metrics.total_duration >= gtfs.metrics.files[&TableName::Stops].duration);
+    let nb_stop_times: usize = gtfs.trips.values().map(|t| t.stop_times.len()).sum();
+    assert_eq!(
+        nb_stop_times,
+        gtfs.metrics.files[&TableName::StopTimes].rows
+    );
+    assert_eq!(gtfs.stops.len(), gtfs.metrics.files[&TableName::Stops].rows);
+    assert!(gtfs.metrics.files[&TableName::Stops].defaulted_values > 0);
+    assert!(!gtfs.metrics.files.contains_key(&TableName::FareRules));
+}
+
+#[test]
+fn reload_if_changed() {
+    let dir = std::env::temp_dir().join("gtfs_structures_reload_if_changed");
+    std::fs::create_dir_all(&dir).unwrap();
+    for entry in std::fs::read_dir("fixtures/basic").unwrap() {
+        let path = entry.unwrap().path();
+        std::fs::copy(&path, dir.join(path.file_name().unwrap())).unwrap();
+    }
+    let reader = GtfsReader::default().hash_files(true).raw();
+    let previous = reader.read_from_path(&dir).unwrap();
+    let stops = std::fs::read_to_string(dir.join("stops.txt")).unwrap();
+    std::fs::write(
+        dir.join("stops.txt"),
+        stops.replace("\"Stop Area\"", "\"Renamed Stop Area\""),
+    )
+    .unwrap();
+
+    let gtfs = reader.reload_if_changed(&dir, &previous).unwrap();
+    let stops_metrics = &gtfs.metrics.files[&TableName::Stops];
+    assert!(!stops_metrics.reused);
+    assert_ne!(
+        previous.metrics.files[&TableName::Stops].sha256,
+        stops_metrics.sha256
+    );
+    assert_eq!(
+        Some(format!(
+            "{:x}",
+            Sha256::digest(std::fs::read(dir.join("stops.txt")).unwrap())
+        )),
+        stops_metrics.sha256
+    );
+    let reused: Vec<_> = gtfs
+        .metrics
+        .files
+        .iter()
+        .filter(|(_, m)| m.reused)
+        .map(|(t, _)| *t)
+        .collect();
+    assert_eq!(previous.metrics.files.len() - 1, reused.len());
+    assert!(reused.contains(&TableName::StopTimes));
+
+    let stops = gtfs.stops.as_ref().unwrap();
+    assert_eq!("Renamed Stop Area", stops[0].name.as_deref().unwrap());
+    assert_eq!(
+        previous.stop_times.as_ref().unwrap().len(),
+        gtfs.stop_times.as_ref().unwrap().len()
+    );
+
+    let gtfs = Gtfs::reload_if_changed(&dir, &gtfs).unwrap();
+    assert!(gtfs.metrics.files.values().all(|m| m.reused));
+    assert_eq!(
+        "Renamed Stop Area",
+        gtfs.stops["stop1"].name.as_deref().unwrap()
+    );
+
+    // Without the sha256 sums, every file is parsed again
+    let unhashed = GtfsReader::default().raw().read_from_path(&dir).unwrap();
+    assert!(unhashed.metrics.files.values().all(|m| m.sha256.is_none()));
+    let gtfs = reader.reload_if_changed(&dir, &unhashed).unwrap();
+    assert!(gtfs.metrics.files.values().all(|m| !m.reused));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn drop_descriptive_fields() {
+    let gtfs = GtfsReader::default()
+        .drop_descriptive_fields(true)
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    let stop = &gtfs.stops["stop1"];
+    assert_eq!(None, stop.description);
+    assert_eq!(None, stop.url);
+    assert_eq!(Some("Stop Area"), stop.name.as_deref());
+    assert!(gtfs
+        .routes
+        .values()
+        .all(|r| r.desc.is_none() && r.url.is_none()));
+    assert!(gtfs.agencies.iter().all(|a| a.url.is_empty()));
+    assert_eq!("BIBUS", gtfs.agencies[0].name);
+}
+
+#[test]
+fn scan_statistics() {
+    let gtfs = Gtfs::from_path("fixtures/zips/gtfs.zip").unwrap();
+    let stats = GtfsScanner::default()
+        .scan("fixtures/zips/gtfs.zip")
+        .unwrap();
+    assert_eq!(gtfs.stops.len(), stats.rows(TableName::Stops));
+    assert_eq!(gtfs.trips.len(), stats.rows(TableName::Trips));
+    assert_eq!(0, stats.rows(TableName::FareRules));
+    assert_eq!(
+        gtfs.metrics.files[&TableName::StopTimes].bytes,
+        stats.bytes(TableName::StopTimes)
+    );
+    assert_eq!(gtfs.service_span(), stats.service_span);
+
+    let bbox = stats.bounding_box.unwrap();
+    for stop in gtfs.stops.values() {
+        if let (Some(lon), Some(lat)) = (stop.longitude, stop.latitude) {
+            assert!(bbox.min_longitude <= lon && lon <= bbox.max_longitude);
+            assert!(bbox.min_latitude <= lat && lat <= bbox.max_latitude);
+        }
+    }
+}
+
+#[test]
+fn departures_from() {
+    let mut gtfs = Gtfs::from_path("fixtures/basic").unwrap();
+    let datetime = |d, h, m| {
+        NaiveDate::from_ymd_opt(2017, 1, d)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    };
+    // Saturday, the frequency makes the trip leave stop2 at 05:30 and stop3 at 06:30
+    let departures = gtfs.departures_from("stop2", datetime(7, 0, 0), chrono::TimeDelta::days(1));
+    assert_eq!(1, departures.len());
+    assert_eq!(datetime(7, 5, 30), departures[0].time);
+    assert_eq!(Some("85088452"), departures[0].headsign);
+    assert!(departures[0].route.is_none());
+    // The last stop of the trip is not a departure, and there is no service on Mondays
+    assert!(gtfs
+        .departures_from("stop4", datetime(7, 0, 0), chrono::TimeDelta::days(1))
+        .is_empty());
+    assert!(gtfs
+        .departures_from("stop2", datetime(9, 0, 0), chrono::TimeDelta::days(1))
+        .is_empty());
+
+    let trip = gtfs.trips.get_mut("trip1").unwrap();
+    trip.frequencies.clear();
+    trip.stop_times[1].departure_time = Some(25 * 3600 + 10 * 60);
+    let departures = gtfs.departures_from("stop3", datetime(8, 0, 0), chrono::TimeDelta::hours(3));
+    assert_eq!(1, departures.len());
+    assert_eq!(datetime(8, 1, 10), departures[0].time);
+    assert_eq!(datetime(7, 0, 0).date(), departures[0].service_date);
+    // The service of Sunday the first is removed by a calendar date
+    assert!(gtfs
+        .departures_from("stop3", datetime(2, 0, 0), chrono::TimeDelta::hours(3))
+        .is_empty());
+    assert_eq!(
+        1,
+        gtfs.departures_from("stop3", datetime(16, 0, 0), chrono::TimeDelta::hours(3))
+            .len()
+    );
+}
+
+#[test]
+fn trips_for_route() {
+    let gtfs = GtfsReader::default()
+        .sorted_storage(true)
+        .read_from_path("fixtures/zips/gtfs.zip")
+        .unwrap();
+    for route_id in gtfs.routes.keys() {
+        let mut expected: Vec<_> = gtfs
+            .trips
+            .values()
+            .filter(|t| &t.route_id == route_id)
+            .map(|t| &t.id)
+            .collect();
+        expected.sort();
+        let trips: Vec<_> = gtfs.trips_for_route(route_id).map(|t| &t.id).collect();
+        assert_eq!(expected, trips);
+    }
+    assert_eq!(0, gtfs.trips_for_route("unknown").count());
+}
+
+#[test]
+fn routes_at_stop() {
+    let gtfs = Gtfs::from_path("fixtures/stations").unwrap();
+    assert_eq!(vec!["tripA1", "tripA2"], gtfs.stop_trips["platform1"]);
+    assert_eq!(
+        vec!["tripA1", "tripA2", "tripA3", "tripB1"],
+        gtfs.stop_trips["station1"]
+    );
+    assert_eq!(vec!["routeA", "routeB"], routes_of(&gtfs, "station1"));
+    assert_eq!(vec!["routeA"], routes_of(&gtfs, "platform1"));
+    assert_eq!(vec!["routeA", "routeB"], routes_of(&gtfs, "platform2"));
+    assert_eq!(vec!["routeA", "routeB"], routes_of(&gtfs, "stop2"));
+    assert!(routes_of(&gtfs, "unknown").is_empty());
+
+    // The index is also built when the stop times are linked from a RawGtfs
+    let raw = RawGtfs::from_path("fixtures/stations").unwrap();
+    let gtfs = Gtfs::try_from(raw).unwrap();
+    assert_eq!(vec!["routeA", "routeB"], routes_of(&gtfs, "station1"));
+}
+
+fn routes_of<'a>(gtfs: &'a Gtfs, stop_id: &str) -> Vec<&'a str> {
+    gtfs.routes_at_stop(stop_id)
+        .iter()
+        .map(|r| r.id.as_str())
+        .collect()
+}
+
+#[test]
+fn stop_pattern() {
+    let gtfs = Gtfs::from_path("fixtures/stations").unwrap();
+    let stop_ids = |route_id, direction_id| -> Vec<String> {
+        gtfs.stop_pattern(route_id, direction_id)
+            .iter()
+            .map(|s| s.id.to_string())
+            .collect()
+    };
+    assert_eq!(
+        vec!["platform2", "platform1", "stop1", "stop2"],
+        stop_ids("routeA", Some(DirectionType::Outbound))
+    );
+    assert_eq!(
+        vec!["stop1", "platform1"],
+        stop_ids("routeA", Some(DirectionType::Inbound))
+    );
+    assert!(stop_ids("routeA", None).is_empty());
+    assert!(stop_ids("unknown", None).is_empty());
+}
+
+#[test]
+fn default_values() {
+    let gtfs = GtfsReader::default()
+        .default_value(TableName::Agency, "agency_timezone", "Europe/Paris")
+        .default_value_with(TableName::Routes, "route_color", |row| {
+            match row.get("route_type") {
+                Some("3") => Some("FFCC00".to_owned()),
+                _ => None,
+            }
+        })
+        .raw()
+        .read_from_path("fixtures/missing_values")
+        .expect("impossible to read gtfs");
+    let agencies = gtfs.agencies.unwrap();
+    assert_eq!("Europe/Berlin", agencies[0].timezone);
+    assert_eq!("Europe/Paris", agencies[1].timezone);
+    let routes = gtfs.routes.unwrap();
+    assert_eq!(RGB8::new(0xFF, 0xCC, 0x00), routes[0].color);
+    assert_eq!(RGB8::new(0xFF, 0xFF, 0xFF), routes[1].color);
+
+    assert_eq!(
+        vec![
+            crate::DefaultedValue {
+                file_name: "agency.txt".to_owned(),
+                line: 3,
+                field_name: "agency_timezone".to_owned(),
+                value: "Europe/Paris".to_owned(),
             },
-            FareRule {
-                fare_id: "presto_fare".to_string(),
-                route_id: Some("line2".to_string()),
-                origin_id: Some("ttc_subway_stations".to_string()),
-                destination_id: Some("ttc_subway_stations".to_string()),
-                contains_id: None,
+            crate::DefaultedValue {
+                file_name: "routes.txt".to_owned(),
+                line: 2,
+                field_name: "route_color".to_owned(),
+                value: "FFCC00".to_owned(),
             },
         ],
+        gtfs.defaulted_values
     );
-    assert_eq!(gtfs.fare_rules, expected_rules);
+}
+
+#[test]
+fn strict_parse_mode() {
+    let gtfs = GtfsReader::default()
+        .parse_mode(ParseMode::Strict)
+        .raw()
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    assert!(gtfs.stops.is_ok());
+    assert!(gtfs.trips.is_ok());
+    match gtfs.routes {
+        Err(crate::Error::UnknownEnumValue {
+            file_name,
+            field_name,
+            value,
+        }) => {
+            assert_eq!("routes.txt", file_name);
+            assert_eq!("route_type", field_name);
+            assert_eq!("42", value);
+        }
+        _ => panic!("route_type 42 should be an error in strict mode"),
+    }
+    assert!(matches!(
+        gtfs.stop_times,
+        Err(crate::Error::UnknownEnumValue { .. })
+    ));
+
+    // The strict mode takes precedence over the conversion to the default value
+    let gtfs = GtfsReader::default()
+        .parse_mode(ParseMode::Strict)
+        .unkown_enum_as_default(true)
+        .raw()
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    assert!(gtfs.stop_times.is_err());
+}
+
+#[test]
+fn strict_parse_mode_route_types() {
+    // Trolleybus, monorail and the extended route types are valid, even without their own RouteType
+    let gtfs = GtfsReader::default()
+        .parse_mode(ParseMode::Strict)
+        .raw()
+        .read_from_path("fixtures/route_types")
+        .expect("impossible to read gtfs");
+    let routes = gtfs.routes.expect("the route types should be valid");
+    assert_eq!(
+        vec![
+            RouteType::Other(11),
+            RouteType::Other(12),
+            RouteType::Bus,
+            RouteType::Other(1700)
+        ],
+        routes.iter().map(|r| r.route_type).collect::<Vec<_>>()
+    );
+
+    let gtfs = GtfsReader::default()
+        .parse_mode(ParseMode::Strict)
+        .raw()
+        .read_from_path("fixtures/basic")
+        .expect("impossible to read gtfs");
+    assert!(matches!(
+        gtfs.routes,
+        Err(crate::Error::UnknownEnumValue { value, .. }) if value == "42"
+    ));
+}
+
+#[test]
+fn strict_parse_mode_custom_file_names() {
+    let gtfs = GtfsReader::default()
+        .parse_mode(ParseMode::Strict)
+        .file_name(TableName::Routes, "lignes.txt")
+        .raw()
+        .read_from_path("fixtures/renamed_unknown_values")
+        .expect("impossible to read gtfs");
+    match gtfs.routes {
+        Err(crate::Error::UnknownEnumValue { file_name, .. }) => {
+            assert_eq!("lignes.txt", file_name)
+        }
+        _ => panic!("route_type 42 should be an error in strict mode"),
+    }
+}
+
+#[test]
+fn strict_parse_mode_new_unknown_values() {
+    let gtfs = GtfsReader::default()
+        .parse_mode(ParseMode::Strict)
+        .raw()
+        .read_from_path("fixtures/unknown_enum_values")
+        .expect("impossible to read gtfs");
+    match gtfs.calendar_dates {
+        Some(Err(crate::Error::UnknownEnumValue {
+            file_name,
+            field_name,
+            value,
+        })) => {
+            assert_eq!("calendar_dates.txt", file_name);
+            assert_eq!("exception_type", field_name);
+            assert_eq!("3", value);
+        }
+        _ => panic!("exception_type 3 should be an error in strict mode"),
+    }
+    assert!(matches!(
+        gtfs.trips,
+        Err(crate::Error::UnknownEnumValue { .. })
+    ));
+
+    // An optional field with an unknown value is considered absent
+    let gtfs = GtfsReader::default()
+        .unkown_enum_as_default(true)
+        .raw()
+        .read_from_path("fixtures/unknown_enum_values")
+        .expect("impossible to read gtfs");
+    assert_eq!(None, gtfs.trips.unwrap()[0].direction_id);
+    let dates = gtfs.calendar_dates.unwrap().unwrap();
+    assert_eq!(Exception::Unknown(3), dates[0].exception_type);
+}
+
+#[test]
+fn unknown_enum_values() {
+    let data = "from_stop_id,to_stop_id,transfer_type,min_transfer_time\nstop1,stop2,9,\n";
+    let transfer: RawTransfer = csv::Reader::from_reader(data.as_bytes())
+        .deserialize()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(TransferType::Unknown(9), transfer.transfer_type);
+
+    let data = "service_id,date,exception_type\nservice1,20170101,3\n";
+    let calendar_date: CalendarDate = csv::Reader::from_reader(data.as_bytes())
+        .deserialize()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(Exception::Unknown(3), calendar_date.exception_type);
+
+    // The original value is kept when serializing
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    wtr.serialize(calendar_date).unwrap();
+    let data_out = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+    assert_eq!(data, data_out);
+
+    let data =
+        "pathway_id,from_stop_id,to_stop_id,pathway_mode,is_bidirectional\np,stop1,stop2,12,2\n";
+    let pathway: RawPathway = csv::Reader::from_reader(data.as_bytes())
+        .deserialize()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(PathwayMode::Unknown(12), pathway.mode);
+    assert_eq!(PathwayDirectionType::Unknown(2), pathway.is_bidirectional);
+}
+
+#[test]
+fn trim_modes() {
+    let read = |trim| {
+        GtfsReader::default()
+            .trim(trim)
+            .raw()
+            .read_from_path("fixtures/untrimmed")
+            .expect("impossible to read gtfs")
+            .stops
+    };
+    let stops = read(TrimMode::All).unwrap();
+    assert_eq!("stop1", stops[0].id);
+    assert_eq!(Some("Padded name".to_owned()), stops[0].name);
+
+    let stops = read(TrimMode::Headers).unwrap();
+    assert_eq!(" stop1 ", stops[0].id);
+    assert_eq!(Some("  Padded name  ".to_owned()), stops[0].name);
+
+    // the headers are not recognized, so the mandatory stop_id is missing
+    assert!(read(TrimMode::None).is_err());
+
+    #[allow(deprecated)]
+    let reader = GtfsReader {
+        trim_fields: false,
+        ..Default::default()
+    };
+    let stops = reader
+        .raw()
+        .read_from_path("fixtures/untrimmed")
+        .expect("impossible to read gtfs")
+        .stops
+        .unwrap();
+    assert_eq!(" stop1 ", stops[0].id);
 }
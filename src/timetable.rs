@@ -0,0 +1,97 @@
+use crate::{objects::*, Error, Gtfs};
+use chrono::NaiveDate;
+use std::sync::Arc;
+
+/// Departure times of the trips of a route at each stop, laid out as a printed timetable, see [Gtfs::timetable]
+#[derive(Debug, Clone, Default)]
+pub struct Timetable<'a> {
+    /// Rows of the timetable, in the order of [Gtfs::stop_pattern]
+    pub stops: Vec<Arc<Stop>>,
+    /// Columns of the timetable, sorted by time at their first stop
+    pub trips: Vec<&'a Trip>,
+    /// Time in seconds since midnight of each trip at each stop, indexed by row then column.
+    /// None if the trip does not call at the stop, or has no time there
+    pub times: Vec<Vec<Option<u32>>>,
+}
+
+impl Timetable<'_> {
+    /// Times of a trip at all the stops, indexed by row. None if the trip is not in the timetable
+    pub fn trip_times(&self, trip_id: &str) -> Option<Vec<Option<u32>>> {
+        let column = self.trips.iter().position(|t| t.id == trip_id)?;
+        Some(self.times.iter().map(|row| row[column]).collect())
+    }
+}
+
+impl Gtfs {
+    /// Builds the timetable of a route in a direction on a day
+    ///
+    /// The rows are the stops given by [Gtfs::stop_pattern], and the columns the trips of the route
+    /// with this `direction_id` running on the day. The time of a trip at a stop is its departure time,
+    /// or else its arrival time. A trip calling twice at a stop only has its first time in the row, unless
+    /// the pattern has the stop twice. The [Frequency] of the trips are ignored, see [Gtfs::expand_frequencies]
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use gtfs_structures::DirectionType;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// let monday = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    /// let timetable = gtfs.timetable("routeA", Some(DirectionType::Outbound), monday)?;
+    /// assert_eq!(4, timetable.stops.len());
+    /// assert_eq!(2, timetable.trips.len());
+    /// // tripA3 leaves platform2 at 10:00
+    /// assert_eq!(Some(10 * 3600), timetable.times[0][1]);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn timetable(
+        &self,
+        route_id: &str,
+        direction_id: Option<DirectionType>,
+        date: NaiveDate,
+    ) -> Result<Timetable<'_>, Error> {
+        self.get_route(route_id)?;
+        let stops = self.stop_pattern(route_id, direction_id);
+        let mut trips: Vec<&Trip> = self
+            .trips_for_route(route_id)
+            .filter(|trip| trip.direction_id == direction_id)
+            .filter(|trip| self.is_service_active(&trip.service_id, date))
+            .collect();
+        trips.sort_by(|a, b| {
+            let start = |trip: &Trip| {
+                trip.stop_times
+                    .iter()
+                    .find_map(|st| st.departure_time.or(st.arrival_time))
+            };
+            match (start(a), start(b)) {
+                (Some(a_start), Some(b_start)) => a_start.cmp(&b_start),
+                (a_start, b_start) => b_start.cmp(&a_start),
+            }
+            .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let mut times = vec![vec![None; trips.len()]; stops.len()];
+        for (column, trip) in trips.iter().enumerate() {
+            let mut next_row = 0;
+            for stop_time in &trip.stop_times {
+                let position =
+                    |rows: &[Arc<Stop>]| rows.iter().position(|s| s.id == stop_time.stop.id);
+                let row = match position(&stops[next_row..]) {
+                    Some(offset) => next_row + offset,
+                    None => match position(&stops) {
+                        Some(row) => row,
+                        None => continue,
+                    },
+                };
+                let cell = &mut times[row][column];
+                if cell.is_none() {
+                    *cell = stop_time.departure_time.or(stop_time.arrival_time);
+                }
+                next_row = row + 1;
+            }
+        }
+        Ok(Timetable {
+            stops,
+            trips,
+            times,
+        })
+    }
+}
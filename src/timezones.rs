@@ -0,0 +1,55 @@
+use crate::{objects::*, Error, Gtfs};
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+
+impl Gtfs {
+    /// Converts the time of a stop time of a trip running on `service_date` into a UTC date and time
+    ///
+    /// The time is the departure time, or else the arrival time. Like every time of `stop_times.txt`, it is local to
+    /// the `agency_timezone` of the agency of the route, whatever the `stop_timezone` of the stop.
+    /// It is counted from noon minus 12 hours on the service date, so the times of the days when daylight saving time
+    /// changes are shifted by an hour before the change, as the specification says
+    ///
+    /// ```
+    /// use chrono::{NaiveDate, TimeZone, Utc};
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// // tripA1 leaves platform1 at 08:00:00 in Europe/Paris, one hour ahead of UTC in winter
+    /// let monday = NaiveDate::from_ymd_opt(2024, 1, 8).unwrap();
+    /// let departure = gtfs.stop_time_utc("tripA1", 1, monday)?;
+    /// assert_eq!(Utc.with_ymd_and_hms(2024, 1, 8, 7, 0, 0).unwrap(), departure);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn stop_time_utc(
+        &self,
+        trip_id: &str,
+        stop_sequence: u16,
+        service_date: NaiveDate,
+    ) -> Result<DateTime<Utc>, Error> {
+        let trip = self.get_trip(trip_id)?;
+        let time = trip
+            .stop_times
+            .iter()
+            .find(|st| st.stop_sequence == stop_sequence)
+            .and_then(|st| st.departure_time.or(st.arrival_time))
+            .ok_or_else(|| Error::UnknownStopTime {
+                trip_id: trip_id.to_owned(),
+                stop_sequence,
+            })?;
+        let timezone = self.trip_timezone(trip)?;
+        let noon = service_date.and_time(NaiveTime::MIN) + TimeDelta::hours(12);
+        let noon = timezone
+            .from_local_datetime(&noon)
+            .earliest()
+            .ok_or_else(|| Error::InvalidTimezone(timezone.name().to_owned()))?;
+        Ok(noon.with_timezone(&Utc) - TimeDelta::hours(12) + TimeDelta::seconds(i64::from(time)))
+    }
+
+    /// Timezone of the agency of the route of the trip, see [Gtfs::agency_for_trip]
+    fn trip_timezone(&self, trip: &Trip) -> Result<Tz, Error> {
+        let agency = self.agency_for_route(&trip.route_id)?;
+        agency
+            .timezone
+            .parse()
+            .map_err(|_| Error::InvalidTimezone(agency.timezone.clone()))
+    }
+}
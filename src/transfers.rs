@@ -0,0 +1,183 @@
+use crate::{objects::*, Gtfs};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Transfer from a stop to another one, either given for these stops or inherited from their parent stations
+#[derive(Debug, Clone)]
+pub struct Transfer<'a> {
+    /// Stop the transfer starts from
+    pub from_stop: &'a Arc<Stop>,
+    /// Stop the transfer leads to
+    pub to_stop: &'a Arc<Stop>,
+    /// Rule of the transfer, as given in transfers.txt for the stops or their stations
+    pub transfer: &'a StopTransfer,
+}
+
+/// Parameters of the transfers generated by [Gtfs::generate_transfers]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferGeneration {
+    /// Longest distance in meters between two stops, as the crow flies
+    pub max_distance: f64,
+    /// Walking speed in meters per second, giving the `min_transfer_time` from the distance
+    pub walking_speed: f64,
+}
+
+impl Default for TransferGeneration {
+    fn default() -> Self {
+        Self {
+            max_distance: 300.,
+            walking_speed: 1.2,
+        }
+    }
+}
+
+impl Gtfs {
+    /// Synthesizes the transfers between the stops close to each other, when no rule applies to them yet
+    ///
+    /// A transfer is generated in each direction between two stops (`location_type` 0) at most
+    /// [TransferGeneration::max_distance] apart, unless [Gtfs::transfer_between] already gives a rule for them.
+    /// The transfers have the type [TransferType::MinTime], with the time to walk the distance as the crow flies.
+    /// They are sorted by `from_stop_id` then `to_stop_id`, and can be added to [crate::RawGtfs::transfers] before
+    /// building the [Gtfs], or written to a new `transfers.txt`
+    ///
+    /// ```
+    /// use gtfs_structures::TransferGeneration;
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// // platform1 and platform2 have the same position, and only the transfer from platform1 to platform2 is given
+    /// let transfers = gtfs.generate_transfers(&TransferGeneration::default());
+    /// assert_eq!(1, transfers.len());
+    /// assert_eq!(("platform2", "platform1"), (&*transfers[0].from_stop_id, &*transfers[0].to_stop_id));
+    /// assert_eq!(Some(0), transfers[0].min_transfer_time);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn generate_transfers(&self, params: &TransferGeneration) -> Vec<RawTransfer> {
+        let mut from_stops: Vec<&Arc<Stop>> = self
+            .stops
+            .values()
+            .filter(|stop| stop.location_type == LocationType::StopPoint)
+            .collect();
+        from_stops.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut transfers = Vec::new();
+        for from in from_stops {
+            let (latitude, longitude) = match (from.latitude, from.longitude) {
+                (Some(latitude), Some(longitude)) => (latitude, longitude),
+                _ => continue,
+            };
+            let mut destinations = self.stops_within(
+                latitude,
+                longitude,
+                params.max_distance,
+                Some(LocationType::StopPoint),
+            );
+            destinations.sort_by(|a, b| a.0.id.cmp(&b.0.id));
+            for (to, distance) in destinations {
+                if to.id == from.id || self.transfer_between(&from.id, &to.id).is_some() {
+                    continue;
+                }
+                transfers.push(RawTransfer {
+                    from_stop_id: from.id.clone(),
+                    to_stop_id: to.id.clone(),
+                    transfer_type: TransferType::MinTime,
+                    min_transfer_time: Some((distance / params.walking_speed).ceil() as u32),
+                });
+            }
+        }
+        transfers
+    }
+
+    /// Lists the transfers from a stop, sorted by destination
+    ///
+    /// A transfer from or to a station applies to all its child stops (but not to its entrances or generic nodes).
+    /// When several rules apply to the same stops, the one with the most specific stops is kept, see [Gtfs::transfer_between]
+    ///
+    /// ```
+    /// let gtfs = gtfs_structures::Gtfs::from_path("fixtures/stations")?;
+    /// // platform2 inherits the transfer of its parent station station1 to stop1
+    /// let transfers = gtfs.transfers_from("platform2");
+    /// assert_eq!(1, transfers.len());
+    /// assert_eq!("stop1", transfers[0].to_stop.id);
+    /// assert_eq!(Some(120), transfers[0].transfer.min_transfer_time);
+    /// # Ok::<(), gtfs_structures::error::Error>(())
+    /// ```
+    pub fn transfers_from(&self, stop_id: &str) -> Vec<Transfer<'_>> {
+        self.transfers_with_children(stop_id, &self.children())
+    }
+
+    /// Gives the transfer rule from a stop to another one. None if no rule applies
+    ///
+    /// The rules are looked up in this order: between the stops, from the stop to the parent station of the destination,
+    /// from the parent station of the stop to the destination, and between both parent stations
+    pub fn transfer_between(&self, from_stop_id: &str, to_stop_id: &str) -> Option<Transfer<'_>> {
+        let from_stop = self.stops.get(from_stop_id)?;
+        let to_stop = self.stops.get(to_stop_id)?;
+        let transfer = self.with_parent(from_stop).find_map(|from| {
+            self.with_parent(to_stop).find_map(|to| {
+                from.transfers
+                    .iter()
+                    .find(|transfer| transfer.to_stop_id == to.id)
+            })
+        })?;
+        Some(Transfer {
+            from_stop,
+            to_stop,
+            transfer,
+        })
+    }
+
+    /// Iterates over the transfers between all the stops, as the edges of a graph, see [Gtfs::transfers_from]
+    pub fn transfers(&self) -> impl Iterator<Item = Transfer<'_>> {
+        let children = self.children();
+        self.stops
+            .keys()
+            .flat_map(move |stop_id| self.transfers_with_children(stop_id, &children))
+    }
+
+    /// The stop followed by its parent station, if it is a stop that inherits its transfers
+    fn with_parent<'a>(&'a self, stop: &'a Arc<Stop>) -> impl Iterator<Item = &'a Arc<Stop>> {
+        std::iter::once(stop).chain(
+            stop.parent_station
+                .as_ref()
+                .filter(|_| stop.location_type == LocationType::StopPoint)
+                .and_then(|parent| self.stops.get(parent)),
+        )
+    }
+
+    /// The child stops of each station, that inherit its transfers
+    fn children(&self) -> HashMap<&str, Vec<&Arc<Stop>>> {
+        let mut children: HashMap<&str, Vec<&Arc<Stop>>> = HashMap::new();
+        for stop in self.stops.values() {
+            if let (Some(parent), LocationType::StopPoint) =
+                (&stop.parent_station, stop.location_type)
+            {
+                children.entry(parent).or_default().push(stop);
+            }
+        }
+        children
+    }
+
+    fn transfers_with_children<'a>(
+        &'a self,
+        stop_id: &str,
+        children: &HashMap<&str, Vec<&'a Arc<Stop>>>,
+    ) -> Vec<Transfer<'a>> {
+        let stop = match self.stops.get(stop_id) {
+            Some(stop) => stop,
+            None => return Vec::new(),
+        };
+        let mut destinations: Vec<&GtfsId> = self
+            .with_parent(stop)
+            .flat_map(|s| &s.transfers)
+            .filter_map(|transfer| self.stops.get(&transfer.to_stop_id))
+            .flat_map(|to| {
+                std::iter::once(to).chain(children.get(&*to.id).into_iter().flatten().copied())
+            })
+            .map(|to| &to.id)
+            .collect();
+        destinations.sort();
+        destinations.dedup();
+        destinations
+            .into_iter()
+            .filter_map(|to| self.transfer_between(stop_id, to))
+            .collect()
+    }
+}
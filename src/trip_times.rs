@@ -0,0 +1,43 @@
+use crate::objects::*;
+
+/// Times of the departures of the trip from a stop it leaves at `time`, in seconds since the midnight of the service day
+///
+/// With [Frequency], `time` is only used as an offset from the first stop of the trip
+pub(crate) fn departure_times(trip: &Trip, time: u32) -> Vec<u32> {
+    if trip.frequencies.is_empty() {
+        return vec![time];
+    }
+    let first_time = first_time(trip);
+    let offset = time.saturating_sub(first_time);
+    trip.frequencies
+        .iter()
+        .filter(|f| f.headway_secs > 0)
+        .flat_map(|f| (f.start_time..f.end_time).step_by(f.headway_secs as usize))
+        .map(|start| start + offset)
+        .collect()
+}
+
+/// First and last times of the trip, in seconds since the midnight of its service day. None if it has no time or no run
+///
+/// They are the earliest and latest times of its stop times. A trip with [Frequency] runs once for each departure
+/// given by [departure_times], and spans from the earliest time of its first run to the latest time of its last run
+pub(crate) fn trip_span(trip: &Trip) -> Option<(u32, u32)> {
+    let times = trip
+        .stop_times
+        .iter()
+        .flat_map(|st| st.arrival_time.into_iter().chain(st.departure_time));
+    let first = times.clone().min()?;
+    let last = times.max()?;
+    Some((
+        departure_times(trip, first).into_iter().min()?,
+        departure_times(trip, last).into_iter().max()?,
+    ))
+}
+
+/// Time the trip leaves its first stop, in seconds since the midnight of the service day
+pub(crate) fn first_time(trip: &Trip) -> u32 {
+    trip.stop_times
+        .first()
+        .and_then(|st| st.departure_time.or(st.arrival_time))
+        .unwrap_or(0)
+}